@@ -3,4 +3,29 @@
 fn main() {
     prost_build::compile_protos(&["proto/vector_tile.proto"], &["proto/"])
         .expect("Failed to compile Protocol Buffer definitions");
+    
+    // Commit the library is built from, for version::version() (absent outside a git checkout).
+    // Rebuild when HEAD moves: HEAD itself, the branch it points to, or packed refs.
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|text| text.trim().to_string())
+    };
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = std::path::Path::new(&git_dir);
+        println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+        println!("cargo:rerun-if-changed={}", git_dir.join("packed-refs").display());
+        if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            println!("cargo:rerun-if-changed={}", git_dir.join(head_ref).display());
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto/vector_tile.proto");
+    if let Some(hash) = git(&["rev-parse", "--short=12", "HEAD"]) {
+        println!("cargo:rustc-env=VECTOR_TILE_CORE_GIT_HASH={}", hash);
+    }
 }
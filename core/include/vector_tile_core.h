@@ -0,0 +1,85 @@
+/* vector-tile-core C API (build with `--features ffi`) */
+#ifndef VECTOR_TILE_CORE_H
+#define VECTOR_TILE_CORE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Borrowed byte buffer, valid until the owning result is freed */
+typedef struct vtc_buffer {
+    const uint8_t *data;
+    size_t len;
+} vtc_buffer;
+
+/* Opaque generation result */
+typedef struct vtc_result vtc_result;
+
+/* Opaque tile source answering tile requests */
+typedef struct vtc_source vtc_source;
+
+/* Generate tiles from GeoJSON bytes. Always returns a result; free it with vtc_result_free. */
+vtc_result *vtc_generate(const uint8_t *geojson, size_t len, uint8_t min_zoom, uint8_t max_zoom,
+                         const char *layer_name);
+
+/* As vtc_generate, with options as a JSON object (NULL for the defaults), e.g.
+   {"preset": "roads", "encoding": "detect", "strict": true, "bbox": [139.5, 35.5, 140.0, 35.9]}.
+   Keys: preset, encoding, parallel, strict, extend_zooms, bbox, extent, max_zoom_extent, schema,
+   remove_duplicates, omit_ids, fill_empty_tiles, validate_input. The input format is detected
+   from its content. Unknown keys or values fail the result. */
+vtc_result *vtc_generate_with_options(const uint8_t *geojson, size_t len, uint8_t min_zoom,
+                                      uint8_t max_zoom, const char *layer_name,
+                                      const char *options_json);
+
+/* As vtc_generate_with_options, writing the tiles to output_path: a PMTiles archive (.pmtiles),
+   an MBTiles database (.mbtiles) or a z/x/y.pbf directory (any other path). compression is
+   "none", "gzip" or "brotli" (NULL for none). The result has the metadata and no tiles. */
+vtc_result *vtc_generate_to_file(const uint8_t *geojson, size_t len, uint8_t min_zoom,
+                                 uint8_t max_zoom, const char *layer_name,
+                                 const char *options_json, const char *output_path,
+                                 const char *compression);
+
+/* Error message of a failed generation, NULL on success */
+const char *vtc_result_error(const vtc_result *result);
+
+/* Number of generated tiles */
+size_t vtc_result_tile_count(const vtc_result *result);
+
+/* Path ("z/x/y.pbf") and data of the tile at index. Returns 0 on success, -1 if out of range. */
+int32_t vtc_result_tile(const vtc_result *result, size_t index, const char **path, vtc_buffer *data);
+
+/* Metadata JSON object with "tilejson", "warnings" and "metrics" */
+const char *vtc_result_metadata_json(const vtc_result *result);
+
+/* Free a result (NULL is ignored) */
+void vtc_result_free(vtc_result *result);
+
+/* Generate tiles into a tile source, as vtc_generate_with_options. Always returns a source;
+   free it with vtc_source_free. */
+vtc_source *vtc_source_new(const uint8_t *geojson, size_t len, uint8_t min_zoom, uint8_t max_zoom,
+                           const char *layer_name, const char *options_json);
+
+/* Error of the failed generation or the last failed vtc_source_tile, NULL if none */
+const char *vtc_source_error(const vtc_source *source);
+
+/* Data of tile z/x/y (overzoomed beyond the max zoom), valid until the next call on source.
+   Returns 0 with the tile, 1 if there is no tile there, -1 on error (see vtc_source_error). */
+int32_t vtc_source_tile(vtc_source *source, uint8_t z, uint32_t x, uint32_t y, vtc_buffer *data);
+
+/* Free a source (NULL is ignored) */
+void vtc_source_free(vtc_source *source);
+
+/* Summarize an encoded tile as JSON ({"layers": [...]} or {"error": "..."}); free with vtc_string_free */
+char *vtc_inspect_tile(const uint8_t *data, size_t len);
+
+/* Free a string returned by vtc_inspect_tile (NULL is ignored) */
+void vtc_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VECTOR_TILE_CORE_H */
@@ -0,0 +1,246 @@
+// Aggregation module
+// Extra layers summarizing the features (hexagon bins, density grid)
+
+use crate::geojson_parser::{feature_centroid, Feature, GeometryType};
+use crate::projection::{lonlat_to_meters, meters_to_world};
+use crate::tiler::{TileFeature, TileGeometry, TileMap, DEFAULT_EXTENT};
+use crate::{pmtiles, TileCoord, TileOptions};
+use std::collections::BTreeMap;
+
+/// Aggregation layers of one tile: `(layer name, features)`
+pub(crate) type AggregateLayers = Vec<(String, Vec<TileFeature>)>;
+
+/// Hexagon binning of point features into an extra polygon layer
+///
+/// Each hexagon carries a `count` attribute and the sum of every property in `sum`
+/// (`<property>_sum`). Hexagons are laid out on one grid per zoom, so those crossing a tile
+/// edge are drawn in every tile they touch with the same totals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hexbin {
+    pub layer_name: String,
+    /// `(zoom, radius)` steps, ascending: the center-to-corner radius in tile units (for
+    /// extent 4096) from that zoom on. Zooms below the first step get no hexagons.
+    pub radius: Vec<(u8, f64)>,
+    /// Numeric properties summed per hexagon (other values are ignored)
+    pub sum: Vec<String>,
+}
+
+impl Hexbin {
+    /// Radius at a zoom, if the layer is generated there
+    pub fn radius_at(&self, zoom: u8) -> Option<f64> {
+        self.radius.iter().rev().find(|&&(from, _)| from <= zoom).map(|&(_, radius)| radius)
+    }
+}
+
+/// Low-zoom density layer: square grid cells carrying the `count` of features in them
+///
+/// Features are counted at the mean of their vertices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overview {
+    pub layer_name: String,
+    /// Cell size in tile units (for extent 4096)
+    pub cell_size: u32,
+    /// Deepest zoom the layer is generated at
+    pub max_zoom: u8,
+}
+
+/// Count and sums of one hexagon
+#[derive(Default)]
+struct Bin {
+    count: u64,
+    sums: Vec<f64>,
+}
+
+/// Aggregation layers of one zoom, by tile in tile id order
+pub(crate) fn aggregate_layers(features: &[Feature], zoom: u8, extent: u32, options: &TileOptions) -> Vec<(TileCoord, AggregateLayers)> {
+    let scale = extent as f64 / DEFAULT_EXTENT as f64;
+    let mut tiles: BTreeMap<u64, (TileCoord, AggregateLayers)> = BTreeMap::new();
+    let mut add = |layer_name: &str, layer_tiles: TileMap| {
+        for (coord, features) in layer_tiles {
+            let (_, layers) = tiles.entry(pmtiles::tile_id(coord)).or_insert_with(|| (coord, Vec::new()));
+            layers.push((layer_name.to_string(), features));
+        }
+    };
+    if let Some(hexbin) = &options.hexbin {
+        if let Some(radius) = hexbin.radius_at(zoom) {
+            add(&hexbin.layer_name, hexbin_tiles(features, zoom, extent, radius * scale, hexbin));
+        }
+    }
+    if let Some(overview) = options.overview.as_ref().filter(|overview| zoom <= overview.max_zoom) {
+        let cell_size = (overview.cell_size as f64 * scale).max(1.0);
+        add(&overview.layer_name, overview_tiles(features, zoom, extent, cell_size));
+    }
+    tiles.into_values().collect()
+}
+
+/// Hexagons of one zoom by tile (`radius` is in tile units at `extent`)
+fn hexbin_tiles(features: &[Feature], zoom: u8, extent: u32, radius: f64, hexbin: &Hexbin) -> TileMap {
+    let sqrt3 = 3f64.sqrt();
+    let mut bins: BTreeMap<(i64, i64), Bin> = BTreeMap::new();
+    for feature in features {
+        let GeometryType::Point(point) = &feature.geometry else {
+            continue;
+        };
+        let (mx, my) = lonlat_to_meters(point.x(), point.y());
+        let (x, y) = meters_to_world(mx, my, zoom, extent);
+        let bin = bins.entry(hex_at(x / radius, y / radius)).or_default();
+        bin.count += 1;
+        bin.sums.resize(hexbin.sum.len(), 0.0);
+        for (sum, key) in bin.sums.iter_mut().zip(&hexbin.sum) {
+            *sum += feature.properties.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        }
+    }
+    
+    let mut tiles = TileMap::default();
+    for ((q, r), bin) in bins {
+        let center = (radius * sqrt3 * (q as f64 + r as f64 / 2.0), radius * 1.5 * r as f64);
+        let corners: Vec<(f64, f64)> = (0..6)
+            .map(|i| {
+                let angle = (30.0 + 60.0 * i as f64).to_radians();
+                (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+            })
+            .collect();
+        
+        let mut properties = serde_json::Map::new();
+        properties.insert("count".to_string(), bin.count.into());
+        for (key, sum) in hexbin.sum.iter().zip(&bin.sums) {
+            properties.insert(format!("{}_sum", key), serde_json::json!(sum));
+        }
+        add_polygon(&mut tiles, zoom, extent, &corners, properties);
+    }
+    tiles
+}
+
+/// Grid cells of one zoom by tile (`cell_size` is in tile units at `extent`)
+fn overview_tiles(features: &[Feature], zoom: u8, extent: u32, cell_size: f64) -> TileMap {
+    let mut cells: BTreeMap<(i64, i64), u64> = BTreeMap::new();
+    for feature in features {
+        let Some((lon, lat)) = feature_centroid(&feature.geometry) else {
+            continue;
+        };
+        let (mx, my) = lonlat_to_meters(lon, lat);
+        let (x, y) = meters_to_world(mx, my, zoom, extent);
+        *cells.entry(((x / cell_size).floor() as i64, (y / cell_size).floor() as i64)).or_insert(0) += 1;
+    }
+    
+    let mut tiles = TileMap::default();
+    for ((cx, cy), count) in cells {
+        let (x0, y0) = (cx as f64 * cell_size, cy as f64 * cell_size);
+        let (x1, y1) = (x0 + cell_size, y0 + cell_size);
+        let mut properties = serde_json::Map::new();
+        properties.insert("count".to_string(), count.into());
+        add_polygon(&mut tiles, zoom, extent, &[(x0, y0), (x1, y0), (x1, y1), (x0, y1)], properties);
+    }
+    tiles
+}
+
+/// Add a polygon given by its corners in world tile units to every tile its bounding box touches
+///
+/// A shape ending exactly on a tile edge is not added to the tile beyond it.
+fn add_polygon(
+    tiles: &mut TileMap,
+    zoom: u8,
+    extent: u32,
+    corners: &[(f64, f64)],
+    properties: serde_json::Map<String, serde_json::Value>,
+) {
+    let extent = extent as f64;
+    let last_tile = (1i64 << zoom) - 1;
+    let tile_range = |min: f64, max: f64| {
+        ((min / extent).floor() as i64).max(0)..=((max / extent).ceil() as i64 - 1).min(last_tile)
+    };
+    let (min_x, max_x) = corners.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &(x, _)| (lo.min(x), hi.max(x)));
+    let (min_y, max_y) = corners.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+    
+    for tx in tile_range(min_x, max_x) {
+        for ty in tile_range(min_y, max_y) {
+            let origin = (tx as f64 * extent, ty as f64 * extent);
+            let mut ring: Vec<(i32, i32)> = corners.iter()
+                .map(|&(x, y)| ((x - origin.0).round() as i32, (y - origin.1).round() as i32))
+                .collect();
+            ring.push(ring[0]);
+            tiles.entry(TileCoord::new(zoom, tx as u32, ty as u32)).or_default().push(TileFeature {
+                geometry: TileGeometry::Polygon(vec![ring]),
+                properties: properties.clone(),
+                id: None,
+            });
+        }
+    }
+}
+
+/// Axial coordinates of the pointy-top hexagon (radius 1) containing a point
+fn hex_at(x: f64, y: f64) -> (i64, i64) {
+    let q = 3f64.sqrt() / 3.0 * x - y / 3.0;
+    let r = 2.0 / 3.0 * y;
+    
+    // Round in cube coordinates, fixing the component with the largest rounding error
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i64, rr as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point;
+
+    fn point(lon: f64, lat: f64, value: f64) -> Feature {
+        let mut properties = serde_json::Map::new();
+        properties.insert("value".to_string(), serde_json::json!(value));
+        Feature::new(GeometryType::Point(Point::new(lon, lat)), properties)
+    }
+
+    #[test]
+    fn test_hex_at() {
+        assert_eq!(hex_at(0.0, 0.0), (0, 0));
+        assert_eq!(hex_at(3f64.sqrt(), 0.0), (1, 0));
+        assert_eq!(hex_at(3f64.sqrt() / 2.0, 1.5), (0, 1));
+    }
+
+    #[test]
+    fn test_hexbin_counts_and_sums() {
+        let hexbin = Hexbin { layer_name: "hex".to_string(), radius: vec![(0, 64.0), (5, 16.0)], sum: vec!["value".to_string()] };
+        assert_eq!(hexbin.radius_at(3), Some(64.0));
+        assert_eq!(hexbin.radius_at(7), Some(16.0));
+        assert_eq!(Hexbin { radius: vec![(2, 64.0)], ..hexbin.clone() }.radius_at(1), None);
+        
+        let features = [point(139.700, 35.700, 1.0), point(139.701, 35.701, 2.5), point(-74.0, 40.7, 4.0)];
+        let options = TileOptions { hexbin: Some(hexbin), ..Default::default() };
+        let tiles = aggregate_layers(&features, 5, 4096, &options);
+        assert_eq!(tiles.len(), 2);
+        
+        let tiles = aggregate_layers(&features, 0, 4096, &options);
+        assert_eq!(tiles.len(), 1);
+        let (name, hexagons) = &tiles[0].1[0];
+        assert_eq!(name, "hex");
+        let mut totals: Vec<(u64, f64)> = hexagons.iter()
+            .map(|f| (f.properties["count"].as_u64().unwrap(), f.properties["value_sum"].as_f64().unwrap()))
+            .collect();
+        totals.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(totals, [(1, 4.0), (2, 3.5)]);
+    }
+
+    #[test]
+    fn test_overview_counts_per_cell() {
+        let overview = Overview { layer_name: "density".to_string(), cell_size: 64, max_zoom: 1 };
+        let options = TileOptions { overview: Some(overview), ..Default::default() };
+        let features = [point(139.700, 35.700, 0.0), point(139.701, 35.701, 0.0), point(-74.0, 40.7, 0.0)];
+        
+        assert!(aggregate_layers(&features, 2, 4096, &options).is_empty());
+        let tiles = aggregate_layers(&features, 0, 4096, &options);
+        let (name, cells) = &tiles[0].1[0];
+        assert_eq!(name, "density");
+        let mut counts: Vec<u64> = cells.iter().map(|f| f.properties["count"].as_u64().unwrap()).collect();
+        counts.sort();
+        assert_eq!(counts, [1, 2]);
+        // Cells are aligned to the grid, so one fits inside a tile exactly
+        let TileGeometry::Polygon(rings) = &cells[0].geometry else { panic!() };
+        assert_eq!((rings[0][2].0 - rings[0][0].0, rings[0][2].1 - rings[0][0].1), (64, 64));
+    }
+}
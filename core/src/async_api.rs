@@ -0,0 +1,263 @@
+// Async API module
+// Runtime-agnostic async wrappers: CPU-bound generation runs on a dedicated thread
+
+use crate::metrics::GenerationMetrics;
+use crate::sink::TileSink;
+use crate::{generate_tiles_to_sink, generate_tiles_with_metrics, TileCoord, TileFile, TileMetadata, TileOptions};
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Poll, Waker};
+use std::thread;
+
+/// Boxed future returned by `AsyncTileSink` methods
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Async destination for generated tiles (e.g. object storage or an async file writer)
+pub trait AsyncTileSink: Send {
+    fn write_tile(&mut self, coord: TileCoord, data: Vec<u8>) -> BoxFuture<'_, Result<(), String>>;
+    fn finish<'a>(&'a mut self, metadata: &'a TileMetadata) -> BoxFuture<'a, Result<(), String>>;
+}
+
+/// Tiles buffered between the generation thread and the async writer
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Generate tiles without blocking the calling executor
+///
+/// Works with any runtime: the work runs on its own thread, which wakes the task when it is done.
+pub async fn generate_tiles_async(
+    geojson_bytes: Vec<u8>,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: String,
+    options: TileOptions,
+) -> Result<(Vec<TileFile>, TileMetadata, GenerationMetrics), String> {
+    run_blocking(move || generate_tiles_with_metrics(&geojson_bytes, min_zoom, max_zoom, &layer_name, &options)).await
+}
+
+/// Generate tiles on a dedicated thread and write them to an async sink as they are encoded
+///
+/// Generation pauses while `CHANNEL_CAPACITY` tiles are waiting to be written. Dropping the
+/// future or a sink error stops the generation thread.
+pub async fn generate_tiles_to_async_sink(
+    geojson_bytes: Vec<u8>,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: String,
+    options: TileOptions,
+    sink: &mut dyn AsyncTileSink,
+) -> Result<(TileMetadata, GenerationMetrics), String> {
+    let channel = Arc::new(Channel::default());
+    let _cancel_on_drop = CancelGuard(channel.clone());
+    
+    let producer = channel.clone();
+    thread::spawn(move || {
+        let mut sink = ChannelSink(producer.clone());
+        let result = catch_panic(|| {
+            generate_tiles_to_sink(&geojson_bytes, min_zoom, max_zoom, &layer_name, &options, &mut sink)
+        });
+        let mut state = producer.state.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    
+    loop {
+        let next = poll_fn(|cx| {
+            let mut state = channel.state.lock().unwrap();
+            if let Some(tile) = state.queue.pop_front() {
+                channel.space.notify_one();
+                return Poll::Ready(Ok(tile));
+            }
+            if let Some(result) = state.result.take() {
+                return Poll::Ready(Err(result));
+            }
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await;
+        
+        match next {
+            Ok((coord, data)) => sink.write_tile(coord, data).await?,
+            Err(result) => {
+                let (metadata, metrics) = result?;
+                sink.finish(&metadata).await?;
+                return Ok((metadata, metrics));
+            }
+        }
+    }
+}
+
+/// Run a blocking closure on its own thread and await its result
+async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    let slot = Arc::new(Mutex::new(Slot { result: None, waker: None }));
+    
+    let producer = slot.clone();
+    thread::spawn(move || {
+        let result = catch_panic(f);
+        let mut slot = producer.lock().unwrap();
+        slot.result = Some(result);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+    });
+    
+    poll_fn(|cx| {
+        let mut slot = slot.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
+
+/// Result handed from the blocking thread to the awaiting task
+struct Slot<T> {
+    result: Option<Result<T, String>>,
+    waker: Option<Waker>,
+}
+
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| Err("Tile generation panicked".to_string()))
+}
+
+type GenerationResult = Result<(TileMetadata, GenerationMetrics), String>;
+
+#[derive(Default)]
+struct Channel {
+    state: Mutex<ChannelState>,
+    /// Signalled when the queue has room or the consumer went away
+    space: Condvar,
+}
+
+#[derive(Default)]
+struct ChannelState {
+    queue: VecDeque<(TileCoord, Vec<u8>)>,
+    result: Option<GenerationResult>,
+    waker: Option<Waker>,
+    cancelled: bool,
+}
+
+/// Producer side used by the generation thread
+struct ChannelSink(Arc<Channel>);
+
+impl TileSink for ChannelSink {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        let mut state = self.0.state.lock().unwrap();
+        while state.queue.len() >= CHANNEL_CAPACITY && !state.cancelled {
+            state = self.0.space.wait(state).unwrap();
+        }
+        if state.cancelled {
+            return Err("Tile generation cancelled".to_string());
+        }
+        
+        state.queue.push_back((coord, data.to_vec()));
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _metadata: &TileMetadata) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Stops the generation thread when the consuming future finishes or is dropped
+struct CancelGuard(Arc<Channel>);
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.0.state.lock() {
+            state.cancelled = true;
+        }
+        self.0.space.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::{Context, Wake};
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Minimal executor (the crate does not depend on an async runtime)
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        tiles: Vec<TileCoord>,
+        finished: bool,
+    }
+
+    impl AsyncTileSink for RecordingSink {
+        fn write_tile(&mut self, coord: TileCoord, _data: Vec<u8>) -> BoxFuture<'_, Result<(), String>> {
+            Box::pin(async move {
+                self.tiles.push(coord);
+                Ok(())
+            })
+        }
+
+        fn finish<'a>(&'a mut self, _metadata: &'a TileMetadata) -> BoxFuture<'a, Result<(), String>> {
+            Box::pin(async move {
+                self.finished = true;
+                Ok(())
+            })
+        }
+    }
+
+    const GEOJSON: &str = r#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+
+    #[test]
+    fn test_generate_tiles_async() {
+        let result = block_on(generate_tiles_async(GEOJSON.into(), 0, 3, "test".into(), TileOptions::default()));
+        
+        let (tiles, metadata, _) = result.unwrap();
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(metadata.max_zoom, 3);
+    }
+
+    #[test]
+    fn test_generate_tiles_to_async_sink() {
+        let mut sink = RecordingSink::default();
+        let result = block_on(generate_tiles_to_async_sink(GEOJSON.into(), 0, 3, "test".into(), TileOptions::default(), &mut sink));
+        
+        assert!(result.is_ok());
+        assert_eq!(sink.tiles.len(), 4);
+        assert!(sink.finished);
+    }
+
+    #[test]
+    fn test_async_errors_are_returned() {
+        let result = block_on(generate_tiles_async(b"not json".to_vec(), 0, 3, "test".into(), TileOptions::default()));
+        assert!(result.is_err());
+    }
+}
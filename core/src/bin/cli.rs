@@ -1,16 +1,211 @@
 // CLI tool for testing vector tile generation
-// Usage: cargo run --bin cli <geojson_file> <output_dir> <min_zoom> <max_zoom> [layer_name]
+// Usage: cargo run --bin cli --version [--json]
+//        cargo run --bin cli info <archive.pmtiles|archive.mbtiles> [--json]
+//        cargo run --bin cli doctor <input> [--json]
+//        cargo run --bin cli export-geojson <tiles.zip|tiles.mbtiles|tiles.pmtiles|tile_dir> <output.geojson> [--zoom <z>] [--crs <EPSG:4326|EPSG:3857>]
+//        cargo run --bin cli <geojson_file|http(s)://url> <output_dir|output.pmtiles|output.mbtiles> <min_zoom> <max_zoom> [layer_name] [--json] [--manifest] [--time <property>=<start>/<end>] [--preset <name>] [--schema <file>] [--memory-budget <MiB>] [--parallel <strategy>] [--extend-zooms <n>] [--bbox <min_lon,min_lat,max_lon,max_lat>] [--encoding <strict|lossy|detect>] [--strict] [--compression <none|gzip|brotli>] [--dry-run] [--append]
+//        cargo run --bin cli --tippecanoe -o <output> [-z <max_zoom>] [-Z <min_zoom>] [-l <layer_name>] [--drop-densest-as-needed] [--extend-zooms-if-still-dropping] <geojson_file>
 
 use std::env;
 use std::fs;
 use std::path::Path;
-use vector_tile_core::generate_tiles;
+use vector_tile_core::mbtiles::MbTilesSink;
+use vector_tile_core::pmtiles::PmTilesSink;
+use vector_tile_core::sink::{DirectorySink, TileCompression, TileSink};
+use vector_tile_core::metrics::GenerationMetrics;
+use vector_tile_core::{generate_tiles_to_sink, TileCoord, TileMetadata, TileOptions};
+
+#[path = "cli/mmap.rs"]
+mod mmap;
+#[path = "cli/tippecanoe.rs"]
+mod tippecanoe;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    
+    // --json: print a machine-readable summary (metadata and metrics) instead of progress output
+    let json = args.iter().any(|a| a == "--json");
+    args.retain(|a| a != "--json");
+    // --manifest: also write manifest.json with the size and SHA-256 of every tile
+    let manifest = args.iter().any(|a| a == "--manifest");
+    args.retain(|a| a != "--manifest");
+    // --dry-run: print the estimated memory use and output size instead of generating tiles
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    args.retain(|a| a != "--dry-run");
+    // --append: add the layer to an existing .mbtiles output, replacing a layer of the same name
+    let append = args.iter().any(|a| a == "--append");
+    args.retain(|a| a != "--append");
+    
+    // --version: print the version, commit and enabled features (for bug reports)
+    if args.iter().any(|a| a == "--version") {
+        let info = vector_tile_core::version::version();
+        if json {
+            println!("{}", serde_json::to_string_pretty(&info).expect("Failed to serialize version"));
+        } else {
+            println!("vector-tile-core {}", info);
+            println!("features: {}", info.features.join(", "));
+        }
+        return;
+    }
+    // info archive: describe an existing archive instead of generating tiles
+    if args.get(1).map(String::as_str) == Some("info") {
+        let Some(path) = args.get(2) else {
+            eprintln!("Usage: {} info <archive.pmtiles|archive.mbtiles> [--json]", args[0]);
+            std::process::exit(1);
+        };
+        if let Err(e) = print_info(path, json) {
+            eprintln!("❌ Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // doctor input: report problems in the input data instead of generating tiles
+    if args.get(1).map(String::as_str) == Some("doctor") {
+        let Some(path) = args.get(2) else {
+            eprintln!("Usage: {} doctor <input> [--json]", args[0]);
+            std::process::exit(1);
+        };
+        if let Err(e) = print_diagnosis(path, json) {
+            eprintln!("❌ Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // export-geojson tileset output: decode a tileset back to GeoJSON (WGS84 unless --crs is given)
+    if args.get(1).map(String::as_str) == Some("export-geojson") {
+        let (Some(archive), Some(output)) = (args.get(2), args.get(3)) else {
+            eprintln!("Usage: {} export-geojson <tiles.zip|tiles.mbtiles|tiles.pmtiles|tile_dir> <output.geojson> [--zoom <z>] [--crs <EPSG:4326|EPSG:3857>]", args[0]);
+            std::process::exit(1);
+        };
+        let value = |flag: &str| args.iter().position(|a| a == flag).and_then(|at| args.get(at + 1));
+        let zoom = value("--zoom").map(|zoom| zoom.parse().expect("--zoom must be a zoom level"));
+        let crs = value("--crs").map_or(Ok(Default::default()), |name| name.parse()).unwrap_or_else(|e: String| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        if let Err(e) = export_geojson(archive, output, zoom, crs) {
+            eprintln!("❌ Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // --time property=start/end: keep features whose timestamp is in [start, end) (either side may be empty)
+    let mut time_window = None;
+    if let Some(at) = args.iter().position(|a| a == "--time") {
+        let spec = args.get(at + 1).cloned().unwrap_or_default();
+        let (property, range) = spec.split_once('=').expect("--time must be <property>=<start>/<end>");
+        let (start, end) = range.split_once('/').unwrap_or((range, ""));
+        let bound = |text: &str| (!text.is_empty()).then(|| text.to_string());
+        time_window = Some(vector_tile_core::properties::TimeWindow {
+            property: property.to_string(),
+            start: bound(start),
+            end: bound(end),
+        });
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --preset name: start from built-in generalization settings (points-dense, roads, boundaries, parcels)
+    let mut preset_options = TileOptions::default();
+    if let Some(at) = args.iter().position(|a| a == "--preset") {
+        let name = args.get(at + 1).cloned().unwrap_or_default();
+        let preset: vector_tile_core::preset::Preset = name.parse().unwrap_or_else(|e: String| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        preset_options = TileOptions::preset(preset);
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --schema file: attribute descriptions (JSON) written to the metadata.json fields
+    if let Some(at) = args.iter().position(|a| a == "--schema") {
+        let path = args.get(at + 1).cloned().unwrap_or_default();
+        let text = fs::read_to_string(&path).expect("Failed to read schema file");
+        preset_options.field_schema = vector_tile_core::tilejson::FieldSchema::from_json(&text).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --memory-budget MiB: spill tiled features to temporary files beyond this size
+    if let Some(at) = args.iter().position(|a| a == "--memory-budget") {
+        let mib: usize = args.get(at + 1).and_then(|v| v.parse().ok()).expect("--memory-budget must be a number of MiB");
+        preset_options.spill = Some(vector_tile_core::spill::SpillOptions {
+            memory_budget: mib << 20,
+            ..Default::default()
+        });
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --parallel strategy: split the work per zoom, per tile or per feature batch (auto by default)
+    if let Some(at) = args.iter().position(|a| a == "--parallel") {
+        let name = args.get(at + 1).cloned().unwrap_or_default();
+        preset_options.parallel_strategy = name.parse().unwrap_or_else(|e: String| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --strict: fail on features with unsupported or invalid geometry instead of skipping them with a warning
+    if args.iter().any(|a| a == "--strict") {
+        preset_options.geometry_checks = vector_tile_core::mvt_encoder::GeometryChecks::Strict;
+        args.retain(|a| a != "--strict");
+    }
+    // --encoding name: read inputs that are not UTF-8 (strict by default, lossy or detect)
+    if let Some(at) = args.iter().position(|a| a == "--encoding") {
+        let name = args.get(at + 1).cloned().unwrap_or_default();
+        preset_options.text_decoding = name.parse().unwrap_or_else(|e: String| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --extend-zooms n: add up to n zooms past max_zoom while tiles still drop features to fit the budget
+    if let Some(at) = args.iter().position(|a| a == "--extend-zooms") {
+        let zooms: u8 = args.get(at + 1).and_then(|v| v.parse().ok()).expect("--extend-zooms must be a number of zooms");
+        preset_options.extend_zooms = Some(zooms);
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --bbox min_lon,min_lat,max_lon,max_lat: tile only the features in this box (FlatGeobuf reads just those)
+    if let Some(at) = args.iter().position(|a| a == "--bbox") {
+        let values: Vec<f64> = args.get(at + 1).map_or(Vec::new(), |v| v.split(',').filter_map(|n| n.trim().parse().ok()).collect());
+        let [min_lon, min_lat, max_lon, max_lat] = values[..] else {
+            panic!("--bbox must be min_lon,min_lat,max_lon,max_lat");
+        };
+        preset_options.bbox_filter = Some((min_lon, min_lat, max_lon, max_lat));
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --compression name: compress the written tiles (none, gzip or brotli) and record the Content-Encoding
+    let mut compression = TileCompression::None;
+    if let Some(at) = args.iter().position(|a| a == "--compression") {
+        let name = args.get(at + 1).cloned().unwrap_or_default();
+        compression = name.parse().unwrap_or_else(|e: String| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // --tippecanoe: read the remaining arguments as tippecanoe flags (-o, -z, -Z, -l, ...)
+    if let Some(at) = args.iter().position(|a| a == "--tippecanoe") {
+        args.remove(at);
+        let flags = tippecanoe::Args::parse(&args[1..]).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        if flags.drop_densest {
+            preset_options.size_strategy = Some(vector_tile_core::reduction::SizeStrategy::DropDensest);
+            if preset_options.tile_budget == Default::default() {
+                preset_options.tile_budget = vector_tile_core::reduction::TileBudget {
+                    max_features: Some(tippecanoe::MAX_TILE_FEATURES),
+                    max_bytes: Some(tippecanoe::MAX_TILE_BYTES),
+                };
+            }
+        }
+        if flags.extend_zooms {
+            preset_options.extend_zooms = Some(tippecanoe::EXTEND_ZOOMS_LIMIT.saturating_sub(flags.max_zoom));
+        }
+        args.truncate(1);
+        args.extend([flags.input, flags.output, flags.min_zoom.to_string(), flags.max_zoom.to_string(), flags.layer]);
+    }
     
     if args.len() < 5 {
-        eprintln!("Usage: {} <geojson_file> <output_dir> <min_zoom> <max_zoom> [layer_name]", args[0]);
+        eprintln!("Usage: {} <geojson_file|http(s)://url> <output_dir|output.pmtiles|output.mbtiles> <min_zoom> <max_zoom> [layer_name] [--json] [--manifest] [--time <property>=<start>/<end>] [--preset <name>] [--schema <file>] [--memory-budget <MiB>] [--parallel <strategy>] [--extend-zooms <n>] [--bbox <min_lon,min_lat,max_lon,max_lat>] [--encoding <strict|lossy|detect>] [--strict] [--compression <none|gzip|brotli>] [--dry-run] [--append]", args[0]);
         eprintln!("Example: {} data.geojson output 0 5 mylayer", args[0]);
         std::process::exit(1);
     }
@@ -25,45 +220,96 @@ fn main() {
         "default"
     };
     
-    println!("🚀 Starting vector tile generation");
-    println!("  Input: {}", geojson_path);
-    println!("  Output: {}", output_dir);
-    println!("  Zoom: {} - {}", min_zoom, max_zoom);
-    println!("  Layer: {}", layer_name);
-    
-    // Read GeoJSON file
-    let geojson_bytes = fs::read(geojson_path)
-        .expect("Failed to read GeoJSON file");
-    
-    println!("\n📖 Parsing GeoJSON...");
-    
-    // Generate tiles
-    match generate_tiles(&geojson_bytes, min_zoom, max_zoom, layer_name) {
-        Ok(tiles) => {
-            println!("✅ Generated {} tiles", tiles.len());
-            
-            // Create output directory
-            fs::create_dir_all(output_dir)
-                .expect("Failed to create output directory");
-            
-            // Save tiles
-            println!("\n💾 Saving tiles...");
-            for tile in tiles {
-                let tile_path = Path::new(output_dir).join(&tile.path);
-                
-                // Create directory
-                if let Some(parent) = tile_path.parent() {
-                    fs::create_dir_all(parent).ok();
+    if !json {
+        println!("🚀 Starting vector tile generation");
+        println!("  Input: {}", geojson_path);
+        println!("  Output: {}", output_dir);
+        println!("  Zoom: {} - {}", min_zoom, max_zoom);
+        println!("  Layer: {}", layer_name);
+    }
+    
+    // http:// and https:// inputs (FlatGeobuf or PMTiles) are read through range requests, not downloaded
+    let remote = geojson_path.starts_with("http://") || geojson_path.starts_with("https://");
+    // Read GeoJSON file (memory-mapped, so large inputs are not copied before parsing)
+    let geojson_bytes = (!remote).then(|| mmap::InputFile::open(Path::new(geojson_path)).expect("Failed to read GeoJSON file"));
+    
+    if !json {
+        println!("\n📖 Parsing GeoJSON...");
+    }
+    
+    // KML, KMZ and zipped Shapefile inputs are detected from their content
+    let options = TileOptions {
+        reader: geojson_bytes.as_deref().and_then(vector_tile_core::input::detect_reader),
+        time_window,
+        ..preset_options
+    };
+    
+    if dry_run {
+        let Some(geojson_bytes) = &geojson_bytes else {
+            eprintln!("❌ Error: --dry-run needs a local input");
+            std::process::exit(1);
+        };
+        if let Err(e) = print_estimate(geojson_bytes, min_zoom, max_zoom, &options, json) {
+            eprintln!("❌ Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
+    // Tiles are written as soon as they are encoded; .pmtiles and .mbtiles outputs are written as single files
+    let mut sink: Box<dyn TileSink> = if output_dir.ends_with(".pmtiles") {
+        let file = fs::File::create(output_dir).expect("Failed to create PMTiles file");
+        Box::new(PmTilesSink::new(std::io::BufWriter::new(file)).with_tile_compression(compression))
+    } else if output_dir.ends_with(".mbtiles") && append && Path::new(output_dir).exists() {
+        let existing = fs::read(output_dir)
+            .map_err(|e| format!("Failed to read {}: {}", output_dir, e))
+            .and_then(|bytes| vector_tile_core::mbtiles::read_mbtiles(&bytes))
+            .unwrap_or_else(|e| {
+                eprintln!("❌ Error: {}", e);
+                std::process::exit(1);
+            });
+        // Written next to the database and moved over it once complete, so a failure keeps the
+        // original; tiles keep the database's compression
+        let file = fs::File::create(format!("{}.partial", output_dir)).expect("Failed to create MBTiles file");
+        Box::new(MbTilesSink::new(std::io::BufWriter::new(file)).appending(existing))
+    } else if output_dir.ends_with(".mbtiles") {
+        let file = fs::File::create(output_dir).expect("Failed to create MBTiles file");
+        Box::new(MbTilesSink::new(std::io::BufWriter::new(file)).with_tile_compression(compression))
+    } else if append {
+        eprintln!("❌ Error: --append needs an .mbtiles output");
+        std::process::exit(1);
+    } else {
+        let directory = DirectorySink::new(output_dir).with_tile_compression(compression);
+        Box::new(if manifest { directory.with_manifest() } else { directory })
+    };
+    if !json {
+        println!("\n💾 Writing tiles...");
+    }
+    let mut progress = ProgressSink { inner: sink.as_mut(), verbose: !json };
+    let generated = match &geojson_bytes {
+        Some(geojson_bytes) => generate_tiles_to_sink(geojson_bytes, min_zoom, max_zoom, layer_name, &options, &mut progress),
+        None => generate_remote(geojson_path, min_zoom, max_zoom, layer_name, &options, &mut progress),
+    };
+    match generated {
+        Ok((metadata, metrics)) => {
+            drop(sink);
+            let partial = format!("{}.partial", output_dir);
+            if append && Path::new(&partial).exists() {
+                fs::rename(&partial, output_dir).expect("Failed to replace the MBTiles file");
+            }
+            let tile_count: usize = metrics.zooms.iter().map(|zoom| zoom.tile_count).sum();
+            if json {
+                print_summary(tile_count, &metadata, &metrics);
+            } else {
+                println!("✅ Wrote {} tiles to {}", tile_count, output_dir);
+                for zoom in &metrics.zooms {
+                    println!(
+                        "  z{}: {} tiles, {} bytes (tiling {:.1} ms, encoding {:.1} ms)",
+                        zoom.zoom, zoom.tile_count, zoom.bytes, zoom.tiling_ms, zoom.encode_ms
+                    );
                 }
-                
-                // Write tile
-                fs::write(&tile_path, &tile.data)
-                    .expect(&format!("Failed to save tile: {}", tile.path));
-                
-                println!("  ✓ {}", tile.path);
+                println!("\n✨ Complete! ({:.1} ms)", metrics.total_ms);
             }
-            
-            println!("\n✨ Complete!");
         }
         Err(e) => {
             eprintln!("❌ Error: {}", e);
@@ -71,3 +317,212 @@ fn main() {
         }
     }
 }
+
+/// Tile a remote FlatGeobuf file or PMTiles archive read through HTTP range requests
+#[cfg(feature = "http")]
+fn generate_remote(
+    url: &str,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+    sink: &mut dyn TileSink,
+) -> Result<(TileMetadata, GenerationMetrics), String> {
+    let reader = vector_tile_core::http::HttpRangeReader::new(url)?;
+    vector_tile_core::generate_ranges_to_sink(&reader, min_zoom, max_zoom, layer_name, options, sink)
+}
+
+#[cfg(not(feature = "http"))]
+fn generate_remote(_: &str, _: u8, _: u8, _: &str, _: &TileOptions, _: &mut dyn TileSink) -> Result<(TileMetadata, GenerationMetrics), String> {
+    Err("Remote inputs need the 'http' feature".to_string())
+}
+
+/// Passes tiles through to the output, listing each one unless `--json` is given
+struct ProgressSink<'a> {
+    inner: &'a mut dyn TileSink,
+    verbose: bool,
+}
+
+impl TileSink for ProgressSink<'_> {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        self.inner.write_tile(coord, data)?;
+        if self.verbose {
+            println!("  ✓ {}", coord.to_path());
+        }
+        Ok(())
+    }
+    
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        self.inner.finish(metadata)
+    }
+}
+
+/// Print the header, tile counts and layers of an archive
+fn print_info(path: &str, json: bool) -> Result<(), String> {
+    let archive = mmap::InputFile::open(Path::new(path)).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    if path.ends_with(".mbtiles") {
+        return print_mbtiles_info(path, &archive, json);
+    }
+    let info = vector_tile_core::pmtiles::read_info(&archive)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info).expect("Failed to serialize archive info"));
+        return Ok(());
+    }
+    
+    // Layers are listed in `vector_layers`, which tippecanoe-style metadata nests in a JSON string
+    let nested: Option<serde_json::Value> = info.metadata["json"].as_str().and_then(|text| serde_json::from_str(text).ok());
+    let vector_layers = nested.as_ref().map_or(&info.metadata["vector_layers"], |nested| &nested["vector_layers"]);
+    let layers: Vec<&str> = vector_layers.as_array().into_iter().flatten().filter_map(|layer| layer["id"].as_str()).collect();
+    let tile_count: u64 = info.tile_counts.values().sum();
+    
+    println!("📦 {}", path);
+    if let Some(name) = info.metadata["name"].as_str() {
+        println!("  Name: {}", name);
+    }
+    println!("  Zoom: {} - {}", info.min_zoom, info.max_zoom);
+    let (min_lon, min_lat, max_lon, max_lat) = info.bounds;
+    println!("  Bounds: {}, {}, {}, {}", min_lon, min_lat, max_lon, max_lat);
+    println!("  Center: {}, {} (z{})", info.center.0, info.center.1, info.center.2);
+    println!("  Tile type: {}", info.tile_type);
+    println!("  Compression: tiles {}, directories {}", info.tile_compression, info.internal_compression);
+    println!("  Tiles: {} ({} distinct), {} bytes of tile data, {} bytes total", tile_count, info.tile_contents, info.tile_data_bytes, info.archive_bytes);
+    for (zoom, count) in &info.tile_counts {
+        println!("  z{}: {} tiles", zoom, count);
+    }
+    if !layers.is_empty() {
+        println!("  Layers: {}", layers.join(", "));
+    }
+    Ok(())
+}
+
+/// Write the features of a tileset's zoom (the deepest by default) as GeoJSON in `crs`
+fn export_geojson(tileset: &str, output: &str, zoom: Option<u8>, crs: vector_tile_core::export::TargetCrs) -> Result<(), String> {
+    use vector_tile_core::input::{InputReader, TilesetReader};
+    
+    let reader = TilesetReader { zoom, ..Default::default() };
+    let features: Vec<_> = match Path::new(tileset).is_dir() {
+        true => reader.read_directory(Path::new(tileset))?,
+        false => {
+            let bytes = mmap::InputFile::open(Path::new(tileset)).map_err(|e| format!("Failed to read {}: {}", tileset, e))?;
+            let features = reader.read_features(&bytes)?.collect();
+            features
+        }
+    };
+    let geojson = vector_tile_core::export::features_to_geojson(&features, crs);
+    fs::write(output, geojson.to_string()).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+    println!("✅ Exported {} features to {} ({})", features.len(), output, crs);
+    Ok(())
+}
+
+/// Print the metadata rows and tile counts of an MBTiles database
+fn print_mbtiles_info(path: &str, database: &[u8], json: bool) -> Result<(), String> {
+    let mbtiles = vector_tile_core::mbtiles::read_mbtiles(database)?;
+    let mut tile_counts: std::collections::BTreeMap<u8, usize> = Default::default();
+    for (coord, _) in &mbtiles.tiles {
+        *tile_counts.entry(coord.z).or_default() += 1;
+    }
+    let compression = format!("{:?}", mbtiles.tile_compression).to_lowercase();
+    if json {
+        let info = serde_json::json!({"metadata": mbtiles.metadata, "tile_counts": tile_counts, "tile_compression": compression});
+        println!("{}", serde_json::to_string_pretty(&info).expect("Failed to serialize archive info"));
+        return Ok(());
+    }
+    
+    println!("📦 {}", path);
+    for key in ["name", "format", "minzoom", "maxzoom", "bounds", "center"] {
+        if let Some(value) = mbtiles.metadata.get(key) {
+            println!("  {}: {}", key, value);
+        }
+    }
+    println!("  Compression: tiles {}", compression);
+    println!("  Tiles: {}", mbtiles.tiles.len());
+    for (zoom, count) in &tile_counts {
+        println!("  z{}: {} tiles", zoom, count);
+    }
+    let nested: Option<serde_json::Value> = mbtiles.metadata.get("json").and_then(|text| serde_json::from_str(text).ok());
+    let layers: Vec<&str> = nested.iter().flat_map(|json| json["vector_layers"].as_array().into_iter().flatten()).filter_map(|layer| layer["id"].as_str()).collect();
+    if !layers.is_empty() {
+        println!("  Layers: {}", layers.join(", "));
+    }
+    Ok(())
+}
+
+/// Print the issues found in an input, grouped by category
+fn print_diagnosis(path: &str, json: bool) -> Result<(), String> {
+    use vector_tile_core::doctor::{diagnose, IssueCategory};
+    // Issues listed per category before the rest are only counted
+    const LISTED: usize = 20;
+    
+    let input = mmap::InputFile::open(Path::new(path)).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let reader = vector_tile_core::input::detect_reader(&input);
+    let diagnosis = diagnose(&input, reader.as_deref())?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diagnosis).expect("Failed to serialize diagnosis"));
+        return Ok(());
+    }
+    
+    println!("🩺 {}", path);
+    println!("  Features: {}", diagnosis.features);
+    if diagnosis.issues.is_empty() {
+        println!("✅ No issues found");
+        return Ok(());
+    }
+    for category in IssueCategory::ALL {
+        let issues: Vec<_> = diagnosis.issues_in(category).collect();
+        if issues.is_empty() {
+            continue;
+        }
+        println!("\n⚠️  {} ({})", category.label(), issues.len());
+        for issue in issues.iter().take(LISTED) {
+            match issue.feature_index {
+                Some(index) => println!("  #{}: {}", index, issue.message),
+                None => println!("  {}", issue.message),
+            }
+        }
+        if issues.len() > LISTED {
+            println!("  ... and {} more", issues.len() - LISTED);
+        }
+    }
+    Ok(())
+}
+
+/// Print the projected resource use for `--dry-run`
+fn print_estimate(bytes: &[u8], min_zoom: u8, max_zoom: u8, options: &TileOptions, json: bool) -> Result<(), String> {
+    let estimate = vector_tile_core::limits::estimate_resources(bytes, min_zoom, max_zoom, options)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&estimate).expect("Failed to serialize estimate"));
+        return Ok(());
+    }
+    
+    let size = |bytes: u64| match bytes {
+        0..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+    };
+    println!("\n📐 Estimate (nothing written)");
+    println!("  Features: {} ({} vertices)", estimate.features, estimate.vertices);
+    for zoom in &estimate.zooms {
+        println!("  z{}: up to {} tiles, ~{}", zoom.zoom, zoom.tiles, size(zoom.bytes));
+    }
+    println!("  Output: ~{}", size(estimate.output_bytes));
+    println!("  Peak memory: ~{} (tiles held in memory)", size(estimate.peak_memory_bytes));
+    for exceeded in &estimate.exceeded {
+        println!("⚠️  {}", exceeded);
+    }
+    Ok(())
+}
+
+/// Print the machine-readable summary for `--json`
+fn print_summary(tiles: usize, metadata: &TileMetadata, metrics: &GenerationMetrics) {
+    let summary = serde_json::json!({
+        "tiles": tiles,
+        "min_zoom": metadata.min_zoom,
+        "max_zoom": metadata.max_zoom,
+        "layer_name": metadata.layer_name,
+        "bounds": metadata.bounds,
+        "center": metadata.center,
+        "warnings": metadata.warnings,
+        "metrics": metrics,
+        "version": vector_tile_core::version::version(),
+    });
+    println!("{}", serde_json::to_string_pretty(&summary).expect("Failed to serialize summary"));
+}
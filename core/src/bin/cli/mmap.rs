@@ -0,0 +1,78 @@
+// Input file access for the CLI
+// Memory-maps inputs on Unix so large files are parsed without copying them into a Vec first
+
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+/// Contents of an input file (mapped read-only where supported)
+pub enum InputFile {
+    #[cfg(unix)]
+    Mapped { ptr: *mut libc::c_void, len: usize },
+    Owned(Vec<u8>),
+}
+
+impl InputFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let len = metadata.len() as usize;
+        
+        // mmap rejects empty files, and pipes (`<(...)`, /dev/stdin) report no length; read those
+        if len == 0 || !metadata.is_file() {
+            let mut bytes = Vec::new();
+            io::Read::read_to_end(&mut file, &mut bytes)?;
+            return Ok(InputFile::Owned(bytes));
+        }
+        Self::map(&file, len)
+    }
+    
+    #[cfg(unix)]
+    fn map(file: &File, len: usize) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+        
+        // SAFETY: a fresh read-only private mapping of an open file; it is unmapped in Drop
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(InputFile::Mapped { ptr, len })
+    }
+    
+    #[cfg(not(unix))]
+    fn map(mut file: &File, len: usize) -> io::Result<Self> {
+        use std::io::Read;
+        
+        let mut bytes = Vec::with_capacity(len);
+        file.read_to_end(&mut bytes)?;
+        Ok(InputFile::Owned(bytes))
+    }
+}
+
+impl Deref for InputFile {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        match self {
+            // SAFETY: the mapping stays valid for `len` bytes until Drop
+            #[cfg(unix)]
+            InputFile::Mapped { ptr, len } => unsafe { std::slice::from_raw_parts(*ptr as *const u8, *len) },
+            InputFile::Owned(bytes) => bytes,
+        }
+    }
+}
+
+impl Drop for InputFile {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let InputFile::Mapped { ptr, len } = *self {
+            // SAFETY: `ptr`/`len` come from a successful mmap call
+            unsafe {
+                libc::munmap(ptr, len);
+            }
+        }
+    }
+}
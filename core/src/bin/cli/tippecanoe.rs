@@ -0,0 +1,134 @@
+// tippecanoe flag compatibility for the CLI (opt-in with --tippecanoe)
+// Reads common tippecanoe arguments so existing build scripts run with few changes
+
+use std::path::Path;
+
+/// tippecanoe's default per-tile limits, used by `--drop-densest-as-needed`
+pub const MAX_TILE_BYTES: usize = 500_000;
+pub const MAX_TILE_FEATURES: usize = 200_000;
+/// Highest zoom tippecanoe reaches with `--extend-zooms-if-still-dropping`
+pub const EXTEND_ZOOMS_LIMIT: u8 = 24;
+
+/// Settings given with tippecanoe flags
+#[derive(Debug)]
+pub struct Args {
+    pub input: String,
+    pub output: String,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub layer: String,
+    /// `--drop-densest-as-needed`
+    pub drop_densest: bool,
+    /// `--extend-zooms-if-still-dropping`
+    pub extend_zooms: bool,
+}
+
+impl Args {
+    /// Read tippecanoe-style arguments (without the program name)
+    ///
+    /// Accepts `-o out`, `-oout` and `--output=out` forms. Zooms default to 0-14 and the layer to
+    /// the input's file name, as in tippecanoe.
+    pub fn parse(args: &[String]) -> Result<Args, String> {
+        let mut inputs: Vec<&str> = Vec::new();
+        let (mut output, mut min_zoom, mut max_zoom, mut layer) = (None, None, None, None);
+        let (mut drop_densest, mut extend_zooms) = (false, false);
+        
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = split_flag(arg);
+            let mut value = || match inline {
+                Some(value) => Ok(value.to_string()),
+                None => args.next().cloned().ok_or_else(|| format!("{} needs a value", flag)),
+            };
+            match flag {
+                "-o" | "--output" => output = Some(value()?),
+                "-z" | "--maximum-zoom" => max_zoom = Some(zoom(flag, &value()?)?),
+                "-Z" | "--minimum-zoom" => min_zoom = Some(zoom(flag, &value()?)?),
+                "-l" | "--layer" => layer = Some(value()?),
+                "--drop-densest-as-needed" => drop_densest = true,
+                "--extend-zooms-if-still-dropping" => extend_zooms = true,
+                // Outputs are always overwritten
+                "-f" | "--force" => {}
+                _ if !arg.starts_with('-') => inputs.push(arg),
+                _ => return Err(format!("Unsupported tippecanoe flag: {}", arg)),
+            }
+        }
+        
+        let input = match inputs.as_slice() {
+            [input] => input.to_string(),
+            [] => return Err("No input file given".to_string()),
+            _ => return Err("Only one input file is supported".to_string()),
+        };
+        let layer = layer.unwrap_or_else(|| {
+            Path::new(&input).file_stem().map_or("default".to_string(), |stem| stem.to_string_lossy().into_owned())
+        });
+        Ok(Args {
+            output: output.ok_or("No output given (-o)")?,
+            min_zoom: min_zoom.unwrap_or(0),
+            max_zoom: max_zoom.unwrap_or(14),
+            input,
+            layer,
+            drop_densest,
+            extend_zooms,
+        })
+    }
+}
+
+/// Split `--flag=value` and `-xvalue` into the flag and its inline value
+fn split_flag(arg: &str) -> (&str, Option<&str>) {
+    if let Some((flag, value)) = arg.split_once('=').filter(|_| arg.starts_with("--")) {
+        return (flag, Some(value));
+    }
+    // Short flags are a single ASCII letter; anything else is left whole and rejected later
+    match (arg.starts_with("--"), arg.get(..2), arg.get(2..)) {
+        (false, Some(flag), Some(value)) if flag.starts_with('-') && !value.is_empty() => (flag, Some(value)),
+        _ => (arg, None),
+    }
+}
+
+fn zoom(flag: &str, value: &str) -> Result<u8, String> {
+    if value == "g" {
+        return Err(format!("{}g (guessing the zoom) is not supported; give a number", flag));
+    }
+    value.parse().map_err(|_| format!("{} must be a zoom level, got '{}'", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_flag_forms() {
+        for args in [
+            &["-o", "out.pmtiles", "-z", "10", "-Z", "2", "-l", "roads", "in.geojson"][..],
+            &["-oout.pmtiles", "-z10", "-Z2", "-lroads", "in.geojson"],
+            &["--output=out.pmtiles", "--maximum-zoom=10", "--minimum-zoom=2", "--layer=roads", "in.geojson"],
+        ] {
+            let args = parse(args).unwrap();
+            assert_eq!((args.output.as_str(), args.min_zoom, args.max_zoom), ("out.pmtiles", 2, 10));
+            assert_eq!((args.input.as_str(), args.layer.as_str()), ("in.geojson", "roads"));
+        }
+        
+        let args = parse(&["data/parks.geojson", "-o", "out", "-f", "--drop-densest-as-needed"]).unwrap();
+        assert_eq!((args.min_zoom, args.max_zoom, args.layer.as_str()), (0, 14, "parks"));
+        assert!(args.drop_densest && !args.extend_zooms);
+    }
+
+    #[test]
+    fn test_rejected_arguments() {
+        assert_eq!(parse(&["in.geojson", "-o"]).unwrap_err(), "-o needs a value");
+        assert_eq!(parse(&["in.geojson"]).unwrap_err(), "No output given (-o)");
+        assert_eq!(parse(&["-o", "out"]).unwrap_err(), "No input file given");
+        assert_eq!(parse(&["a.geojson", "b.geojson", "-o", "out"]).unwrap_err(), "Only one input file is supported");
+        assert!(parse(&["in.geojson", "-o", "out", "-zg"]).unwrap_err().contains("not supported"));
+        assert!(parse(&["in.geojson", "-o", "out", "-z", "high"]).unwrap_err().contains("must be a zoom level"));
+        assert!(parse(&["in.geojson", "-o", "out", "--coalesce"]).unwrap_err().starts_with("Unsupported tippecanoe flag"));
+        // Non-ASCII flags are rejected rather than split inside a character
+        assert_eq!(parse(&["in.geojson", "-o", "out", "-é"]).unwrap_err(), "Unsupported tippecanoe flag: -é");
+        assert_eq!(split_flag("-éx"), ("-éx", None));
+    }
+}
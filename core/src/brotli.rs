@@ -0,0 +1,475 @@
+// Brotli module
+// Brotli (RFC 7932) compression for brotli-compressed tiles
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Window of 4 MiB (WBITS 22); matches reach back at most this far
+const WINDOW_BITS: u32 = 22;
+const MAX_DISTANCE: usize = (1 << WINDOW_BITS) - 16;
+const MIN_MATCH: usize = 4;
+const HASH_BITS: u32 = 16;
+/// Meta-blocks hold at most 2^24 bytes
+const MAX_META_BLOCK: usize = 1 << 24;
+/// Longest code of the literal, command and distance alphabets
+const MAX_CODE_LENGTH: usize = 15;
+/// Longest code of the code length alphabet
+const MAX_CODE_LENGTH_CODE_LENGTH: usize = 5;
+const LITERAL_BITS: u32 = 8;
+const COMMAND_BITS: u32 = 10;
+/// 16 special codes and 48 distance buckets (no direct codes, no postfix bits)
+const DISTANCE_BITS: u32 = 6;
+
+const INSERT_BASE: [u32; 24] = [0, 1, 2, 3, 4, 5, 6, 8, 10, 14, 18, 26, 34, 50, 66, 98, 130, 194, 322, 578, 1090, 2114, 6210, 22594];
+const INSERT_EXTRA: [u32; 24] = [0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 12, 14, 24];
+const COPY_BASE: [u32; 24] = [2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 18, 22, 30, 38, 54, 70, 102, 134, 198, 326, 582, 1094, 2118];
+const COPY_EXTRA: [u32; 24] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 24];
+/// Order in which the lengths of the code length code are stored
+const CODE_LENGTH_ORDER: [usize; 18] = [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+/// Code length symbol repeating a zero length (3 extra bits)
+const REPEAT_ZERO: usize = 17;
+
+/// Compress into a Brotli stream
+///
+/// Matches are found as in `deflate::deflate` (the latest earlier position with the same four
+/// bytes), and each meta-block gets one prefix code per alphabet built from its symbol counts.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    // WBITS 22
+    out.bits(1, 1);
+    out.bits(WINDOW_BITS - 17, 3);
+    if data.is_empty() {
+        // Last and empty
+        out.bits(1, 1);
+        out.bits(1, 1);
+        return out.finish();
+    }
+
+    let mut latest = vec![usize::MAX; 1 << HASH_BITS];
+    let mut start = 0;
+    while start < data.len() {
+        let end = (start + MAX_META_BLOCK).min(data.len());
+        let commands = find_commands(data, start, end, &mut latest);
+        write_meta_block(&mut out, &data[start..end], &commands, end == data.len());
+        start = end;
+    }
+    out.finish()
+}
+
+/// `insert` literals followed by a copy of `copy` bytes from `distance` bytes back (no copy in
+/// a final command with `copy` 0)
+struct Command {
+    insert: usize,
+    copy: usize,
+    distance: usize,
+}
+
+/// Commands producing `data[start..end]`; matches may reach into earlier meta-blocks
+fn find_commands(data: &[u8], start: usize, end: usize, latest: &mut [usize]) -> Vec<Command> {
+    let hash = |at: usize| {
+        let prefix = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        (prefix.wrapping_mul(0x1E35_A7BD) >> (32 - HASH_BITS)) as usize
+    };
+    // Positions up to here have four bytes to hash
+    let hashed_end = data.len().saturating_sub(MIN_MATCH - 1);
+    let mut commands = Vec::new();
+    let mut insert = 0;
+    let mut at = start;
+    while at < end {
+        let mut length = 0;
+        if at < hashed_end {
+            let slot = hash(at);
+            let candidate = latest[slot];
+            latest[slot] = at;
+            if candidate != usize::MAX && at - candidate <= MAX_DISTANCE {
+                // Copies stay inside the meta-block
+                length = (0..end - at).take_while(|&i| data[candidate + i] == data[at + i]).count();
+                if length >= MIN_MATCH {
+                    commands.push(Command { insert, copy: length, distance: at - candidate });
+                    insert = 0;
+                }
+            }
+        }
+        if length < MIN_MATCH {
+            insert += 1;
+            at += 1;
+            continue;
+        }
+        // Positions inside the match can start later matches
+        for inside in at + 1..(at + length).min(hashed_end) {
+            latest[hash(inside)] = inside;
+        }
+        at += length;
+    }
+    if insert > 0 {
+        commands.push(Command { insert, copy: 0, distance: 0 });
+    }
+    commands
+}
+
+fn write_meta_block(out: &mut BitWriter, block: &[u8], commands: &[Command], last: bool) {
+    // Symbols of each command, then the prefix codes built from their counts
+    let mut literal_counts = vec![0u32; 256];
+    let mut command_counts = vec![0u32; 704];
+    let mut distance_counts = vec![0u32; 64];
+    let mut symbols = Vec::with_capacity(commands.len());
+    let mut at = 0;
+    for command in commands {
+        let insert = length_code(&INSERT_BASE, command.insert);
+        let copy = length_code(&COPY_BASE, command.copy.max(2));
+        let distance = (command.copy > 0).then(|| distance_code(command.distance));
+        let symbol = command_symbol(insert, copy);
+        command_counts[symbol] += 1;
+        for &byte in &block[at..at + command.insert] {
+            literal_counts[byte as usize] += 1;
+        }
+        if let Some((code, _, _)) = distance {
+            distance_counts[code] += 1;
+        }
+        symbols.push((symbol, insert, copy, distance));
+        at += command.insert + command.copy;
+    }
+    let literals = PrefixCode::new(&literal_counts);
+    let command_code = PrefixCode::new(&command_counts);
+    let distances = PrefixCode::new(&distance_counts);
+
+    out.bits(last as u32, 1);
+    if last {
+        // ISLASTEMPTY
+        out.bits(0, 1);
+    }
+    let nibbles = match block.len() - 1 {
+        0..=0xFFFF => 4,
+        0x1_0000..=0xF_FFFF => 5,
+        _ => 6,
+    };
+    out.bits(nibbles - 4, 2);
+    out.bits((block.len() - 1) as u32, nibbles * 4);
+    if !last {
+        // ISUNCOMPRESSED
+        out.bits(0, 1);
+    }
+    // One block type per category, no postfix bits or direct distances, one literal context
+    // mode and one prefix code per category (no context maps)
+    out.bits(0, 3);
+    out.bits(0, 2);
+    out.bits(0, 4);
+    out.bits(0, 2);
+    out.bits(0, 2);
+    literals.write(out, LITERAL_BITS);
+    command_code.write(out, COMMAND_BITS);
+    distances.write(out, DISTANCE_BITS);
+
+    let mut at = 0;
+    for (command, (symbol, insert, copy, distance)) in commands.iter().zip(symbols) {
+        command_code.symbol(out, symbol);
+        out.bits(command.insert as u32 - INSERT_BASE[insert], INSERT_EXTRA[insert]);
+        out.bits(command.copy.max(2) as u32 - COPY_BASE[copy], COPY_EXTRA[copy]);
+        for &byte in &block[at..at + command.insert] {
+            literals.symbol(out, byte as usize);
+        }
+        if let Some((code, extra, extra_bits)) = distance {
+            distances.symbol(out, code);
+            out.bits(extra, extra_bits);
+        }
+        at += command.insert + command.copy;
+    }
+}
+
+/// Insert or copy length code of `length`
+fn length_code(base: &[u32; 24], length: usize) -> usize {
+    base.partition_point(|&start| start as usize <= length) - 1
+}
+
+/// Insert-and-copy symbol with an explicit distance (the cells after the first 128 symbols)
+fn command_symbol(insert: usize, copy: usize) -> usize {
+    let cell = match (insert >> 3, copy >> 3) {
+        (0, 0) => 128,
+        (0, 1) => 192,
+        (1, 0) => 256,
+        (1, 1) => 320,
+        (0, _) => 384,
+        (2, 0) => 448,
+        (1, _) => 512,
+        (_, 1) => 576,
+        _ => 640,
+    };
+    cell + ((insert & 7) << 3) + (copy & 7)
+}
+
+/// Distance symbol, extra bits and their count for distances from 1
+///
+/// Symbol 16 + 2 * (n - 1) + h covers `((2 + h) << n) - 3` onwards with `n` extra bits.
+fn distance_code(distance: usize) -> (usize, u32, u32) {
+    let x = distance as u64 + 3;
+    let n = 62 - x.leading_zeros();
+    let high = (x >> n) & 1;
+    (16 + 2 * (n as usize - 1) + high as usize, (x - ((2 + high) << n)) as u32, n)
+}
+
+/// Canonical prefix code of an alphabet
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u32>,
+    /// Symbol of a code with at most one used symbol
+    only: usize,
+}
+
+impl PrefixCode {
+    fn new(counts: &[u32]) -> Self {
+        Self::limited(counts, MAX_CODE_LENGTH)
+    }
+
+    /// Code lengths of at most `limit` bits; counts are raised to a doubling floor until the
+    /// Huffman tree fits, as the reference encoder does
+    ///
+    /// A single used symbol gets length 0 (it is written as a simple code that takes no bits).
+    fn limited(counts: &[u32], limit: usize) -> Self {
+        let used: Vec<usize> = (0..counts.len()).filter(|&symbol| counts[symbol] > 0).collect();
+        let mut lengths = vec![0u8; counts.len()];
+        let mut floor = 1;
+        while used.len() > 1 {
+            let mut heap: BinaryHeap<Reverse<(u64, usize)>> =
+                used.iter().enumerate().map(|(node, &symbol)| Reverse((counts[symbol].max(floor) as u64, node))).collect();
+            let mut parent = vec![usize::MAX; used.len()];
+            while let (Some(Reverse((a, x))), Some(Reverse((b, y)))) = (heap.pop(), heap.pop()) {
+                let node = parent.len();
+                parent.push(usize::MAX);
+                parent[x] = node;
+                parent[y] = node;
+                heap.push(Reverse((a + b, node)));
+            }
+            let depth = |mut node: usize| {
+                let mut depth = 0;
+                while parent[node] != usize::MAX {
+                    node = parent[node];
+                    depth += 1;
+                }
+                depth
+            };
+            let depths: Vec<usize> = (0..used.len()).map(depth).collect();
+            if depths.iter().all(|&depth| depth <= limit) {
+                for (&symbol, depth) in used.iter().zip(depths) {
+                    lengths[symbol] = depth as u8;
+                }
+                break;
+            }
+            floor *= 2;
+        }
+
+        // Shorter codes first, symbols in order within a length
+        let mut count = [0u32; MAX_CODE_LENGTH + 1];
+        for &length in &lengths {
+            count[length as usize] += 1;
+        }
+        count[0] = 0;
+        let mut next = [0u32; MAX_CODE_LENGTH + 1];
+        let mut code = 0;
+        for bits in 1..=MAX_CODE_LENGTH {
+            code = (code + count[bits - 1]) << 1;
+            next[bits] = code;
+        }
+        let codes = lengths
+            .iter()
+            .map(|&length| {
+                let code = next[length as usize];
+                next[length as usize] += 1;
+                code
+            })
+            .collect();
+        Self { lengths, codes, only: used.first().copied().unwrap_or(0) }
+    }
+
+    fn symbol(&self, out: &mut BitWriter, symbol: usize) {
+        out.code(self.codes[symbol], self.lengths[symbol] as u32);
+    }
+
+    /// Write the code: a simple code for at most one used symbol, otherwise the code lengths
+    /// compressed with a code length code
+    fn write(&self, out: &mut BitWriter, alphabet_bits: u32) {
+        let Some(last) = self.lengths.iter().rposition(|&length| length > 0) else {
+            // HSKIP 1 (simple), one symbol
+            out.bits(1, 2);
+            out.bits(0, 2);
+            out.bits(self.only as u32, alphabet_bits);
+            return;
+        };
+
+        // Code length symbols with their extra bits
+        let mut tokens: Vec<(usize, u32)> = Vec::new();
+        let mut symbol = 0;
+        while symbol <= last {
+            let length = self.lengths[symbol] as usize;
+            let run = self.lengths[symbol..=last].iter().take_while(|&&other| other as usize == length).count();
+            match length {
+                0 => repeat_zeros(&mut tokens, run),
+                _ => tokens.extend(std::iter::repeat_n((length, 0), run)),
+            }
+            symbol += run;
+        }
+        let mut counts = [0u32; 18];
+        for &(token, _) in &tokens {
+            counts[token] += 1;
+        }
+        let mut code_lengths = PrefixCode::limited(&counts, MAX_CODE_LENGTH_CODE_LENGTH);
+        // A single code length symbol is stored with length 1 and written with no bits
+        let single = counts.iter().filter(|&&count| count > 0).count() == 1;
+        let stored: Vec<u8> = match single {
+            true => counts.iter().map(|&count| (count > 0) as u8).collect(),
+            false => code_lengths.lengths.clone(),
+        };
+
+        // HSKIP 0; lengths in storage order until the code is complete
+        out.bits(0, 2);
+        let mut space = 32;
+        for &token in &CODE_LENGTH_ORDER {
+            let length = stored[token];
+            let (value, bits) = [(0, 2), (7, 4), (3, 3), (2, 2), (1, 2), (15, 4)][length as usize];
+            out.bits(value, bits);
+            if length > 0 {
+                space -= 32 >> length;
+                if space == 0 {
+                    break;
+                }
+            }
+        }
+        if single {
+            code_lengths.lengths.iter_mut().for_each(|length| *length = 0);
+        }
+        for (token, extra) in tokens {
+            code_lengths.symbol(out, token);
+            if token == REPEAT_ZERO {
+                out.bits(extra, 3);
+            }
+        }
+    }
+}
+
+/// Tokens for `run` zero lengths: plain zeros below three, otherwise a chain of repeat codes
+/// (each code after the first multiplies the count so far by eight), as the reference encoder writes them
+fn repeat_zeros(tokens: &mut Vec<(usize, u32)>, mut run: usize) {
+    if run == 11 {
+        tokens.push((0, 0));
+        run -= 1;
+    }
+    if run < 3 {
+        tokens.extend(std::iter::repeat_n((0, 0), run));
+        return;
+    }
+    let start = tokens.len();
+    run -= 3;
+    loop {
+        tokens.push((REPEAT_ZERO, (run & 7) as u32));
+        run >>= 3;
+        if run == 0 {
+            break;
+        }
+        run -= 1;
+    }
+    tokens[start..].reverse();
+}
+
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    /// Append the low `count` bits of `value`, least significant first
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.out.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Prefix codes are packed most significant bit first
+    fn code(&mut self, code: u32, length: u32) {
+        if length > 0 {
+            self.bits(code.reverse_bits() >> (32 - length), length);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.out.push(self.bit_buffer as u8);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process::{Command as Process, Stdio};
+
+    /// Decompress with Node's zlib (the reference decoder), or None when node is not installed
+    fn node_decompress(data: &[u8]) -> Option<Vec<u8>> {
+        let script = "process.stdout.write(require('zlib').brotliDecompressSync(require('fs').readFileSync(0)))";
+        let mut child = Process::new("node").args(["-e", script]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().ok()?;
+        child.stdin.take().unwrap().write_all(data).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "node could not decompress the stream");
+        Some(output.stdout)
+    }
+
+    fn samples() -> Vec<Vec<u8>> {
+        let mut noise = Vec::new();
+        let mut state = 0x2545_F491u32;
+        for _ in 0..100_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            noise.push(state as u8);
+        }
+        let text = b"{\"type\":\"Feature\",\"properties\":{\"name\":\"Tokyo\"}}".repeat(2_000);
+        // Long matches, far matches and every byte value
+        let mut mixed = noise[..5_000].to_vec();
+        mixed.extend(vec![7u8; 70_000]);
+        mixed.extend_from_slice(&noise[..5_000]);
+        mixed.extend((0..=255u8).cycle().take(3_000));
+        vec![Vec::new(), b"a".to_vec(), b"abc".to_vec(), vec![0; 11], vec![0; 3_000_000], text, noise, mixed]
+    }
+
+    #[test]
+    fn test_reference_decoder() {
+        for sample in samples() {
+            let compressed = compress(&sample);
+            let Some(decompressed) = node_decompress(&compressed) else { return };
+            assert_eq!(decompressed, sample, "{} bytes", sample.len());
+        }
+        // Several meta-blocks with matches into the previous one
+        let mut large = (0..MAX_META_BLOCK + 1_000).map(|i| (i % 251) as u8 ^ (i >> 12) as u8).collect::<Vec<u8>>();
+        large.extend_from_slice(&samples()[6][..20_000]);
+        assert_eq!(node_decompress(&compress(&large)).unwrap(), large);
+    }
+
+    #[test]
+    fn test_compresses() {
+        assert_eq!(compress(b""), vec![0x3B]);
+        assert!(compress(&vec![0; 3_000_000]).len() < 100);
+        let text = b"{\"type\":\"Feature\",\"properties\":{\"name\":\"Tokyo\"}}".repeat(2_000);
+        assert!(compress(&text).len() < text.len() / 50);
+    }
+
+    #[test]
+    fn test_codes() {
+        assert_eq!(distance_code(1), (16, 0, 1));
+        assert_eq!(distance_code(3), (17, 0, 1));
+        assert_eq!(distance_code(5), (18, 0, 2));
+        assert_eq!(distance_code(MAX_DISTANCE).0, 55);
+        assert_eq!(command_symbol(0, 0), 128);
+        assert_eq!(command_symbol(23, 23), 703);
+        assert_eq!(length_code(&INSERT_BASE, 22_594 + (1 << 24) - 1), 23);
+        assert_eq!(length_code(&COPY_BASE, 11), 8);
+        // Skewed counts still fit 15 bits
+        let counts: Vec<u32> = (0..40).map(|i| 1 << (i % 31)).collect();
+        assert!(PrefixCode::new(&counts).lengths.iter().all(|&length| (1..=15).contains(&length)));
+    }
+}
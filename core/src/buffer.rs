@@ -0,0 +1,352 @@
+// Geometry buffering module
+// Expand/shrink geometries by a distance in meters before tiling
+
+use crate::geojson_parser::{polygon_geometry, Feature, GeometryType};
+use crate::projection::{lonlat_to_meters, meters_to_lonlat};
+use crate::Warning;
+use geo_types::{Coord, LineString, Polygon};
+use std::f64::consts::PI;
+
+/// Buffer settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Buffer {
+    /// Buffer distance in meters (negative values shrink polygons)
+    pub distance: f64,
+    /// Segments used to approximate a full circle (point buffers and line caps)
+    pub circle_segments: usize,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self {
+            distance: 0.0,
+            circle_segments: 32,
+        }
+    }
+}
+
+/// Longest miter allowed at sharp corners, as a multiple of the buffer distance
+const MITER_LIMIT: f64 = 4.0;
+
+type Point = (f64, f64);
+
+/// Buffer all features (points and lines become polygons)
+///
+/// The buffer is computed in WebMercator space, with the distance scaled by the
+/// local scale factor at each feature, and corners are mitered rather than rounded.
+/// This is an approximation: tight bends in lines can produce self-overlapping outlines.
+pub fn buffer_features(features: &mut Vec<Feature>, buffer: &Buffer, warnings: &mut Vec<Warning>) {
+    if buffer.distance == 0.0 {
+        return;
+    }
+    
+    let mut index = 0;
+    features.retain_mut(|feature| {
+        let feature_index = index;
+        index += 1;
+        
+        match buffer_geometry(&feature.geometry, buffer) {
+            Some(geometry) => {
+                feature.geometry = geometry;
+                true
+            }
+            None => {
+                warnings.push(Warning {
+                    feature_index: Some(feature_index),
+                    message: format!("Feature removed: it collapsed under a {} m buffer", buffer.distance),
+                });
+                false
+            }
+        }
+    });
+}
+
+fn buffer_geometry(geometry: &GeometryType, buffer: &Buffer) -> Option<GeometryType> {
+    let segments = buffer.circle_segments.max(8);
+    
+    match geometry {
+        GeometryType::Point(point) => {
+            if buffer.distance < 0.0 {
+                return None;
+            }
+            let center = lonlat_to_meters(point.x(), point.y());
+            let radius = scaled_distance(buffer.distance, point.y());
+            Some(GeometryType::Polygon(Polygon::new(to_lonlat(&circle(center, radius, segments)), Vec::new())))
+        }
+        GeometryType::LineString(line) => {
+            if buffer.distance < 0.0 || line.0.is_empty() {
+                return None;
+            }
+            let coords = to_meters(&line.0);
+            let distance = scaled_distance(buffer.distance, mean_lat(&line.0));
+            Some(GeometryType::Polygon(Polygon::new(to_lonlat(&line_outline(&coords, distance, segments)), Vec::new())))
+        }
+        GeometryType::Polygon(polygon) => buffer_polygon(polygon, buffer).map(GeometryType::Polygon),
+        // Polygons that shrink away are dropped; the feature goes when none is left
+        GeometryType::MultiPolygon(polygons) => {
+            let buffered: Vec<Polygon<f64>> = polygons.iter().filter_map(|polygon| buffer_polygon(polygon, buffer)).collect();
+            (!buffered.is_empty()).then(|| polygon_geometry(buffered))
+        }
+    }
+}
+
+fn buffer_polygon(polygon: &Polygon<f64>, buffer: &Buffer) -> Option<Polygon<f64>> {
+    let distance = scaled_distance(buffer.distance, mean_lat(&polygon.exterior().0));
+    let exterior = offset_ring(&to_meters(&polygon.exterior().0), distance, false)?;
+    // Holes shrink when the polygon grows; holes that close up disappear
+    let interiors = polygon
+        .interiors()
+        .iter()
+        .filter_map(|ring| offset_ring(&to_meters(&ring.0), distance, true))
+        .map(|ring| to_lonlat(&ring))
+        .collect();
+    Some(Polygon::new(to_lonlat(&exterior), interiors))
+}
+
+/// Ground meters to WebMercator meters at the given latitude
+fn scaled_distance(distance: f64, lat: f64) -> f64 {
+    distance / (lat * PI / 180.0).cos().max(1e-6)
+}
+
+fn mean_lat(coords: &[Coord<f64>]) -> f64 {
+    if coords.is_empty() {
+        return 0.0;
+    }
+    coords.iter().map(|c| c.y).sum::<f64>() / coords.len() as f64
+}
+
+fn to_meters(coords: &[Coord<f64>]) -> Vec<Point> {
+    coords.iter().map(|c| lonlat_to_meters(c.x, c.y)).collect()
+}
+
+fn to_lonlat(coords: &[Point]) -> LineString<f64> {
+    LineString::from(
+        coords
+            .iter()
+            .map(|&(mx, my)| {
+                let (x, y) = meters_to_lonlat(mx, my);
+                Coord { x, y }
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Closed counter-clockwise circle
+fn circle(center: Point, radius: f64, segments: usize) -> Vec<Point> {
+    let mut ring: Vec<Point> = (0..segments)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / segments as f64;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect();
+    ring.push(ring[0]);
+    ring
+}
+
+/// Closed counter-clockwise outline of a line buffered by `distance` (round caps)
+fn line_outline(coords: &[Point], distance: f64, segments: usize) -> Vec<Point> {
+    let mut coords = coords.to_vec();
+    coords.dedup();
+    if coords.len() < 2 {
+        return circle(coords[0], distance, segments);
+    }
+    
+    // Right side forward, then the left side backward, joined by semicircular caps
+    let reversed: Vec<Point> = coords.iter().rev().copied().collect();
+    let mut ring = offset_path(&coords, distance);
+    ring.extend(cap(coords[coords.len() - 1], coords[coords.len() - 2], distance, segments));
+    ring.extend(offset_path(&reversed, distance));
+    ring.extend(cap(coords[0], coords[1], distance, segments));
+    ring.push(ring[0]);
+    ring
+}
+
+/// Semicircle around `end`, facing away from `previous` (excluding both side points)
+fn cap(end: Point, previous: Point, distance: f64, segments: usize) -> Vec<Point> {
+    let direction = (end.1 - previous.1).atan2(end.0 - previous.0);
+    let steps = segments / 2;
+    (1..steps)
+        .map(|i| {
+            // Sweep counter-clockwise from the right side to the left side
+            let angle = direction - PI / 2.0 + PI * i as f64 / steps as f64;
+            (end.0 + distance * angle.cos(), end.1 + distance * angle.sin())
+        })
+        .collect()
+}
+
+/// Offset an open path to its right by `distance` (mitered joints)
+fn offset_path(coords: &[Point], distance: f64) -> Vec<Point> {
+    let n = coords.len();
+    (0..n)
+        .map(|i| {
+            let before = if i > 0 { Some(right_normal(coords[i - 1], coords[i])) } else { None };
+            let after = if i + 1 < n { Some(right_normal(coords[i], coords[i + 1])) } else { None };
+            offset_vertex(coords[i], before, after, distance)
+        })
+        .collect()
+}
+
+/// Offset a closed ring outward (or, for holes, into the hole) by `distance`
+///
+/// Returns `None` when the ring collapses (its edges invert or it degenerates).
+fn offset_ring(ring: &[Point], distance: f64, is_hole: bool) -> Option<Vec<Point>> {
+    let mut open: Vec<Point> = ring.to_vec();
+    if open.len() > 1 && open.first() == open.last() {
+        open.pop();
+    }
+    open.dedup();
+    if open.len() < 3 {
+        return None;
+    }
+    
+    let area = signed_area(&open);
+    // The right-hand normal points away from a counter-clockwise ring's interior
+    let mut distance = if area > 0.0 { distance } else { -distance };
+    if is_hole {
+        distance = -distance;
+    }
+    
+    let n = open.len();
+    let mut result: Vec<Point> = (0..n)
+        .map(|i| {
+            let before = right_normal(open[(i + n - 1) % n], open[i]);
+            let after = right_normal(open[i], open[(i + 1) % n]);
+            offset_vertex(open[i], Some(before), Some(after), distance)
+        })
+        .collect();
+    
+    // Shrinking past the ring's size inverts its edges
+    let alignment: f64 = (0..n)
+        .map(|i| {
+            let (a, b) = (open[i], open[(i + 1) % n]);
+            let (c, d) = (result[i], result[(i + 1) % n]);
+            (b.0 - a.0) * (d.0 - c.0) + (b.1 - a.1) * (d.1 - c.1)
+        })
+        .sum();
+    if alignment <= 0.0 || signed_area(&result).signum() != area.signum() {
+        return None;
+    }
+    
+    result.push(result[0]);
+    Some(result)
+}
+
+/// Move a vertex along the bisector of its adjacent edge normals
+fn offset_vertex(p: Point, before: Option<Point>, after: Option<Point>, distance: f64) -> Point {
+    let (nx, ny) = match (before, after) {
+        (Some(a), Some(b)) => {
+            let dot = a.0 * b.0 + a.1 * b.1;
+            if 1.0 + dot < 1e-9 {
+                // The path turns back on itself
+                a
+            } else {
+                // Miter vector of length 1 / cos(half the turn angle)
+                let (mx, my) = ((a.0 + b.0) / (1.0 + dot), (a.1 + b.1) / (1.0 + dot));
+                let length = (mx * mx + my * my).sqrt();
+                if length > MITER_LIMIT {
+                    (mx / length * MITER_LIMIT, my / length * MITER_LIMIT)
+                } else {
+                    (mx, my)
+                }
+            }
+        }
+        (Some(n), None) | (None, Some(n)) => n,
+        (None, None) => (0.0, 0.0),
+    };
+    (p.0 + nx * distance, p.1 + ny * distance)
+}
+
+/// Unit normal pointing to the right of the segment `a`-`b`
+fn right_normal(a: Point, b: Point) -> Point {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return (0.0, 0.0);
+    }
+    (dy / length, -dx / length)
+}
+
+/// Signed area of an open ring (positive when counter-clockwise)
+fn signed_area(ring: &[Point]) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..ring.len() {
+        let (x1, y1) = ring[i];
+        let (x2, y2) = ring[(i + 1) % ring.len()];
+        sum += x1 * y2 - x2 * y1;
+    }
+    sum / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point as GeoPoint;
+
+    fn feature(geometry: GeometryType) -> Feature {
+        Feature::new(geometry, serde_json::Map::new())
+    }
+
+    fn exterior_area(feature: &Feature) -> f64 {
+        match &feature.geometry {
+            GeometryType::Polygon(polygon) => signed_area(&to_meters(&polygon.exterior().0)).abs(),
+            _ => panic!("Expected Polygon geometry"),
+        }
+    }
+
+    fn square() -> GeometryType {
+        let ring: Vec<Coord<f64>> = [(0.0, 0.0), (0.01, 0.0), (0.01, 0.01), (0.0, 0.01), (0.0, 0.0)]
+            .iter()
+            .map(|&(x, y)| Coord { x, y })
+            .collect();
+        GeometryType::Polygon(Polygon::new(LineString::from(ring), Vec::new()))
+    }
+
+    #[test]
+    fn test_point_buffer_becomes_circle() {
+        let mut features = vec![feature(GeometryType::Point(GeoPoint::new(0.0, 0.0)))];
+        let buffer = Buffer { distance: 100.0, ..Default::default() };
+        
+        buffer_features(&mut features, &buffer, &mut Vec::new());
+        
+        let area = exterior_area(&features[0]);
+        assert!((area - PI * 100.0 * 100.0).abs() / area < 0.02);
+    }
+
+    #[test]
+    fn test_line_buffer_surrounds_line() {
+        let line = LineString::from(vec![Coord { x: 0.0, y: 0.0 }, Coord { x: 0.01, y: 0.0 }]);
+        let mut features = vec![feature(GeometryType::LineString(line))];
+        let buffer = Buffer { distance: 50.0, ..Default::default() };
+        
+        buffer_features(&mut features, &buffer, &mut Vec::new());
+        
+        // Roughly a 1.1 km x 100 m stadium
+        let area = exterior_area(&features[0]);
+        let length = lonlat_to_meters(0.01, 0.0).0;
+        assert!(area > length * 100.0 && area < length * 100.0 + PI * 50.0 * 50.0 * 1.01);
+    }
+
+    #[test]
+    fn test_polygon_grow_and_shrink() {
+        let original = exterior_area(&feature(square()));
+        
+        let mut grown = vec![feature(square())];
+        buffer_features(&mut grown, &Buffer { distance: 100.0, ..Default::default() }, &mut Vec::new());
+        let mut shrunk = vec![feature(square())];
+        buffer_features(&mut shrunk, &Buffer { distance: -100.0, ..Default::default() }, &mut Vec::new());
+        
+        assert!(exterior_area(&grown[0]) > original);
+        assert!(exterior_area(&shrunk[0]) < original);
+    }
+
+    #[test]
+    fn test_collapsed_polygon_is_removed() {
+        let mut features = vec![feature(square())];
+        let mut warnings = Vec::new();
+        
+        buffer_features(&mut features, &Buffer { distance: -5000.0, ..Default::default() }, &mut warnings);
+        
+        assert!(features.is_empty());
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+// Geometry clipping module
+// Clip lines and polygon rings to an axis-aligned square (tile extent plus buffer)
+
+type Point = (f64, f64);
+
+/// Square clip region `[min, max]` on both axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipBox {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ClipBox {
+    fn contains(&self, (x, y): Point) -> bool {
+        x >= self.min && x <= self.max && y >= self.min && y <= self.max
+    }
+}
+
+/// Clip a point (kept only if inside the box)
+pub fn clip_point(point: Point, clip: &ClipBox) -> Option<Point> {
+    clip.contains(point).then_some(point)
+}
+
+/// Clip a line, returning the parts inside the box (a line can leave and re-enter the box)
+pub fn clip_line(points: &[Point], clip: &ClipBox) -> Vec<Vec<Point>> {
+    let mut parts = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    
+    for segment in points.windows(2) {
+        match clip_segment(segment[0], segment[1], clip) {
+            Some((a, b)) => {
+                if current.last() != Some(&a) {
+                    if current.len() > 1 {
+                        parts.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                    current.push(a);
+                }
+                current.push(b);
+                // The segment was cut short, so the line leaves the box here
+                if b != segment[1] {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            None => {
+                if current.len() > 1 {
+                    parts.push(std::mem::take(&mut current));
+                }
+                current.clear();
+            }
+        }
+    }
+    if current.len() > 1 {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Liang-Barsky segment clipping
+fn clip_segment(a: Point, b: Point, clip: &ClipBox) -> Option<(Point, Point)> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    
+    for (p, q) in [
+        (-dx, a.0 - clip.min),
+        (dx, clip.max - a.0),
+        (-dy, a.1 - clip.min),
+        (dy, clip.max - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    
+    let at = |t: f64| if t == 0.0 { a } else if t == 1.0 { b } else { (a.0 + t * dx, a.1 + t * dy) };
+    Some((at(t0), at(t1)))
+}
+
+/// Box edge used by ring clipping (y grows downward in tile coordinates)
+#[derive(Clone, Copy)]
+enum Edge {
+    Left(f64),
+    Right(f64),
+    Top(f64),
+    Bottom(f64),
+}
+
+impl Edge {
+    fn inside(self, (x, y): Point) -> bool {
+        match self {
+            Edge::Left(v) => x >= v,
+            Edge::Right(v) => x <= v,
+            Edge::Top(v) => y >= v,
+            Edge::Bottom(v) => y <= v,
+        }
+    }
+
+    /// Intersection of segment `a`-`b` (which crosses the edge) with the edge line
+    fn intersect(self, a: Point, b: Point) -> Point {
+        match self {
+            Edge::Left(v) | Edge::Right(v) => (v, a.1 + (b.1 - a.1) * (v - a.0) / (b.0 - a.0)),
+            Edge::Top(v) | Edge::Bottom(v) => (a.0 + (b.0 - a.0) * (v - a.1) / (b.1 - a.1), v),
+        }
+    }
+}
+
+/// Clip a closed ring (Sutherland-Hodgman); returns an empty ring if nothing is left
+///
+/// The result is closed (first point repeated at the end) and keeps the input orientation.
+pub fn clip_ring(ring: &[Point], clip: &ClipBox) -> Vec<Point> {
+    let open = match ring.split_last() {
+        Some((last, rest)) if rest.first() == Some(last) => rest,
+        _ => ring,
+    };
+    
+    let mut output = open.to_vec();
+    for edge in [Edge::Left(clip.min), Edge::Right(clip.max), Edge::Top(clip.min), Edge::Bottom(clip.max)] {
+        if output.is_empty() {
+            break;
+        }
+        let input = std::mem::take(&mut output);
+        let mut previous = input[input.len() - 1];
+        for &point in &input {
+            match (edge.inside(point), edge.inside(previous)) {
+                (true, true) => output.push(point),
+                (true, false) => {
+                    output.push(edge.intersect(previous, point));
+                    output.push(point);
+                }
+                (false, true) => output.push(edge.intersect(previous, point)),
+                (false, false) => {}
+            }
+            previous = point;
+        }
+    }
+    
+    output.dedup();
+    if output.len() > 1 && output.first() == output.last() {
+        output.pop();
+    }
+    if output.len() < 3 {
+        return Vec::new();
+    }
+    output.push(output[0]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIP: ClipBox = ClipBox { min: 0.0, max: 10.0 };
+
+    #[test]
+    fn test_clip_line_splits_on_reentry() {
+        let line = [(-5.0, 5.0), (5.0, 5.0), (5.0, 20.0), (8.0, 20.0), (8.0, 5.0)];
+        let parts = clip_line(&line, &CLIP);
+        
+        assert_eq!(parts, vec![
+            vec![(0.0, 5.0), (5.0, 5.0), (5.0, 10.0)],
+            vec![(8.0, 10.0), (8.0, 5.0)],
+        ]);
+    }
+
+    #[test]
+    fn test_clip_line_outside() {
+        assert!(clip_line(&[(20.0, 20.0), (30.0, 20.0)], &CLIP).is_empty());
+    }
+
+    #[test]
+    fn test_clip_ring() {
+        let ring = [(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0), (5.0, 5.0)];
+        let clipped = clip_ring(&ring, &CLIP);
+        
+        assert_eq!(clipped, vec![(5.0, 10.0), (5.0, 5.0), (10.0, 5.0), (10.0, 10.0), (5.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_clip_ring_outside() {
+        let ring = [(20.0, 20.0), (30.0, 20.0), (30.0, 30.0), (20.0, 20.0)];
+        assert!(clip_ring(&ring, &CLIP).is_empty());
+    }
+
+    #[test]
+    fn test_clip_point() {
+        assert_eq!(clip_point((1.0, 2.0), &CLIP), Some((1.0, 2.0)));
+        assert_eq!(clip_point((-1.0, 2.0), &CLIP), None);
+    }
+}
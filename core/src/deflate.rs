@@ -0,0 +1,146 @@
+// Deflate module
+// DEFLATE (RFC 1951) compression for gzip-compressed PMTiles tiles
+
+use crate::inflate::{DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+const END_OF_BLOCK: u16 = 256;
+
+/// Compress into a gzip member (RFC 1952)
+pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+    // No file name or modification time; the OS is unknown
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend(deflate(data));
+    out.extend_from_slice(&crate::sink::crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Compress into a raw DEFLATE stream
+///
+/// One block with the fixed Huffman codes; each match is the longest run shared with the
+/// latest earlier position that starts with the same three bytes.
+pub(crate) fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    // Last block, fixed codes
+    out.bits(1, 1);
+    out.bits(1, 2);
+    
+    let hash = |at: usize| {
+        let prefix = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], 0]);
+        (prefix.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    };
+    // Positions up to here have three bytes to hash
+    let hashed_end = data.len().saturating_sub(MIN_MATCH - 1);
+    let mut latest = vec![usize::MAX; 1 << HASH_BITS];
+    let mut at = 0;
+    while at < data.len() {
+        let mut length = 0;
+        if at < hashed_end {
+            let slot = hash(at);
+            let candidate = latest[slot];
+            latest[slot] = at;
+            if candidate != usize::MAX && at - candidate <= WINDOW {
+                let longest = (data.len() - at).min(MAX_MATCH);
+                length = (0..longest).take_while(|&i| data[candidate + i] == data[at + i]).count();
+                if length >= MIN_MATCH {
+                    out.copy(length, at - candidate);
+                }
+            }
+        }
+        if length < MIN_MATCH {
+            out.symbol(data[at] as u16);
+            at += 1;
+            continue;
+        }
+        // Positions inside the match can start later matches
+        for inside in at + 1..(at + length).min(hashed_end) {
+            latest[hash(inside)] = inside;
+        }
+        at += length;
+    }
+    out.symbol(END_OF_BLOCK);
+    out.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    /// Append the low `count` bits of `value`, least significant first
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.out.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+    
+    /// Huffman codes are packed most significant bit first
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+    
+    /// Literal/length symbol in the fixed code
+    fn symbol(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xC0 + symbol - 280, 8),
+        }
+    }
+    
+    /// Copy of `length` bytes from `distance` bytes back
+    fn copy(&mut self, length: usize, distance: usize) {
+        let index = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap();
+        self.symbol(257 + index as u16);
+        self.bits((length - LENGTH_BASE[index] as usize) as u32, LENGTH_EXTRA[index] as u32);
+        let index = DISTANCE_BASE.iter().rposition(|&base| base as usize <= distance).unwrap();
+        self.code(index as u32, 5);
+        self.bits((distance - DISTANCE_BASE[index] as usize) as u32, DISTANCE_EXTRA[index] as u32);
+    }
+    
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.out.push(self.bit_buffer as u8);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inflate::{gunzip, inflate};
+
+    #[test]
+    fn test_deflate_roundtrip() {
+        let repeated: Vec<u8> = b"layer roads highway primary ".iter().copied().cycle().take(100_000).collect();
+        let noisy: Vec<u8> = (0..70_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        for data in [&b""[..], b"a", b"aaaaaaaaaa", b"hello hello hello hello", &repeated, &noisy] {
+            assert_eq!(inflate(&deflate(data)).unwrap(), data);
+            assert_eq!(gunzip(&gzip(data)).unwrap(), data);
+        }
+        assert!(deflate(&repeated).len() < repeated.len() / 50);
+    }
+
+    #[test]
+    fn test_gzip_trailer() {
+        let data = b"hello hello hello hello";
+        let compressed = gzip(data);
+        let trailer = &compressed[compressed.len() - 8..];
+        assert_eq!(trailer[..4], crate::sink::crc32(data).to_le_bytes());
+        assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+    }
+}
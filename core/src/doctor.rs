@@ -0,0 +1,253 @@
+// Doctor module
+// Diagnose problems in input data before tiling
+
+use crate::geojson_parser::{Feature, GeometryType};
+use crate::input::InputReader;
+use crate::projection::MAX_LATITUDE;
+use std::collections::BTreeMap;
+#[cfg(feature = "geojson")]
+use std::collections::HashMap;
+
+/// Kind of problem found in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCategory {
+    /// Geometry types the tiler skips (multi-geometries, collections, missing geometry)
+    UnsupportedType,
+    /// Geometry failing the OGC validity checks (see `validity::validate_features`)
+    InvalidGeometry,
+    /// Coordinates that are not finite, outside ±180°/±90° or beyond the WebMercator limit
+    OutOfRange,
+    /// Feature ids used by more than one feature
+    DuplicateId,
+    /// Attributes with mixed value types or nested values
+    PropertyAnomaly,
+}
+
+impl IssueCategory {
+    pub const ALL: [IssueCategory; 5] = [
+        IssueCategory::UnsupportedType,
+        IssueCategory::InvalidGeometry,
+        IssueCategory::OutOfRange,
+        IssueCategory::DuplicateId,
+        IssueCategory::PropertyAnomaly,
+    ];
+
+    /// Heading used in the report
+    pub fn label(self) -> &'static str {
+        match self {
+            IssueCategory::UnsupportedType => "Unsupported types",
+            IssueCategory::InvalidGeometry => "Invalid geometries",
+            IssueCategory::OutOfRange => "Out-of-range coordinates",
+            IssueCategory::DuplicateId => "Duplicate ids",
+            IssueCategory::PropertyAnomaly => "Property anomalies",
+        }
+    }
+}
+
+/// One problem, with the index of the input feature it was found in (if any)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Issue {
+    pub category: IssueCategory,
+    pub feature_index: Option<usize>,
+    pub message: String,
+}
+
+/// Result of diagnosing an input
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct Diagnosis {
+    /// Features in the input (including the ones the tiler would skip)
+    pub features: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl Diagnosis {
+    /// Issues of one category
+    pub fn issues_in(&self, category: IssueCategory) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(move |issue| issue.category == category)
+    }
+}
+
+/// Parse an input leniently and report what would be dropped or misrendered
+///
+/// GeoJSON is read feature by feature, so features the parser would skip are reported at their
+/// position in the input; other formats are read through `reader`.
+pub fn diagnose(bytes: &[u8], reader: Option<&dyn InputReader>) -> Result<Diagnosis, String> {
+    let mut diagnosis = Diagnosis::default();
+    let features: Vec<(usize, Feature)> = match reader {
+        Some(reader) => reader.read_features(bytes)?.enumerate().collect(),
+        #[cfg(feature = "geojson")]
+        None => read_geojson(bytes, &mut diagnosis)?,
+        #[cfg(not(feature = "geojson"))]
+        None => return Err("No input reader configured (GeoJSON input needs the 'geojson' feature)".to_string()),
+    };
+    if reader.is_some() {
+        diagnosis.features = features.len();
+    }
+    
+    for (index, feature) in &features {
+        check_coordinates(*index, &feature.geometry, &mut diagnosis.issues);
+        check_geometry(*index, &feature.geometry, &mut diagnosis.issues);
+    }
+    check_properties(&features, &mut diagnosis.issues);
+    diagnosis.issues.sort_by_key(|issue| (issue.category, issue.feature_index));
+    Ok(diagnosis)
+}
+
+/// Features the parser accepts, reporting the others and repeated `id` members
+#[cfg(feature = "geojson")]
+fn read_geojson(bytes: &[u8], diagnosis: &mut Diagnosis) -> Result<Vec<(usize, Feature)>, String> {
+    let text = crate::encoding::utf8_text(bytes)?;
+    let mut raw = Vec::new();
+    for document in crate::geojson_parser::split_documents(text)? {
+        match document {
+            geojson::GeoJson::FeatureCollection(fc) => raw.extend(fc.features),
+            geojson::GeoJson::Feature(feature) => raw.push(feature),
+            geojson::GeoJson::Geometry(_) => return Err("Input is a bare geometry, not a Feature or FeatureCollection".to_string()),
+        }
+    }
+    diagnosis.features = raw.len();
+    
+    let mut first_with_id: HashMap<String, usize> = HashMap::new();
+    let mut features = Vec::with_capacity(raw.len());
+    for (index, feature) in raw.into_iter().enumerate() {
+        if let Some(id) = &feature.id {
+            let key = match id {
+                geojson::feature::Id::String(s) => format!("\"{}\"", s),
+                geojson::feature::Id::Number(n) => n.to_string(),
+            };
+            match first_with_id.get(&key) {
+                Some(&first) => diagnosis.issues.push(Issue {
+                    category: IssueCategory::DuplicateId,
+                    feature_index: Some(index),
+                    message: format!("Id {} is already used by feature {}", key, first),
+                }),
+                None => {
+                    first_with_id.insert(key, index);
+                }
+            }
+        }
+        let type_name = feature.geometry.as_ref().map(|geometry| geometry.value.type_name());
+        match crate::geojson_parser::parse_feature(feature) {
+            Ok(feature) => features.push((index, feature)),
+            Err(e) => diagnosis.issues.push(Issue {
+                category: IssueCategory::UnsupportedType,
+                feature_index: Some(index),
+                message: match type_name {
+                    Some(name) => format!("{} geometries are not supported and will be skipped", name),
+                    None => format!("Feature will be skipped: {}", e),
+                },
+            }),
+        }
+    }
+    Ok(features)
+}
+
+fn coords_of(geometry: &GeometryType) -> Vec<geo_types::Coord<f64>> {
+    match geometry {
+        GeometryType::Point(point) => vec![point.0],
+        GeometryType::LineString(line) => line.0.clone(),
+        GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => geometry
+            .polygons()
+            .iter()
+            .flat_map(|polygon| std::iter::once(polygon.exterior()).chain(polygon.interiors()))
+            .flat_map(|ring| ring.0.iter().copied())
+            .collect(),
+    }
+}
+
+fn check_coordinates(index: usize, geometry: &GeometryType, issues: &mut Vec<Issue>) {
+    let coords = coords_of(geometry);
+    let message = if let Some(c) = coords.iter().find(|c| !c.x.is_finite() || !c.y.is_finite()) {
+        format!("Non-finite coordinate ({}, {})", c.x, c.y)
+    } else if let Some(c) = coords.iter().find(|c| c.x.abs() > 180.0 || c.y.abs() > 90.0) {
+        format!("Coordinate ({}, {}) is outside ±180°/±90° (projected data or swapped axes?)", c.x, c.y)
+    } else if coords.iter().all(|c| c.y.abs() > MAX_LATITUDE) {
+        "Feature lies entirely beyond the WebMercator latitude limit".to_string()
+    } else {
+        return;
+    };
+    issues.push(Issue { category: IssueCategory::OutOfRange, feature_index: Some(index), message });
+}
+
+fn check_geometry(index: usize, geometry: &GeometryType, issues: &mut Vec<Issue>) {
+    let mut found = Vec::new();
+    crate::validity::validate_geometry(index, geometry, &mut found);
+    issues.extend(found.into_iter().map(|issue| Issue {
+        category: IssueCategory::InvalidGeometry,
+        feature_index: Some(issue.feature_index),
+        message: issue.message,
+    }));
+}
+
+/// Keys whose values mix types across features, and keys holding arrays or objects
+fn check_properties(features: &[(usize, Feature)], issues: &mut Vec<Issue>) {
+    // Per key: features per value type, and the first feature with each type
+    let mut types: BTreeMap<&str, BTreeMap<&'static str, (usize, usize)>> = BTreeMap::new();
+    for (index, feature) in features {
+        for (key, value) in &feature.properties {
+            let value_type = match value {
+                serde_json::Value::Null => continue,
+                serde_json::Value::Bool(_) => "boolean",
+                serde_json::Value::Number(_) => "number",
+                serde_json::Value::String(_) => "string",
+                serde_json::Value::Array(_) => "array",
+                serde_json::Value::Object(_) => "object",
+            };
+            let entry = types.entry(key).or_default().entry(value_type).or_insert((0, *index));
+            entry.0 += 1;
+        }
+    }
+    
+    for (key, by_type) in types {
+        for nested in ["array", "object"] {
+            if let Some(&(count, first)) = by_type.get(nested) {
+                issues.push(Issue {
+                    category: IssueCategory::PropertyAnomaly,
+                    feature_index: Some(first),
+                    message: format!("'{}' holds {} values in {} features (written as JSON text)", key, nested, count),
+                });
+            }
+        }
+        if by_type.len() > 1 {
+            // The first feature of the least common type is the likely outlier
+            let (_, &(_, outlier)) = by_type.iter().min_by_key(|(_, &(count, _))| count).unwrap();
+            let counts: Vec<String> = by_type.iter().map(|(value_type, (count, _))| format!("{} {}", count, value_type)).collect();
+            issues.push(Issue {
+                category: IssueCategory::PropertyAnomaly,
+                feature_index: Some(outlier),
+                message: format!("'{}' has mixed value types ({})", key, counts.join(", ")),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose_geojson() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "id": 1, "properties": {"pop": 10}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "id": 1, "properties": {"pop": "n/a"}, "geometry": {"type": "Point", "coordinates": [35.7, 139.7]}},
+            {"type": "Feature", "properties": {"pop": 12}, "geometry": {"type": "MultiPoint", "coordinates": [[0, 0]]}},
+            {"type": "Feature", "properties": {"pop": 13, "tags": ["a"]}, "geometry": {"type": "LineString", "coordinates": [[0, 0], [0, 0]]}}
+        ]}"#;
+        
+        let diagnosis = diagnose(geojson, None).unwrap();
+        
+        assert_eq!(diagnosis.features, 4);
+        let found: Vec<(IssueCategory, Option<usize>)> = diagnosis.issues.iter().map(|i| (i.category, i.feature_index)).collect();
+        assert_eq!(found, [
+            (IssueCategory::UnsupportedType, Some(2)),
+            (IssueCategory::InvalidGeometry, Some(3)),
+            (IssueCategory::OutOfRange, Some(1)),
+            (IssueCategory::DuplicateId, Some(1)),
+            (IssueCategory::PropertyAnomaly, Some(1)),
+            (IssueCategory::PropertyAnomaly, Some(3)),
+        ]);
+        // The skipped MultiPoint is not counted
+        assert_eq!(diagnosis.issues[4].message, "'pop' has mixed value types (2 number, 1 string)");
+    }
+}
@@ -0,0 +1,139 @@
+// Encoding module
+// Text inputs that start with a byte order mark or are not UTF-8 (e.g. saved from Excel)
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// How text inputs that are not valid UTF-8 are read
+///
+/// A UTF-8 byte order mark is always skipped. Zip archives (KMZ, Shapefile) are never decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDecoding {
+    /// Fail with a UTF-8 error
+    #[default]
+    Strict,
+    /// Replace invalid byte sequences with U+FFFD
+    Lossy,
+    /// UTF-16 when the input starts with its byte order mark, Latin-1 otherwise
+    Detect,
+}
+
+impl TextDecoding {
+    pub const ALL: [TextDecoding; 3] = [TextDecoding::Strict, TextDecoding::Lossy, TextDecoding::Detect];
+
+    /// Name used by `--encoding` (e.g. `lossy`)
+    pub fn name(self) -> &'static str {
+        match self {
+            TextDecoding::Strict => "strict",
+            TextDecoding::Lossy => "lossy",
+            TextDecoding::Detect => "detect",
+        }
+    }
+}
+
+impl fmt::Display for TextDecoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TextDecoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        TextDecoding::ALL.into_iter().find(|decoding| decoding.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = TextDecoding::ALL.iter().map(|decoding| decoding.name()).collect();
+            format!("Unknown text decoding '{}' (expected one of: {})", name, names.join(", "))
+        })
+    }
+}
+
+/// The input without a leading UTF-8 byte order mark
+pub fn strip_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)
+}
+
+/// The input as UTF-8 text, without a byte order mark
+#[cfg(any(feature = "geojson", feature = "csv"))]
+pub(crate) fn utf8_text(bytes: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(strip_bom(bytes)).map_err(|e| {
+        format!("UTF-8 conversion error: {} (other encodings are read with the lossy or detect text decoding)", e)
+    })
+}
+
+/// Re-encode the input as UTF-8 under `decoding`, with a warning when it was not UTF-8
+pub(crate) fn decode(bytes: &[u8], decoding: TextDecoding) -> (Cow<'_, [u8]>, Option<String>) {
+    if decoding == TextDecoding::Strict || bytes.starts_with(b"PK\x03\x04") || std::str::from_utf8(bytes).is_ok() {
+        return (Cow::Borrowed(bytes), None);
+    }
+    let (text, warning) = match decoding {
+        TextDecoding::Detect if bytes.starts_with(b"\xFF\xFE") || bytes.starts_with(b"\xFE\xFF") => {
+            let little_endian = bytes[0] == 0xFF;
+            let units = bytes[2..].chunks_exact(2).map(|pair| match little_endian {
+                true => u16::from_le_bytes([pair[0], pair[1]]),
+                false => u16::from_be_bytes([pair[0], pair[1]]),
+            });
+            let text: String = char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect();
+            (text, format!("Input is UTF-16{}, not UTF-8; it was converted", if little_endian { "LE" } else { "BE" }))
+        }
+        TextDecoding::Detect => {
+            let text: String = strip_bom(bytes).iter().map(|&b| b as char).collect();
+            (text, "Input is not valid UTF-8; it was read as Latin-1".to_string())
+        }
+        _ => {
+            let invalid = bytes.utf8_chunks().filter(|chunk| !chunk.invalid().is_empty()).count();
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            (text, format!("Input is not valid UTF-8; replaced {} invalid byte sequences with U+FFFD", invalid))
+        }
+    };
+    (Cow::Owned(text.into_bytes()), Some(warning))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoding_names() {
+        for decoding in TextDecoding::ALL {
+            assert_eq!(decoding.name().parse::<TextDecoding>(), Ok(decoding));
+        }
+        assert!("latin1".parse::<TextDecoding>().unwrap_err().contains("detect"));
+    }
+
+    #[cfg(feature = "geojson")]
+    #[test]
+    fn test_utf8_text() {
+        assert!(utf8_text(b"caf\xE9").unwrap_err().starts_with("UTF-8 conversion error"));
+        assert_eq!(utf8_text("\u{FEFF}café".as_bytes()), Ok("café"));
+        
+        let geojson = b"{\"type\": \"Feature\", \"properties\": {\"name\": \"caf\xE9\"}, \"geometry\": {\"type\": \"Point\", \"coordinates\": [1, 2]}}";
+        assert!(crate::generate_tiles(geojson, 0, 0, "test").is_err());
+        let options = crate::TileOptions { text_decoding: TextDecoding::Detect, ..Default::default() };
+        let (_, metadata) = crate::generate_tiles_with_options(geojson, 0, 0, "test", &options).unwrap();
+        assert!(metadata.warnings[0].message.contains("Latin-1"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let latin1 = b"caf\xE9";
+        assert_eq!(decode(latin1, TextDecoding::Strict), (Cow::Borrowed(&latin1[..]), None));
+        
+        let (text, warning) = decode(latin1, TextDecoding::Detect);
+        assert_eq!((&*text, warning.unwrap().contains("Latin-1")), ("café".as_bytes(), true));
+        let (text, warning) = decode(latin1, TextDecoding::Lossy);
+        assert_eq!(&*text, "caf\u{FFFD}".as_bytes());
+        assert!(warning.unwrap().contains("replaced 1 invalid"));
+        
+        let utf16: Vec<u8> = [0xFEFF_u16].into_iter().chain("café".encode_utf16()).flat_map(u16::to_le_bytes).collect();
+        let (text, warning) = decode(&utf16, TextDecoding::Detect);
+        assert_eq!((&*text, warning.unwrap().contains("UTF-16LE")), ("café".as_bytes(), true));
+        
+        // Valid UTF-8 and zip archives are left as they are
+        assert_eq!(decode("café".as_bytes(), TextDecoding::Detect).1, None);
+        assert_eq!(decode(b"PK\x03\x04\xE9", TextDecoding::Lossy).1, None);
+    }
+}
@@ -0,0 +1,131 @@
+// GeoJSON export module
+// Features (e.g. decoded from a tileset) written back as a GeoJSON FeatureCollection
+
+use crate::geojson_parser::{Feature, GeometryType};
+use geo_types::{Coord, LineString, Polygon};
+use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Coordinate reference system of exported coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetCrs {
+    /// WGS84 lon/lat (EPSG:4326), the only CRS of RFC 7946 GeoJSON
+    #[default]
+    Wgs84,
+    /// Web Mercator meters (EPSG:3857), for mercator-native pipelines
+    WebMercator,
+}
+
+impl TargetCrs {
+    pub const ALL: [TargetCrs; 2] = [TargetCrs::Wgs84, TargetCrs::WebMercator];
+
+    /// Name used by `--crs` (e.g. `EPSG:3857`)
+    pub fn name(self) -> &'static str {
+        match self {
+            TargetCrs::Wgs84 => "EPSG:4326",
+            TargetCrs::WebMercator => "EPSG:3857",
+        }
+    }
+}
+
+impl fmt::Display for TargetCrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TargetCrs {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        TargetCrs::ALL.into_iter().find(|crs| crs.name().eq_ignore_ascii_case(name)).ok_or_else(|| {
+            let names: Vec<&str> = TargetCrs::ALL.iter().map(|crs| crs.name()).collect();
+            format!("Unsupported target CRS '{}' (expected one of: {})", name, names.join(", "))
+        })
+    }
+}
+
+/// A FeatureCollection of lon/lat `features`, with coordinates in `crs`
+///
+/// Collections in another CRS than WGS84 name it in a `crs` member (as GeoJSON 2008 did), since
+/// RFC 7946 readers assume lon/lat.
+pub fn features_to_geojson(features: &[Feature], crs: TargetCrs) -> Value {
+    let position = |c: &Coord<f64>| {
+        let (x, y) = match crs {
+            TargetCrs::Wgs84 => (c.x, c.y),
+            TargetCrs::WebMercator => crate::projection::lonlat_to_meters(c.x, c.y),
+        };
+        json!([x, y])
+    };
+    let line = |line: &LineString<f64>| Value::Array(line.0.iter().map(position).collect());
+    let rings = |polygon: &Polygon<f64>| {
+        Value::Array(std::iter::once(polygon.exterior()).chain(polygon.interiors()).map(line).collect())
+    };
+    
+    let features: Vec<Value> = features
+        .iter()
+        .map(|feature| {
+            let (kind, coordinates) = match &feature.geometry {
+                GeometryType::Point(point) => ("Point", position(&point.0)),
+                GeometryType::LineString(coords) => ("LineString", line(coords)),
+                GeometryType::Polygon(polygon) => ("Polygon", rings(polygon)),
+                GeometryType::MultiPolygon(polygons) => ("MultiPolygon", polygons.0.iter().map(rings).collect()),
+            };
+            let mut value = json!({
+                "type": "Feature",
+                "geometry": {"type": kind, "coordinates": coordinates},
+                "properties": feature.properties,
+            });
+            if let Some(id) = feature.id {
+                value["id"] = json!(id);
+            }
+            value
+        })
+        .collect();
+    
+    let mut collection = json!({"type": "FeatureCollection", "features": features});
+    if crs != TargetCrs::Wgs84 {
+        let urn = format!("urn:ogc:def:crs:{}", crs.name().replace(':', "::"));
+        collection["crs"] = json!({"type": "name", "properties": {"name": urn}});
+    }
+    collection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point;
+
+    #[test]
+    fn test_crs_names() {
+        for crs in TargetCrs::ALL {
+            assert_eq!(crs.name().parse::<TargetCrs>(), Ok(crs));
+        }
+        assert_eq!("epsg:3857".parse::<TargetCrs>(), Ok(TargetCrs::WebMercator));
+        assert!("EPSG:2193".parse::<TargetCrs>().unwrap_err().contains("EPSG:3857"));
+    }
+
+    #[test]
+    fn test_features_to_geojson() {
+        let mut point = Feature::new(GeometryType::Point(Point::new(180.0, 0.0)), serde_json::Map::new());
+        point.id = Some(7);
+        let line = LineString::from(vec![(0.0, 0.0), (1.0, 1.0)]);
+        let features = [point, Feature::new(GeometryType::LineString(line), serde_json::Map::new())];
+        
+        let wgs84 = features_to_geojson(&features, TargetCrs::Wgs84);
+        assert_eq!(wgs84["features"][0]["geometry"]["coordinates"], json!([180.0, 0.0]));
+        assert_eq!(wgs84["features"][0]["id"], 7);
+        assert!(wgs84.get("crs").is_none());
+        
+        let mercator = features_to_geojson(&features, TargetCrs::WebMercator);
+        assert_eq!(mercator["crs"]["properties"]["name"], "urn:ogc:def:crs:EPSG::3857");
+        let x = mercator["features"][0]["geometry"]["coordinates"][0].as_f64().unwrap();
+        assert!((x - 20037508.342789244).abs() < 1e-6);
+        assert_eq!(mercator["features"][1]["geometry"]["type"], "LineString");
+        
+        // The export reads back as the same features
+        #[cfg(feature = "geojson")]
+        assert_eq!(crate::geojson_parser::parse_geojson(wgs84.to_string().as_bytes()).unwrap(), features);
+    }
+}
@@ -0,0 +1,648 @@
+// C FFI module
+// extern "C" API for embedding the tiler (declared in include/vector_tile_core.h)
+
+use crate::mbtiles::MbTilesSink;
+use crate::metrics::GenerationMetrics;
+use crate::mvt_encoder::GeometryChecks;
+use crate::pmtiles::PmTilesSink;
+use crate::sink::{DirectorySink, TileCompression, TileSink};
+use crate::tile_source::TileSource;
+use crate::tilejson::FieldSchema;
+use crate::{generate_tiles_to_sink, generate_tiles_with_metrics, input, inspect, tilejson, TileCoord, TileMetadata, TileOptions};
+use std::ffi::{c_char, CStr, CString};
+use std::fs::File;
+use std::io::BufWriter;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Borrowed byte buffer (valid until the owning result is freed)
+#[repr(C)]
+pub struct VtcBuffer {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Opaque generation result
+pub struct VtcResult {
+    tiles: Vec<(CString, Vec<u8>)>,
+    metadata_json: CString,
+    error: Option<CString>,
+}
+
+impl VtcResult {
+    fn error(message: &str) -> Self {
+        Self {
+            tiles: Vec::new(),
+            metadata_json: CString::default(),
+            error: Some(to_c_string(message)),
+        }
+    }
+}
+
+/// Generate tiles from GeoJSON bytes
+///
+/// Always returns a result (check `vtc_result_error`); free it with `vtc_result_free`.
+///
+/// # Safety
+/// `geojson` must point to `len` readable bytes and `layer_name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_generate(
+    geojson: *const u8,
+    len: usize,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: *const c_char,
+) -> *mut VtcResult {
+    let result = if geojson.is_null() || layer_name.is_null() {
+        VtcResult::error("Null argument")
+    } else {
+        let bytes = std::slice::from_raw_parts(geojson, len);
+        let layer_name = CStr::from_ptr(layer_name).to_string_lossy();
+        panic::catch_unwind(AssertUnwindSafe(|| generate(bytes, min_zoom, max_zoom, &layer_name, &TileOptions::default())))
+            .unwrap_or_else(|_| VtcResult::error("Tile generation panicked"))
+    };
+    Box::into_raw(Box::new(result))
+}
+
+/// As `vtc_generate`, with options given as a JSON object (NULL or `{}` for the defaults)
+///
+/// The input format is detected from its content as in the CLI; see `FfiOptions` for the keys.
+///
+/// # Safety
+/// `geojson` must point to `len` readable bytes; `layer_name` and a non-NULL `options_json`
+/// must be NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_generate_with_options(
+    geojson: *const u8,
+    len: usize,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: *const c_char,
+    options_json: *const c_char,
+) -> *mut VtcResult {
+    let result = if geojson.is_null() || layer_name.is_null() {
+        VtcResult::error("Null argument")
+    } else {
+        let bytes = std::slice::from_raw_parts(geojson, len);
+        let layer_name = CStr::from_ptr(layer_name).to_string_lossy();
+        match read_options(bytes, options_json) {
+            Ok(options) => {
+                panic::catch_unwind(AssertUnwindSafe(|| generate(bytes, min_zoom, max_zoom, &layer_name, &options)))
+                    .unwrap_or_else(|_| VtcResult::error("Tile generation panicked"))
+            }
+            Err(e) => VtcResult::error(&e),
+        }
+    };
+    Box::into_raw(Box::new(result))
+}
+
+/// As `vtc_generate_with_options`, writing the tiles to `output_path` instead of the result
+///
+/// A path ending in `.pmtiles` gets a PMTiles archive, one ending in `.mbtiles` an MBTiles
+/// database, and any other path a `z/x/y.pbf` directory. `compression` is `none`, `gzip` or
+/// `brotli` (NULL for none). The result holds the metadata (or the error) and no tiles.
+///
+/// # Safety
+/// As `vtc_generate_with_options`; `output_path` and a non-NULL `compression` must be
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_generate_to_file(
+    geojson: *const u8,
+    len: usize,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: *const c_char,
+    options_json: *const c_char,
+    output_path: *const c_char,
+    compression: *const c_char,
+) -> *mut VtcResult {
+    let result = if geojson.is_null() || layer_name.is_null() || output_path.is_null() {
+        VtcResult::error("Null argument")
+    } else {
+        let bytes = std::slice::from_raw_parts(geojson, len);
+        let layer_name = CStr::from_ptr(layer_name).to_string_lossy();
+        let output_path = CStr::from_ptr(output_path).to_string_lossy();
+        let compression = match compression.is_null() {
+            true => Ok(TileCompression::None),
+            false => CStr::from_ptr(compression).to_string_lossy().parse(),
+        };
+        match read_options(bytes, options_json).and_then(|options| Ok((options, compression?))) {
+            Ok((options, compression)) => panic::catch_unwind(AssertUnwindSafe(|| {
+                generate_to_file(bytes, min_zoom, max_zoom, &layer_name, &options, &output_path, compression)
+            }))
+            .unwrap_or_else(|_| VtcResult::error("Tile generation panicked")),
+            Err(e) => VtcResult::error(&e),
+        }
+    };
+    Box::into_raw(Box::new(result))
+}
+
+/// Options of a non-NULL `options_json` (the defaults for NULL) with the reader detected from `bytes`
+unsafe fn read_options(bytes: &[u8], options_json: *const c_char) -> Result<TileOptions, String> {
+    let options_json = match options_json.is_null() {
+        true => "{}".into(),
+        false => CStr::from_ptr(options_json).to_string_lossy(),
+    };
+    let options = parse_options(&options_json)?;
+    Ok(TileOptions { reader: input::detect_reader(bytes), ..options })
+}
+
+/// Options accepted by `vtc_generate_with_options`, named after the CLI flags
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FfiOptions {
+    /// Built-in generalization settings the other keys start from (`roads`, `parcels`, ...)
+    preset: Option<String>,
+    /// Text decoding of inputs that are not UTF-8 (`strict`, `lossy` or `detect`)
+    encoding: Option<String>,
+    /// Work split across threads (`auto`, `per-zoom`, `per-tile` or `per-feature-batch`)
+    parallel: Option<String>,
+    /// Fail on unsupported or invalid geometry instead of skipping it with a warning
+    strict: bool,
+    extend_zooms: Option<u8>,
+    /// `[min_lon, min_lat, max_lon, max_lat]`
+    bbox: Option<[f64; 4]>,
+    extent: Option<u32>,
+    max_zoom_extent: Option<u32>,
+    /// Attribute descriptions (as in a `--schema` file)
+    schema: Option<serde_json::Value>,
+    remove_duplicates: bool,
+    omit_ids: bool,
+    fill_empty_tiles: bool,
+    validate_input: bool,
+}
+
+fn parse_options(json: &str) -> Result<TileOptions, String> {
+    let parsed: FfiOptions = serde_json::from_str(json).map_err(|e| format!("Invalid options: {}", e))?;
+    let mut options = match &parsed.preset {
+        Some(name) => TileOptions::preset(name.parse()?),
+        None => TileOptions::default(),
+    };
+    if let Some(name) = &parsed.encoding {
+        options.text_decoding = name.parse()?;
+    }
+    if let Some(name) = &parsed.parallel {
+        options.parallel_strategy = name.parse()?;
+    }
+    if parsed.strict {
+        options.geometry_checks = GeometryChecks::Strict;
+    }
+    if let Some(schema) = &parsed.schema {
+        options.field_schema = FieldSchema::from_json(&schema.to_string())?;
+    }
+    options.extend_zooms = parsed.extend_zooms.or(options.extend_zooms);
+    options.bbox_filter = parsed.bbox.map(|[min_lon, min_lat, max_lon, max_lat]| (min_lon, min_lat, max_lon, max_lat));
+    options.extent = parsed.extent.or(options.extent);
+    options.max_zoom_extent = parsed.max_zoom_extent.or(options.max_zoom_extent);
+    options.remove_duplicates |= parsed.remove_duplicates;
+    options.omit_ids |= parsed.omit_ids;
+    options.fill_empty_tiles |= parsed.fill_empty_tiles;
+    options.validate_input |= parsed.validate_input;
+    Ok(options)
+}
+
+fn generate(bytes: &[u8], min_zoom: u8, max_zoom: u8, layer_name: &str, options: &TileOptions) -> VtcResult {
+    match generate_tiles_with_metrics(bytes, min_zoom, max_zoom, layer_name, options) {
+        Ok((tiles, metadata, metrics)) => VtcResult {
+            tiles: tiles.into_iter().map(|t| (to_c_string(&t.path), t.data)).collect(),
+            metadata_json: metadata_json(&metadata, &metrics),
+            error: None,
+        },
+        Err(e) => VtcResult::error(&e),
+    }
+}
+
+fn generate_to_file(
+    bytes: &[u8],
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+    path: &str,
+    compression: TileCompression,
+) -> VtcResult {
+    let create = |path: &str| File::create(path).map(BufWriter::new).map_err(|e| format!("Failed to create {}: {}", path, e));
+    let sink: Result<Box<dyn TileSink>, String> = if path.ends_with(".pmtiles") {
+        create(path).map(|file| Box::new(PmTilesSink::new(file).with_tile_compression(compression)) as Box<dyn TileSink>)
+    } else if path.ends_with(".mbtiles") {
+        create(path).map(|file| Box::new(MbTilesSink::new(file).with_tile_compression(compression)) as Box<dyn TileSink>)
+    } else {
+        Ok(Box::new(DirectorySink::new(path).with_tile_compression(compression)))
+    };
+    match sink.and_then(|mut sink| generate_tiles_to_sink(bytes, min_zoom, max_zoom, layer_name, options, sink.as_mut())) {
+        Ok((metadata, metrics)) => VtcResult {
+            tiles: Vec::new(),
+            metadata_json: metadata_json(&metadata, &metrics),
+            error: None,
+        },
+        Err(e) => VtcResult::error(&e),
+    }
+}
+
+fn metadata_json(metadata: &TileMetadata, metrics: &GenerationMetrics) -> CString {
+    let json = serde_json::json!({
+        "tilejson": tilejson::to_tilejson(metadata),
+        "warnings": metadata.warnings,
+        "metrics": metrics,
+    });
+    to_c_string(&json.to_string())
+}
+
+/// Error message of a failed generation (NULL on success)
+///
+/// # Safety
+/// `result` must be a pointer returned by `vtc_generate` or `vtc_generate_with_options` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_result_error(result: *const VtcResult) -> *const c_char {
+    match result.as_ref().and_then(|r| r.error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Number of generated tiles
+///
+/// # Safety
+/// `result` must be a pointer returned by `vtc_generate` or `vtc_generate_with_options` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_result_tile_count(result: *const VtcResult) -> usize {
+    result.as_ref().map_or(0, |r| r.tiles.len())
+}
+
+/// Path (`z/x/y.pbf`, NUL-terminated) and data of the tile at `index`
+///
+/// Returns 0 on success, -1 if `index` is out of range.
+///
+/// # Safety
+/// `result` must be a pointer returned by `vtc_generate` or `vtc_generate_with_options` that has not been freed;
+/// `path` and `data` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_result_tile(
+    result: *const VtcResult,
+    index: usize,
+    path: *mut *const c_char,
+    data: *mut VtcBuffer,
+) -> i32 {
+    let Some((tile_path, tile_data)) = result.as_ref().and_then(|r| r.tiles.get(index)) else {
+        return -1;
+    };
+    if !path.is_null() {
+        *path = tile_path.as_ptr();
+    }
+    if !data.is_null() {
+        *data = VtcBuffer {
+            data: tile_data.as_ptr(),
+            len: tile_data.len(),
+        };
+    }
+    0
+}
+
+/// Metadata as a JSON object with `tilejson`, `warnings` and `metrics` (empty string on error)
+///
+/// # Safety
+/// `result` must be a pointer returned by `vtc_generate` or `vtc_generate_with_options` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_result_metadata_json(result: *const VtcResult) -> *const c_char {
+    match result.as_ref() {
+        Some(result) => result.metadata_json.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Free a result returned by `vtc_generate` or `vtc_generate_with_options` (NULL is ignored)
+///
+/// # Safety
+/// `result` must be NULL or a pointer returned by `vtc_generate` or `vtc_generate_with_options` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_result_free(result: *mut VtcResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+/// Opaque tileset answering tile requests (`TileSource`), overzoomed beyond the max zoom
+pub struct VtcSource {
+    source: TileSource,
+    /// Data of the last tile returned by `vtc_source_tile`
+    tile: Vec<u8>,
+    error: Option<CString>,
+}
+
+/// Generate tiles into a tile source, as `vtc_generate_with_options` does
+///
+/// Always returns a source (check `vtc_source_error`); free it with `vtc_source_free`.
+///
+/// # Safety
+/// As `vtc_generate_with_options`.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_source_new(
+    geojson: *const u8,
+    len: usize,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: *const c_char,
+    options_json: *const c_char,
+) -> *mut VtcSource {
+    let generated = if geojson.is_null() || layer_name.is_null() {
+        Err("Null argument".to_string())
+    } else {
+        let bytes = std::slice::from_raw_parts(geojson, len);
+        let layer_name = CStr::from_ptr(layer_name).to_string_lossy();
+        read_options(bytes, options_json).and_then(|options| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut source = TileSource::new();
+                generate_tiles_to_sink(bytes, min_zoom, max_zoom, &layer_name, &options, &mut source).map(|_| source)
+            }))
+            .unwrap_or_else(|_| Err("Tile generation panicked".to_string()))
+        })
+    };
+    let source = match generated {
+        Ok(source) => VtcSource { source, tile: Vec::new(), error: None },
+        Err(e) => VtcSource { source: TileSource::new(), tile: Vec::new(), error: Some(to_c_string(&e)) },
+    };
+    Box::into_raw(Box::new(source))
+}
+
+/// Error message of the failed generation or the last failed `vtc_source_tile` (NULL if none)
+///
+/// # Safety
+/// `source` must be a pointer returned by `vtc_source_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_source_error(source: *const VtcSource) -> *const c_char {
+    match source.as_ref().and_then(|s| s.error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Data of tile `z/x/y`, derived from the max-zoom ancestor above the generated zooms
+///
+/// Returns 0 with the tile in `data` (valid until the next call on `source`), 1 if there is no
+/// tile at that coordinate, or -1 on error (see `vtc_source_error`).
+///
+/// # Safety
+/// `source` must be a pointer returned by `vtc_source_new` that has not been freed; `data`
+/// must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_source_tile(source: *mut VtcSource, z: u8, x: u32, y: u32, data: *mut VtcBuffer) -> i32 {
+    let Some(source) = source.as_mut() else {
+        return -1;
+    };
+    let tile = panic::catch_unwind(AssertUnwindSafe(|| source.source.get_tile(TileCoord::new(z, x, y)).map(|tile| tile.map(|data| data.into_owned()))))
+        .unwrap_or_else(|_| Err("Tile lookup panicked".to_string()));
+    match tile {
+        Ok(Some(tile)) => {
+            source.tile = tile;
+            if !data.is_null() {
+                *data = VtcBuffer {
+                    data: source.tile.as_ptr(),
+                    len: source.tile.len(),
+                };
+            }
+            0
+        }
+        Ok(None) => 1,
+        Err(e) => {
+            source.error = Some(to_c_string(&e));
+            -1
+        }
+    }
+}
+
+/// Free a source returned by `vtc_source_new` (NULL is ignored)
+///
+/// # Safety
+/// `source` must be NULL or a pointer returned by `vtc_source_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_source_free(source: *mut VtcSource) {
+    if !source.is_null() {
+        drop(Box::from_raw(source));
+    }
+}
+
+/// Summarize an encoded tile as JSON (`{"layers": [...]}` or `{"error": "..."}`)
+///
+/// Free the returned string with `vtc_string_free`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_inspect_tile(data: *const u8, len: usize) -> *mut c_char {
+    let summary = if data.is_null() {
+        serde_json::json!({ "error": "Null argument" })
+    } else {
+        match inspect::inspect_tile(std::slice::from_raw_parts(data, len)) {
+            Ok(layers) => serde_json::json!({ "layers": layers }),
+            Err(e) => serde_json::json!({ "error": e }),
+        }
+    };
+    to_c_string(&summary.to_string()).into_raw()
+}
+
+/// Free a string returned by `vtc_inspect_tile` (NULL is ignored)
+///
+/// # Safety
+/// `s` must be NULL or a pointer returned by `vtc_inspect_tile` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn vtc_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Interior NUL bytes cannot cross the C boundary, so they are dropped
+fn to_c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_roundtrip() {
+        let geojson = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        let layer = CString::new("points").unwrap();
+        
+        unsafe {
+            let result = vtc_generate(geojson.as_ptr(), geojson.len(), 0, 2, layer.as_ptr());
+            assert!(vtc_result_error(result).is_null());
+            assert_eq!(vtc_result_tile_count(result), 3);
+            
+            let mut path = ptr::null();
+            let mut data = VtcBuffer { data: ptr::null(), len: 0 };
+            assert_eq!(vtc_result_tile(result, 0, &mut path, &mut data), 0);
+            assert_eq!(CStr::from_ptr(path).to_str().unwrap(), "0/0/0.pbf");
+            assert_eq!(vtc_result_tile(result, 3, &mut path, &mut data), -1);
+            
+            let summary = vtc_inspect_tile(data.data, data.len);
+            let json: serde_json::Value = serde_json::from_str(CStr::from_ptr(summary).to_str().unwrap()).unwrap();
+            assert_eq!(json["layers"][0]["name"], "points");
+            vtc_string_free(summary);
+            
+            vtc_result_free(result);
+        }
+    }
+
+    #[test]
+    fn test_generate_with_options() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-74.0, 40.7]}}
+        ]}"#;
+        let layer = CString::new("points").unwrap();
+        let generate = |options: &str| unsafe {
+            let options = CString::new(options).unwrap();
+            let result = vtc_generate_with_options(geojson.as_ptr(), geojson.len(), 0, 2, layer.as_ptr(), options.as_ptr());
+            let outcome = match vtc_result_error(result).as_ref() {
+                Some(_) => Err(CStr::from_ptr(vtc_result_error(result)).to_string_lossy().into_owned()),
+                None => Ok(vtc_result_tile_count(result)),
+            };
+            vtc_result_free(result);
+            outcome
+        };
+        
+        assert_eq!(generate("{}"), Ok(5));
+        assert_eq!(generate(r#"{"bbox": [139.0, 35.0, 140.0, 36.0], "preset": "points-dense"}"#), Ok(3));
+        assert!(generate(r#"{"extent": "big"}"#).unwrap_err().starts_with("Invalid options"));
+        assert!(generate(r#"{"simplify": true}"#).unwrap_err().contains("unknown field"));
+        assert!(generate(r#"{"preset": "rivers"}"#).unwrap_err().contains("roads"));
+        unsafe {
+            let result = vtc_generate_with_options(geojson.as_ptr(), geojson.len(), 0, 0, layer.as_ptr(), ptr::null());
+            assert_eq!(vtc_result_tile_count(result), 1);
+            vtc_result_free(result);
+        }
+    }
+
+    /// Every `extern "C"` function of this module, declared in the header with the same signature
+    #[test]
+    fn test_header_signatures() {
+        fn normalize(declaration: &str) -> String {
+            declaration.split_whitespace().collect::<Vec<_>>().join(" ").replace(" *", "*").replace("* ", "*").replace("( ", "(")
+        }
+        let c_type = |rust: &str| match rust.trim() {
+            "" => "void",
+            "u8" => "uint8_t",
+            "u32" => "uint32_t",
+            "i32" => "int32_t",
+            "usize" => "size_t",
+            "*const u8" => "const uint8_t *",
+            "*const c_char" => "const char *",
+            "*mut c_char" => "char *",
+            "*mut *const c_char" => "const char **",
+            "*const VtcResult" => "const vtc_result *",
+            "*mut VtcResult" => "vtc_result *",
+            "*mut VtcBuffer" => "vtc_buffer *",
+            "*const VtcSource" => "const vtc_source *",
+            "*mut VtcSource" => "vtc_source *",
+            other => panic!("No C type for {}", other),
+        };
+        
+        let source = include_str!("ffi.rs");
+        let marker = ["pub unsafe extern ", "\"C\" fn "].concat();
+        let rust: Vec<String> = source
+            .split(marker.as_str())
+            .skip(1)
+            .map(|item| {
+                let signature = &item[..item.find('{').unwrap()];
+                let (name, rest) = signature.split_once('(').unwrap();
+                let (params, ret) = rest.rsplit_once(')').unwrap();
+                let params: Vec<String> = params
+                    .split(',')
+                    .filter(|param| !param.trim().is_empty())
+                    .map(|param| {
+                        let (name, ty) = param.split_once(':').unwrap();
+                        format!("{} {}", c_type(ty), name.trim())
+                    })
+                    .collect();
+                normalize(&format!("{} {}({})", c_type(ret.trim().trim_start_matches("->")), name, params.join(", ")))
+            })
+            .collect();
+        
+        let mut header = include_str!("../include/vector_tile_core.h").to_string();
+        while let Some(start) = header.find("/*") {
+            let end = header[start..].find("*/").unwrap() + start + 2;
+            header.replace_range(start..end, "");
+        }
+        let header: String = header.lines().filter(|line| !line.starts_with('#')).collect::<Vec<_>>().join("\n");
+        let declared: Vec<String> = header
+            .split(';')
+            .map(|statement| normalize(statement.rsplit(['{', '}']).next().unwrap()))
+            .filter(|statement| statement.contains("vtc_") && statement.contains('(') && !statement.contains("typedef"))
+            .collect();
+        
+        assert_eq!(rust.len(), 14);
+        assert_eq!(declared, rust);
+    }
+
+    #[test]
+    fn test_generate_to_file() {
+        let geojson = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        let layer = CString::new("points").unwrap();
+        let root = std::env::temp_dir().join(format!("vtc-ffi-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let generate = |name: &str, compression: &str| unsafe {
+            let path = CString::new(root.join(name).to_str().unwrap()).unwrap();
+            let compression = CString::new(compression).unwrap();
+            let result = vtc_generate_to_file(geojson.as_ptr(), geojson.len(), 0, 2, layer.as_ptr(), ptr::null(), path.as_ptr(), compression.as_ptr());
+            let outcome = match vtc_result_error(result).as_ref() {
+                Some(_) => Err(CStr::from_ptr(vtc_result_error(result)).to_string_lossy().into_owned()),
+                None => Ok((vtc_result_tile_count(result), CStr::from_ptr(vtc_result_metadata_json(result)).to_string_lossy().into_owned())),
+            };
+            vtc_result_free(result);
+            outcome
+        };
+        
+        let (tiles, metadata) = generate("points.pmtiles", "brotli").unwrap();
+        assert_eq!(tiles, 0);
+        assert!(metadata.contains("\"tilejson\""));
+        let info = crate::pmtiles::read_info(&std::fs::read(root.join("points.pmtiles")).unwrap()).unwrap();
+        assert_eq!((info.tile_compression.as_str(), info.tile_counts.values().sum::<u64>()), ("brotli", 3));
+        generate("points.mbtiles", "none").unwrap();
+        assert_eq!(crate::mbtiles::read_mbtiles(&std::fs::read(root.join("points.mbtiles")).unwrap()).unwrap().tiles.len(), 3);
+        generate("points", "none").unwrap();
+        assert!(root.join("points/2/3/1.pbf").exists());
+        assert!(generate("points.pmtiles", "zstd").unwrap_err().contains("Unknown tile compression"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_source_tiles() {
+        let geojson = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        let layer = CString::new("points").unwrap();
+        
+        unsafe {
+            let source = vtc_source_new(geojson.as_ptr(), geojson.len(), 0, 2, layer.as_ptr(), ptr::null());
+            assert!(vtc_source_error(source).is_null());
+            let mut data = VtcBuffer { data: ptr::null(), len: 0 };
+            assert_eq!(vtc_source_tile(source, 2, 3, 1, &mut data), 0);
+            assert!(data.len > 0);
+            // Overzoomed from 2/3/1, and empty elsewhere
+            assert_eq!(vtc_source_tile(source, 4, 14, 6, &mut data), 0);
+            let summary = vtc_inspect_tile(data.data, data.len);
+            assert!(CStr::from_ptr(summary).to_str().unwrap().contains("points"));
+            vtc_string_free(summary);
+            assert_eq!(vtc_source_tile(source, 2, 0, 0, &mut data), 1);
+            assert_eq!(vtc_source_tile(source, 40, 0, 0, &mut data), -1);
+            assert!(CStr::from_ptr(vtc_source_error(source)).to_str().unwrap().contains("InvalidZoomRange"));
+            vtc_source_free(source);
+            
+            let source = vtc_source_new(b"{".as_ptr(), 1, 0, 2, layer.as_ptr(), ptr::null());
+            assert!(!vtc_source_error(source).is_null());
+            vtc_source_free(source);
+        }
+    }
+
+    #[test]
+    fn test_generate_error() {
+        let layer = CString::new("points").unwrap();
+        
+        unsafe {
+            let result = vtc_generate(b"{".as_ptr(), 1, 0, 2, layer.as_ptr());
+            assert!(!vtc_result_error(result).is_null());
+            assert_eq!(vtc_result_tile_count(result), 0);
+            vtc_result_free(result);
+        }
+    }
+}
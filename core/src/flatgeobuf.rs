@@ -0,0 +1,718 @@
+// FlatGeobuf module
+// FlatGeobuf (.fgb) parsing into features, reading only the features in a box through the packed R-tree
+
+use crate::geojson_parser::{polygon_geometry, Feature, GeometryType};
+use crate::mvt_encoder::GeometryChecks;
+use crate::pmtiles::{read_ranges, RangeReader};
+use crate::Warning;
+use geo_types::{Coord, LineString, Point, Polygon};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+const MAGIC: &[u8] = b"fgb\x03";
+/// Each R-tree node is a box (four f64) and an offset (u64)
+const NODE_LEN: usize = 40;
+
+/// (min_lon, min_lat, max_lon, max_lat)
+pub type Bounds = (f64, f64, f64, f64);
+
+/// Whether the input starts with the FlatGeobuf v3 signature
+pub fn is_flatgeobuf(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC) && bytes.get(4..7) == Some(b"fgb")
+}
+
+/// Parse a FlatGeobuf file into features (coordinates must be WGS84 lon/lat)
+///
+/// With `bbox` and a spatial index, only the features whose index box intersects it are
+/// decoded; without an index every feature is read and the others are dropped. Multi-points and
+/// multi-lines become one feature per part, Z/M values are dropped, and unsupported geometry
+/// types are skipped with a warning (failing under strict checks).
+pub fn parse_flatgeobuf(bytes: &[u8], bbox: Option<Bounds>, checks: GeometryChecks) -> Result<(Vec<Feature>, Vec<Warning>), String> {
+    read_flatgeobuf(bytes, bbox, checks)
+}
+
+/// `parse_flatgeobuf` through range reads (for remote files, see `http::HttpRangeReader`)
+///
+/// With `bbox` and a spatial index only the header, the index nodes whose boxes intersect it
+/// (a level at a time) and the matching features are read; otherwise the whole file is.
+pub fn read_flatgeobuf<R: RangeReader + ?Sized>(reader: &R, bbox: Option<Bounds>, checks: GeometryChecks) -> Result<(Vec<Feature>, Vec<Warning>), String> {
+    let prefix = reader.read_range(0, 12)?;
+    if !is_flatgeobuf(&prefix) {
+        return Err("Not a FlatGeobuf file (.fgb)".to_string());
+    }
+    let header_len = u32_at(&prefix, 8)? as u64;
+    let header_end = 12 + header_len;
+    let header_bytes = read_exact(reader, 12, header_len)?;
+    let header = Table::root(&header_bytes)?;
+    check_crs(header)?;
+    let geometry_type = header.u8(2)?.unwrap_or(0);
+    let columns = parse_columns(header.tables(7)?)?;
+    let features_count = header.u64(8)?.unwrap_or(0) as usize;
+    let node_size = header.u16(9)?.unwrap_or(16) as usize;
+    
+    let index_len = match (node_size, features_count) {
+        (0, _) | (_, 0) => 0,
+        _ => (level_bounds(features_count, node_size)?[0].end * NODE_LEN) as u64,
+    };
+    let data_start = header_end + index_len;
+    
+    // Each feature to decode with its size prefix, in file order
+    let data;
+    let encoded: Vec<Cow<[u8]>> = match bbox {
+        Some(bbox) if index_len > 0 => {
+            let found = search(reader, header_end, features_count, node_size, bbox)?;
+            // Features whose end is unknown (the last one) are read in two steps
+            let ranges: Vec<(u64, u64)> = found.iter().map(|&(offset, end)| (data_start + offset, end.map_or(4, |end| end - offset))).collect();
+            let mut encoded = read_ranges(reader, &ranges)?;
+            for (feature, &(offset, end)) in encoded.iter_mut().zip(&found) {
+                if end.is_none() {
+                    let len = u32_at(feature, 0)? as u64;
+                    *feature = read_exact(reader, data_start + offset, 4 + len)?;
+                }
+            }
+            encoded
+        }
+        _ => {
+            data = reader.read_range(data_start, u64::MAX - data_start)?;
+            let offsets = scan(&data)?;
+            offsets.into_iter().map(|offset| feature_slice(&data, offset).map(Cow::Borrowed)).collect::<Result<_, _>>()?
+        }
+    };
+    let mut features = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, encoded) in encoded.iter().enumerate() {
+        let feature = Table::root(feature_slice(encoded, 0)?.get(4..).unwrap_or_default())?;
+        let Some(geometry) = feature.table(0)? else {
+            continue;
+        };
+        let kind = match geometry_type {
+            0 => geometry.u8(6)?,
+            kind => Some(kind),
+        };
+        let geometries = match parse_geometry(geometry, kind.unwrap_or(0))? {
+            Some(geometries) => geometries,
+            None => {
+                let name = type_name(kind.unwrap_or(0));
+                if checks == GeometryChecks::Strict {
+                    return Err(format!("Feature {} has an unsupported {} geometry (lenient geometry checks skip it)", index, name));
+                }
+                warnings.push(Warning {
+                    feature_index: Some(index),
+                    message: format!("Skipped a feature with an unsupported {} geometry", name),
+                });
+                continue;
+            }
+        };
+        let columns = match feature.tables(2)? {
+            tables if tables.is_empty() => columns.clone(),
+            tables => parse_columns(tables)?,
+        };
+        let properties = parse_properties(feature.bytes(1)?.unwrap_or_default(), &columns)?;
+        for geometry in geometries {
+            if bbox.is_none_or(|bbox| intersects(crate::limits::geometry_bounds(&geometry), bbox)) {
+                features.push(Feature::new(geometry, properties.clone()));
+            }
+        }
+    }
+    Ok((features, warnings))
+}
+
+fn check_crs(header: Table) -> Result<(), String> {
+    let Some(crs) = header.table(10)? else {
+        return Ok(());
+    };
+    match crs.i32(1)?.unwrap_or(0) {
+        0 | 4326 => Ok(()),
+        code => Err(format!("FlatGeobuf files in EPSG:{} are not supported; reproject to WGS84 (EPSG:4326)", code)),
+    }
+}
+
+/// `length` bytes at `offset`, failing if the file ends first
+fn read_exact<R: RangeReader + ?Sized>(reader: &R, offset: u64, length: u64) -> Result<Cow<'_, [u8]>, String> {
+    let bytes = reader.read_range(offset, length)?;
+    match bytes.len() as u64 == length {
+        true => Ok(bytes),
+        false => Err(truncated()),
+    }
+}
+
+/// The feature at `offset` of `data`, with its size prefix
+fn feature_slice(data: &[u8], offset: usize) -> Result<&[u8], String> {
+    let len = u32_at(data, offset)? as usize;
+    data.get(offset..offset + 4 + len).ok_or_else(truncated)
+}
+
+/// Offsets of every feature, from the size prefixes
+fn scan(data: &[u8]) -> Result<Vec<usize>, String> {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        offsets.push(offset);
+        offset += 4 + u32_at(data, offset)? as usize;
+    }
+    Ok(offsets)
+}
+
+/// Node index ranges of each R-tree level, leaves first (the root level is stored first)
+fn level_bounds(items: usize, node_size: usize) -> Result<Vec<std::ops::Range<usize>>, String> {
+    if node_size < 2 {
+        return Err("FlatGeobuf index node size must be at least 2".to_string());
+    }
+    let mut counts = vec![items];
+    let mut n = items;
+    loop {
+        n = n.div_ceil(node_size);
+        counts.push(n);
+        if n == 1 {
+            break;
+        }
+    }
+    let mut end: usize = counts.iter().sum();
+    Ok(counts
+        .into_iter()
+        .map(|count| {
+            end -= count;
+            end..end + count
+        })
+        .collect())
+}
+
+/// Feature offsets whose leaf boxes intersect `bbox` with the offset of the next feature (when
+/// known), in file order
+///
+/// The index starts at `index_start`; each level reads the children of the nodes matched in the
+/// level above, and the leaf reads take one extra node for the next offset.
+fn search<R: RangeReader + ?Sized>(reader: &R, index_start: u64, items: usize, node_size: usize, bbox: Bounds) -> Result<Vec<(u64, Option<u64>)>, String> {
+    let levels = level_bounds(items, node_size)?;
+    let leaves = levels[0].clone();
+    let node = |bytes: &[u8], i: usize| -> Result<(Bounds, u64), String> {
+        let f64_at = |field: usize| f64_at(bytes, i * NODE_LEN + field * 8);
+        let bounds = (f64_at(0)?, f64_at(1)?, f64_at(2)?, f64_at(3)?);
+        Ok((bounds, u64_at(bytes, i * NODE_LEN + 32)?))
+    };
+    
+    let mut offsets = Vec::new();
+    // First node of each group to read in the current level
+    let mut groups = vec![0];
+    for level in (0..levels.len()).rev() {
+        if !groups.iter().all(|first| levels[level].contains(first)) {
+            return Err("FlatGeobuf index is malformed".to_string());
+        }
+        let spans: Vec<(usize, usize)> = groups
+            .iter()
+            .map(|&first| {
+                let end = (first + node_size).min(levels[level].end);
+                (first, if level == 0 { (end + 1).min(leaves.end) } else { end })
+            })
+            .collect();
+        let ranges: Vec<(u64, u64)> =
+            spans.iter().map(|&(first, end)| (index_start + (first * NODE_LEN) as u64, ((end - first) * NODE_LEN) as u64)).collect();
+        let mut children = Vec::new();
+        for (bytes, &(first, end)) in read_ranges(reader, &ranges)?.iter().zip(&spans) {
+            let matched = (first + node_size).min(levels[level].end);
+            for at in first..matched {
+                let (bounds, offset) = node(bytes, at - first)?;
+                if !intersects(bounds, bbox) {
+                    continue;
+                }
+                if level > 0 {
+                    children.push(usize::try_from(offset).map_err(|_| "FlatGeobuf index is malformed".to_string())?);
+                    continue;
+                }
+                let next = match at + 1 < end {
+                    true => Some(node(bytes, at + 1 - first)?.1).filter(|&next| next > offset),
+                    false => None,
+                };
+                offsets.push((offset, next));
+            }
+        }
+        children.sort_unstable();
+        children.dedup();
+        groups = children;
+    }
+    offsets.sort_unstable();
+    Ok(offsets)
+}
+
+fn intersects(a: Bounds, b: Bounds) -> bool {
+    a.0 <= b.2 && a.2 >= b.0 && a.1 <= b.3 && a.3 >= b.1
+}
+
+/// Geometries of a feature (`None` for types the tiler cannot represent)
+fn parse_geometry(geometry: Table, kind: u8) -> Result<Option<Vec<GeometryType>>, String> {
+    let xy = geometry.f64s(1)?;
+    let coords: Vec<Coord<f64>> = xy.chunks_exact(2).map(|pair| Coord { x: pair[0], y: pair[1] }).collect();
+    // Parts end at these coordinate indices (one part when there are none)
+    let ends = geometry.u32s(0)?;
+    let parts = || -> Vec<LineString<f64>> {
+        let ends = match ends.is_empty() {
+            true => vec![coords.len() as u32],
+            false => ends.clone(),
+        };
+        let mut start = 0;
+        ends.iter()
+            .map(|&end| {
+                let end = (end as usize).clamp(start, coords.len());
+                let part = LineString(coords[start..end].to_vec());
+                start = end;
+                part
+            })
+            .collect()
+    };
+    let polygon = |rings: Vec<LineString<f64>>| {
+        let mut rings = rings.into_iter();
+        rings.next().map(|exterior| Polygon::new(exterior, rings.collect()))
+    };
+    
+    Ok(Some(match kind {
+        1 => coords.first().map(|&c| GeometryType::Point(Point(c))).into_iter().collect(),
+        2 => vec![GeometryType::LineString(LineString(coords))],
+        3 => polygon(parts()).map(GeometryType::Polygon).into_iter().collect(),
+        4 => coords.into_iter().map(|c| GeometryType::Point(Point(c))).collect(),
+        5 => parts().into_iter().map(GeometryType::LineString).collect(),
+        6 => {
+            let mut polygons = Vec::new();
+            for part in geometry.tables(7)? {
+                let rings = match parse_geometry(part, 3)? {
+                    Some(mut geometries) if geometries.len() == 1 => geometries.remove(0),
+                    _ => continue,
+                };
+                polygons.extend(rings.polygons().iter().cloned());
+            }
+            match polygons.is_empty() {
+                true => Vec::new(),
+                false => vec![polygon_geometry(polygons)],
+            }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+fn type_name(kind: u8) -> &'static str {
+    match kind {
+        7 => "GeometryCollection",
+        8 => "CircularString",
+        9 => "CompoundCurve",
+        10 => "CurvePolygon",
+        11 => "MultiCurve",
+        12 => "MultiSurface",
+        13 => "Curve",
+        14 => "Surface",
+        15 => "PolyhedralSurface",
+        16 => "TIN",
+        17 => "Triangle",
+        _ => "unknown",
+    }
+}
+
+/// Column names and types
+fn parse_columns(tables: Vec<Table>) -> Result<Vec<(String, u8)>, String> {
+    tables.into_iter().map(|column| Ok((column.str(0)?.unwrap_or_default().to_string(), column.u8(1)?.unwrap_or(0)))).collect()
+}
+
+/// Properties as column index (u16) and value pairs; strings and other variable-length values
+/// are length-prefixed (u32)
+fn parse_properties(bytes: &[u8], columns: &[(String, u8)]) -> Result<Map<String, Value>, String> {
+    let mut properties = Map::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let column = bytes.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize).ok_or_else(truncated)?;
+        let (name, kind) = columns.get(column).ok_or("FlatGeobuf property refers to a missing column")?;
+        at += 2;
+        let fixed = |len: usize| bytes.get(at..at + len).ok_or_else(truncated);
+        let (value, len) = match kind {
+            0 => (Value::from(fixed(1)?[0] as i8), 1),
+            1 => (Value::from(fixed(1)?[0]), 1),
+            2 => (Value::from(fixed(1)?[0] != 0), 1),
+            3 => (Value::from(i16::from_le_bytes(fixed(2)?.try_into().unwrap())), 2),
+            4 => (Value::from(u16::from_le_bytes(fixed(2)?.try_into().unwrap())), 2),
+            5 => (Value::from(i32::from_le_bytes(fixed(4)?.try_into().unwrap())), 4),
+            6 => (Value::from(u32::from_le_bytes(fixed(4)?.try_into().unwrap())), 4),
+            7 => (Value::from(i64::from_le_bytes(fixed(8)?.try_into().unwrap())), 8),
+            8 => (Value::from(u64::from_le_bytes(fixed(8)?.try_into().unwrap())), 8),
+            9 => (Value::from(f32::from_le_bytes(fixed(4)?.try_into().unwrap()) as f64), 4),
+            10 => (Value::from(f64::from_le_bytes(fixed(8)?.try_into().unwrap())), 8),
+            11..=14 => {
+                let len = u32_at(bytes, at)? as usize;
+                let value = bytes.get(at + 4..at + 4 + len).ok_or_else(truncated)?;
+                let text = || String::from_utf8_lossy(value).into_owned();
+                let value = match kind {
+                    12 => serde_json::from_slice(value).unwrap_or_else(|_| Value::String(text())),
+                    // Binary values have no JSON equivalent
+                    14 => Value::Null,
+                    _ => Value::String(text()),
+                };
+                (value, 4 + len)
+            }
+            _ => return Err(format!("Unknown FlatGeobuf column type {}", kind)),
+        };
+        at += len;
+        // Non-finite numbers become null, which carries no property
+        if !value.is_null() {
+            properties.insert(name.clone(), value);
+        }
+    }
+    Ok(properties)
+}
+
+fn truncated() -> String {
+    "FlatGeobuf file is truncated".to_string()
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Result<u32, String> {
+    bytes.get(at..at + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).ok_or_else(truncated)
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> Result<u64, String> {
+    bytes.get(at..at + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap())).ok_or_else(truncated)
+}
+
+fn f64_at(bytes: &[u8], at: usize) -> Result<f64, String> {
+    u64_at(bytes, at).map(f64::from_bits)
+}
+
+/// A FlatBuffers table: fields are found through its vtable, and offsets point forward
+#[derive(Clone, Copy)]
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Table<'a> {
+    fn root(buf: &'a [u8]) -> Result<Self, String> {
+        Ok(Table { buf, pos: u32_at(buf, 0)? as usize })
+    }
+
+    /// Position of field `index`, if present
+    fn field(&self, index: usize) -> Result<Option<usize>, String> {
+        let soffset = u32_at(self.buf, self.pos)? as i32;
+        let vtable = usize::try_from(self.pos as i64 - soffset as i64).map_err(|_| truncated())?;
+        let u16_at = |at: usize| self.buf.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize).ok_or_else(truncated);
+        let slot = 4 + 2 * index;
+        if slot + 2 > u16_at(vtable)? {
+            return Ok(None);
+        }
+        Ok(match u16_at(vtable + slot)? {
+            0 => None,
+            offset => Some(self.pos + offset),
+        })
+    }
+
+    fn scalar<const N: usize>(&self, index: usize) -> Result<Option<[u8; N]>, String> {
+        match self.field(index)? {
+            Some(at) => self.buf.get(at..at + N).map(|b| Some(b.try_into().unwrap())).ok_or_else(truncated),
+            None => Ok(None),
+        }
+    }
+
+    fn u8(&self, index: usize) -> Result<Option<u8>, String> {
+        Ok(self.scalar::<1>(index)?.map(|b| b[0]))
+    }
+
+    fn u16(&self, index: usize) -> Result<Option<u16>, String> {
+        Ok(self.scalar(index)?.map(u16::from_le_bytes))
+    }
+
+    fn i32(&self, index: usize) -> Result<Option<i32>, String> {
+        Ok(self.scalar(index)?.map(i32::from_le_bytes))
+    }
+
+    fn u64(&self, index: usize) -> Result<Option<u64>, String> {
+        Ok(self.scalar(index)?.map(u64::from_le_bytes))
+    }
+
+    /// Target of the offset stored in field `index`
+    fn indirect(&self, index: usize) -> Result<Option<usize>, String> {
+        match self.field(index)? {
+            Some(at) => Ok(Some(at + u32_at(self.buf, at)? as usize)),
+            None => Ok(None),
+        }
+    }
+
+    /// Elements of a vector with `size`-byte elements (strings are byte vectors)
+    fn bytes_of(&self, index: usize, size: usize) -> Result<Option<&'a [u8]>, String> {
+        let Some(at) = self.indirect(index)? else {
+            return Ok(None);
+        };
+        let len = u32_at(self.buf, at)? as usize;
+        self.buf.get(at + 4..at + 4 + len * size).map(Some).ok_or_else(truncated)
+    }
+
+    fn bytes(&self, index: usize) -> Result<Option<&'a [u8]>, String> {
+        self.bytes_of(index, 1)
+    }
+
+    fn str(&self, index: usize) -> Result<Option<&'a str>, String> {
+        match self.bytes(index)? {
+            Some(bytes) => std::str::from_utf8(bytes).map(Some).map_err(|e| format!("FlatGeobuf string is not UTF-8: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    fn f64s(&self, index: usize) -> Result<Vec<f64>, String> {
+        let bytes = self.bytes_of(index, 8)?.unwrap_or_default();
+        Ok(bytes.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect())
+    }
+
+    fn u32s(&self, index: usize) -> Result<Vec<u32>, String> {
+        let bytes = self.bytes_of(index, 4)?.unwrap_or_default();
+        Ok(bytes.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect())
+    }
+
+    fn table(&self, index: usize) -> Result<Option<Table<'a>>, String> {
+        Ok(self.indirect(index)?.map(|pos| Table { buf: self.buf, pos }))
+    }
+
+    fn tables(&self, index: usize) -> Result<Vec<Table<'a>>, String> {
+        let Some(at) = self.indirect(index)? else {
+            return Ok(Vec::new());
+        };
+        let len = u32_at(self.buf, at)? as usize;
+        (0..len)
+            .map(|i| {
+                let element = at + 4 + i * 4;
+                Ok(Table { buf: self.buf, pos: element + u32_at(self.buf, element)? as usize })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FlatBuffers value for the test builder
+    enum Fb {
+        Bytes(Vec<u8>),
+        /// Vector of `count` elements stored as `bytes`
+        Vector(usize, Vec<u8>),
+        Table(Vec<(usize, Fb)>),
+        Tables(Vec<Fb>),
+    }
+
+    fn f64s(values: &[f64]) -> Fb {
+        Fb::Vector(values.len(), values.iter().flat_map(|v| v.to_le_bytes()).collect())
+    }
+
+    fn string(text: &str) -> Fb {
+        Fb::Vector(text.len(), text.as_bytes().to_vec())
+    }
+
+    /// Serialize with the root table first and every offset pointing forward
+    fn build(root: Fb) -> Vec<u8> {
+        let mut buf = vec![0; 4];
+        let pos = write(&mut buf, &root);
+        buf[..4].copy_from_slice(&(pos as u32).to_le_bytes());
+        buf
+    }
+
+    fn patch(buf: &mut [u8], at: usize, target: usize) {
+        buf[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+    }
+
+    /// Write `value` and return its position
+    fn write(buf: &mut Vec<u8>, value: &Fb) -> usize {
+        match value {
+            Fb::Bytes(bytes) => {
+                buf.extend_from_slice(bytes);
+                buf.len() - bytes.len()
+            }
+            Fb::Vector(count, bytes) => {
+                buf.extend_from_slice(&(*count as u32).to_le_bytes());
+                buf.extend_from_slice(bytes);
+                buf.len() - bytes.len() - 4
+            }
+            Fb::Table(fields) => {
+                let slots = fields.iter().map(|(index, _)| index + 1).max().unwrap_or(0);
+                let vtable = buf.len();
+                buf.resize(vtable + 4 + 2 * slots, 0);
+                let pos = buf.len();
+                buf.extend_from_slice(&((pos - vtable) as i32).to_le_bytes());
+                let mut children = Vec::new();
+                for (index, field) in fields {
+                    let offset = (buf.len() - pos) as u16;
+                    buf[vtable + 4 + 2 * index..][..2].copy_from_slice(&offset.to_le_bytes());
+                    match field {
+                        Fb::Bytes(bytes) => buf.extend_from_slice(bytes),
+                        child => {
+                            children.push((buf.len(), child));
+                            buf.extend_from_slice(&[0; 4]);
+                        }
+                    }
+                }
+                buf[vtable..vtable + 2].copy_from_slice(&((4 + 2 * slots) as u16).to_le_bytes());
+                let table_len = (buf.len() - pos) as u16;
+                buf[vtable + 2..vtable + 4].copy_from_slice(&table_len.to_le_bytes());
+                for (at, child) in children {
+                    let target = write(buf, child);
+                    patch(buf, at, target);
+                }
+                pos
+            }
+            Fb::Tables(tables) => {
+                let pos = buf.len();
+                buf.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+                buf.resize(pos + 4 + 4 * tables.len(), 0);
+                for (i, table) in tables.iter().enumerate() {
+                    let target = write(buf, table);
+                    patch(buf, pos + 4 + 4 * i, target);
+                }
+                pos
+            }
+        }
+    }
+
+    /// A packed R-tree over the feature boxes (in the given order) and their byte offsets
+    fn index(items: &[(Bounds, usize)], node_size: usize) -> Vec<u8> {
+        let levels = level_bounds(items.len(), node_size).unwrap();
+        let mut nodes = vec![((0.0, 0.0, 0.0, 0.0), 0); levels[0].end];
+        nodes[levels[0].clone()].copy_from_slice(items);
+        for pair in levels.windows(2) {
+            let (children, parents) = (&pair[0], &pair[1]);
+            for (i, parent) in parents.clone().enumerate() {
+                let first = children.start + i * node_size;
+                let bounds = nodes[first..(first + node_size).min(children.end)].iter().fold(
+                    (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+                    |a, (b, _)| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)),
+                );
+                nodes[parent] = (bounds, first);
+            }
+        }
+        nodes
+            .iter()
+            .flat_map(|&((a, b, c, d), offset)| [a, b, c, d].iter().flat_map(|v| v.to_le_bytes()).chain((offset as u64).to_le_bytes()).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// A file from its header fields and features; `node_size` adds an index
+    fn file(mut header: Vec<(usize, Fb)>, features: Vec<(Bounds, Vec<u8>)>, node_size: Option<u16>) -> Vec<u8> {
+        header.push((8, Fb::Bytes((features.len() as u64).to_le_bytes().to_vec())));
+        header.push((9, Fb::Bytes(node_size.unwrap_or(0).to_le_bytes().to_vec())));
+        let header = build(Fb::Table(header));
+        let mut data = Vec::new();
+        let mut items = Vec::new();
+        for (bounds, feature) in features {
+            items.push((bounds, data.len()));
+            data.extend_from_slice(&(feature.len() as u32).to_le_bytes());
+            data.extend_from_slice(&feature);
+        }
+        
+        let mut bytes = b"fgb\x03fgb\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&header);
+        if let Some(node_size) = node_size {
+            bytes.extend_from_slice(&index(&items, node_size as usize));
+        }
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    fn feature(geometry: Vec<(usize, Fb)>, properties: Vec<u8>) -> Vec<u8> {
+        build(Fb::Table(vec![(0, Fb::Table(geometry)), (1, Fb::Vector(properties.len(), properties))]))
+    }
+
+    fn point(x: f64, y: f64) -> (Bounds, Vec<u8>) {
+        ((x, y, x, y), feature(vec![(1, f64s(&[x, y]))], Vec::new()))
+    }
+
+    #[test]
+    fn test_bbox_pushdown() {
+        // The far-away feature is corrupt: decoding it fails, so the index must skip it
+        let corrupt = ((100.0, 50.0, 100.0, 50.0), vec![0xF0, 0xFF, 0xFF, 0xFF]);
+        let mut features: Vec<_> = (0..5).map(|i| point(i as f64, i as f64)).collect();
+        features.insert(2, corrupt);
+        let bytes = file(vec![(2, Fb::Bytes(vec![1]))], features, Some(2));
+        
+        let (found, _) = parse_flatgeobuf(&bytes, Some((0.5, 0.5, 3.5, 3.5)), GeometryChecks::Lenient).unwrap();
+        let coords: Vec<_> = found.iter().map(|f| crate::limits::geometry_bounds(&f.geometry).0).collect();
+        assert_eq!(coords, [1.0, 2.0, 3.0]);
+        assert_eq!(parse_flatgeobuf(&bytes, None, GeometryChecks::Lenient).unwrap_err(), "FlatGeobuf file is truncated");
+        
+        // Without an index every feature is read, then filtered
+        let features: Vec<_> = (0..5).map(|i| point(i as f64, i as f64)).collect();
+        let bytes = file(vec![(2, Fb::Bytes(vec![1]))], features, None);
+        assert_eq!(parse_flatgeobuf(&bytes, None, GeometryChecks::Lenient).unwrap().0.len(), 5);
+        assert_eq!(parse_flatgeobuf(&bytes, Some((2.5, 2.5, 9.0, 9.0)), GeometryChecks::Lenient).unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn test_read_ranges() {
+        struct Counting<'a>(&'a [u8], std::cell::Cell<(usize, u64)>);
+        impl RangeReader for Counting<'_> {
+            fn read_range(&self, offset: u64, length: u64) -> Result<Cow<'_, [u8]>, String> {
+                let bytes = self.0.read_range(offset, length)?;
+                let (requests, read) = self.1.get();
+                self.1.set((requests + 1, read + bytes.len() as u64));
+                Ok(bytes)
+            }
+        }
+        
+        let features: Vec<_> = (0..5000).map(|i| point((i % 100) as f64, (i / 100) as f64)).collect();
+        let bytes = file(vec![(2, Fb::Bytes(vec![1]))], features, Some(16));
+        let bbox = (10.0, 20.0, 12.0, 21.0);
+        let reader = Counting(&bytes, Default::default());
+        let (found, _) = read_flatgeobuf(&reader, Some(bbox), GeometryChecks::Lenient).unwrap();
+        assert_eq!(found.len(), 6);
+        assert!(found.iter().map(|f| f.geometry.clone()).eq(parse_flatgeobuf(&bytes, Some(bbox), GeometryChecks::Lenient).unwrap().0.into_iter().map(|f| f.geometry)));
+        // Prefix, header, one request per index level and the two rows of features
+        let (requests, read) = reader.1.get();
+        assert!(requests <= 8, "{} requests", requests);
+        assert!(read * 20 < bytes.len() as u64, "{} of {} bytes read", read, bytes.len());
+        
+        // The last feature has no successor to bound it
+        let (found, _) = read_flatgeobuf(&bytes[..], Some((99.0, 49.0, 99.0, 49.0)), GeometryChecks::Lenient).unwrap();
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_properties_and_geometries() {
+        let columns = Fb::Tables(vec![
+            Fb::Table(vec![(0, string("name")), (1, Fb::Bytes(vec![11]))]),
+            Fb::Table(vec![(0, string("count")), (1, Fb::Bytes(vec![5]))]),
+            Fb::Table(vec![(0, string("open")), (1, Fb::Bytes(vec![2]))]),
+            Fb::Table(vec![(0, string("area")), (1, Fb::Bytes(vec![10]))]),
+        ]);
+        let mut properties = vec![0, 0, 4, 0, 0, 0];
+        properties.extend_from_slice(b"Park");
+        properties.extend_from_slice(&[1, 0]);
+        properties.extend_from_slice(&(-3_i32).to_le_bytes());
+        properties.extend_from_slice(&[2, 0, 1, 3, 0]);
+        properties.extend_from_slice(&2.5_f64.to_le_bytes());
+        let square = |x: f64| f64s(&[x, 0.0, x + 1.0, 0.0, x + 1.0, 1.0, x, 1.0, x, 0.0]);
+        let multipolygon = feature(
+            vec![
+                (6, Fb::Bytes(vec![6])),
+                (7, Fb::Tables(vec![Fb::Table(vec![(1, square(0.0))]), Fb::Table(vec![(1, square(2.0))])])),
+            ],
+            properties,
+        );
+        let multiline = feature(vec![(0, Fb::Vector(2, [2_u32, 4].iter().flat_map(|v| v.to_le_bytes()).collect())), (1, f64s(&[0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0])), (6, Fb::Bytes(vec![5]))], Vec::new());
+        let collection = feature(vec![(6, Fb::Bytes(vec![7]))], Vec::new());
+        let bytes = file(
+            vec![(7, columns)],
+            vec![((0.0, 0.0, 3.0, 1.0), multipolygon), ((0.0, 0.0, 3.0, 3.0), multiline), ((0.0, 0.0, 0.0, 0.0), collection)],
+            None,
+        );
+        
+        let (features, warnings) = parse_flatgeobuf(&bytes, None, GeometryChecks::Lenient).unwrap();
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0].geometry.polygons().len(), 2);
+        assert_eq!(Value::Object(features[0].properties.clone()), serde_json::json!({"name": "Park", "count": -3, "open": true, "area": 2.5}));
+        assert!(matches!(&features[1].geometry, GeometryType::LineString(line) if line.0.len() == 2));
+        assert!(warnings[0].message.contains("GeometryCollection"));
+        assert!(parse_flatgeobuf(&bytes, None, GeometryChecks::Strict).unwrap_err().contains("unsupported GeometryCollection"));
+    }
+
+    #[test]
+    fn test_rejected_files() {
+        assert!(!is_flatgeobuf(b"fgb\x03xyz"));
+        let crs = Fb::Table(vec![(1, Fb::Bytes(3857_i32.to_le_bytes().to_vec()))]);
+        let bytes = file(vec![(10, crs)], vec![point(0.0, 0.0)], None);
+        assert!(parse_flatgeobuf(&bytes, None, GeometryChecks::Lenient).unwrap_err().contains("EPSG:3857"));
+        
+        let bytes = file(vec![(2, Fb::Bytes(vec![1]))], vec![point(1.0, 2.0)], Some(16));
+        let reader = crate::input::detect_reader(&bytes).expect("FlatGeobuf should be detected");
+        assert_eq!(reader.read_features(&bytes).unwrap().count(), 1);
+        assert!(parse_flatgeobuf(&bytes[..bytes.len() - 4], None, GeometryChecks::Lenient).is_err());
+    }
+}
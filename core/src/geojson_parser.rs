@@ -1,47 +1,188 @@
 // GeoJSON parsing module
-use geojson::{GeoJson, FeatureCollection, Geometry, Value};
-use geo_types::{Point, LineString, Polygon, Coord};
+#[cfg(feature = "geojson")]
+use geojson::{GeoJson, Geometry, Value};
+use geo_types::{Coord, Point, LineString, MultiPolygon, Polygon};
+#[cfg(feature = "geojson")]
+use crate::logging::log_warn;
+#[cfg(feature = "geojson")]
+use crate::mvt_encoder::GeometryChecks;
+#[cfg(feature = "geojson")]
+use crate::Warning;
+use crate::validity;
 
 /// Parsed feature structure
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Feature {
     pub geometry: GeometryType,
     pub properties: serde_json::Map<String, serde_json::Value>,
+    /// Feature id written to the tiles (the per-tile index is used when `None`)
+    pub id: Option<u64>,
+    /// Per-feature settings (see `FeatureConfig`)
+    pub config: Option<Box<FeatureConfig>>,
+}
+
+impl Feature {
+    /// Feature without an id or per-feature settings
+    pub fn new(geometry: GeometryType, properties: serde_json::Map<String, serde_json::Value>) -> Self {
+        Self { geometry, properties, id: None, config: None }
+    }
+}
+
+/// Per-feature settings, read from a GeoJSON feature's `tippecanoe` foreign member
+/// (`{"layer": "roads", "minzoom": 8, "maxzoom": 14}`)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FeatureConfig {
+    /// Layer the feature is written to by `layers::generate_routed_layers`
+    pub layer: Option<String>,
+    /// Lowest zoom the feature is tiled at
+    pub min_zoom: Option<u8>,
+    /// Highest zoom the feature is tiled at
+    pub max_zoom: Option<u8>,
+}
+
+impl FeatureConfig {
+    /// Read the `tippecanoe` member (`None` when it is missing or sets nothing)
+    pub fn from_foreign_members(members: &serde_json::Map<String, serde_json::Value>) -> Option<Self> {
+        let member = members.get("tippecanoe")?.as_object()?;
+        let zoom = |key: &str| member.get(key).and_then(|v| v.as_u64()).map(|zoom| zoom.min(crate::MAX_ZOOM as u64) as u8);
+        let config = FeatureConfig {
+            layer: member.get("layer").and_then(|v| v.as_str()).map(str::to_string),
+            min_zoom: zoom("minzoom"),
+            max_zoom: zoom("maxzoom"),
+        };
+        (config != FeatureConfig::default()).then_some(config)
+    }
+}
+
+impl Feature {
+    /// Whether the feature is tiled at `zoom` (see `FeatureConfig`)
+    pub fn visible_at(&self, zoom: u8) -> bool {
+        self.config.as_ref().is_none_or(|config| {
+            config.min_zoom.is_none_or(|min| zoom >= min) && config.max_zoom.is_none_or(|max| zoom <= max)
+        })
+    }
 }
 
 /// Supported geometry types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum GeometryType {
     Point(Point<f64>),
     LineString(LineString<f64>),
     Polygon(Polygon<f64>),
+    /// Several polygons of one feature (tiled as one feature with all their rings)
+    MultiPolygon(MultiPolygon<f64>),
+}
+
+impl GeometryType {
+    /// The polygons of a Polygon or MultiPolygon (none for points and lines)
+    pub fn polygons(&self) -> &[Polygon<f64>] {
+        match self {
+            GeometryType::Polygon(polygon) => std::slice::from_ref(polygon),
+            GeometryType::MultiPolygon(polygons) => &polygons.0,
+            GeometryType::Point(_) | GeometryType::LineString(_) => &[],
+        }
+    }
 }
 
 /// Parse features from GeoJSON bytes
+#[cfg(feature = "geojson")]
 pub fn parse_geojson(bytes: &[u8]) -> Result<Vec<Feature>, String> {
-    let geojson_str = std::str::from_utf8(bytes)
-        .map_err(|e| format!("UTF-8 conversion error: {}", e))?;
-    
-    let geojson = geojson_str.parse::<GeoJson>()
-        .map_err(|e| format!("GeoJSON parse error: {}", e))?;
+    parse_geojson_with_members(bytes).map(|(features, _)| features)
+}
+
+/// Parse features and the top-level foreign members (e.g. `name`, `crs`) from GeoJSON bytes
+///
+/// Several concatenated documents, or an array of them, are read as one collection. Features
+/// with unsupported geometry types are skipped (see `parse_geojson_checked`).
+#[cfg(feature = "geojson")]
+pub fn parse_geojson_with_members(bytes: &[u8]) -> Result<(Vec<Feature>, serde_json::Map<String, serde_json::Value>), String> {
+    let (features, members, skipped) = parse_geojson_checked(bytes, GeometryChecks::Lenient)?;
+    log_skipped(&skipped);
+    Ok((features, members))
+}
+
+/// Parse features, top-level foreign members and warnings from GeoJSON bytes
+///
+/// A feature with an unsupported geometry type (e.g. MultiPoint) fails parsing under
+/// `GeometryChecks::Strict`, and is skipped with a warning naming the type otherwise.
+#[cfg(feature = "geojson")]
+pub fn parse_geojson_checked(bytes: &[u8], checks: GeometryChecks) -> Result<crate::Input, String> {
+    let geojson_str = crate::encoding::utf8_text(bytes)?;
     
-    match geojson {
-        GeoJson::FeatureCollection(fc) => parse_feature_collection(fc),
-        GeoJson::Feature(f) => {
-            let features = vec![parse_feature(f)?];
-            Ok(features)
+    convert_documents(split_documents(geojson_str)?, checks)
+}
+
+/// The GeoJSON documents of a text: one, several concatenated, or an array of them
+#[cfg(feature = "geojson")]
+pub(crate) fn split_documents(text: &str) -> Result<Vec<GeoJson>, String> {
+    let mut documents = Vec::new();
+    for value in serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>() {
+        let values = match value.map_err(|e| format!("GeoJSON parse error: {}", e))? {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            documents.push(GeoJson::from_json_value(value).map_err(|e| format!("GeoJSON parse error: {}", e))?);
         }
-        _ => Err("Unsupported GeoJSON format".to_string()),
     }
+    if documents.is_empty() {
+        return Err("GeoJSON parse error: the input has no GeoJSON document".to_string());
+    }
+    Ok(documents)
 }
 
-fn parse_feature_collection(fc: FeatureCollection) -> Result<Vec<Feature>, String> {
+/// Convert already deserialized documents (e.g. from JS objects) into one collection
+///
+/// Foreign members of the collections (or of a lone Feature) are merged, the first document's
+/// value winning on repeated keys. Unsupported geometries are handled as in `parse_geojson_checked`.
+#[cfg(feature = "geojson")]
+pub fn convert_documents(documents: Vec<GeoJson>, checks: GeometryChecks) -> Result<crate::Input, String> {
+    let lone = documents.len() == 1;
+    let mut members = serde_json::Map::new();
+    let mut raw = Vec::new();
+    for document in documents {
+        let (document_members, features) = match document {
+            GeoJson::FeatureCollection(fc) => (fc.foreign_members.unwrap_or_default(), fc.features),
+            GeoJson::Feature(feature) if lone => (feature.foreign_members.clone().unwrap_or_default(), vec![feature]),
+            GeoJson::Feature(feature) => (serde_json::Map::new(), vec![feature]),
+            GeoJson::Geometry(_) => return Err("Unsupported GeoJSON format".to_string()),
+        };
+        for (key, value) in document_members {
+            members.entry(key).or_insert(value);
+        }
+        raw.extend(features);
+    }
+    let mut warnings = Vec::new();
+    let features = parse_features(raw, checks, &mut warnings)?;
+    Ok((features, members, warnings))
+}
+
+/// Convert an already deserialized FeatureCollection or Feature (e.g. from a JS object)
+#[cfg(feature = "geojson")]
+pub fn convert_geojson(geojson: GeoJson) -> Result<(Vec<Feature>, serde_json::Map<String, serde_json::Value>), String> {
+    let (features, members, skipped) = convert_documents(vec![geojson], GeometryChecks::Lenient)?;
+    log_skipped(&skipped);
+    Ok((features, members))
+}
+
+#[cfg(feature = "geojson")]
+fn parse_features(raw: Vec<geojson::Feature>, checks: GeometryChecks, warnings: &mut Vec<Warning>) -> Result<Vec<Feature>, String> {
     let mut features = Vec::new();
     
-    for feature in fc.features {
+    for (index, feature) in raw.into_iter().enumerate() {
+        if let Some(name) = unsupported_type(&feature) {
+            if checks == GeometryChecks::Strict {
+                return Err(format!("Feature {} has an unsupported {} geometry (lenient geometry checks skip it)", index, name));
+            }
+            warnings.push(Warning {
+                feature_index: Some(index),
+                message: format!("Skipped a feature with an unsupported {} geometry", name),
+            });
+            continue;
+        }
         match parse_feature(feature) {
-            Ok(f) => features.push(f),
-            Err(e) => eprintln!("Feature parse warning: {}", e),
+            Ok(feature) => features.push(feature),
+            Err(e) => log_warn!("Feature parse warning: {}", e),
         }
     }
     
@@ -52,66 +193,142 @@ fn parse_feature_collection(fc: FeatureCollection) -> Result<Vec<Feature>, Strin
     Ok(features)
 }
 
-fn parse_feature(feature: geojson::Feature) -> Result<Feature, String> {
+/// Name of the feature's geometry type when the tiler cannot represent it
+#[cfg(feature = "geojson")]
+fn unsupported_type(feature: &geojson::Feature) -> Option<&'static str> {
+    let geometry = feature.geometry.as_ref()?;
+    match geometry.value {
+        Value::Point(_) | Value::LineString(_) | Value::Polygon(_) | Value::MultiPolygon(_) => None,
+        _ => Some(geometry.value.type_name()),
+    }
+}
+
+#[cfg(feature = "geojson")]
+fn log_skipped(warnings: &[Warning]) {
+    for warning in warnings {
+        log_warn!("Feature parse warning: {}", warning);
+    }
+}
+
+#[cfg(feature = "geojson")]
+pub(crate) fn parse_feature(feature: geojson::Feature) -> Result<Feature, String> {
     let geometry = feature.geometry
         .ok_or("No geometry")?;
     
     let geometry_type = parse_geometry(geometry)?;
     
     let properties = feature.properties
-        .unwrap_or_else(|| serde_json::Map::new());
+        .unwrap_or_default();
+    let config = feature.foreign_members.as_ref().and_then(FeatureConfig::from_foreign_members).map(Box::new);
+    // MVT ids are unsigned integers, so only those (and integral strings) are kept
+    let id = match &feature.id {
+        Some(geojson::feature::Id::Number(n)) => n.as_u64(),
+        Some(geojson::feature::Id::String(s)) => s.trim().parse().ok(),
+        None => None,
+    };
     
     Ok(Feature {
         geometry: geometry_type,
         properties,
+        id,
+        config,
     })
 }
 
+#[cfg(feature = "geojson")]
 fn parse_geometry(geometry: Geometry) -> Result<GeometryType, String> {
+    let ring = |coords: &Vec<Vec<f64>>| -> LineString<f64> {
+        coords.iter().map(|c| Coord { x: c[0], y: c[1] }).collect::<Vec<_>>().into()
+    };
     match geometry.value {
         Value::Point(coords) => {
             let point = Point::new(coords[0], coords[1]);
             Ok(GeometryType::Point(point))
         }
-        Value::LineString(coords) => {
-            let line: Vec<Coord<f64>> = coords
-                .iter()
-                .map(|c| Coord { x: c[0], y: c[1] })
-                .collect();
-            Ok(GeometryType::LineString(LineString::from(line)))
-        }
+        Value::LineString(coords) => Ok(GeometryType::LineString(ring(&coords))),
+        // RFC 7946: the first ring is the exterior and the others are its holes
         Value::Polygon(rings) => {
             if rings.is_empty() {
                 return Err("Empty polygon".to_string());
             }
             
-            // Exterior ring
-            let exterior: Vec<Coord<f64>> = rings[0]
-                .iter()
-                .map(|c| Coord { x: c[0], y: c[1] })
-                .collect();
-            
-            // Interior rings (holes)
-            let interiors: Vec<LineString<f64>> = rings[1..]
-                .iter()
-                .map(|ring| {
-                    let coords: Vec<Coord<f64>> = ring
-                        .iter()
-                        .map(|c| Coord { x: c[0], y: c[1] })
-                        .collect();
-                    LineString::from(coords)
-                })
-                .collect();
-            
-            Ok(GeometryType::Polygon(Polygon::new(
-                LineString::from(exterior),
-                interiors,
-            )))
+            let exterior = ring(&rings[0]);
+            let interiors = rings[1..].iter().map(ring).collect();
+            Ok(GeometryType::Polygon(Polygon::new(exterior, interiors)))
+        }
+        // Rings of all parts are pooled, so holes given as separate parts find their exterior
+        Value::MultiPolygon(polygons) => {
+            let rings: Vec<LineString<f64>> = polygons.iter().flatten().map(ring).collect();
+            if rings.is_empty() {
+                return Err("Empty polygon".to_string());
+            }
+            Ok(polygon_geometry(assign_holes(rings)))
         }
         _ => Err(format!("Unsupported geometry type: {:?}", geometry.value)),
     }
 }
 
+/// A Polygon, or a MultiPolygon when there are several
+pub(crate) fn polygon_geometry(mut polygons: Vec<Polygon<f64>>) -> GeometryType {
+    match polygons.len() {
+        1 => GeometryType::Polygon(polygons.remove(0)),
+        _ => GeometryType::MultiPolygon(polygons.into()),
+    }
+}
+
+/// Group rings given without nesting into polygons, by containment
+///
+/// A ring inside an even number of the other rings is an exterior (an island in a hole is
+/// inside two), any other a hole of the innermost ring containing it. Polygons follow the
+/// order of their exteriors, and holes keep their order.
+pub fn assign_holes(rings: Vec<LineString<f64>>) -> Vec<Polygon<f64>> {
+    if rings.len() < 2 {
+        return rings.into_iter().map(|ring| Polygon::new(ring, Vec::new())).collect();
+    }
+    
+    let vertices: Vec<Vec<Coord<f64>>> = rings.iter().map(validity::ring_vertices).collect();
+    let bounds: Vec<[f64; 4]> = vertices.iter().map(|v| validity::bbox(v)).collect();
+    // Rings containing each ring (rings with fewer than 3 vertices contain nothing and lie nowhere)
+    let containers: Vec<Vec<usize>> = (0..rings.len())
+        .map(|i| {
+            (0..rings.len())
+                .filter(|&j| {
+                    j != i
+                        && !vertices[i].is_empty()
+                        && vertices[j].len() >= 3
+                        && validity::overlaps(&bounds[i], &bounds[j])
+                        && validity::inside(&vertices[i], &vertices[j])
+                })
+                .collect()
+        })
+        .collect();
+    let parent = |i: usize| match containers[i].len() % 2 {
+        0 => None,
+        _ => containers[i].iter().copied().find(|&j| containers[j].len() + 1 == containers[i].len()),
+    };
+    
+    let mut polygons: Vec<(LineString<f64>, Vec<LineString<f64>>)> = Vec::new();
+    let mut polygon_of = vec![None; rings.len()];
+    let mut holes = Vec::new();
+    for (i, ring) in rings.into_iter().enumerate() {
+        match parent(i) {
+            Some(parent) => holes.push((parent, ring)),
+            // Exteriors, and holes without a container one level up (e.g. two identical rings)
+            None => {
+                polygon_of[i] = Some(polygons.len());
+                polygons.push((ring, Vec::new()));
+            }
+        }
+    }
+    for (parent, ring) in holes {
+        match polygon_of[parent] {
+            Some(polygon) => polygons[polygon].1.push(ring),
+            None => polygons.push((ring, Vec::new())),
+        }
+    }
+    polygons.into_iter().map(|(exterior, holes)| Polygon::new(exterior, holes)).collect()
+}
+
 /// Calculate bounds (bounding box) from GeoJSON features
 pub fn calculate_bounds(features: &[Feature]) -> Result<(f64, f64, f64, f64), String> {
     if features.is_empty() {
@@ -141,8 +358,8 @@ pub fn calculate_bounds(features: &[Feature]) -> Result<(f64, f64, f64, f64), St
                     max_lat = max_lat.max(coord.y);
                 }
             }
-            GeometryType::Polygon(polygon) => {
-                for coord in polygon.exterior().0.iter() {
+            GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => {
+                for coord in feature.geometry.polygons().iter().flat_map(|polygon| polygon.exterior().0.iter()) {
                     min_lon = min_lon.min(coord.x);
                     min_lat = min_lat.min(coord.y);
                     max_lon = max_lon.max(coord.x);
@@ -152,7 +369,9 @@ pub fn calculate_bounds(features: &[Feature]) -> Result<(f64, f64, f64, f64), St
         }
     }
     
-    Ok((min_lon, min_lat, max_lon, max_lat))
+    // Coordinates beyond the WebMercator limit are drawn at the edge of the map
+    let max_latitude = crate::projection::MAX_LATITUDE;
+    Ok((min_lon, min_lat.max(-max_latitude), max_lon, max_lat.min(max_latitude)))
 }
 
 /// Calculate center coordinates from bounds
@@ -163,6 +382,80 @@ pub fn calculate_center(bounds: (f64, f64, f64, f64)) -> (f64, f64) {
     (center_lon, center_lat)
 }
 
+/// How the dataset center (TileJSON `center`) is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CenterStrategy {
+    /// Midpoint of the bounds
+    #[default]
+    BoundsMidpoint,
+    /// Mean of the feature centroids
+    MeanCentroid,
+    /// Mean of the feature centroids in the most crowded cell of a grid laid over the bounds
+    DensestCell,
+}
+
+/// Grid cells per side used by `CenterStrategy::DensestCell`
+const CENTER_GRID: usize = 16;
+
+/// Calculate the dataset center with the given strategy
+///
+/// Feature centroids are vertex means (polygons use their exterior ring), which is enough
+/// to place the initial map view over the data.
+pub fn calculate_center_with(features: &[Feature], bounds: (f64, f64, f64, f64), strategy: CenterStrategy) -> (f64, f64) {
+    let centroids = features.iter().filter_map(|feature| feature_centroid(&feature.geometry));
+    let mean = |centroids: &mut dyn Iterator<Item = (f64, f64)>| {
+        let (sum_lon, sum_lat, count) = centroids.fold((0.0, 0.0, 0usize), |(lon, lat, n), (x, y)| (lon + x, lat + y, n + 1));
+        (count > 0).then(|| (sum_lon / count as f64, sum_lat / count as f64))
+    };
+    
+    let center = match strategy {
+        CenterStrategy::BoundsMidpoint => None,
+        CenterStrategy::MeanCentroid => mean(&mut centroids.clone()),
+        CenterStrategy::DensestCell => {
+            let (min_lon, min_lat, max_lon, max_lat) = bounds;
+            let cell_of = |(lon, lat): (f64, f64)| {
+                let column = ((lon - min_lon) / (max_lon - min_lon) * CENTER_GRID as f64) as usize;
+                let row = ((lat - min_lat) / (max_lat - min_lat) * CENTER_GRID as f64) as usize;
+                column.min(CENTER_GRID - 1) + row.min(CENTER_GRID - 1) * CENTER_GRID
+            };
+            let mut counts = vec![0usize; CENTER_GRID * CENTER_GRID];
+            for centroid in centroids.clone() {
+                counts[cell_of(centroid)] += 1;
+            }
+            // Ties go to the lowest cell index
+            let densest = (0..counts.len()).max_by_key(|&i| (counts[i], std::cmp::Reverse(i)));
+            densest.and_then(|cell| mean(&mut centroids.clone().filter(|&c| cell_of(c) == cell)))
+        }
+    };
+    center.unwrap_or_else(|| calculate_center(bounds))
+}
+
+/// Mean of the feature's vertices (the exterior rings' for polygons)
+pub(crate) fn feature_centroid(geometry: &GeometryType) -> Option<(f64, f64)> {
+    let coords: std::borrow::Cow<[Coord<f64>]> = match geometry {
+        GeometryType::Point(point) => return Some((point.x(), point.y())),
+        GeometryType::LineString(line) => (&line.0[..]).into(),
+        // Skip the closing coordinate so the first vertex is not counted twice
+        GeometryType::Polygon(polygon) => {
+            let ring = &polygon.exterior().0;
+            (&ring[..ring.len().saturating_sub(1)]).into()
+        }
+        GeometryType::MultiPolygon(polygons) => polygons
+            .iter()
+            .flat_map(|polygon| {
+                let ring = &polygon.exterior().0;
+                ring[..ring.len().saturating_sub(1)].iter().copied()
+            })
+            .collect::<Vec<_>>()
+            .into(),
+    };
+    let count = coords.len() as f64;
+    (!coords.is_empty()).then(|| {
+        let (lon, lat) = coords.iter().fold((0.0, 0.0), |(lon, lat), c| (lon + c.x, lat + c.y));
+        (lon / count, lat / count)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,4 +489,96 @@ mod tests {
             _ => panic!("Expected Point geometry"),
         }
     }
+
+    #[test]
+    fn test_concatenated_documents() {
+        let collection = |name: &str, lon: f64| {
+            format!(r#"{{"type": "FeatureCollection", "name": "{}", "features": [
+                {{"type": "Feature", "properties": {{}}, "geometry": {{"type": "Point", "coordinates": [{}, 0]}}}}]}}"#, name, lon)
+        };
+        let longitudes = |features: &[Feature]| -> Vec<f64> {
+            features.iter().map(|feature| match &feature.geometry {
+                GeometryType::Point(point) => point.x(),
+                other => panic!("unexpected geometry: {:?}", other),
+            }).collect()
+        };
+        
+        let concatenated = format!("{}\n{}{}", collection("a", 1.0), collection("b", 2.0), collection("c", 3.0));
+        let (features, members) = parse_geojson_with_members(concatenated.as_bytes()).unwrap();
+        assert_eq!(longitudes(&features), vec![1.0, 2.0, 3.0]);
+        assert_eq!(members["name"], "a");
+        
+        let array = format!("[{}, {}]", collection("a", 1.0), collection("b", 2.0));
+        assert_eq!(longitudes(&parse_geojson(array.as_bytes()).unwrap()), vec![1.0, 2.0]);
+        
+        assert!(parse_geojson(b"  ").unwrap_err().starts_with("GeoJSON parse error"));
+        let truncated = format!("{}{{\"type\": ", collection("a", 1.0));
+        assert!(parse_geojson(truncated.as_bytes()).unwrap_err().starts_with("GeoJSON parse error"));
+    }
+
+    #[test]
+    fn test_unsupported_geometry_types() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "MultiPoint", "coordinates": [[1, 2], [3, 4]]}}
+        ]}"#;
+        
+        let err = parse_geojson_checked(geojson, GeometryChecks::Strict).unwrap_err();
+        assert!(err.starts_with("Feature 1 has an unsupported MultiPoint geometry"));
+        let (features, _, warnings) = parse_geojson_checked(geojson, GeometryChecks::Lenient).unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].feature_index, Some(1));
+        assert!(warnings[0].message.contains("MultiPoint"));
+        
+        // Generation skips it by default, reports it in the metadata, and fails under strict checks
+        let (_, metadata) = crate::generate_tiles_with_metadata(geojson, 0, 0, "test").unwrap();
+        assert_eq!(metadata.warnings, warnings);
+        let options = crate::TileOptions { geometry_checks: GeometryChecks::Strict, ..Default::default() };
+        assert!(crate::generate_tiles_with_options(geojson, 0, 0, "test", &options).is_err());
+    }
+
+    #[test]
+    fn test_holes_assigned_by_containment() {
+        let square = |x: f64, y: f64, size: f64| vec![[x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y]];
+        let parse = |geometry: serde_json::Value| -> Vec<Feature> {
+            let geojson = serde_json::json!({"type": "Feature", "properties": {"name": "lake"}, "geometry": geometry});
+            parse_geojson(geojson.to_string().as_bytes()).unwrap()
+        };
+        let polygons = |feature: &Feature| -> Vec<(usize, f64)> {
+            feature.geometry.polygons().iter().map(|polygon| (polygon.interiors().len(), polygon.exterior().0[0].x)).collect()
+        };
+        
+        // Polygon rings keep their RFC 7946 roles, so a "hole" outside the exterior is reported
+        let siblings = parse(serde_json::json!({"type": "Polygon", "coordinates": [square(0.0, 0.0, 1.0), square(5.0, 0.0, 4.0), square(6.0, 1.0, 1.0)]}));
+        assert_eq!(siblings.len(), 1);
+        assert_eq!(polygons(&siblings[0]), [(2, 0.0)]);
+        let issues = validity::validate_features(&siblings);
+        assert!(issues.iter().any(|issue| issue.kind == validity::ValidityKind::HoleOutsideShell));
+        
+        // MultiPolygon parts are pooled: a hole given as its own part, holding an island, stays one feature
+        let parts = parse(serde_json::json!({"type": "MultiPolygon", "coordinates": [
+            [square(3.0, 3.0, 4.0)], [square(0.0, 0.0, 10.0)], [square(4.0, 4.0, 1.0)]
+        ]}));
+        assert_eq!(parts.len(), 1);
+        assert!(matches!(parts[0].geometry, GeometryType::MultiPolygon(_)));
+        assert_eq!(polygons(&parts[0]), [(1, 0.0), (0, 4.0)]);
+    }
+
+    #[test]
+    fn test_calculate_center_with() {
+        // A coastal dataset: a cluster on land and one far-off island
+        let features: Vec<Feature> = [(139.70, 35.68), (139.71, 35.69), (139.72, 35.70), (153.98, 24.28)]
+            .into_iter()
+            .map(|(lon, lat)| Feature::new(GeometryType::Point(Point::new(lon, lat)), serde_json::Map::new()))
+            .collect();
+        let bounds = calculate_bounds(&features).unwrap();
+        
+        let (lon, lat) = calculate_center_with(&features, bounds, CenterStrategy::BoundsMidpoint);
+        assert!((lon - 146.84).abs() < 1e-9 && (lat - 29.99).abs() < 1e-9);
+        let (lon, _) = calculate_center_with(&features, bounds, CenterStrategy::MeanCentroid);
+        assert!((lon - 143.2775).abs() < 1e-9);
+        let (lon, lat) = calculate_center_with(&features, bounds, CenterStrategy::DensestCell);
+        assert!((lon - 139.71).abs() < 1e-9 && (lat - 35.69).abs() < 1e-9);
+    }
 }
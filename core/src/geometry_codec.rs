@@ -0,0 +1,575 @@
+// Geometry codec module
+// MVT geometry command streams: MoveTo/LineTo/ClosePath with zig-zag encoded deltas
+
+use crate::mvt_encoder::vector_tile::tile::GeomType;
+use crate::mvt_encoder::Winding;
+use crate::tiler::{ring_signed_area, TileGeometry};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Malformed geometry found while encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeometryError {
+    /// No commands were produced (e.g. every polygon ring had fewer than 4 points)
+    Empty,
+    /// First and last points of a polygon ring differ
+    UnclosedRing { ring: usize },
+    /// A command announces more parameters than the stream holds
+    CommandCount { position: usize, expected: usize, available: usize },
+    /// A command that is unknown or not allowed at this point of the geometry
+    UnexpectedCommand { position: usize, command: u32 },
+    /// The stream ends mid-geometry (a line without LineTo, a ring without ClosePath)
+    Incomplete,
+    /// A coordinate or the delta to it does not fit in an i32
+    CoordinateOverflow { position: usize },
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::Empty => write!(f, "Geometry encodes to no commands"),
+            GeometryError::UnclosedRing { ring } => write!(f, "Polygon ring {} is not closed", ring),
+            GeometryError::CommandCount { position, expected, available } => write!(
+                f,
+                "Command at {} expects {} parameters but only {} follow",
+                position, expected, available
+            ),
+            GeometryError::UnexpectedCommand { position, command } => {
+                write!(f, "Unexpected command {} at {}", command, position)
+            }
+            GeometryError::Incomplete => write!(f, "Geometry command stream ends mid-geometry"),
+            GeometryError::CoordinateOverflow { position } => {
+                write!(f, "Coordinate at {} is out of the i32 range", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+impl From<GeometryError> for String {
+    fn from(error: GeometryError) -> Self {
+        format!("Invalid geometry: {}", error)
+    }
+}
+
+/// Number of command integers `encode_geometry` produces (so the buffer is allocated once)
+fn command_len(geometry: &TileGeometry) -> usize {
+    // MoveTo + LineTo headers, plus two parameters per vertex
+    let path_len = |points: usize| if points > 1 { 2 + 2 * points } else { 3 };
+    match geometry {
+        TileGeometry::Point(..) => 3,
+        TileGeometry::LineString(coords) => path_len(coords.len()),
+        TileGeometry::Polygon(rings) => rings
+            .iter()
+            .filter(|ring| ring.len() >= 4)
+            .map(|ring| path_len(ring.len() - 1) + 1)
+            .sum(),
+    }
+}
+
+/// Encode geometry in MVT format
+///
+/// The command stream is checked before it is returned, so malformed geometry never reaches a tile.
+pub(crate) fn encode_geometry(geometry: &TileGeometry, winding: Winding) -> Result<(GeomType, Vec<u32>), GeometryError> {
+    let (geom_type, commands) = build_commands(geometry, winding)?;
+    validate_commands(geom_type, &commands)?;
+    Ok((geom_type, commands))
+}
+
+/// Check that a geometry can be encoded
+pub fn check_geometry(geometry: &TileGeometry) -> Result<(), GeometryError> {
+    encode_geometry(geometry, Winding::default()).map(|_| ())
+}
+
+/// Check a command stream against the MVT geometry grammar
+///
+/// Points are one MoveTo; lines repeat MoveTo, LineTo; polygons repeat MoveTo, LineTo, ClosePath.
+pub fn validate_commands(geom_type: GeomType, commands: &[u32]) -> Result<(), GeometryError> {
+    if commands.is_empty() {
+        return Err(GeometryError::Empty);
+    }
+    
+    let mut position = 0;
+    let mut previous = None;
+    while position < commands.len() {
+        let (command, count) = (commands[position] & 0x7, commands[position] >> 3);
+        let allowed = matches!(
+            (geom_type, previous, command),
+            (_, None, 1)
+                | (GeomType::Linestring, Some(1), 2)
+                | (GeomType::Linestring, Some(2), 1)
+                | (GeomType::Polygon, Some(1), 2)
+                | (GeomType::Polygon, Some(2), 7)
+                | (GeomType::Polygon, Some(7), 1)
+        );
+        // Lines and rings start with a single MoveTo, and ClosePath takes no parameters
+        let valid_count = match command {
+            1 => count == 1 || (geom_type == GeomType::Point && count > 0),
+            2 => count > 0,
+            _ => count == 1,
+        };
+        if !allowed || !valid_count {
+            return Err(GeometryError::UnexpectedCommand { position, command });
+        }
+        
+        let expected = if command == 7 { 0 } else { 2 * count as usize };
+        let available = commands.len() - position - 1;
+        if available < expected {
+            return Err(GeometryError::CommandCount { position, expected, available });
+        }
+        position += 1 + expected;
+        previous = Some(command);
+    }
+    
+    match (geom_type, previous) {
+        (GeomType::Point, Some(1)) | (GeomType::Linestring, Some(2)) | (GeomType::Polygon, Some(7)) => Ok(()),
+        _ => Err(GeometryError::Incomplete),
+    }
+}
+
+fn build_commands(geometry: &TileGeometry, winding: Winding) -> Result<(GeomType, Vec<u32>), GeometryError> {
+    match geometry {
+        TileGeometry::Point(x, y) => {
+            let commands = vec![
+                // MoveTo command (command=1, count=1)
+                command_integer(1, 1),
+                // Coordinates (zig-zag encoding)
+                zigzag_encode(*x),
+                zigzag_encode(*y),
+            ];
+            
+            Ok((GeomType::Point, commands))
+        }
+        TileGeometry::LineString(coords) => {
+            if coords.is_empty() {
+                return Err(GeometryError::Empty);
+            }
+            
+            let mut commands = Vec::with_capacity(command_len(geometry));
+            
+            // MoveTo first point (command=1, count=1)
+            commands.push(command_integer(1, 1));
+            commands.push(zigzag_encode(coords[0].0));
+            commands.push(zigzag_encode(coords[0].1));
+            
+            if coords.len() > 1 {
+                // LineTo remaining points (command=2, count=n-1)
+                commands.push(command_integer(2, (coords.len() - 1) as u32));
+                
+                for pair in coords.windows(2) {
+                    push_delta(&mut commands, pair[1], pair[0])?;
+                }
+            }
+            
+            Ok((GeomType::Linestring, commands))
+        }
+        TileGeometry::Polygon(rings) => {
+            if rings.is_empty() {
+                return Err(GeometryError::Empty);
+            }
+            
+            let mut commands = Vec::with_capacity(command_len(geometry));
+            // MoveTo is relative to the end of the previous ring
+            let mut cursor = (0, 0);
+            let first_sign = rings.iter().find(|ring| ring.len() >= 4).map_or(0.0, |ring| ring_signed_area(ring).signum());
+            let exterior_sign = match winding {
+                Winding::MvtScreen => 1.0,
+                Winding::Rfc7946 => -1.0,
+            };
+            
+            for (index, ring) in rings.iter().enumerate() {
+                if ring.len() < 4 {
+                    // Polygon requires at least 4 points (first and last are the same)
+                    continue;
+                }
+                if ring[0] != ring[ring.len() - 1] {
+                    return Err(GeometryError::UnclosedRing { ring: index });
+                }
+                let sign = ring_signed_area(ring).signum();
+                let wanted = if sign == first_sign { exterior_sign } else { -exterior_sign };
+                let ring: Cow<[(i32, i32)]> = match sign != 0.0 && sign != wanted {
+                    true => Cow::Owned(ring.iter().rev().copied().collect()),
+                    false => Cow::Borrowed(ring),
+                };
+                
+                // In GeoJSON, last point = first point, so exclude the last point
+                let point_count = ring.len() - 1;
+                
+                // MoveTo first point
+                commands.push(command_integer(1, 1));
+                push_delta(&mut commands, ring[0], cursor)?;
+                cursor = ring[point_count - 1];
+                
+                // LineTo remaining points (excluding last point)
+                if point_count > 1 {
+                    commands.push(command_integer(2, (point_count - 1) as u32));
+                    
+                    for pair in ring[..point_count].windows(2) {
+                        push_delta(&mut commands, pair[1], pair[0])?;
+                    }
+                }
+                
+                // ClosePath
+                commands.push(command_integer(7, 1));
+            }
+            
+            Ok((GeomType::Polygon, commands))
+        }
+    }
+}
+
+/// Encode command and count
+fn command_integer(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+/// Append the zig-zag encoded move from `from` to `to` (points further apart than an i32 cannot be written)
+fn push_delta(commands: &mut Vec<u32>, to: (i32, i32), from: (i32, i32)) -> Result<(), GeometryError> {
+    let overflow = GeometryError::CoordinateOverflow { position: commands.len() };
+    let dx = to.0.checked_sub(from.0).ok_or_else(|| overflow.clone())?;
+    let dy = to.1.checked_sub(from.1).ok_or(overflow)?;
+    commands.push(zigzag_encode(dx));
+    commands.push(zigzag_encode(dy));
+    Ok(())
+}
+
+/// Zig-zag encoding
+fn zigzag_encode(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+/// Decode a geometry command stream (polygon rings are returned closed, like the tiler produces them)
+pub(crate) fn decode_geometry(geom_type: GeomType, commands: &[u32]) -> Result<Vec<TileGeometry>, GeometryError> {
+    let mut paths: Vec<Vec<(i32, i32)>> = Vec::new();
+    let mut cursor = (0i32, 0i32);
+    let mut i = 0;
+    
+    while i < commands.len() {
+        let position = i;
+        let id = commands[i] & 0x7;
+        let count = (commands[i] >> 3) as usize;
+        i += 1;
+        
+        match id {
+            // MoveTo / LineTo
+            1 | 2 => {
+                let available = commands.len() - i;
+                if available < count * 2 {
+                    return Err(GeometryError::CommandCount { position, expected: count * 2, available });
+                }
+                if id == 2 && paths.is_empty() {
+                    return Err(GeometryError::UnexpectedCommand { position, command: id });
+                }
+                for _ in 0..count {
+                    let overflow = GeometryError::CoordinateOverflow { position: i };
+                    cursor = match (
+                        cursor.0.checked_add(zigzag_decode(commands[i])),
+                        cursor.1.checked_add(zigzag_decode(commands[i + 1])),
+                    ) {
+                        (Some(x), Some(y)) => (x, y),
+                        _ => return Err(overflow),
+                    };
+                    i += 2;
+                    
+                    match (id, paths.last_mut()) {
+                        (2, Some(path)) => path.push(cursor),
+                        _ => paths.push(vec![cursor]),
+                    }
+                }
+            }
+            // ClosePath
+            7 => {
+                if let Some(path) = paths.last_mut() {
+                    if let Some(&first) = path.first() {
+                        path.push(first);
+                    }
+                }
+            }
+            _ => return Err(GeometryError::UnexpectedCommand { position, command: id }),
+        }
+    }
+    
+    Ok(match geom_type {
+        GeomType::Point => paths.into_iter().flatten().map(|(x, y)| TileGeometry::Point(x, y)).collect(),
+        GeomType::Linestring => paths.into_iter().map(TileGeometry::LineString).collect(),
+        GeomType::Polygon if !paths.is_empty() => vec![TileGeometry::Polygon(paths)],
+        _ => Vec::new(),
+    })
+}
+
+/// Zig-zag decoding
+fn zigzag_decode(n: u32) -> i32 {
+    ((n >> 1) as i32) ^ -((n & 1) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_len_matches_encoding() {
+        let square = vec![(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)];
+        let geometries = vec![
+            TileGeometry::Point(5, 5),
+            TileGeometry::LineString(vec![(0, 0)]),
+            TileGeometry::LineString(vec![(0, 0), (5, 5), (9, 1)]),
+            TileGeometry::Polygon(vec![square.clone(), vec![(0, 0), (1, 1), (0, 0)], square]),
+        ];
+        
+        for geometry in &geometries {
+            let (_, commands) = build_commands(geometry, Winding::default()).unwrap();
+            assert_eq!(commands.len(), command_len(geometry), "{:?}", geometry);
+            assert_eq!(commands.capacity(), commands.len());
+        }
+    }
+
+    #[test]
+    fn test_validate_commands() {
+        let moveto = command_integer(1, 1);
+        let lineto = |count| command_integer(2, count);
+        let close = command_integer(7, 1);
+        
+        assert_eq!(validate_commands(GeomType::Point, &[command_integer(1, 2), 0, 0, 2, 2]), Ok(()));
+        assert_eq!(validate_commands(GeomType::Linestring, &[moveto, 0, 0, lineto(1), 2, 2]), Ok(()));
+        assert_eq!(validate_commands(GeomType::Linestring, &[moveto, 0, 0]), Err(GeometryError::Incomplete));
+        assert_eq!(
+            validate_commands(GeomType::Linestring, &[moveto, 0, 0, lineto(2), 2, 2]),
+            Err(GeometryError::CommandCount { position: 3, expected: 4, available: 2 })
+        );
+        assert_eq!(
+            validate_commands(GeomType::Polygon, &[moveto, 0, 0, close]),
+            Err(GeometryError::UnexpectedCommand { position: 3, command: 7 })
+        );
+        assert_eq!(validate_commands(GeomType::Polygon, &[]), Err(GeometryError::Empty));
+        let degenerate = TileGeometry::Polygon(vec![vec![(0, 0), (1, 1), (0, 0)]]);
+        assert_eq!(check_geometry(&degenerate), Err(GeometryError::Empty));
+        let unclosed = TileGeometry::Polygon(vec![vec![(0, 0), (4, 0), (4, 4), (0, 4)]]);
+        assert_eq!(check_geometry(&unclosed), Err(GeometryError::UnclosedRing { ring: 0 }));
+    }
+
+    #[test]
+    fn test_winding() {
+        // Exterior turning the RFC 7946 way (negative area in tile coordinates) with a hole turning the other way
+        let exterior = vec![(0, 0), (0, 100), (100, 100), (100, 0), (0, 0)];
+        let hole = vec![(20, 20), (80, 20), (80, 80), (20, 80), (20, 20)];
+        let polygon = TileGeometry::Polygon(vec![exterior.clone(), hole.clone()]);
+        let rings_with = |winding| {
+            let (geom_type, commands) = encode_geometry(&polygon, winding).unwrap();
+            match &decode_geometry(geom_type, &commands).unwrap()[0] {
+                TileGeometry::Polygon(rings) => rings.iter().map(|ring| ring_signed_area(ring).signum()).collect::<Vec<_>>(),
+                other => panic!("unexpected geometry: {:?}", other),
+            }
+        };
+        
+        assert_eq!(rings_with(Winding::MvtScreen), [1.0, -1.0]);
+        assert_eq!(rings_with(Winding::Rfc7946), [-1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_zigzag_roundtrip() {
+        for n in [0, 1, -1, 4095, -4096, i32::MAX, i32::MIN] {
+            assert_eq!(zigzag_decode(((n << 1) ^ (n >> 31)) as u32), n);
+        }
+        for n in (i32::MIN..=i32::MAX).step_by(65_521) {
+            assert_eq!(zigzag_decode(zigzag_encode(n)), n);
+        }
+        // Small magnitudes get small codes, alternating in sign
+        assert_eq!((-2..=2).map(zigzag_encode).collect::<Vec<_>>(), [3, 1, 0, 2, 4]);
+    }
+
+    #[test]
+    fn test_truncated_geometry() {
+        assert_eq!(
+            decode_geometry(GeomType::Point, &[9, 2]).unwrap_err(),
+            GeometryError::CommandCount { position: 0, expected: 2, available: 1 }
+        );
+    }
+
+    /// Deterministic pseudo-random numbers (a 64-bit LCG), so failures reproduce
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: u32) -> i32 {
+            self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            ((self.0 >> 33) % n as u64) as i32
+        }
+
+        fn coord(&mut self) -> (i32, i32) {
+            // Mostly inside the tile, sometimes far outside it (as unclipped geometries are)
+            let range = if self.below(8) == 0 { 1 << 20 } else { 4096 };
+            (self.below(2 * range) - range as i32, self.below(2 * range) - range as i32)
+        }
+
+        fn path(&mut self, min: u32, max: u32) -> Vec<(i32, i32)> {
+            let len = min as i32 + self.below(max - min + 1);
+            (0..len).map(|_| self.coord()).collect()
+        }
+
+        /// A value at or near either end of the i32 range, or around zero
+        fn extreme(&mut self) -> i32 {
+            match self.below(3) {
+                0 => i32::MIN + self.below(1000),
+                1 => i32::MAX - self.below(1000),
+                _ => self.below(2000) - 1000,
+            }
+        }
+    }
+
+    #[test]
+    fn test_extreme_deltas() {
+        let mut rng = Lcg(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..3000 {
+            let path: Vec<_> = (0..2 + rng.below(4)).map(|_| (rng.extreme(), rng.extreme())).collect();
+            let fits = path.windows(2).all(|pair| {
+                let dx = pair[1].0 as i64 - pair[0].0 as i64;
+                let dy = pair[1].1 as i64 - pair[0].1 as i64;
+                i32::try_from(dx).is_ok() && i32::try_from(dy).is_ok()
+            });
+            
+            let geometry = TileGeometry::LineString(path.clone());
+            match encode_geometry(&geometry, Winding::default()) {
+                Ok((geom_type, commands)) => {
+                    assert!(fits, "{:?}", path);
+                    let decoded = decode_geometry(geom_type, &commands).unwrap();
+                    assert_eq!(format!("{:?}", decoded), format!("{:?}", [geometry]));
+                }
+                Err(error) => {
+                    assert!(!fits, "{:?}: {}", path, error);
+                    assert!(matches!(error, GeometryError::CoordinateOverflow { .. }), "{}", error);
+                }
+            }
+        }
+        
+        // Every delta fits, but together they run past the ends of the range
+        let z = zigzag_encode;
+        for (x, dx) in [(i32::MAX, 1), (i32::MIN, -1), (i32::MAX, i32::MAX), (-1, i32::MIN)] {
+            let commands = [command_integer(1, 1), z(x), z(0), command_integer(2, 1), z(dx), z(0)];
+            assert_eq!(validate_commands(GeomType::Linestring, &commands), Ok(()));
+            assert_eq!(
+                decode_geometry(GeomType::Linestring, &commands).unwrap_err(),
+                GeometryError::CoordinateOverflow { position: 4 }
+            );
+        }
+        let points = [command_integer(1, 2), z(i32::MIN), z(i32::MAX), z(i32::MAX), z(i32::MIN)];
+        assert_eq!(decode_geometry(GeomType::Point, &points).unwrap().len(), 2);
+        assert_eq!(
+            decode_geometry(GeomType::Point, &[command_integer(1, 2), z(0), z(i32::MAX), z(0), z(1)]).unwrap_err(),
+            GeometryError::CoordinateOverflow { position: 3 }
+        );
+    }
+
+    #[test]
+    fn test_random_geometries_roundtrip() {
+        let mut rng = Lcg(0x2545_f491_4f6c_dd1d);
+        for _ in 0..3000 {
+            let geometry = match rng.below(3) {
+                0 => {
+                    let (x, y) = rng.coord();
+                    TileGeometry::Point(x, y)
+                }
+                1 => TileGeometry::LineString(rng.path(2, 40)),
+                _ => TileGeometry::Polygon(
+                    (0..1 + rng.below(4))
+                        .map(|_| {
+                            // Some rings are degenerate (fewer than 4 points) and left out
+                            let mut ring = rng.path(1, 12);
+                            ring.push(ring[0]);
+                            ring
+                        })
+                        .collect(),
+                ),
+            };
+            let winding = if rng.below(2) == 0 { Winding::MvtScreen } else { Winding::Rfc7946 };
+            
+            let (geom_type, commands) = match encode_geometry(&geometry, winding) {
+                Ok(encoded) => encoded,
+                Err(GeometryError::Empty) => {
+                    assert!(matches!(&geometry, TileGeometry::Polygon(rings) if rings.iter().all(|ring| ring.len() < 4)));
+                    continue;
+                }
+                Err(error) => panic!("{:?}: {}", geometry, error),
+            };
+            assert_eq!(commands.len(), command_len(&geometry), "{:?}", geometry);
+            let decoded = decode_geometry(geom_type, &commands).unwrap();
+            
+            match (&geometry, decoded.as_slice()) {
+                (TileGeometry::Polygon(rings), [TileGeometry::Polygon(decoded_rings)]) => {
+                    let rings: Vec<_> = rings.iter().filter(|ring| ring.len() >= 4).collect();
+                    assert_eq!(rings.len(), decoded_rings.len());
+                    for (ring, decoded_ring) in rings.into_iter().zip(decoded_rings) {
+                        // Rings may be turned to the winding, keeping their first point
+                        let reversed: Vec<_> = ring.iter().rev().copied().collect();
+                        assert!(decoded_ring == ring || *decoded_ring == reversed, "{:?} != {:?}", decoded_ring, ring);
+                    }
+                }
+                (geometry, decoded) => assert_eq!(format!("{:?}", decoded), format!("{:?}", [geometry])),
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_part_command_sequences() {
+        let moveto = |count| command_integer(1, count);
+        let lineto = |count| command_integer(2, count);
+        let close = command_integer(7, 1);
+        let z = zigzag_encode;
+        
+        // MultiPoint: one MoveTo with a parameter pair per point, each relative to the previous one
+        let multi_point = [moveto(3), z(5), z(5), z(2), z(-3), z(-10), z(0)];
+        assert_eq!(validate_commands(GeomType::Point, &multi_point), Ok(()));
+        let points = decode_geometry(GeomType::Point, &multi_point).unwrap();
+        assert_eq!(format!("{:?}", points), "[Point(5, 5), Point(7, 2), Point(-3, 2)]");
+        
+        // MultiLineString: the second MoveTo continues from the end of the first line
+        let multi_line = [moveto(1), z(2), z(2), lineto(2), z(0), z(8), z(8), z(0), moveto(1), z(-10), z(-10), lineto(1), z(1), z(1)];
+        assert_eq!(validate_commands(GeomType::Linestring, &multi_line), Ok(()));
+        let lines = decode_geometry(GeomType::Linestring, &multi_line).unwrap();
+        assert_eq!(format!("{:?}", lines), "[LineString([(2, 2), (2, 10), (10, 10)]), LineString([(0, 0), (1, 1)])]");
+        assert_eq!(
+            validate_commands(GeomType::Linestring, &[moveto(1), z(0), z(0), moveto(1), z(1), z(1)]),
+            Err(GeometryError::UnexpectedCommand { position: 3, command: 1 })
+        );
+        
+        // MultiPolygon: two exteriors (same winding), the first with a hole
+        let square = |x: i32, y: i32, size: i32| vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size), (x, y)];
+        let hole: Vec<_> = square(2, 2, 4).into_iter().rev().collect();
+        let rings = vec![square(0, 0, 8), hole, square(20, 0, 8)];
+        let (geom_type, commands) = encode_geometry(&TileGeometry::Polygon(rings.clone()), Winding::MvtScreen).unwrap();
+        // Each ring is MoveTo, LineTo of 3 points and ClosePath (11 integers)
+        assert_eq!(commands.len(), 33);
+        assert!([10, 21, 32].iter().all(|&i| commands[i] == close));
+        // The hole's MoveTo is relative to the last vertex written for the exterior, (0, 8)
+        assert_eq!(&commands[11..14], &[moveto(1), z(2), z(-6)]);
+        match decode_geometry(geom_type, &commands).unwrap().as_slice() {
+            [TileGeometry::Polygon(decoded)] => assert_eq!(decoded, &rings),
+            other => panic!("unexpected geometries: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // A single vertex makes no line (MoveTo without LineTo)
+        let single = TileGeometry::LineString(vec![(3, 4)]);
+        assert_eq!(check_geometry(&single), Err(GeometryError::Incomplete));
+        assert_eq!(check_geometry(&TileGeometry::LineString(Vec::new())), Err(GeometryError::Empty));
+        assert_eq!(check_geometry(&TileGeometry::Polygon(Vec::new())), Err(GeometryError::Empty));
+        
+        // Repeated vertices are written as zero-length moves
+        let repeated = TileGeometry::LineString(vec![(7, 7), (7, 7)]);
+        let (geom_type, commands) = encode_geometry(&repeated, Winding::default()).unwrap();
+        assert_eq!(commands, [command_integer(1, 1), 14, 14, command_integer(2, 1), 0, 0]);
+        assert_eq!(format!("{:?}", decode_geometry(geom_type, &commands).unwrap()), "[LineString([(7, 7), (7, 7)])]");
+        
+        // A stream without geometry decodes to nothing
+        assert!(decode_geometry(GeomType::Polygon, &[]).unwrap().is_empty());
+        assert_eq!(
+            decode_geometry(GeomType::Linestring, &[command_integer(2, 1), 0, 0]).unwrap_err(),
+            GeometryError::UnexpectedCommand { position: 0, command: 2 }
+        );
+        assert_eq!(
+            decode_geometry(GeomType::Point, &[command_integer(5, 1)]).unwrap_err(),
+            GeometryError::UnexpectedCommand { position: 0, command: 5 }
+        );
+    }
+}
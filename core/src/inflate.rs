@@ -0,0 +1,279 @@
+// Inflate module
+// DEFLATE (RFC 1951) decompression for ZIP entries
+
+pub(crate) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+pub(crate) const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+pub(crate) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+pub(crate) const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order of the code length code lengths in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+/// Largest decompressed output (guards against zip bombs)
+pub(crate) const MAX_INFLATED_SIZE: usize = 1 << 30;
+
+/// Decompress a raw DEFLATE stream (no zlib or gzip header)
+pub(crate) fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    inflate_with_limit(data, MAX_INFLATED_SIZE)
+}
+
+/// Decompress a raw DEFLATE stream, failing once the output would exceed `limit` bytes
+fn inflate_with_limit(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut input = BitReader { data, pos: 0, bit_buffer: 0, bit_count: 0 };
+    let mut out = Vec::with_capacity(data.len().saturating_mul(3).min(limit));
+    
+    loop {
+        let last = input.bits(1)? == 1;
+        match input.bits(2)? {
+            0 => {
+                input.align();
+                let len = input.bits(16)? as usize;
+                if input.bits(16)? as usize != !len & 0xFFFF {
+                    return Err("Invalid stored DEFLATE block".to_string());
+                }
+                check_limit(&out, len, limit)?;
+                for _ in 0..len {
+                    out.push(input.bits(8)? as u8);
+                }
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut input, &mut out, &literals, &distances, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut input)?;
+                inflate_block(&mut input, &mut out, &literals, &distances, limit)?;
+            }
+            _ => return Err("Invalid DEFLATE block type".to_string()),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn check_limit(out: &[u8], additional: usize, limit: usize) -> Result<(), String> {
+    match additional > limit - out.len() {
+        true => Err(format!("Decompressed data is larger than the {} byte limit", limit)),
+        false => Ok(()),
+    }
+}
+
+/// Decompress a gzip member (RFC 1952); the trailing CRC is not checked
+pub(crate) fn gunzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 18 || data[0..3] != [0x1f, 0x8b, 8] {
+        return Err("Not a gzip stream".to_string());
+    }
+    let flags = data[3];
+    let mut at = 10;
+    if flags & 0x04 != 0 {
+        let extra = data.get(at..at + 2).ok_or("gzip header is truncated")?;
+        at += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    // File name and comment are zero-terminated
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            at += data.get(at..).and_then(|rest| rest.iter().position(|&b| b == 0)).ok_or("gzip header is truncated")? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        at += 2;
+    }
+    inflate(data.get(at..).ok_or("gzip header is truncated")?)
+}
+
+/// LSB-first bit reader
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.pos).ok_or("DEFLATE stream is truncated")?;
+            self.pos += 1;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u64 << count) - 1) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// Drop the bits left in the current byte (stored blocks start on a byte boundary)
+    fn align(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// Canonical Huffman code (symbol counts per code length, symbols in code order)
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, input: &mut BitReader) -> Result<u16, String> {
+        // Codes are read bit by bit; `first` is the first code of the current length
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= input.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("Invalid DEFLATE Huffman code".to_string())
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(input: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = input.bits(5)? as usize + 257;
+    let distance_count = input.bits(5)? as usize + 1;
+    let code_length_count = input.bits(4)? as usize + 4;
+    
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = input.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+    
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_length_code.decode(input)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("DEFLATE length repeat without a previous length")?, 3 + input.bits(2)?),
+            17 => (0, 3 + input.bits(3)?),
+            _ => (0, 11 + input.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("DEFLATE code lengths overrun the header counts".to_string());
+    }
+    
+    let (literals, distances) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literals), Huffman::new(distances)))
+}
+
+fn inflate_block(
+    input: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(input)? as usize;
+        match symbol {
+            0..=255 => {
+                check_limit(out, 1, limit)?;
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err("Invalid DEFLATE length code".to_string());
+                }
+                let length = LENGTH_BASE[index] as usize + input.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                
+                let index = distances.decode(input)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err("Invalid DEFLATE distance code".to_string());
+                }
+                let distance = DISTANCE_BASE[index] as usize + input.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("DEFLATE distance reaches before the start of the output".to_string());
+                }
+                
+                check_limit(out, length, limit)?;
+                
+                // Copies may overlap their own output (e.g. distance 1 repeats the last byte)
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate_stored_and_fixed_blocks() {
+        // Stored block: "abc"
+        assert_eq!(inflate(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c']).unwrap(), b"abc");
+        // Fixed Huffman block with back-references (zlib output for "hello hello hello hello")
+        let fixed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01];
+        assert_eq!(inflate(&fixed).unwrap(), b"hello hello hello hello");
+    }
+
+    #[test]
+    fn test_inflate_truncated() {
+        assert!(inflate(&[0xcb, 0x48]).is_err());
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let fixed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01];
+        assert!(inflate_with_limit(&fixed, 23).is_ok());
+        assert!(inflate_with_limit(&fixed, 22).unwrap_err().contains("limit"));
+        assert!(inflate_with_limit(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'], 2).is_err());
+    }
+
+    #[test]
+    fn test_gunzip() {
+        // gzip output for "hello hello hello hello"
+        let gzip = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40,
+            0x27, 0x01, 0xe3, 0x51, 0x3d, 0x8d, 0x17, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(gunzip(&gzip).unwrap(), b"hello hello hello hello");
+        assert!(gunzip(b"hello").is_err());
+    }
+}
@@ -0,0 +1,716 @@
+// Input reader module
+// Pluggable input formats producing features for the tiler
+
+use crate::geojson_parser::Feature;
+use crate::mvt_encoder::GeometryChecks;
+use crate::Warning;
+use std::borrow::Cow;
+use std::fmt;
+#[cfg(any(feature = "csv", feature = "shapefile"))]
+use crate::logging::log_warn;
+
+/// Source format that turns input bytes into features
+///
+/// Readers downstream of this crate can implement this trait to add formats.
+pub trait InputReader: Send + Sync {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String>;
+
+    /// Features together with dataset-level members of the input (none unless the format has them)
+    fn read_features_with_members<'a>(&self, bytes: &'a [u8]) -> Result<(FeatureIter<'a>, serde_json::Map<String, serde_json::Value>), String> {
+        Ok((self.read_features(bytes)?, serde_json::Map::new()))
+    }
+    
+    /// As `read_features_with_members`, with warnings about skipped input features
+    ///
+    /// Readers that check geometry types honour `checks` (failing under `Strict`); the default
+    /// reports no warnings.
+    fn read_features_checked<'a>(&self, bytes: &'a [u8], _checks: GeometryChecks) -> Result<CheckedFeatures<'a>, String> {
+        let (features, members) = self.read_features_with_members(bytes)?;
+        Ok((features, members, Vec::new()))
+    }
+    
+    /// As `read_features_checked`, for generation that keeps only features intersecting `bbox`
+    ///
+    /// Readers with a spatial index can skip the features outside the box; the default reads
+    /// them all and leaves the filtering to generation.
+    fn read_features_in<'a>(&self, bytes: &'a [u8], checks: GeometryChecks, _bbox: (f64, f64, f64, f64)) -> Result<CheckedFeatures<'a>, String> {
+        self.read_features_checked(bytes, checks)
+    }
+}
+
+/// Features produced by an `InputReader`
+pub type FeatureIter<'a> = Box<dyn Iterator<Item = Feature> + 'a>;
+
+/// Features, dataset-level members and warnings from `InputReader::read_features_checked`
+pub type CheckedFeatures<'a> = (FeatureIter<'a>, serde_json::Map<String, serde_json::Value>, Vec<Warning>);
+
+impl fmt::Debug for dyn InputReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("InputReader")
+    }
+}
+
+/// GeoJSON FeatureCollection or single Feature (the default input format)
+#[cfg(feature = "geojson")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GeoJsonReader;
+
+#[cfg(feature = "geojson")]
+impl InputReader for GeoJsonReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        let features = crate::geojson_parser::parse_geojson(bytes)?;
+        Ok(Box::new(features.into_iter()))
+    }
+
+    fn read_features_with_members<'a>(&self, bytes: &'a [u8]) -> Result<(FeatureIter<'a>, serde_json::Map<String, serde_json::Value>), String> {
+        let (features, members) = crate::geojson_parser::parse_geojson_with_members(bytes)?;
+        Ok((Box::new(features.into_iter()), members))
+    }
+    
+    fn read_features_checked<'a>(&self, bytes: &'a [u8], checks: GeometryChecks) -> Result<CheckedFeatures<'a>, String> {
+        let (features, members, warnings) = crate::geojson_parser::parse_geojson_checked(bytes, checks)?;
+        Ok((Box::new(features.into_iter()), members, warnings))
+    }
+}
+
+/// Point features from CSV with a header row and longitude/latitude columns
+///
+/// Remaining columns become properties (numeric values are stored as numbers, empty cells are omitted).
+#[cfg(feature = "csv")]
+#[derive(Debug, Clone)]
+pub struct CsvReader {
+    pub lon_column: String,
+    pub lat_column: String,
+    pub delimiter: char,
+}
+
+#[cfg(feature = "csv")]
+impl Default for CsvReader {
+    fn default() -> Self {
+        Self {
+            lon_column: "lon".to_string(),
+            lat_column: "lat".to_string(),
+            delimiter: ',',
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl InputReader for CsvReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        let text = crate::encoding::utf8_text(bytes)?;
+        let mut records = csv_records(text, self.delimiter);
+        
+        let header = records.next().ok_or("CSV has no header row")?;
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("CSV column not found: {}", name))
+        };
+        let lon_index = column(&self.lon_column)?;
+        let lat_index = column(&self.lat_column)?;
+        
+        let features = records.enumerate().filter_map(move |(row, record)| {
+            let coordinate = |index: usize| record.get(index).and_then(|v| v.trim().parse::<f64>().ok());
+            let (Some(lon), Some(lat)) = (coordinate(lon_index), coordinate(lat_index)) else {
+                log_warn!("Feature parse warning: CSV row {} has no valid coordinates", row + 1);
+                return None;
+            };
+            
+            let mut properties = serde_json::Map::new();
+            for (index, value) in record.iter().enumerate() {
+                if index == lon_index || index == lat_index || value.is_empty() {
+                    continue;
+                }
+                let Some(key) = header.get(index) else { continue };
+                properties.insert(key.trim().to_string(), csv_value(value));
+            }
+            
+            Some(Feature::new(crate::geojson_parser::GeometryType::Point(geo_types::Point::new(lon, lat)), properties))
+        });
+        
+        Ok(Box::new(features))
+    }
+}
+
+/// Numbers become JSON numbers, everything else stays a string
+#[cfg(feature = "csv")]
+pub(crate) fn csv_value(value: &str) -> serde_json::Value {
+    if let Ok(n) = value.parse::<i64>() {
+        return n.into();
+    }
+    match value.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        Some(n) => serde_json::Value::Number(n),
+        None => serde_json::Value::String(value.to_string()),
+    }
+}
+
+/// Split CSV text into records (RFC 4180 quoting, blank lines skipped)
+#[cfg(feature = "csv")]
+pub(crate) fn csv_records(text: &str, delimiter: char) -> impl Iterator<Item = Vec<String>> + '_ {
+    let mut chars = text.chars().peekable();
+    std::iter::from_fn(move || loop {
+        chars.peek()?;
+        
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                '\r' if !quoted => {}
+                '\n' if !quoted => break,
+                c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+        }
+        record.push(field);
+        
+        if record.len() > 1 || !record[0].is_empty() {
+            return Some(record);
+        }
+    })
+}
+
+/// Features of an existing tileset: a ZIP of `z/x/y.pbf` tiles (as written by `ZipSink`), an
+/// MBTiles database, a PMTiles archive or (through `read_directory`) a `z/x/y.pbf` directory
+///
+/// Tiles of one zoom are decoded and converted back to lon/lat, so a tileset can be re-tiled
+/// with new options when its source data is gone. Geometries are cut at tile edges, so
+/// features spanning several tiles come back as one piece per tile.
+#[derive(Debug, Clone, Default)]
+pub struct TilesetReader {
+    /// Zoom to read (defaults to the deepest zoom in the archive)
+    pub zoom: Option<u8>,
+    /// Layers to read (all layers when empty)
+    pub layers: Vec<String>,
+}
+
+impl TilesetReader {
+    /// Features of a tile directory (`z/x/y.pbf` files, as written by `DirectorySink`)
+    pub fn read_directory(&self, root: &std::path::Path) -> Result<Vec<Feature>, String> {
+        let entries = |dir: &std::path::Path| -> Result<Vec<(String, std::path::PathBuf)>, String> {
+            let read_dir = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+            read_dir
+                .map(|entry| {
+                    let entry = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+                    Ok((entry.file_name().to_string_lossy().into_owned(), entry.path()))
+                })
+                .collect()
+        };
+        let zooms: Vec<u8> = entries(root)?.iter().filter_map(|(name, path)| name.parse().ok().filter(|_| path.is_dir())).collect();
+        let zoom = match self.zoom {
+            Some(zoom) => zoom,
+            None => zooms.iter().copied().max().ok_or("Tileset contains no tiles")?,
+        };
+        
+        let mut tiles = Vec::new();
+        if zooms.contains(&zoom) {
+            for (x, column) in entries(&root.join(zoom.to_string()))? {
+                if !column.is_dir() {
+                    continue;
+                }
+                for (y, file) in entries(&column)? {
+                    let Some(coord) = tile_coord_from_path(&format!("{}/{}/{}", zoom, x, y)) else {
+                        continue;
+                    };
+                    let data = std::fs::read(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+                    tiles.push((coord, Cow::Owned(data)));
+                }
+            }
+        }
+        tiles.sort_by_key(|(coord, _)| (coord.x, coord.y));
+        self.tiles_to_features(tiles, Some(zoom))
+    }
+
+    /// Features of a PMTiles archive read through range requests (for remote archives, see
+    /// `http::HttpRangeReader`), from the tiles touching `bbox` only
+    pub fn read_ranges<R: crate::pmtiles::RangeReader + ?Sized>(&self, reader: &R, bbox: Option<(f64, f64, f64, f64)>) -> Result<Vec<Feature>, String> {
+        let header = reader.read_range(0, 127)?;
+        if !header.starts_with(b"PMTiles") || header.len() < 127 {
+            return Err("Not a PMTiles v3 archive".to_string());
+        }
+        // Bytes 98 and 101 of the header hold the tile compression and the maximum zoom
+        match header[98] {
+            3 => return Err("PMTiles tiles compressed with brotli are not supported".to_string()),
+            4 => return Err("PMTiles tiles compressed with zstd are not supported".to_string()),
+            _ => {}
+        }
+        let zoom = self.zoom.unwrap_or(header[101]);
+        let range = bbox.and_then(|bbox| crate::limits::tile_range(bbox, zoom));
+        let tiles = crate::pmtiles::read_zoom_tiles_ranges(reader, zoom, |coord| {
+            range.as_ref().is_none_or(|(xs, ys)| xs.contains(&coord.x) && ys.contains(&coord.y))
+        })?;
+        self.tiles_to_features(tiles.into_iter().map(|(coord, data)| (coord, Cow::Owned(data))).collect(), Some(zoom))
+    }
+
+    /// Decode the tiles of the chosen zoom (the deepest of `tiles` when `zoom` is `None`)
+    fn tiles_to_features(&self, tiles: Vec<(crate::TileCoord, Cow<[u8]>)>, zoom: Option<u8>) -> Result<Vec<Feature>, String> {
+        let zoom = match self.zoom.or(zoom) {
+            Some(zoom) => zoom,
+            None => tiles.iter().map(|(coord, _)| coord.z).max().ok_or("Tileset contains no tiles")?,
+        };
+        
+        let mut features = Vec::new();
+        for (coord, data) in tiles.into_iter().filter(|(coord, _)| coord.z == zoom) {
+            // Tiles written by other tools are often gzipped
+            let data = match data.starts_with(&[0x1f, 0x8b]) {
+                #[cfg(feature = "zip")]
+                true => Cow::Owned(crate::inflate::gunzip(&data)?),
+                #[cfg(not(feature = "zip"))]
+                true => return Err("Gzipped tiles need the 'zip' feature".to_string()),
+                false => data,
+            };
+            features.extend(tile_to_features(&data, coord, &self.layers)?);
+        }
+        Ok(features)
+    }
+}
+
+impl InputReader for TilesetReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        let features = if bytes.starts_with(b"PMTiles") {
+            let info = crate::pmtiles::read_info(bytes)?;
+            if !matches!(info.tile_compression.as_str(), "none" | "gzip") {
+                return Err(format!("PMTiles tiles compressed with {} are not supported", info.tile_compression));
+            }
+            let zoom = self.zoom.unwrap_or(info.max_zoom);
+            let tiles = crate::pmtiles::read_zoom_tiles(bytes, zoom)?;
+            self.tiles_to_features(tiles.into_iter().map(|(coord, data)| (coord, Cow::Borrowed(data))).collect(), Some(zoom))?
+        } else if bytes.starts_with(b"SQLite format 3\0") {
+            let mbtiles = crate::mbtiles::read_mbtiles(bytes)?;
+            if mbtiles.tile_compression == crate::pmtiles::TileCompression::Brotli {
+                return Err("MBTiles tiles compressed with brotli are not supported".to_string());
+            }
+            self.tiles_to_features(mbtiles.tiles.into_iter().map(|(coord, data)| (coord, Cow::Owned(data))).collect(), None)?
+        } else {
+            #[cfg(feature = "zip")]
+            {
+                let tiles = zip_entries(bytes)?
+                    .into_iter()
+                    .filter_map(|(name, data)| Some((tile_coord_from_path(&name)?, data)))
+                    .collect();
+                self.tiles_to_features(tiles, None)?
+            }
+            #[cfg(not(feature = "zip"))]
+            return Err("Zipped tilesets need the 'zip' feature".to_string());
+        };
+        Ok(Box::new(features.into_iter()))
+    }
+}
+
+/// Features of a zipped Shapefile (`.shp` with its `.dbf` and `.prj`, as downloaded from open-data portals)
+///
+/// The first `.shp` in the archive is read; its `.prj` must declare WGS84 lon/lat.
+#[cfg(feature = "shapefile")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShapefileReader;
+
+#[cfg(feature = "shapefile")]
+impl InputReader for ShapefileReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        let entries = zip_entries(bytes)?;
+        // macOS archives carry `__MACOSX/._name.shp` resource forks next to the real files
+        let mut shapefiles = entries
+            .iter()
+            .filter(|(name, _)| !name.starts_with("__MACOSX/") && name.to_ascii_lowercase().ends_with(".shp"));
+        let (shp_name, shp) = shapefiles.next().ok_or("No .shp file found in the ZIP archive")?;
+        if let Some((other, _)) = shapefiles.next() {
+            log_warn!("Reading {} only; other Shapefiles in the archive (such as {}) are ignored", shp_name, other);
+        }
+        
+        let stem = &shp_name[..shp_name.len() - 4];
+        let sidecar = |extension: &str| {
+            entries
+                .iter()
+                .find(|(name, _)| {
+                    name.len() == stem.len() + extension.len()
+                        && name.starts_with(stem)
+                        && name[stem.len()..].eq_ignore_ascii_case(extension)
+                })
+                .map(|(_, data)| data.as_ref())
+        };
+        if let Some(prj) = sidecar(".prj") {
+            crate::shapefile::check_projection(&String::from_utf8_lossy(prj))?;
+        }
+        
+        let features = crate::shapefile::parse_shapefile(shp, sidecar(".dbf"))?;
+        Ok(Box::new(features.into_iter()))
+    }
+}
+
+/// Placemarks of a KML document
+#[cfg(feature = "kml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KmlReader;
+
+#[cfg(feature = "kml")]
+impl InputReader for KmlReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        let text = std::str::from_utf8(crate::encoding::strip_bom(bytes)).map_err(|e| format!("KML is not valid UTF-8: {}", e))?;
+        Ok(Box::new(crate::kml::parse_kml(text)?.into_iter()))
+    }
+}
+
+/// Placemarks of a KMZ archive (zipped KML, unzipped in memory)
+///
+/// The root document is `doc.kml` when present, otherwise the first `.kml` file in the archive.
+#[cfg(feature = "kml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KmzReader;
+
+#[cfg(feature = "kml")]
+impl InputReader for KmzReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        let entries = zip_entries(bytes)?;
+        let is_kml = |name: &str| name.to_ascii_lowercase().ends_with(".kml");
+        let (_, document) = entries
+            .iter()
+            .find(|(name, _)| name == "doc.kml")
+            .or_else(|| entries.iter().find(|(name, _)| is_kml(name)))
+            .ok_or("No .kml file found in the KMZ archive")?;
+        let features: Vec<Feature> = KmlReader.read_features(document)?.collect();
+        Ok(Box::new(features.into_iter()))
+    }
+}
+
+/// FlatGeobuf file; with `TileOptions::bbox_filter`, only the features its packed R-tree
+/// places in the box are decoded
+#[cfg(feature = "flatgeobuf")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlatGeobufReader;
+
+#[cfg(feature = "flatgeobuf")]
+impl InputReader for FlatGeobufReader {
+    fn read_features<'a>(&self, bytes: &'a [u8]) -> Result<Box<dyn Iterator<Item = Feature> + 'a>, String> {
+        Ok(self.read_features_checked(bytes, GeometryChecks::Lenient)?.0)
+    }
+
+    fn read_features_checked<'a>(&self, bytes: &'a [u8], checks: GeometryChecks) -> Result<CheckedFeatures<'a>, String> {
+        let (features, warnings) = crate::flatgeobuf::parse_flatgeobuf(bytes, None, checks)?;
+        Ok((Box::new(features.into_iter()), serde_json::Map::new(), warnings))
+    }
+
+    fn read_features_in<'a>(&self, bytes: &'a [u8], checks: GeometryChecks, bbox: (f64, f64, f64, f64)) -> Result<CheckedFeatures<'a>, String> {
+        let (features, warnings) = crate::flatgeobuf::parse_flatgeobuf(bytes, Some(bbox), checks)?;
+        Ok((Box::new(features.into_iter()), serde_json::Map::new(), warnings))
+    }
+}
+
+/// Pick a reader from the content of the input (`None` means GeoJSON, the default)
+///
+/// ZIP archives are read as KMZ when they contain a `.kml` file, as tilesets when they
+/// contain tiles and as zipped Shapefiles otherwise; MBTiles and PMTiles archives are read as
+/// tilesets, documents starting with `<` as KML, and FlatGeobuf files by their signature.
+pub fn detect_reader(bytes: &[u8]) -> Option<std::sync::Arc<dyn InputReader>> {
+    if bytes.starts_with(b"PMTiles") || bytes.starts_with(b"SQLite format 3\0") {
+        return Some(std::sync::Arc::new(TilesetReader::default()));
+    }
+    #[cfg(feature = "zip")]
+    if bytes.starts_with(b"PK\x03\x04") {
+        let names: Vec<String> = zip_directory(bytes)
+            .ok()?
+            .into_iter()
+            .map(|entry| entry.name.to_ascii_lowercase())
+            .collect();
+        if names.iter().any(|name| tile_coord_from_path(name).is_some()) {
+            return Some(std::sync::Arc::new(TilesetReader::default()));
+        }
+        #[cfg(feature = "kml")]
+        if names.iter().any(|name| name.ends_with(".kml")) {
+            return Some(std::sync::Arc::new(KmzReader));
+        }
+        #[cfg(feature = "shapefile")]
+        if names.iter().any(|name| name.ends_with(".shp")) {
+            return Some(std::sync::Arc::new(ShapefileReader));
+        }
+        return None;
+    }
+
+    #[cfg(feature = "kml")]
+    if crate::encoding::strip_bom(bytes).trim_ascii_start().starts_with(b"<") {
+        return Some(std::sync::Arc::new(KmlReader));
+    }
+    #[cfg(feature = "flatgeobuf")]
+    if crate::flatgeobuf::is_flatgeobuf(bytes) {
+        return Some(std::sync::Arc::new(FlatGeobufReader));
+    }
+    None
+}
+
+/// Decode an encoded tile into lon/lat features (`layers` filters by name, empty keeps all)
+///
+/// Geometry is clipped to the tile itself (the buffer is dropped). Multi-part lines are split
+/// into one feature per part; polygons stay one feature.
+pub fn tile_to_features(data: &[u8], coord: crate::TileCoord, layers: &[String]) -> Result<Vec<Feature>, String> {
+    use crate::clip::{clip_line, clip_ring, ClipBox};
+    use crate::geojson_parser::GeometryType;
+    use crate::tiler::TileGeometry;
+    use geo_types::{LineString, Point, Polygon};
+    
+    let (min_x, min_y, max_x, max_y) = crate::projection::tile_bounds(coord.x, coord.y, coord.z);
+    let mut features = Vec::new();
+    
+    for layer in crate::mvt_decoder::decode_tile(data)? {
+        if !layers.is_empty() && !layers.contains(&layer.name) {
+            continue;
+        }
+        let extent = layer.extent as f64;
+        let clip_box = ClipBox { min: 0.0, max: extent };
+        let to_lonlat = |(px, py): (f64, f64)| {
+            crate::projection::meters_to_lonlat(
+                min_x + px / extent * (max_x - min_x),
+                max_y - py / extent * (max_y - min_y),
+            )
+        };
+        let line_string = |points: Vec<(f64, f64)>| LineString::from(points.into_iter().map(to_lonlat).collect::<Vec<_>>());
+        
+        for feature in layer.features {
+            let geometries: Vec<GeometryType> = match &feature.geometry {
+                // Points on the right/bottom edge belong to the neighboring tile
+                TileGeometry::Point(x, y) if (0..layer.extent as i32).contains(x) && (0..layer.extent as i32).contains(y) => {
+                    let (lon, lat) = to_lonlat((*x as f64, *y as f64));
+                    vec![GeometryType::Point(Point::new(lon, lat))]
+                }
+                TileGeometry::Point(..) => Vec::new(),
+                TileGeometry::LineString(coords) => {
+                    let points: Vec<(f64, f64)> = coords.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+                    clip_line(&points, &clip_box)
+                        .into_iter()
+                        .map(|part| GeometryType::LineString(line_string(part)))
+                        .collect()
+                }
+                TileGeometry::Polygon(rings) => {
+                    // Each ring with the winding of the first ring starts a new polygon
+                    let Some(first) = rings.first() else { continue };
+                    let exterior_sign = crate::tiler::ring_signed_area(first).signum();
+                    let mut polygons: Vec<(LineString<f64>, Vec<LineString<f64>>)> = Vec::new();
+                    for ring in rings {
+                        let is_exterior = crate::tiler::ring_signed_area(ring).signum() == exterior_sign;
+                        let points: Vec<(f64, f64)> = ring.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+                        let clipped = clip_ring(&points, &clip_box);
+                        if clipped.len() < 4 {
+                            continue;
+                        }
+                        if is_exterior {
+                            polygons.push((line_string(clipped), Vec::new()));
+                        } else if let Some((_, holes)) = polygons.last_mut() {
+                            holes.push(line_string(clipped));
+                        }
+                    }
+                    let polygons: Vec<Polygon<f64>> =
+                        polygons.into_iter().map(|(exterior, holes)| Polygon::new(exterior, holes)).collect();
+                    match polygons.is_empty() {
+                        true => Vec::new(),
+                        false => vec![crate::geojson_parser::polygon_geometry(polygons)],
+                    }
+                }
+            };
+            features.extend(geometries.into_iter().map(|geometry| Feature {
+                id: feature.id,
+                ..Feature::new(geometry, feature.properties.clone())
+            }));
+        }
+    }
+    
+    Ok(features)
+}
+
+/// Parse a `z/x/y.pbf` (or `.mvt`) archive path
+fn tile_coord_from_path(path: &str) -> Option<crate::TileCoord> {
+    let (path, _) = path.rsplit_once('.')?;
+    let mut parts = path.rsplit('/');
+    let y = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    Some(crate::TileCoord::new(z, x, y))
+}
+
+/// Named files of a ZIP archive (borrowed when stored, inflated otherwise)
+#[cfg(feature = "zip")]
+pub(crate) type ZipEntries<'a> = Vec<(String, Cow<'a, [u8]>)>;
+
+/// File entries of a ZIP archive (stored or deflated; directories are skipped)
+#[cfg(feature = "zip")]
+pub(crate) fn zip_entries(bytes: &[u8]) -> Result<ZipEntries<'_>, String> {
+    zip_directory(bytes)?
+        .into_iter()
+        .map(|entry| {
+            let data = match entry.method {
+                0 => Cow::Borrowed(entry.data),
+                8 => Cow::Owned(crate::inflate::inflate(entry.data)?),
+                other => return Err(format!("Unsupported ZIP compression method {}: {}", other, entry.name)),
+            };
+            Ok((entry.name, data))
+        })
+        .collect()
+}
+
+/// Central directory entry with its (still compressed) data
+#[cfg(feature = "zip")]
+struct ZipDirectoryEntry<'a> {
+    name: String,
+    method: usize,
+    data: &'a [u8],
+}
+
+#[cfg(feature = "zip")]
+fn zip_directory(bytes: &[u8]) -> Result<Vec<ZipDirectoryEntry<'_>>, String> {
+    let u16_at = |at: usize| bytes.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+    let u32_at = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    let truncated = || "ZIP archive is truncated".to_string();
+    
+    // The end of central directory record closes the archive (followed by a comment of up to 64 KiB)
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take(65536)
+        .find(|&at| u32_at(at) == Some(0x06054b50))
+        .ok_or("Not a ZIP archive")?;
+    let entry_count = u16_at(end + 10).ok_or_else(truncated)?;
+    let mut offset = u32_at(end + 16).ok_or_else(truncated)?;
+    
+    // Sizes are read from the central directory, since local headers may defer them to a data descriptor
+    let mut entries = Vec::with_capacity(entry_count);
+    for _ in 0..entry_count {
+        if u32_at(offset) != Some(0x02014b50) {
+            return Err("Invalid ZIP central directory".to_string());
+        }
+        let flags = u16_at(offset + 8).ok_or_else(truncated)?;
+        let method = u16_at(offset + 10).ok_or_else(truncated)?;
+        let size = u32_at(offset + 20).ok_or_else(truncated)?;
+        let name_len = u16_at(offset + 28).ok_or_else(truncated)?;
+        let extra_len = u16_at(offset + 30).ok_or_else(truncated)?;
+        let comment_len = u16_at(offset + 32).ok_or_else(truncated)?;
+        let local = u32_at(offset + 42).ok_or_else(truncated)?;
+        let name = bytes.get(offset + 46..offset + 46 + name_len).ok_or_else(truncated)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        offset += 46 + name_len + extra_len + comment_len;
+        
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 0x1 != 0 {
+            return Err(format!("Encrypted ZIP entries are not supported: {}", name));
+        }
+        if u32_at(local) != Some(0x04034b50) {
+            return Err("Invalid ZIP local file header".to_string());
+        }
+        let data_start = local + 30 + u16_at(local + 26).ok_or_else(truncated)? + u16_at(local + 28).ok_or_else(truncated)?;
+        let data = bytes.get(data_start..data_start + size).ok_or_else(truncated)?;
+        entries.push(ZipDirectoryEntry { name, method, data });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geojson_reader() {
+        let geojson = r#"{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1.0, 2.0]}, "properties": {}}"#;
+        let features: Vec<Feature> = GeoJsonReader.read_features(geojson.as_bytes()).unwrap().collect();
+        
+        assert_eq!(features.len(), 1);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv_reader() {
+        let csv = "name,lat,lon,population\n\"Tokyo, Japan\",35.68,139.69,\"13960000\"\nbroken,,\n\nOsaka,34.69,135.50,\n";
+        let features: Vec<Feature> = CsvReader::default().read_features(csv.as_bytes()).unwrap().collect();
+        
+        assert_eq!(features.len(), 2);
+        match &features[0].geometry {
+            crate::geojson_parser::GeometryType::Point(p) => assert_eq!((p.x(), p.y()), (139.69, 35.68)),
+            other => panic!("unexpected geometry: {:?}", other),
+        }
+        assert_eq!(features[0].properties["name"], "Tokyo, Japan");
+        assert_eq!(features[0].properties["population"], 13960000);
+        assert!(!features[1].properties.contains_key("population"));
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_tileset_reader_round_trip() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [139.69, 35.68]}, "properties": {"name": "Tokyo"}},
+            {"type": "Feature", "geometry": {"type": "Polygon", "coordinates": [[[139.0, 35.0], [139.5, 35.0], [139.5, 35.5], [139.0, 35.0]]]}, "properties": {}}
+        ]}"#;
+        let mut sink = crate::sink::ZipSink::new(Vec::new());
+        crate::generate_tiles_to_sink(geojson.as_bytes(), 4, 6, "places", &crate::TileOptions::default(), &mut sink).unwrap();
+        let archive = sink.into_inner();
+        
+        let features: Vec<Feature> = TilesetReader::default().read_features(&archive).unwrap().collect();
+        
+        assert_eq!(features.len(), 2);
+        let crate::geojson_parser::GeometryType::Point(point) = &features[0].geometry else {
+            panic!("Expected Point geometry");
+        };
+        assert!((point.x() - 139.69).abs() < 0.01 && (point.y() - 35.68).abs() < 0.01);
+        assert_eq!(features[0].properties["name"], "Tokyo");
+        assert!(matches!(features[1].geometry, crate::geojson_parser::GeometryType::Polygon(_)));
+        assert_eq!(detect_reader(&archive).unwrap().read_features(&archive).unwrap().count(), 2);
+        
+        // MBTiles, PMTiles and a tile directory decode to the same features
+        let mut mbtiles = crate::mbtiles::MbTilesSink::new(Vec::new());
+        crate::generate_tiles_to_sink(geojson.as_bytes(), 4, 6, "places", &crate::TileOptions::default(), &mut mbtiles).unwrap();
+        let mut pmtiles = crate::pmtiles::PmTilesSink::new(Vec::new());
+        crate::generate_tiles_to_sink(geojson.as_bytes(), 4, 6, "places", &crate::TileOptions::default(), &mut pmtiles).unwrap();
+        for archive in [mbtiles.into_inner(), pmtiles.into_inner()] {
+            let reader = detect_reader(&archive).expect("Tileset should be detected");
+            assert_eq!(reader.read_features(&archive).unwrap().collect::<Vec<_>>(), features);
+        }
+        let root = std::env::temp_dir().join(format!("vtc-tileset-{}", std::process::id()));
+        let mut directory = crate::sink::DirectorySink::new(&root);
+        crate::generate_tiles_to_sink(geojson.as_bytes(), 4, 6, "places", &crate::TileOptions::default(), &mut directory).unwrap();
+        let from_directory = TilesetReader::default().read_directory(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(from_directory.unwrap(), features);
+    }
+
+    #[cfg(feature = "shapefile")]
+    #[test]
+    fn test_shapefile_reader() {
+        // Deflated ZIP with a polygon Shapefile: a polygon with a hole plus a second part, a null shape, a square
+        let archive = include_bytes!("../../test_data/areas_shp.zip");
+        let features: Vec<Feature> = ShapefileReader.read_features(archive).unwrap().collect();
+        
+        assert_eq!(features.len(), 2);
+        let crate::geojson_parser::GeometryType::MultiPolygon(polygons) = &features[0].geometry else {
+            panic!("Expected MultiPolygon geometry");
+        };
+        assert_eq!(polygons.0.iter().map(|polygon| polygon.interiors().len()).collect::<Vec<_>>(), [1, 0]);
+        assert_eq!(features[0].properties["NAME"], "東京");
+        assert_eq!(features[0].properties["POP"], 13960000);
+        assert_eq!(features[0].properties["AREA"], 2194.05);
+        assert_eq!(features[0].properties["FLAG"], true);
+        assert_eq!(features[1].properties["NAME"], "Square");
+    }
+
+    #[cfg(feature = "kml")]
+    #[test]
+    fn test_detect_reader() {
+        let kml = b"<kml><Placemark><Point><coordinates>139.69,35.68</coordinates></Point></Placemark></kml>";
+        let reader = detect_reader(kml).expect("KML should be detected");
+        assert_eq!(reader.read_features(kml).unwrap().count(), 1);
+        assert!(detect_reader(br#"{"type": "FeatureCollection", "features": []}"#).is_none());
+        
+        // KMZ: a stored ZIP with doc.kml
+        let mut sink = crate::sink::ZipSink::new(Vec::new());
+        sink.add_entry("doc.kml".to_string(), kml).unwrap();
+        let metadata = crate::TileMetadata {
+            layer_name: "kml".to_string(),
+            ..Default::default()
+        };
+        crate::sink::TileSink::finish(&mut sink, &metadata).unwrap();
+        let kmz = sink.into_inner();
+        let reader = detect_reader(&kmz).expect("KMZ should be detected");
+        assert_eq!(reader.read_features(&kmz).unwrap().count(), 1);
+    }
+}
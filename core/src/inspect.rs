@@ -0,0 +1,74 @@
+// Tile inspection module
+// Decode an encoded MVT tile and summarize its layers
+
+use crate::mvt_encoder::vector_tile::{self, tile::GeomType};
+use prost::Message;
+
+/// Layer summary of an encoded tile
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct LayerSummary {
+    pub name: String,
+    pub version: u32,
+    pub extent: u32,
+    pub features: usize,
+    pub points: usize,
+    pub lines: usize,
+    pub polygons: usize,
+    /// Property keys used in the layer
+    pub keys: Vec<String>,
+}
+
+/// Decode a tile and summarize each layer
+pub fn inspect_tile(bytes: &[u8]) -> Result<Vec<LayerSummary>, String> {
+    let tile = vector_tile::Tile::decode(bytes)
+        .map_err(|e| format!("MVT decode error: {}", e))?;
+    
+    Ok(tile
+        .layers
+        .into_iter()
+        .map(|layer| {
+            let count = |geom_type: GeomType| {
+                layer.features.iter().filter(|f| f.r#type() == geom_type).count()
+            };
+            LayerSummary {
+                version: layer.version,
+                extent: layer.extent(),
+                features: layer.features.len(),
+                points: count(GeomType::Point),
+                lines: count(GeomType::Linestring),
+                polygons: count(GeomType::Polygon),
+                keys: layer.keys.clone(),
+                name: layer.name,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::{TileFeature, TileGeometry};
+
+    #[test]
+    fn test_inspect_tile() {
+        let mut properties = serde_json::Map::new();
+        properties.insert("name".to_string(), "a".into());
+        let features = vec![
+            TileFeature { geometry: TileGeometry::Point(10, 10), properties, id: None },
+            TileFeature { geometry: TileGeometry::LineString(vec![(0, 0), (10, 10)]), properties: serde_json::Map::new(), id: None },
+        ];
+        let bytes = crate::mvt_encoder::encode_tile(&features, "roads").unwrap();
+        
+        let layers = inspect_tile(&bytes).unwrap();
+        
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].name, "roads");
+        assert_eq!((layers[0].features, layers[0].points, layers[0].lines), (2, 1, 1));
+        assert_eq!(layers[0].keys, vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn test_inspect_invalid_tile() {
+        assert!(inspect_tile(&[0xff, 0xff, 0xff]).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+// KML module
+// Placemarks of a KML document (OGC KML 2.2) parsed into features
+
+use crate::geojson_parser::{Feature, GeometryType};
+use geo_types::{Coord, LineString, Point, Polygon};
+use serde_json::{Map, Value};
+
+/// Parse the Placemarks of a KML document
+///
+/// `name`, `description` and `ExtendedData` (`Data` and `SchemaData`) become properties, with
+/// numeric values stored as numbers. `MultiGeometry` parts become one feature each; styles,
+/// altitudes and other geometries (such as `gx:Track`) are ignored.
+pub fn parse_kml(text: &str) -> Result<Vec<Feature>, String> {
+    let root = parse_xml(text)?;
+    
+    let mut placemarks = Vec::new();
+    collect(&root, "Placemark", &mut placemarks);
+    
+    let mut features = Vec::new();
+    for placemark in placemarks {
+        let properties = placemark_properties(placemark);
+        let mut geometries = Vec::new();
+        for child in placemark.elements() {
+            collect_geometries(child, &mut geometries);
+        }
+        for geometry in geometries {
+            features.push(Feature::new(geometry, properties.clone()));
+        }
+    }
+    Ok(features)
+}
+
+/// XML element (names are stored without their namespace prefix)
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.name == name)
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Concatenated text content of the element and its descendants
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for node in &self.children {
+            match node {
+                Node::Text(t) => text.push_str(t),
+                Node::Element(e) => text.push_str(&e.text()),
+            }
+        }
+        text
+    }
+}
+
+fn collect<'a>(element: &'a Element, name: &str, out: &mut Vec<&'a Element>) {
+    for child in element.elements() {
+        if child.name == name {
+            out.push(child);
+        } else {
+            collect(child, name, out);
+        }
+    }
+}
+
+fn placemark_properties(placemark: &Element) -> Map<String, Value> {
+    let mut properties = Map::new();
+    for key in ["name", "description"] {
+        if let Some(element) = placemark.child(key) {
+            properties.insert(key.to_string(), Value::String(element.text().trim().to_string()));
+        }
+    }
+    
+    if let Some(data) = placemark.child("ExtendedData") {
+        let mut fields = Vec::new();
+        collect(data, "Data", &mut fields);
+        collect(data, "SimpleData", &mut fields);
+        for field in fields {
+            let Some(name) = field.attribute("name") else {
+                continue;
+            };
+            let text = match field.child("value") {
+                Some(value) => value.text(),
+                None if field.name == "SimpleData" => field.text(),
+                None => continue,
+            };
+            properties.insert(name.to_string(), property_value(text.trim()));
+        }
+    }
+    properties
+}
+
+fn property_value(text: &str) -> Value {
+    match text.parse::<f64>() {
+        Ok(n) if n.is_finite() => match text.parse::<i64>() {
+            Ok(i) => Value::from(i),
+            Err(_) => Value::from(n),
+        },
+        _ => Value::String(text.to_string()),
+    }
+}
+
+fn collect_geometries(element: &Element, out: &mut Vec<GeometryType>) {
+    let coordinates = |e: &Element| e.child("coordinates").map(|c| parse_coordinates(&c.text())).unwrap_or_default();
+    match element.name.as_str() {
+        "Point" => {
+            if let Some(&coord) = coordinates(element).first() {
+                out.push(GeometryType::Point(Point(coord)));
+            }
+        }
+        "LineString" => {
+            let coords = coordinates(element);
+            if coords.len() >= 2 {
+                out.push(GeometryType::LineString(LineString(coords)));
+            }
+        }
+        "LinearRing" => {
+            let ring = close_ring(coordinates(element));
+            if ring.0.len() >= 4 {
+                out.push(GeometryType::LineString(ring));
+            }
+        }
+        "Polygon" => {
+            let boundary = |b: &Element| close_ring(b.child("LinearRing").map(coordinates).unwrap_or_default());
+            let Some(exterior) = element.child("outerBoundaryIs").map(boundary).filter(|r| r.0.len() >= 4) else {
+                return;
+            };
+            let holes = element
+                .elements()
+                .filter(|e| e.name == "innerBoundaryIs")
+                .map(boundary)
+                .filter(|r| r.0.len() >= 4)
+                .collect();
+            out.push(GeometryType::Polygon(Polygon::new(exterior, holes)));
+        }
+        "MultiGeometry" => {
+            for child in element.elements() {
+                collect_geometries(child, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Close a ring whose last coordinate does not repeat the first
+fn close_ring(mut coords: Vec<Coord<f64>>) -> LineString<f64> {
+    if coords.len() >= 3 && coords.first() != coords.last() {
+        coords.push(coords[0]);
+    }
+    LineString(coords)
+}
+
+/// Whitespace-separated `lon,lat[,alt]` tuples (malformed tuples are skipped)
+fn parse_coordinates(text: &str) -> Vec<Coord<f64>> {
+    text.split_whitespace()
+        .filter_map(|tuple| {
+            let mut parts = tuple.split(',');
+            let x = parts.next()?.parse().ok()?;
+            let y = parts.next()?.parse().ok()?;
+            Some(Coord { x, y })
+        })
+        .collect()
+}
+
+/// Minimal XML parser (elements, attributes, text, CDATA and entities; no DTDs)
+fn parse_xml(text: &str) -> Result<Element, String> {
+    let mut stack = vec![Element::default()];
+    let mut rest = text;
+    
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut stack, decode_entities(rest));
+            break;
+        };
+        if start > 0 {
+            push_text(&mut stack, decode_entities(&rest[..start]));
+        }
+        rest = &rest[start..];
+        
+        if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body.find("]]>").ok_or("Unterminated CDATA section")?;
+            push_text(&mut stack, body[..end].to_string());
+            rest = &body[end + 3..];
+        } else if let Some(body) = rest.strip_prefix("<!--") {
+            let end = body.find("-->").ok_or("Unterminated XML comment")?;
+            rest = &body[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest.find('>').ok_or("Unterminated XML declaration")?;
+            rest = &rest[end + 1..];
+        } else if let Some(body) = rest.strip_prefix("</") {
+            let end = body.find('>').ok_or("Unterminated XML closing tag")?;
+            let name = local_name(body[..end].trim());
+            let element = stack.pop().filter(|_| !stack.is_empty()).ok_or("Unexpected XML closing tag")?;
+            if element.name != name {
+                return Err(format!("Mismatched XML closing tag: expected </{}>, found </{}>", element.name, name));
+            }
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(Node::Element(element));
+            }
+            rest = &body[end + 1..];
+        } else {
+            let end = tag_end(rest).ok_or("Unterminated XML tag")?;
+            let tag = &rest[1..end];
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let element = parse_tag(tag)?;
+            if self_closing {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Element(element));
+                }
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+    
+    if stack.len() != 1 {
+        return Err("Unclosed XML element".to_string());
+    }
+    Ok(stack.pop().unwrap_or_default())
+}
+
+fn push_text(stack: &mut [Element], text: String) {
+    if let Some(element) = stack.last_mut() {
+        if !text.trim().is_empty() {
+            element.children.push(Node::Text(text));
+        }
+    }
+}
+
+/// End of a start tag (`>` outside quoted attribute values)
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_tag(tag: &str) -> Result<Element, String> {
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let mut element = Element { name: local_name(&tag[..name_end]).to_string(), ..Default::default() };
+    
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest.find('=').ok_or_else(|| format!("Malformed XML attribute in <{}>", element.name))?;
+        let key = local_name(rest[..eq].trim()).to_string();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let quote = quote.ok_or_else(|| format!("Unquoted XML attribute in <{}>", element.name))?;
+        let end = value[1..].find(quote).ok_or("Unterminated XML attribute value")?;
+        element.attributes.push((key, decode_entities(&value[1..end + 1])));
+        rest = value[end + 2..].trim_start();
+    }
+    Ok(element)
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            // Stray ampersands are kept as-is
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kml() {
+        let kml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2">
+  <Document>
+    <Folder>
+      <Placemark>
+        <name>Tokyo &amp; Yokohama</name>
+        <description><![CDATA[<b>Capital</b>]]></description>
+        <ExtendedData>
+          <Data name="population"><value>13960000</value></Data>
+          <SchemaData schemaUrl="#s"><SimpleData name="kind">city</SimpleData></SchemaData>
+        </ExtendedData>
+        <MultiGeometry>
+          <Point><coordinates>139.69,35.68,0</coordinates></Point>
+          <Polygon>
+            <outerBoundaryIs><LinearRing><coordinates>
+              139.0,35.0 140.0,35.0 140.0,36.0 139.0,35.0
+            </coordinates></LinearRing></outerBoundaryIs>
+            <innerBoundaryIs><LinearRing><coordinates>
+              139.4,35.2 139.6,35.2 139.6,35.4
+            </coordinates></LinearRing></innerBoundaryIs>
+          </Polygon>
+        </MultiGeometry>
+      </Placemark>
+      <Placemark><Style/><LineString><coordinates>0,0 1,1</coordinates></LineString></Placemark>
+    </Folder>
+  </Document>
+</kml>"##;
+        
+        let features = parse_kml(kml).unwrap();
+        
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0].properties["name"], "Tokyo & Yokohama");
+        assert_eq!(features[0].properties["description"], "<b>Capital</b>");
+        assert_eq!(features[0].properties["population"], 13960000);
+        assert_eq!(features[0].properties["kind"], "city");
+        let GeometryType::Polygon(polygon) = &features[1].geometry else {
+            panic!("Expected Polygon geometry");
+        };
+        // The unclosed hole is closed
+        assert_eq!(polygon.interiors()[0].0.len(), 4);
+        assert!(matches!(features[2].geometry, GeometryType::LineString(_)));
+        assert!(features[2].properties.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_kml() {
+        assert!(parse_kml("<kml><Placemark></kml>").is_err());
+    }
+}
@@ -0,0 +1,326 @@
+// Multi-layer module
+// Generate several layers into one tileset, each with its own settings
+
+use crate::properties::{IdStrategy, PropertyFilter, PropertyZoomRule};
+use crate::geojson_parser::Feature;
+use crate::sink::MemorySink;
+use crate::transform::FeatureTransform;
+use crate::{generate_from_input, input, read_input, reduction, simplify, tiler};
+use crate::{TileFile, TileMetadata, TileOptions, MAX_ZOOM};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// One layer of a multi-layer tileset
+#[derive(Debug, Clone)]
+pub struct LayerInput<'a> {
+    pub name: String,
+    pub data: &'a [u8],
+    pub options: LayerOptions,
+}
+
+/// Per-layer settings overriding the shared defaults (`None` keeps the default)
+///
+/// A simplification with tolerance 0 turns simplification off for the layer. Hexbin and
+/// overview layers are not per layer: those of the defaults are generated once per tileset.
+#[derive(Debug, Clone, Default)]
+pub struct LayerOptions {
+    pub simplification: Option<simplify::Simplification>,
+    pub point_thinning: Option<reduction::PointThinning>,
+    pub limit_per_tile: Option<tiler::TileLimit>,
+    pub tile_budget: Option<reduction::TileBudget>,
+    /// Minimum polygon size drawn in the layer (e.g. larger for buildings than for parks)
+    pub tiny_polygons: Option<reduction::TinyPolygons>,
+    pub size_strategy: Option<reduction::SizeStrategy>,
+    pub sort_by: Option<tiler::SortBy>,
+    pub reader: Option<Arc<dyn input::InputReader>>,
+    /// How the layer's feature ids are assigned (e.g. `IdStrategy::Property("osm_id".into())`)
+    pub id_strategy: Option<IdStrategy>,
+    /// Properties the layer keeps (a trailing `*` matches any suffix, e.g. `name:*`); all when `None`
+    ///
+    /// Applied by the tiler like `property_zoom_rules`, after the default rules.
+    pub keep_properties: Option<Vec<String>>,
+    /// Extent of the layer at every zoom (MVT extents are per layer, so a coarse reference
+    /// layer can use e.g. 256 next to 4096 for detail); replaces the default `max_zoom_extent`
+    pub extent: Option<u32>,
+}
+
+impl LayerOptions {
+    /// The shared defaults with this layer's overrides applied
+    pub fn apply(&self, defaults: &TileOptions) -> TileOptions {
+        let mut options = defaults.clone();
+        if let Some(simplification) = self.simplification {
+            options.simplification = Some(simplification);
+        }
+        if let Some(thinning) = &self.point_thinning {
+            options.point_thinning = Some(thinning.clone());
+        }
+        if let Some(limit) = &self.limit_per_tile {
+            options.limit_per_tile = Some(limit.clone());
+        }
+        if let Some(budget) = self.tile_budget {
+            options.tile_budget = budget;
+        }
+        if let Some(tiny_polygons) = self.tiny_polygons {
+            options.tiny_polygons = Some(tiny_polygons);
+        }
+        if let Some(strategy) = self.size_strategy {
+            options.size_strategy = Some(strategy);
+        }
+        if let Some(sort_by) = &self.sort_by {
+            options.sort_by = Some(sort_by.clone());
+        }
+        if let Some(reader) = &self.reader {
+            options.reader = Some(reader.clone());
+        }
+        if let Some(strategy) = &self.id_strategy {
+            options.id_strategy = strategy.clone();
+        }
+        if let Some(extent) = self.extent {
+            options.extent = Some(extent);
+            options.max_zoom_extent = None;
+        }
+        if let Some(keep) = &self.keep_properties {
+            options.property_zoom_rules.push(PropertyZoomRule {
+                min_zoom: 0,
+                max_zoom: MAX_ZOOM,
+                filter: PropertyFilter::IncludeMatching(keep.clone()),
+            });
+        }
+        options
+    }
+}
+
+/// Generate every layer and combine them into one tileset
+///
+/// Tiles of the same coordinate are concatenated (an encoded tile is a list of layers), in
+/// the order the layers are given, followed by the hexbin and overview layers of `defaults`
+/// computed from the features of every layer. Metadata is returned per layer.
+pub fn generate_layers(
+    layers: &[LayerInput],
+    min_zoom: u8,
+    max_zoom: u8,
+    defaults: &TileOptions,
+) -> Result<(Vec<TileFile>, Vec<TileMetadata>), String> {
+    let mut tileset = Tileset::default();
+    let mut metadata = Vec::with_capacity(layers.len());
+    let mut all_features = Vec::new();
+    
+    for (i, layer) in layers.iter().enumerate() {
+        if layers[..i].iter().any(|other| other.name == layer.name) {
+            return Err(format!("Duplicate layer name: {}", layer.name));
+        }
+        let options = without_aggregates(layer.options.apply(defaults));
+        let mut generate = || {
+            options.limits.check_input_size(layer.data.len())?;
+            let input = read_input(layer.data, &options)?;
+            if has_aggregates(defaults) {
+                all_features.extend(input.0.iter().cloned());
+            }
+            generate_from_input(input, min_zoom, max_zoom, &layer.name, &options)
+        };
+        let (layer_tiles, layer_metadata, _) = generate().map_err(|e| format!("Layer {}: {}", layer.name, e))?;
+        tileset.add(layer_tiles);
+        metadata.push(layer_metadata);
+    }
+    tileset.add(aggregate_tiles(all_features, min_zoom, max_zoom, defaults)?);
+    
+    Ok((tileset.tiles, metadata))
+}
+
+/// Generate one input into the layers its features name (`FeatureConfig::layer`, as in
+/// tippecanoe's `"tippecanoe": {"layer": "roads"}` member)
+///
+/// Features without a layer go to `default_layer`. Layers are written in the order they first
+/// appear in the input, with metadata per layer.
+pub fn generate_routed_layers(
+    data: &[u8],
+    min_zoom: u8,
+    max_zoom: u8,
+    default_layer: &str,
+    options: &TileOptions,
+) -> Result<(Vec<TileFile>, Vec<TileMetadata>), String> {
+    options.limits.check_input_size(data.len())?;
+    let (features, foreign_members, warnings) = read_input(data, options)?;
+    let mut routes: Vec<(String, Vec<Feature>)> = Vec::new();
+    for feature in features {
+        let name = feature.config.as_ref().and_then(|config| config.layer.as_deref()).unwrap_or(default_layer);
+        match routes.iter_mut().find(|(route, _)| route == name) {
+            Some((_, layer_features)) => layer_features.push(feature),
+            None => routes.push((name.to_string(), vec![feature])),
+        }
+    }
+    
+    let mut tileset = Tileset::default();
+    let mut metadata = Vec::with_capacity(routes.len());
+    let layer_options = without_aggregates(options.clone());
+    let all_features: Vec<Feature> = match has_aggregates(options) {
+        true => routes.iter().flat_map(|(_, features)| features.iter().cloned()).collect(),
+        false => Vec::new(),
+    };
+    for (name, features) in routes {
+        // Warnings from reading the input are reported with every layer
+        let input = (features, foreign_members.clone(), warnings.clone());
+        let (layer_tiles, layer_metadata, _) = generate_from_input(input, min_zoom, max_zoom, &name, &layer_options)
+            .map_err(|e| format!("Layer {}: {}", name, e))?;
+        tileset.add(layer_tiles);
+        metadata.push(layer_metadata);
+    }
+    tileset.add(aggregate_tiles(all_features, min_zoom, max_zoom, options)?);
+    Ok((tileset.tiles, metadata))
+}
+
+fn has_aggregates(options: &TileOptions) -> bool {
+    options.hexbin.is_some() || options.overview.is_some()
+}
+
+fn without_aggregates(options: TileOptions) -> TileOptions {
+    TileOptions { hexbin: None, overview: None, ..options }
+}
+
+/// Tiles holding only the hexbin and overview layers of `options`, computed from `features`
+fn aggregate_tiles(mut features: Vec<Feature>, min_zoom: u8, max_zoom: u8, options: &TileOptions) -> Result<Vec<TileFile>, String> {
+    if !has_aggregates(options) || features.is_empty() {
+        return Ok(Vec::new());
+    }
+    // Aggregates are computed from the source features, so dropping every feature in the
+    // tiler leaves the base layer out
+    let drop_features: Arc<dyn FeatureTransform> = Arc::new(|_: Feature, _: u8| None);
+    let options = TileOptions {
+        transforms: vec![drop_features],
+        property_zoom_rules: Vec::new(),
+        fill_empty_tiles: false,
+        extend_zooms: None,
+        ..options.clone()
+    };
+    // Warnings about the features were reported by the layers already
+    let mut sink = MemorySink::default();
+    if crate::prepare_features(&mut features, &options, &mut Vec::new())?.is_none() {
+        let input = (features, Default::default(), Vec::new());
+        crate::tile_prepared_features(min_zoom, max_zoom, "aggregates", &options, &mut sink, input, 0.0)?;
+    }
+    Ok(sink.tiles)
+}
+
+/// Tiles of several layers, concatenated by coordinate (an encoded tile is a list of layers)
+#[derive(Default)]
+struct Tileset {
+    tiles: Vec<TileFile>,
+    index: HashMap<String, usize>,
+}
+
+impl Tileset {
+    fn add(&mut self, tiles: Vec<TileFile>) {
+        for tile in tiles {
+            match self.index.get(&tile.path) {
+                Some(&index) => self.tiles[index].data.extend(tile.data),
+                None => {
+                    self.index.insert(tile.path.clone(), self.tiles.len());
+                    self.tiles.push(tile);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvt_decoder::decode_tile;
+
+    #[test]
+    fn test_generate_layers_with_overrides() {
+        let points = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"rank": 2, "name": "Shinjuku", "name:en": "Shinjuku City"}, "geometry": {"type": "Point", "coordinates": [139.70, 35.70]}},
+            {"type": "Feature", "properties": {"rank": 1}, "geometry": {"type": "Point", "coordinates": [139.71, 35.71]}}
+        ]}"#;
+        let layers = [
+            LayerInput { name: "all".to_string(), data: points, options: LayerOptions::default() },
+            LayerInput {
+                name: "top".to_string(),
+                data: points,
+                options: LayerOptions {
+                    limit_per_tile: Some(tiler::TileLimit { n: 1, rank_by: "rank".to_string() }),
+                    keep_properties: Some(vec!["name:*".to_string()]),
+                    ..Default::default()
+                },
+            },
+        ];
+        
+        let (tiles, metadata) = generate_layers(&layers, 0, 2, &TileOptions::default()).unwrap();
+        
+        assert_eq!(metadata.len(), 2);
+        let tile = tiles.iter().find(|t| t.path == "0/0/0.pbf").unwrap();
+        let decoded = decode_tile(&tile.data).unwrap();
+        let counts: Vec<(&str, usize)> = decoded.iter().map(|l| (l.name.as_str(), l.features.len())).collect();
+        assert_eq!(counts, [("all", 2), ("top", 1)]);
+        let kept: Vec<&String> = decoded[1].features[0].properties.keys().collect();
+        assert_eq!(kept, ["name:en"]);
+        assert!(generate_layers(&[layers[0].clone(), layers[0].clone()], 0, 2, &TileOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_aggregates_once_per_tileset() {
+        let point = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.70, 35.70]}}"#;
+        let dust = reduction::TinyPolygons { min_area: 4.0, cell_size: 16 };
+        let layers = [
+            LayerInput { name: "places".to_string(), data: point, options: LayerOptions::default() },
+            LayerInput { name: "stations".to_string(), data: point, options: LayerOptions { tiny_polygons: Some(dust), ..Default::default() } },
+        ];
+        let defaults = TileOptions {
+            hexbin: Some(crate::aggregate::Hexbin { layer_name: "hex".to_string(), radius: vec![(0, 256.0)], sum: Vec::new() }),
+            ..Default::default()
+        };
+        
+        let (tiles, metadata) = generate_layers(&layers, 0, 0, &defaults).unwrap();
+        
+        assert_eq!(metadata.len(), 2);
+        let decoded = decode_tile(&tiles[0].data).unwrap();
+        let names: Vec<&str> = decoded.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["places", "stations", "hex"]);
+        // One hexagon layer counting the points of both layers
+        assert_eq!(decoded[2].features[0].properties["count"], 2);
+        assert_eq!(layers[1].options.apply(&defaults).tiny_polygons, Some(dust));
+        assert_eq!(layers[0].options.apply(&defaults).tiny_polygons, None);
+    }
+
+    #[test]
+    fn test_generate_routed_layers() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "tippecanoe": {"layer": "roads", "minzoom": 2}, "properties": {}, "geometry": {"type": "Point", "coordinates": [139.70, 35.70]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.71, 35.71]}},
+            {"type": "Feature", "tippecanoe": {"maxzoom": 1}, "properties": {}, "geometry": {"type": "Point", "coordinates": [139.72, 35.72]}}
+        ]}"#;
+        
+        let (tiles, metadata) = generate_routed_layers(geojson, 0, 3, "places", &TileOptions::default()).unwrap();
+        
+        let names: Vec<&str> = metadata.iter().map(|m| m.layer_name.as_str()).collect();
+        assert_eq!(names, ["roads", "places"]);
+        let counts_at = |zoom: u8| {
+            let tile = tiles.iter().find(|t| t.path.starts_with(&format!("{}/", zoom))).unwrap();
+            decode_tile(&tile.data).unwrap().iter().map(|l| (l.name.clone(), l.features.len())).collect::<Vec<_>>()
+        };
+        assert_eq!(counts_at(0), [("places".to_string(), 2)]);
+        assert_eq!(counts_at(3), [("roads".to_string(), 1), ("places".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_per_layer_extent() {
+        let geojson = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "LineString", "coordinates": [[139.0, 35.0], [139.1, 35.2], [139.3, 35.1]]}}"#;
+        let coarse = LayerOptions { extent: Some(256), ..Default::default() };
+        let layers = [
+            LayerInput { name: "detail".to_string(), data: geojson, options: LayerOptions::default() },
+            LayerInput { name: "reference".to_string(), data: geojson, options: coarse },
+        ];
+        let defaults = TileOptions { max_zoom_extent: Some(8192), ..Default::default() };
+        
+        let (tiles, _) = generate_layers(&layers, 0, 2, &defaults).unwrap();
+        
+        for tile in &tiles {
+            let extents: Vec<u32> = decode_tile(&tile.data).unwrap().iter().map(|l| l.extent).collect();
+            let detail = if tile.path.starts_with("2/") { 8192 } else { 4096 };
+            assert_eq!(extents, [detail, 256]);
+        }
+        let invalid = TileOptions { extent: Some(0), ..Default::default() };
+        assert!(crate::generate_tiles_with_options(geojson, 0, 0, "test", &invalid).unwrap_err().starts_with("extent must be"));
+    }
+}
@@ -5,11 +5,85 @@ pub mod geojson_parser;
 pub mod projection;
 pub mod tiler;
 pub mod mvt_encoder;
+pub mod geometry_codec;
+pub mod properties;
+pub mod reduction;
+pub mod simplify;
+pub mod topology;
+pub mod buffer;
+pub mod transform;
+pub mod tilejson;
+pub mod sink;
+pub mod input;
+pub mod encoding;
+pub mod logging;
+pub mod metrics;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod async_api;
+pub mod inspect;
+pub mod export;
+pub mod mvt_decoder;
+pub mod clip;
+pub mod tile_source;
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+#[cfg(all(feature = "node", unix))]
+mod node;
+#[cfg(feature = "mvt3")]
+pub mod mvt3;
+pub mod merge;
+pub mod pmtiles;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod mbtiles;
+mod sqlite;
+pub mod hash;
+#[cfg(feature = "zip")]
+mod inflate;
+#[cfg(feature = "zip")]
+mod deflate;
+mod brotli;
+#[cfg(feature = "shapefile")]
+pub mod shapefile;
+#[cfg(feature = "kml")]
+pub mod kml;
+#[cfg(feature = "flatgeobuf")]
+pub mod flatgeobuf;
+pub mod preset;
+pub mod layers;
+pub mod aggregate;
+pub mod version;
+pub mod doctor;
+pub mod validity;
+pub mod limits;
+pub mod parallel;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spill;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 pub mod wasm_api;
 
-use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Deepest supported zoom level
+///
+/// Tile coordinates are `u32` and positions are projected in `f64`; at z30 even a 2^20 extent
+/// keeps world coordinates below 2^53, where `f64` is still exact.
+pub const MAX_ZOOM: u8 = 30;
+
+/// Largest supported tile extent (leaves room for buffers and clipping in `i32` tile coordinates)
+pub const MAX_EXTENT: u32 = 1 << 20;
+
+/// Check that `min_zoom <= max_zoom <= MAX_ZOOM` (`InvalidZoomRange` errors otherwise)
+pub fn validate_zoom_range(min_zoom: u8, max_zoom: u8) -> Result<(), String> {
+    if min_zoom > max_zoom {
+        return Err(format!("InvalidZoomRange: min_zoom {} is greater than max_zoom {}", min_zoom, max_zoom));
+    }
+    if max_zoom > MAX_ZOOM {
+        return Err(format!("InvalidZoomRange: max_zoom {} exceeds the supported maximum of {}", max_zoom, MAX_ZOOM));
+    }
+    Ok(())
+}
 
 /// Tile coordinate structure
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -37,13 +111,154 @@ pub struct TileFile {
 }
 
 /// Tile metadata (for TileJSON generation)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct TileMetadata {
     pub min_zoom: u8,
     pub max_zoom: u8,
     pub layer_name: String,
     pub bounds: (f64, f64, f64, f64), // (min_lon, min_lat, max_lon, max_lat)
     pub center: (f64, f64),            // (center_lon, center_lat)
+    pub warnings: Vec<Warning>,
+    /// Feature and attribute statistics (tilestats)
+    pub stats: tilejson::LayerStats,
+    /// Attribute descriptions for `vector_layers`
+    pub schema: tilejson::FieldSchema,
+    /// Tile counts and sizes per zoom level (empty when read back from an archive)
+    pub zooms: Vec<metrics::ZoomSummary>,
+    /// Top-level GeoJSON foreign members kept by `TileOptions::foreign_members`
+    pub foreign_members: serde_json::Map<String, serde_json::Value>,
+    /// Source property the feature ids come from (`IdStrategy::Property`)
+    pub id_field: Option<String>,
+}
+
+/// Non-fatal issue reported during tile generation
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Warning {
+    /// Index of the input feature the warning refers to (if any)
+    pub feature_index: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.feature_index {
+            Some(index) => write!(f, "Feature {}: {}", index, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Tile generation options
+#[derive(Debug, Clone, Default)]
+pub struct TileOptions {
+    /// How `null` property values are encoded
+    pub null_handling: mvt_encoder::NullHandling,
+    /// Maximum length (in characters) of string property values
+    pub max_string_length: Option<usize>,
+    /// What to do with strings longer than `max_string_length`
+    pub string_overflow: properties::StringOverflow,
+    /// Feature order within each tile (input order when `None`)
+    pub sort_by: Option<tiler::SortBy>,
+    /// Keep only the top-N ranked features in each tile
+    pub limit_per_tile: Option<tiler::TileLimit>,
+    /// Buffer geometries before tiling (points and lines become polygons)
+    pub buffer: Option<buffer::Buffer>,
+    /// Line and polygon simplification (geometries are kept as-is when `None`)
+    pub simplification: Option<simplify::Simplification>,
+    /// Store polygons as shared arcs and rebuild them per zoom
+    /// (with topology-preserving simplification, arcs are simplified once per zoom for the whole dataset)
+    pub build_topology: bool,
+    /// Keep at most one point per grid cell in each tile
+    pub point_thinning: Option<reduction::PointThinning>,
+    /// Per-tile feature/byte budget enforced by `size_strategy`
+    pub tile_budget: reduction::TileBudget,
+    /// How tiles over `tile_budget` are reduced (budget is ignored when `None`)
+    pub size_strategy: Option<reduction::SizeStrategy>,
+    /// Add up to this many zooms past `max_zoom` while the last zoom still drops features to
+    /// fit `tile_budget` (tippecanoe's `--extend-zooms-if-still-dropping`); the metadata
+    /// reports the zoom reached
+    pub extend_zooms: Option<u8>,
+    /// Custom per-zoom feature transforms, run in order by the tiler
+    /// (shared so options stay cloneable)
+    pub transforms: Vec<Arc<dyn transform::FeatureTransform>>,
+    /// Input format (GeoJSON when `None`)
+    pub reader: Option<Arc<dyn input::InputReader>>,
+    /// Only tile features whose bounds intersect this (min_lon, min_lat, max_lon, max_lat) box;
+    /// readers with a spatial index (FlatGeobuf) skip the others while reading
+    pub bbox_filter: Option<(f64, f64, f64, f64)>,
+    /// How text inputs that are not UTF-8 are read (a warning names the encoding used)
+    pub text_decoding: encoding::TextDecoding,
+    /// Extent used at `max_zoom` and any zooms added by `extend_zooms` (e.g. 8192 or 16384) so
+    /// tiles stay crisp when overzoomed; other zooms use `extent`. Simplification tolerance and thinning cell size scale with it.
+    pub max_zoom_extent: Option<u32>,
+    /// Extent of the other zooms (4096 when `None`, e.g. 256 for a coarse layer); tile-unit
+    /// settings scale with it as with `max_zoom_extent`
+    pub extent: Option<u32>,
+    /// MVT version written to each layer
+    pub mvt_version: mvt_encoder::MvtVersion,
+    /// Compatibility mode for legacy MVT consumers
+    pub compatibility: mvt_encoder::Compatibility,
+    /// How feature ids are assigned (source ids or per-tile numbering by default)
+    pub id_strategy: properties::IdStrategy,
+    /// Drop exact duplicate input features (the count is reported as a warning)
+    pub remove_duplicates: bool,
+    /// Thresholds for flagging high-cardinality attributes and key counts
+    pub cardinality_limits: metrics::CardinalityLimits,
+    /// Keep only features whose timestamp property falls in this window
+    pub time_window: Option<properties::TimeWindow>,
+    /// Per-zoom property include/exclude rules, applied by the tiler before `transforms`
+    pub property_zoom_rules: Vec<properties::PropertyZoomRule>,
+    /// How the dataset center in the metadata is chosen
+    pub center: geojson_parser::CenterStrategy,
+    /// Attribute descriptions and units written to the TileJSON `vector_layers` fields
+    pub field_schema: tilejson::FieldSchema,
+    /// Whether features with malformed or unsupported geometry fail generation or are left out
+    pub geometry_checks: mvt_encoder::GeometryChecks,
+    /// How features sharing an id within a tile layer are written
+    pub id_collision: mvt_encoder::IdCollision,
+    /// Polygon ring orientation written to the tiles
+    pub winding: mvt_encoder::Winding,
+    /// Attribute table merged into the features before tiling
+    #[cfg(feature = "csv")]
+    pub csv_join: Option<properties::CsvJoin>,
+    /// Spill tiled features to temporary files when they exceed a memory budget (native only)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub spill: Option<spill::SpillOptions>,
+    /// Size above which a tile is reported together with its largest features
+    pub size_warning: metrics::TileSizeWarning,
+    /// Accumulate sub-pixel polygons into representative squares instead of drawing each one
+    pub tiny_polygons: Option<reduction::TinyPolygons>,
+    /// Extra layer of hexagons counting the point features
+    pub hexbin: Option<aggregate::Hexbin>,
+    /// Extra low-zoom layer of grid cells counting the features (for density maps)
+    pub overview: Option<aggregate::Overview>,
+    /// Number the points of each tile by an attribute (after `limit_per_tile`)
+    pub label_rank: Option<tiler::LabelRank>,
+    /// Top-level GeoJSON foreign members copied to the metadata (none when `None`;
+    /// `IncludeMatching(vec!["*".into()])` keeps all)
+    pub foreign_members: Option<properties::PropertyFilter>,
+    /// Add a `sort_rank` attribute; tiles are ordered by it when `sort_by` is not set
+    pub sort_rank: Option<properties::SortRank>,
+    /// Write an empty-layer tile for every coordinate within the data bounds that has no
+    /// features (for caches that expect full coverage; the count grows 4x per zoom)
+    pub fill_empty_tiles: bool,
+    /// Write features without ids (neither source ids nor per-tile numbers), for strict
+    /// consumers and so identical features encode identically
+    pub omit_ids: bool,
+    /// Check input geometries for OGC validity before tiling (see `validity::validate_features`):
+    /// each problem is a warning, or the first invalid feature fails generation under `GeometryChecks::Strict`
+    pub validate_input: bool,
+    /// Input and output sizes above which generation fails early (unlimited by default)
+    pub limits: limits::ResourceLimits,
+    /// How tiling and encoding are split across threads (native only; picked from the input by default)
+    pub parallel_strategy: parallel::ParallelStrategy,
+}
+
+impl TileOptions {
+    /// Options of a built-in generalization preset (other fields can be overridden with `..`)
+    pub fn preset(preset: preset::Preset) -> Self {
+        preset.options()
+    }
 }
 
 /// Main tile generation function (with metadata)
@@ -53,12 +268,474 @@ pub fn generate_tiles_with_metadata(
     max_zoom: u8,
     layer_name: &str,
 ) -> Result<(Vec<TileFile>, TileMetadata), String> {
-    // 1. Parse GeoJSON
-    let features = geojson_parser::parse_geojson(geojson_bytes)?;
+    generate_tiles_with_options(geojson_bytes, min_zoom, max_zoom, layer_name, &TileOptions::default())
+}
+
+/// Main tile generation function (with metadata and options)
+pub fn generate_tiles_with_options(
+    geojson_bytes: &[u8],
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+) -> Result<(Vec<TileFile>, TileMetadata), String> {
+    let (tiles, metadata, _metrics) = generate_tiles_with_metrics(geojson_bytes, min_zoom, max_zoom, layer_name, options)?;
+    Ok((tiles, metadata))
+}
+
+/// Tile generation with per-stage timing metrics
+pub fn generate_tiles_with_metrics(
+    geojson_bytes: &[u8],
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+) -> Result<(Vec<TileFile>, TileMetadata, metrics::GenerationMetrics), String> {
+    let mut sink = sink::MemorySink::default();
+    let (metadata, metrics) = generate_tiles_to_sink(geojson_bytes, min_zoom, max_zoom, layer_name, options, &mut sink)?;
+    Ok((sink.tiles, metadata, metrics))
+}
+
+/// Generate tiles into a sink (tiles are written as soon as they are encoded)
+///
+/// Tiles reach the sink zoom by zoom in tile id order (see `pmtiles::tile_id`), so the output
+/// is byte-identical between runs and unaffected by the zoom pipeline or spilling.
+pub fn generate_tiles_to_sink(
+    geojson_bytes: &[u8],
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+    sink: &mut dyn sink::TileSink,
+) -> Result<(TileMetadata, metrics::GenerationMetrics), String> {
+    options.limits.check_input_size(geojson_bytes.len())?;
+    generate_to_sink(min_zoom, max_zoom, layer_name, options, sink, || read_input(geojson_bytes, options))
+}
+
+/// Generate tiles into a sink from a FlatGeobuf file or PMTiles archive read through `reader`
+///
+/// Made for remote sources (see `http::HttpRangeReader`): with `bbox_filter`, only the
+/// FlatGeobuf index nodes and features in the box, or the PMTiles tiles of the deepest zoom that
+/// touch it, are read. PMTiles archives are read as `input::TilesetReader` reads them.
+pub fn generate_ranges_to_sink<R: pmtiles::RangeReader + ?Sized>(
+    reader: &R,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+    sink: &mut dyn sink::TileSink,
+) -> Result<(TileMetadata, metrics::GenerationMetrics), String> {
+    generate_to_sink(min_zoom, max_zoom, layer_name, options, sink, || read_range_input(reader, options))
+}
+
+/// Features of a FlatGeobuf file or PMTiles archive behind `reader`
+fn read_range_input<R: pmtiles::RangeReader + ?Sized>(reader: &R, options: &TileOptions) -> Result<Input, String> {
+    let prefix = reader.read_range(0, 8)?;
+    let (features, warnings) = if prefix.starts_with(b"PMTiles") {
+        (input::TilesetReader::default().read_ranges(reader, options.bbox_filter)?, Vec::new())
+    } else if prefix.starts_with(b"fgb\x03") {
+        #[cfg(feature = "flatgeobuf")]
+        {
+            flatgeobuf::read_flatgeobuf(reader, options.bbox_filter, options.geometry_checks)?
+        }
+        #[cfg(not(feature = "flatgeobuf"))]
+        return Err("FlatGeobuf input needs the 'flatgeobuf' feature".to_string());
+    } else {
+        return Err("Range reads need a FlatGeobuf file or a PMTiles archive".to_string());
+    };
+    Ok((options.limits.collect_features(features.into_iter())?, serde_json::Map::new(), warnings))
+}
+
+/// Features, top-level foreign members and warnings of an input
+pub type Input = (Vec<geojson_parser::Feature>, serde_json::Map<String, serde_json::Value>, Vec<Warning>);
+
+/// Features and top-level foreign members read with `options.reader` (GeoJSON when `None`)
+pub(crate) fn read_input(bytes: &[u8], options: &TileOptions) -> Result<Input, String> {
+    let reader: &dyn input::InputReader = match &options.reader {
+        Some(reader) => reader.as_ref(),
+        #[cfg(feature = "geojson")]
+        None => &input::GeoJsonReader,
+        #[cfg(not(feature = "geojson"))]
+        None => return Err("No input reader configured (GeoJSON input needs the 'geojson' feature)".to_string()),
+    };
+    let (bytes, decoded) = encoding::decode(bytes, options.text_decoding);
+    let (features, foreign_members, mut warnings) = match options.bbox_filter {
+        Some(bbox) => reader.read_features_in(&bytes, options.geometry_checks, bbox)?,
+        None => reader.read_features_checked(&bytes, options.geometry_checks)?,
+    };
+    warnings.splice(0..0, decoded.map(|message| Warning { feature_index: None, message }));
+    Ok((options.limits.collect_features(features)?, foreign_members, warnings))
+}
+
+/// Tile generation from already parsed features (and top-level foreign members)
+///
+/// Skips the input reader, so callers holding features in memory need not serialize them.
+pub fn generate_tiles_from_features(
+    features: Vec<geojson_parser::Feature>,
+    foreign_members: serde_json::Map<String, serde_json::Value>,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+) -> Result<(Vec<TileFile>, TileMetadata, metrics::GenerationMetrics), String> {
+    generate_from_input((features, foreign_members, Vec::new()), min_zoom, max_zoom, layer_name, options)
+}
+
+/// `generate_tiles_from_features` with warnings from reading the input, reported first
+pub(crate) fn generate_from_input(
+    input: Input,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+) -> Result<(Vec<TileFile>, TileMetadata, metrics::GenerationMetrics), String> {
+    let mut sink = sink::MemorySink::default();
+    let (metadata, metrics) = generate_to_sink(min_zoom, max_zoom, layer_name, options, &mut sink, || Ok(input))?;
+    Ok((sink.tiles, metadata, metrics))
+}
+
+/// Shared pipeline; `read` supplies the features once the options are validated
+pub(crate) fn generate_to_sink(
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+    sink: &mut dyn sink::TileSink,
+    read: impl FnOnce() -> Result<Input, String>,
+) -> Result<(TileMetadata, metrics::GenerationMetrics), String> {
+    let start = metrics::now_ms();
+    let _span = logging::span(logging::Stage::new("generate", layer_name));
+    
+    validate_zoom_range(min_zoom, max_zoom)?;
+    for (name, extent) in [("extent", options.extent), ("max_zoom_extent", options.max_zoom_extent)] {
+        if matches!(extent, Some(extent) if extent == 0 || extent > MAX_EXTENT) {
+            return Err(format!("{} must be between 1 and {}", name, MAX_EXTENT));
+        }
+    }
+    
+    // 1. Parse input
+    let parsing = logging::span(logging::Stage::new("parse", layer_name));
+    let (mut features, mut foreign_members, mut warnings) = read()?;
+    options.limits.check_features(&features)?;
+    match &options.foreign_members {
+        Some(filter) => foreign_members.retain(|key, _| filter.keeps(key)),
+        None => foreign_members.clear(),
+    }
+    if options.validate_input {
+        validity::check_features(&features, options.geometry_checks, &mut warnings)?;
+    }
+    if let Some(reason) = prepare_features(&mut features, options, &mut warnings)? {
+        return Err(reason.to_string());
+    }
+    drop(parsing);
+    let (metadata, metrics) =
+        tile_prepared_features(min_zoom, max_zoom, layer_name, options, sink, (features, foreign_members, warnings), start)?;
+    // Internal passes over the same input (aggregates, edits) call `tile_prepared_features`
+    // directly, so each warning is logged once
+    logging::log_warnings(&metadata.warnings);
+    Ok((metadata, metrics))
+}
+
+/// The tiling and encoding half of `generate_to_sink`, for features already prepared with
+/// `prepare_features`; `start` is when reading began
+pub(crate) fn tile_prepared_features(
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+    options: &TileOptions,
+    sink: &mut dyn sink::TileSink,
+    (mut features, foreign_members, mut warnings): Input,
+    start: f64,
+) -> Result<(TileMetadata, metrics::GenerationMetrics), String> {
+    let mut metrics = metrics::GenerationMetrics::default();
+    // Written tiles (empty ones included) are counted against max_tiles as they are written
+    let mut sink = limits::LimitedSink::new(sink, options.limits.max_tiles);
+    let sink: &mut dyn sink::TileSink = &mut sink;
+    let extent_at = |zoom: u8| match options.max_zoom_extent {
+        Some(extent) if zoom >= max_zoom => extent,
+        _ => options.extent.unwrap_or(tiler::DEFAULT_EXTENT),
+    };
+    metrics.parse_ms = metrics::now_ms() - start;
+    let stats = tilejson::LayerStats::from_features(&features);
     
-    // 2. Calculate metadata
+    // 2. Calculate bounds
     let bounds = geojson_parser::calculate_bounds(&features)?;
-    let center = geojson_parser::calculate_center(bounds);
+    let center = geojson_parser::calculate_center_with(&features, bounds, options.center);
+    // Taken before a topology empties the polygons it takes over, for the extended zooms too
+    let tile_boxes = options.limits.tile_boxes(&features, bounds, options.fill_empty_tiles);
+    options.limits.check_tiles(&tile_boxes, min_zoom, max_zoom)?;
+    
+    let topology = if options.build_topology {
+        let topology = topology::Topology::from_features(&mut features);
+        logging::log_debug!("Built topology with {} arcs", topology.arc_count());
+        Some(topology)
+    } else {
+        None
+    };
+    let dataset_simplification = options.simplification
+        .filter(|s| topology.is_some() && s.mode == simplify::SimplifyMode::TopologyPreserving);
+    
+    let mut transforms = options.transforms.clone();
+    if !options.property_zoom_rules.is_empty() {
+        let rules = options.property_zoom_rules.clone();
+        transforms.insert(0, Arc::new(move |mut feature: geojson_parser::Feature, zoom: u8| {
+            properties::apply_property_zoom_rules(&mut feature.properties, &rules, zoom);
+            Some(feature)
+        }));
+    }
+    
+    let strategy = options.parallel_strategy.resolve(&features);
+    logging::log_debug!("Parallel strategy: {}", strategy);
+    // Every zoom tiles the same mercator coordinates, so they are projected once (not when
+    // spilling, which trades time for memory, or for a single zoom)
+    #[cfg(not(target_arch = "wasm32"))]
+    let spilling = options.spill.is_some();
+    #[cfg(target_arch = "wasm32")]
+    let spilling = false;
+    let projected = (min_zoom < max_zoom && topology.is_none() && !spilling).then(|| tiler::project_features(&features));
+    
+    // 3. Generate tiles for each zoom level
+    let mut dropped_features = 0;
+    let mut clamped_coordinates = 0;
+    let mut invalid_geometries = 0;
+    let mut id_collisions = 0;
+    
+    // 4. Assign features to tiles
+    let tile_zoom = |zoom: u8| -> Result<TiledZoom, String> {
+        let tiling_start = metrics::now_ms();
+        let _span = logging::span(logging::Stage::new("tile", layer_name).at_zoom(zoom));
+        let zoom_features;
+        let source = match &topology {
+            Some(topology) => {
+                let tolerance = dataset_simplification.map(|s| simplify::tolerance_in_meters(s.tolerance, zoom));
+                zoom_features = topology.rebuild_features(&features, tolerance);
+                &zoom_features
+            }
+            None => &features,
+        };
+        
+        // `features` holds placeholders for the polygons a topology took over
+        let aggregates = aggregate::aggregate_layers(source, zoom, extent_at(zoom), options);
+        
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(spill) = &options.spill {
+            let (tiles, clamped) = spill::tile_features(source, zoom, extent_at(zoom), &transforms, spill)?;
+            return Ok(TiledZoom { zoom, tiles, aggregates, clamped, tiling_ms: metrics::now_ms() - tiling_start });
+        }
+        let projected = projected.as_deref();
+        #[cfg(not(target_arch = "wasm32"))]
+        let (tiles, clamped) = match strategy {
+            parallel::ParallelStrategy::PerFeatureBatch => {
+                parallel::tile_feature_batches(source, projected, zoom, extent_at(zoom), &transforms)?
+            }
+            _ => tiler::tile_features_counting_clamped(source, projected, zoom, extent_at(zoom), &transforms)?,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let (tiles, clamped) = tiler::tile_features_counting_clamped(source, projected, zoom, extent_at(zoom), &transforms)?;
+        let tiles = ZoomTiles::Memory(tiles);
+        Ok(TiledZoom { zoom, tiles, aggregates, clamped, tiling_ms: metrics::now_ms() - tiling_start })
+    };
+    
+    // 5. Encode each tile in MVT format
+    // Returns the features dropped to fit the tile budget
+    let mut encode_zoom = |TiledZoom { zoom, tiles, aggregates, clamped, tiling_ms }: TiledZoom| -> Result<usize, String> {
+        let mut tile_count = 0;
+        let mut zoom_dropped = 0;
+        clamped_coordinates += clamped;
+        let mut zoom_bytes = 0;
+        let mut max_tile_bytes = 0;
+        let encode_start = metrics::now_ms();
+        let _span = logging::span(logging::Stage::new("encode", layer_name).at_zoom(zoom));
+        
+        // Tile-unit settings are given for the default extent
+        let extent = extent_at(zoom);
+        let scale = extent as f64 / tiler::DEFAULT_EXTENT as f64;
+        let simplification = options.simplification.map(|s| simplify::Simplification {
+            tolerance: s.tolerance * scale,
+            ..s
+        });
+        let thinning = options.point_thinning.as_ref().map(|t| reduction::PointThinning {
+            cell_size: (t.cell_size as f64 * scale).round() as i32,
+            ..t.clone()
+        });
+        let tiny_polygons = options.tiny_polygons.map(|t| reduction::TinyPolygons {
+            min_area: t.min_area * scale * scale,
+            cell_size: (t.cell_size as f64 * scale).round() as i32,
+        });
+        // Coordinates in the bounds, written as empty tiles unless a tile with data comes first
+        let empty_data = match options.fill_empty_tiles {
+            true => mvt_encoder::encode_empty_tile(layer_name, extent, options)?,
+            false => Vec::new(),
+        };
+        let mut empty_tiles = options.fill_empty_tiles.then(|| coverage(bounds, zoom)).into_iter().flatten().peekable();
+        let mut empty_count = 0;
+        let mut write_empty_before = |next: Option<TileCoord>, sink: &mut dyn sink::TileSink| -> Result<usize, String> {
+            let mut count = 0;
+            while let Some(&empty) = empty_tiles.peek() {
+                if matches!(next, Some(next) if pmtiles::tile_id(next) < pmtiles::tile_id(empty)) {
+                    break;
+                }
+                empty_tiles.next();
+                if Some(empty) != next {
+                    sink.write_tile(empty, &empty_data)?;
+                    count += 1;
+                }
+            }
+            Ok(count)
+        };
+        
+        let stages = TileStages {
+            layer_name,
+            extent,
+            options,
+            topology_simplified: dataset_simplification.is_some(),
+            simplification,
+            tiny_polygons,
+            thinning,
+        };
+        let tiles = with_aggregates(tiles.into_tiles(), aggregates);
+        for tile in parallel::encode_tiles(tiles, &stages, strategy == parallel::ParallelStrategy::PerTile) {
+            let tile = tile?;
+            invalid_geometries += tile.invalid_geometries;
+            zoom_dropped += tile.dropped_features;
+            id_collisions += tile.id_collisions;
+            // Every feature of the tile may have been left out
+            let Some(mvt_data) = tile.data else {
+                continue;
+            };
+            zoom_bytes += mvt_data.len();
+            max_tile_bytes = max_tile_bytes.max(mvt_data.len());
+            if let Some(sizes) = &tile.feature_sizes {
+                metrics.record_oversized(tile.coord, layer_name, mvt_data.len(), sizes, options.size_warning.top_features);
+            }
+            metrics.record_cardinality(tile.coord, &tile.keys, &options.cardinality_limits);
+            empty_count += write_empty_before(Some(tile.coord), sink)?;
+            sink.write_tile(tile.coord, &mvt_data)?;
+            tile_count += 1;
+        }
+        empty_count += write_empty_before(None, sink)?;
+        if empty_count > 0 {
+            tile_count += empty_count;
+            zoom_bytes += empty_count * empty_data.len();
+            max_tile_bytes = max_tile_bytes.max(empty_data.len());
+        }
+        logging::log_debug!("Zoom {}: wrote {} tiles ({} bytes)", zoom, tile_count, zoom_bytes);
+        
+        metrics.zooms.push(metrics::ZoomMetrics {
+            zoom,
+            tiling_ms,
+            encode_ms: metrics::now_ms() - encode_start,
+            tile_count,
+            bytes: zoom_bytes,
+            max_tile_bytes,
+        });
+        dropped_features += zoom_dropped;
+        Ok(zoom_dropped)
+    };
+    
+    // Natively, the next zoom is tiled on a second thread while the current one is encoded
+    let mut still_dropping = false;
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::scope(|scope| {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+        scope.spawn(move || {
+            for zoom in min_zoom..=max_zoom {
+                let tiled = tile_zoom(zoom);
+                let failed = tiled.is_err();
+                // A closed channel means encoding failed and nothing more is needed
+                if sender.send(tiled).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        receiver.into_iter().try_for_each(|tiled| {
+            still_dropping = encode_zoom(tiled?)? > 0;
+            Ok::<(), String>(())
+        })
+    })?;
+    
+    #[cfg(target_arch = "wasm32")]
+    for zoom in min_zoom..=max_zoom {
+        still_dropping = encode_zoom(tile_zoom(zoom)?)? > 0;
+    }
+    
+    // Extra zooms, one at a time since each depends on whether the previous one dropped features
+    let last_zoom = max_zoom.saturating_add(options.extend_zooms.unwrap_or(0)).min(MAX_ZOOM);
+    let mut max_zoom = max_zoom;
+    while still_dropping && max_zoom < last_zoom {
+        max_zoom += 1;
+        options.limits.check_tiles(&tile_boxes, min_zoom, max_zoom)
+            .map_err(|e| format!("{} (zoom {} was added by extend_zooms)", e, max_zoom))?;
+        still_dropping = encode_zoom(tile_zoom(max_zoom)?)? > 0;
+    }
+    
+    metrics.encode_ms = metrics.zooms.iter().map(|z| z.encode_ms).sum();
+    metrics.features_dropped = dropped_features;
+    
+    for report in &metrics.high_cardinality {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!(
+                "Attribute '{}' has {} distinct values in {} features (tile {}); consider dropping or bucketing it",
+                report.key, report.distinct_values, report.features, report.tile
+            ),
+        });
+    }
+    if metrics.max_keys_per_tile > options.cardinality_limits.max_keys {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!(
+                "A tile has {} distinct attribute keys (limit {})",
+                metrics.max_keys_per_tile, options.cardinality_limits.max_keys
+            ),
+        });
+    }
+    for report in &metrics.oversized_tiles {
+        let largest: Vec<String> = report.largest_features.iter()
+            .map(|feature| match feature.id {
+                Some(id) => format!("#{} (id {}, {} bytes)", feature.index, id, feature.bytes),
+                None => format!("#{} ({} bytes)", feature.index, feature.bytes),
+            })
+            .collect();
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!(
+                "Tile {} is {} bytes (limit {}); largest features in layer '{}': {}",
+                report.tile, report.bytes, options.size_warning.max_bytes, report.layer, largest.join(", ")
+            ),
+        });
+    }
+    if clamped_coordinates > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!(
+                "Clamped {} tile coordinates lying more than {} tile extents away from their tile",
+                clamped_coordinates, projection::COORDINATE_LIMIT_FACTOR
+            ),
+        });
+    }
+    if dropped_features > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("Removed {} tile features (dropped or coalesced) to fit the tile budget", dropped_features),
+        });
+    }
+    if invalid_geometries > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("Left out {} tile features with invalid geometry", invalid_geometries),
+        });
+    }
+    if id_collisions > 0 {
+        let action = match options.id_collision {
+            mvt_encoder::IdCollision::Rehash => "given new ids",
+            _ => "written without an id",
+        };
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("{} tile features sharing an id within their tile were {}", id_collisions, action),
+        });
+    }
     
     let metadata = TileMetadata {
         min_zoom,
@@ -66,26 +743,274 @@ pub fn generate_tiles_with_metadata(
         layer_name: layer_name.to_string(),
         bounds,
         center,
+        warnings,
+        stats,
+        schema: options.field_schema.clone(),
+        zooms: metrics.zooms.iter().map(metrics::ZoomSummary::from).collect(),
+        foreign_members,
+        id_field: match &options.id_strategy {
+            properties::IdStrategy::Property(key) if !options.omit_ids => Some(key.clone()),
+            _ => None,
+        },
     };
+    let writing = logging::span(logging::Stage::new("write", layer_name));
+    sink.finish(&metadata)?;
+    drop(writing);
+    metrics.total_ms = metrics::now_ms() - start;
+    Ok((metadata, metrics))
+}
+
+/// Filter and preprocess parsed features as generation does before tiling
+///
+/// Returns why no features are left when a stage removed the last ones.
+pub(crate) fn prepare_features(
+    features: &mut Vec<geojson_parser::Feature>,
+    options: &TileOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<&'static str>, String> {
+    properties::remove_unprojectable(features, warnings);
+    if features.is_empty() {
+        return Ok(Some("No valid features found"));
+    }
+    logging::log_debug!("Parsed {} features", features.len());
+    if let Some((min_lon, min_lat, max_lon, max_lat)) = options.bbox_filter {
+        features.retain(|feature| {
+            let bounds = limits::geometry_bounds(&feature.geometry);
+            bounds.0 <= max_lon && bounds.2 >= min_lon && bounds.1 <= max_lat && bounds.3 >= min_lat
+        });
+        if features.is_empty() {
+            return Ok(Some("No features intersect the bbox filter"));
+        }
+    }
     
-    // 3. Generate tiles for each zoom level
-    let mut tile_files = Vec::new();
+    // Preprocess properties
+    if let Some(window) = &options.time_window {
+        properties::filter_time_window(features, window, warnings)?;
+        if features.is_empty() {
+            return Ok(Some("No features fall in the time window"));
+        }
+    }
+    #[cfg(feature = "csv")]
+    if let Some(join) = &options.csv_join {
+        properties::join_csv(features, join, warnings)?;
+        if features.is_empty() {
+            return Ok(Some("No features matched the join CSV"));
+        }
+    }
+    if options.remove_duplicates {
+        properties::remove_duplicates(features, warnings);
+    }
+    properties::assign_ids(features, &options.id_strategy);
+    if let properties::IdStrategy::Property(key) = &options.id_strategy {
+        properties::check_property_ids(features, key, warnings);
+    }
+    if let Some(rank) = &options.sort_rank {
+        properties::assign_sort_rank(features, rank);
+    }
+    if let Some(max_length) = options.max_string_length {
+        properties::limit_string_lengths(features, max_length, options.string_overflow, warnings);
+    }
     
-    for zoom in min_zoom..=max_zoom {
-        // 4. Assign features to tiles
-        let tiles = tiler::tile_features(&features, zoom)?;
+    if let Some(buffer) = &options.buffer {
+        buffer::buffer_features(features, buffer, warnings);
+        if features.is_empty() {
+            return Ok(Some("No features are left after buffering"));
+        }
+    }
+    Ok(None)
+}
+
+/// Tile coordinates covering the bounds at a zoom, generated lazily in tile id order
+fn coverage(bounds: (f64, f64, f64, f64), zoom: u8) -> impl Iterator<Item = TileCoord> {
+    let (min_lon, min_lat, max_lon, max_lat) = bounds;
+    let (min_x, min_y) = projection::lonlat_to_tile(min_lon, max_lat, zoom);
+    let (max_x, max_y) = projection::lonlat_to_tile(max_lon, min_lat, zoom);
+    // Quadtree cells (x, y, size) still to visit, the one with the lowest tile ids on top. Each
+    // cell is one contiguous range of tile ids, so cells outside the bounds are skipped whole.
+    let mut cells = vec![(0u32, 0u32, 1u32 << zoom)];
+    std::iter::from_fn(move || {
+        while let Some((x, y, size)) = cells.pop() {
+            if x > max_x || y > max_y || x + size <= min_x || y + size <= min_y {
+                continue;
+            }
+            if size == 1 {
+                return Some(TileCoord::new(zoom, x, y));
+            }
+            let half = size / 2;
+            let mut children = [(x, y), (x + half, y), (x, y + half), (x + half, y + half)];
+            children.sort_unstable_by_key(|&(x, y)| std::cmp::Reverse(pmtiles::tile_id(TileCoord::new(zoom, x, y))));
+            cells.extend(children.map(|(x, y)| (x, y, half)));
+        }
+        None
+    })
+}
+
+/// Tiles of one zoom level, handed from tiling to encoding
+struct TiledZoom {
+    zoom: u8,
+    tiles: ZoomTiles,
+    /// Aggregation layers in tile id order
+    aggregates: Vec<(TileCoord, aggregate::AggregateLayers)>,
+    /// Coordinates clamped during quantization
+    clamped: usize,
+    tiling_ms: f64,
+}
+
+/// Settings of the per-tile stages of a zoom (scaled to its extent)
+pub(crate) struct TileStages<'a> {
+    layer_name: &'a str,
+    extent: u32,
+    options: &'a TileOptions,
+    /// Polygons were already simplified through the topology
+    topology_simplified: bool,
+    simplification: Option<simplify::Simplification>,
+    tiny_polygons: Option<reduction::TinyPolygons>,
+    thinning: Option<reduction::PointThinning>,
+}
+
+/// One tile after the per-tile stages, with what it adds to the warnings and metrics
+pub(crate) struct EncodedTile {
+    coord: TileCoord,
+    /// `None` when every feature was left out
+    data: Option<Vec<u8>>,
+    invalid_geometries: usize,
+    dropped_features: usize,
+    id_collisions: usize,
+    /// Encoded feature sizes, for tiles over the size warning
+    feature_sizes: Option<Vec<(Option<u64>, usize)>>,
+    keys: Vec<(String, usize, usize)>,
+}
+
+impl TileStages<'_> {
+    /// Generalize, order and encode one tile's features
+    fn encode(&self, coord: TileCoord, mut features: Vec<tiler::TileFeature>, aggregate_layers: aggregate::AggregateLayers) -> Result<EncodedTile, String> {
+        let options = self.options;
+        let mut tile = EncodedTile {
+            coord,
+            data: None,
+            invalid_geometries: 0,
+            dropped_features: 0,
+            id_collisions: 0,
+            feature_sizes: None,
+            keys: Vec::new(),
+        };
+        match (self.topology_simplified, &self.simplification) {
+            (true, Some(simplification)) => simplify::simplify_lines(&mut features, simplification.tolerance),
+            (false, Some(simplification)) => simplify::simplify_features(&mut features, simplification),
+            (_, None) => {}
+        }
+        if let Some(tiny) = &self.tiny_polygons {
+            reduction::accumulate_tiny_polygons(&mut features, tiny);
+        }
+        if let Some(thinning) = &self.thinning {
+            reduction::thin_points(&mut features, thinning);
+        }
+        if let Some(limit) = &options.limit_per_tile {
+            tiler::limit_features(&mut features, limit);
+        }
+        if let Some(sort_by) = &options.sort_by {
+            tiler::sort_features(&mut features, sort_by);
+        } else if options.sort_rank.is_some() {
+            tiler::sort_features(&mut features, &tiler::SortBy::Property(properties::SORT_RANK.to_string()));
+        }
+        if let Some(rank) = &options.label_rank {
+            tiler::rank_labels(&mut features, rank);
+        }
+        if options.geometry_checks == mvt_encoder::GeometryChecks::Lenient {
+            let before = features.len();
+            features.retain(|feature| geometry_codec::check_geometry(&feature.geometry).is_ok());
+            tile.invalid_geometries = before - features.len();
+        }
+        if features.is_empty() && aggregate_layers.is_empty() {
+            return Ok(tile);
+        }
         
-        // 5. Encode each tile in MVT format
-        for (coord, features) in tiles {
-            let mvt_data = mvt_encoder::encode_tile(&features, layer_name)?;
-            tile_files.push(TileFile {
-                path: coord.to_path(),
-                data: mvt_data,
-            });
+        let encode = |features: &[tiler::TileFeature]| {
+            let mut layers = vec![(self.layer_name, features)];
+            layers.extend(aggregate_layers.iter().map(|(name, layer)| (name.as_str(), layer.as_slice())));
+            mvt_encoder::encode_tile_multi(&layers, self.extent, options)
+        };
+        let mvt_data = match options.size_strategy {
+            Some(strategy) => {
+                let (data, dropped) = reduction::fit_to_budget(&mut features, &options.tile_budget, strategy, self.extent, encode)?;
+                tile.dropped_features = dropped;
+                data
+            }
+            None => encode(&features)?,
+        };
+        if mvt_data.len() > options.size_warning.max_bytes {
+            tile.feature_sizes = Some(mvt_encoder::feature_sizes(&features, self.extent, options)?);
         }
+        if options.id_collision != mvt_encoder::IdCollision::Keep {
+            tile.id_collisions = mvt_encoder::feature_ids(&features, options).1;
+        }
+        tile.keys = mvt_encoder::key_cardinality(&features, options);
+        tile.data = Some(mvt_data);
+        Ok(tile)
     }
-    
-    Ok((tile_files, metadata))
+}
+
+/// One tile's features, or the error reading them back
+pub(crate) type TileResult = Result<(TileCoord, Vec<tiler::TileFeature>), String>;
+
+/// Tiled features of one zoom level
+pub(crate) enum ZoomTiles {
+    Memory(tiler::TileMap),
+    /// Written to sorted run files (see `spill`)
+    #[cfg(not(target_arch = "wasm32"))]
+    Spilled(spill::SpillRuns),
+}
+
+impl ZoomTiles {
+    /// Tiles in tile id order
+    fn into_tiles(self) -> Box<dyn Iterator<Item = TileResult>> {
+        match self {
+            ZoomTiles::Memory(tiles) => {
+                let mut tiles: Vec<_> = tiles.into_iter().collect();
+                tiles.sort_unstable_by_key(|&(coord, _)| pmtiles::tile_id(coord));
+                Box::new(tiles.into_iter().map(Ok))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ZoomTiles::Spilled(runs) => runs.into_tiles(),
+        }
+    }
+}
+
+/// One tile's features and aggregation layers
+pub(crate) type TiledTile = (TileCoord, Vec<tiler::TileFeature>, aggregate::AggregateLayers);
+
+/// Join the tiles of a zoom with its aggregation layers (both in tile id order)
+///
+/// Tiles that only have aggregation layers come with no features.
+fn with_aggregates(
+    tiles: Box<dyn Iterator<Item = TileResult>>,
+    aggregates: Vec<(TileCoord, aggregate::AggregateLayers)>,
+) -> impl Iterator<Item = Result<TiledTile, String>> {
+    let mut tiles = tiles.peekable();
+    let mut aggregates = aggregates.into_iter().peekable();
+    std::iter::from_fn(move || {
+        let tile_id = match tiles.peek() {
+            Some(Ok((coord, _))) => Some(pmtiles::tile_id(*coord)),
+            Some(Err(_)) => return tiles.next().map(|tile| tile.map(|(coord, features)| (coord, features, Vec::new()))),
+            None => None,
+        };
+        let aggregate_id = aggregates.peek().map(|(coord, _)| pmtiles::tile_id(*coord));
+        match (tile_id, aggregate_id) {
+            (None, None) => None,
+            (Some(tile), Some(aggregate)) if aggregate < tile => {
+                aggregates.next().map(|(coord, layers)| Ok((coord, Vec::new(), layers)))
+            }
+            (None, Some(_)) => aggregates.next().map(|(coord, layers)| Ok((coord, Vec::new(), layers))),
+            (Some(tile), aggregate) => {
+                let layers = if aggregate == Some(tile) {
+                    aggregates.next().map(|(_, layers)| layers).unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                tiles.next().map(|next| next.map(|(coord, features)| (coord, features, layers)))
+            }
+        }
+    })
 }
 
 /// Main tile generation function (for backward compatibility)
@@ -102,10 +1027,371 @@ pub fn generate_tiles(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_tile_coord() {
         let coord = TileCoord::new(5, 10, 12);
         assert_eq!(coord.to_path(), "5/10/12.pbf");
     }
+
+    #[test]
+    fn test_generate_with_topology() {
+        let geojson = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "properties": {}, "geometry": {"type": "Polygon",
+                 "coordinates": [[[0, 0], [1, 0], [1.001, 0.5], [1, 1], [0, 1], [0, 0]]]}},
+                {"type": "Feature", "properties": {}, "geometry": {"type": "Polygon",
+                 "coordinates": [[[1, 0], [2, 0], [2, 1], [1, 1], [1.001, 0.5], [1, 0]]]}}
+            ]
+        }"#;
+        let options = TileOptions {
+            build_topology: true,
+            simplification: Some(simplify::Simplification {
+                tolerance: 16.0,
+                mode: simplify::SimplifyMode::TopologyPreserving,
+            }),
+            ..Default::default()
+        };
+        
+        let (tiles, _) = generate_tiles_with_options(geojson.as_bytes(), 0, 3, "test", &options).unwrap();
+        // Tiles south of the equator hold the polygons' southern edge within the buffer
+        assert_eq!(tiles.len(), 7);
+        for tile in &tiles {
+            let layers = mvt_decoder::decode_tile(&tile.data).unwrap();
+            let rings: Vec<Vec<(i32, i32)>> = layers[0].features.iter().map(|feature| match &feature.geometry {
+                tiler::TileGeometry::Polygon(rings) => rings[0].clone(),
+                _ => panic!("Expected Polygon geometry"),
+            }).collect();
+            assert_eq!(rings.len(), 2, "{}", tile.path);
+            
+            // Both polygons keep the same vertices along the shared edge, so the
+            // simplified outlines have no gap or overlap
+            let edge_x = rings[1].iter().map(|v| v.0).min().unwrap();
+            let left_max_x = rings[0].iter().map(|v| v.0).max().unwrap();
+            let left: HashSet<_> = rings[0].iter().filter(|v| v.0 >= edge_x).collect();
+            let right: HashSet<_> = rings[1].iter().filter(|v| v.0 <= left_max_x).collect();
+            assert!(left.len() >= 2, "{}", tile.path);
+            assert_eq!(left, right, "{}", tile.path);
+        }
+        
+        // Polygons moved into the topology are still counted by an overview layer
+        let options = TileOptions {
+            overview: Some(aggregate::Overview { layer_name: "density".to_string(), cell_size: 4096, max_zoom: 0 }),
+            ..options
+        };
+        let (tiles, _) = generate_tiles_with_options(geojson.as_bytes(), 0, 0, "test", &options).unwrap();
+        let layers = mvt_decoder::decode_tile(&tiles[0].data).unwrap();
+        let density = layers.iter().find(|layer| layer.name == "density").expect("Missing overview layer");
+        assert_eq!(density.features[0].properties["count"], 2);
+    }
+
+    #[test]
+    fn test_generation_metrics() {
+        let geojson = r#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        
+        let (tiles, metadata, metrics) = generate_tiles_with_metrics(geojson.as_bytes(), 2, 4, "test", &TileOptions::default()).unwrap();
+        
+        assert_eq!(metrics.zooms.iter().map(|z| z.zoom).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(metrics.zooms.iter().map(|z| z.tile_count).sum::<usize>(), tiles.len());
+        assert_eq!(metrics.zooms.iter().map(|z| z.bytes).sum::<usize>(), tiles.iter().map(|t| t.data.len()).sum::<usize>());
+        assert!(metrics.total_ms >= metrics.parse_ms);
+        
+        let largest = tiles.iter().filter(|t| t.path.starts_with("3/")).map(|t| t.data.len()).max().unwrap();
+        let z3 = &metadata.zooms[1];
+        assert_eq!((z3.zoom, z3.tile_count, z3.max_tile_bytes, z3.average_tile_bytes), (3, 1, largest, largest));
+    }
+
+    #[test]
+    fn test_max_zoom_extent() {
+        let geojson = r#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        let options = TileOptions {
+            max_zoom_extent: Some(8192),
+            ..Default::default()
+        };
+        
+        let (tiles, _) = generate_tiles_with_options(geojson.as_bytes(), 0, 2, "test", &options).unwrap();
+        
+        for tile in tiles {
+            let extent = inspect::inspect_tile(&tile.data).unwrap()[0].extent;
+            assert_eq!(extent, if tile.path.starts_with("2/") { 8192 } else { 4096 });
+        }
+    }
+
+    #[test]
+    fn test_extend_zooms_while_dropping() {
+        let points: Vec<String> = (0..16)
+            .map(|i| format!(r#"{{"type": "Feature", "properties": {{}}, "geometry": {{"type": "Point", "coordinates": [{}, {}]}}}}"#, i % 4, i / 4))
+            .collect();
+        let geojson = format!(r#"{{"type": "FeatureCollection", "features": [{}]}}"#, points.join(","));
+        let generate = |extend_zooms| {
+            let options = TileOptions {
+                tile_budget: reduction::TileBudget { max_features: Some(6), max_bytes: None },
+                size_strategy: Some(reduction::SizeStrategy::DropDensest),
+                extend_zooms,
+                ..Default::default()
+            };
+            generate_tiles_with_metrics(geojson.as_bytes(), 0, 2, "test", &options).unwrap()
+        };
+        
+        let (_, metadata, _) = generate(None);
+        assert_eq!(metadata.max_zoom, 2);
+        let (_, metadata, _) = generate(Some(1));
+        assert_eq!(metadata.max_zoom, 3);
+        
+        // Zooms are added until a zoom keeps all 16 points, which spreads them over enough tiles
+        let (tiles, metadata, _) = generate(Some(10));
+        assert!(metadata.max_zoom > 3 && metadata.max_zoom < 12);
+        let prefix = format!("{}/", metadata.max_zoom);
+        let last_zoom: usize = tiles.iter()
+            .filter(|tile| tile.path.starts_with(&prefix))
+            .map(|tile| inspect::inspect_tile(&tile.data).unwrap()[0].features)
+            .sum();
+        assert_eq!(last_zoom, 16);
+    }
+
+    #[test]
+    fn test_zoom_validation() {
+        let geojson = r#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        
+        let err = generate_tiles(geojson.as_bytes(), 5, 3, "test").unwrap_err();
+        assert!(err.starts_with("InvalidZoomRange"));
+        assert!(validate_zoom_range(0, MAX_ZOOM + 1).is_err());
+        
+        // The deepest zoom still produces a tile with in-range coordinates
+        let (tiles, _) = generate_tiles_with_metadata(geojson.as_bytes(), MAX_ZOOM, MAX_ZOOM, "test").unwrap();
+        assert_eq!(tiles.len(), 1);
+        assert!(tiles[0].path.starts_with("30/"));
+    }
+
+    #[test]
+    fn test_lenient_geometry_checks() {
+        // The polygon's only ring is too short to encode
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Polygon",
+                "coordinates": [[[139.0, 35.0], [140.0, 36.0], [139.0, 35.0]]]}}
+        ]}"#;
+        
+        let options = TileOptions { geometry_checks: mvt_encoder::GeometryChecks::Strict, ..Default::default() };
+        let strict = generate_tiles_with_options(geojson.as_bytes(), 0, 1, "test", &options);
+        assert!(strict.unwrap_err().contains("Geometry encodes to no commands"));
+        
+        let (tiles, metadata) = generate_tiles_with_options(geojson.as_bytes(), 0, 1, "test", &TileOptions::default()).unwrap();
+        assert!(!tiles.is_empty());
+        assert!(metadata.warnings.iter().any(|w| w.message == "Left out 2 tile features with invalid geometry"));
+    }
+
+    #[test]
+    fn test_bbox_filter() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-74.0, 40.7]}}
+        ]}"#;
+        let options = TileOptions { bbox_filter: Some((139.0, 35.0, 140.0, 36.0)), ..Default::default() };
+        let (tiles, _) = generate_tiles_with_options(geojson.as_bytes(), 2, 2, "test", &options).unwrap();
+        assert_eq!(tiles.iter().map(|tile| tile.path.as_str()).collect::<Vec<_>>(), ["2/3/1.pbf"]);
+        
+        let options = TileOptions { bbox_filter: Some((0.0, 0.0, 1.0, 1.0)), ..Default::default() };
+        let empty = generate_tiles_with_options(geojson.as_bytes(), 2, 2, "test", &options);
+        assert_eq!(empty.unwrap_err(), "No features intersect the bbox filter");
+    }
+
+    #[test]
+    fn test_generate_from_ranges() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "Tokyo"}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "properties": {"name": "New York"}, "geometry": {"type": "Point", "coordinates": [-74.0, 40.7]}}
+        ]}"#;
+        let mut archive = pmtiles::PmTilesSink::new(Vec::new());
+        generate_tiles_to_sink(geojson.as_bytes(), 0, 6, "cities", &TileOptions::default(), &mut archive).unwrap();
+        let archive = archive.into_inner();
+        
+        // Only the z6 tile around Tokyo is read back
+        let options = TileOptions { bbox_filter: Some((139.0, 35.0, 140.0, 36.0)), ..Default::default() };
+        let mut sink = sink::MemorySink::default();
+        generate_ranges_to_sink(&archive[..], 0, 2, "cities", &options, &mut sink).unwrap();
+        let layers = mvt_decoder::decode_tile(&sink.tiles[0].data).unwrap();
+        assert_eq!(layers[0].features.len(), 1);
+        assert_eq!(layers[0].features[0].properties["name"], "Tokyo");
+        
+        let err = generate_ranges_to_sink(geojson.as_bytes(), 0, 2, "cities", &options, &mut sink).unwrap_err();
+        assert!(err.contains("FlatGeobuf file or a PMTiles archive"), "{}", err);
+    }
+
+    #[test]
+    fn test_output_is_deterministic() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let options = TileOptions::default();
+        let (pipelined, _) = generate_tiles_with_options(geojson, 0, 4, "test", &options).unwrap();
+        
+        // One zoom per run leaves the tiling thread nothing to overlap with
+        let mut serial = Vec::new();
+        for zoom in 0..=4 {
+            serial.extend(generate_tiles_with_options(geojson, zoom, zoom, "test", &options).unwrap().0);
+        }
+        
+        let listing = |tiles: &[TileFile]| tiles.iter().map(|t| (t.path.clone(), t.data.clone())).collect::<Vec<_>>();
+        assert_eq!(listing(&pipelined), listing(&serial));
+        assert_eq!(listing(&pipelined), listing(&generate_tiles_with_options(geojson, 0, 4, "test", &options).unwrap().0));
+    }
+
+    #[test]
+    fn test_oversized_tile_report() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "id": 1, "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "id": 2, "properties": {}, "geometry": {"type": "LineString",
+                "coordinates": [[139.0, 35.0], [139.5, 35.9], [140.0, 35.1], [140.5, 35.8]]}}
+        ]}"#;
+        let options = TileOptions {
+            size_warning: metrics::TileSizeWarning { max_bytes: 10, top_features: 1 },
+            ..Default::default()
+        };
+        
+        let (_, metadata, metrics) = generate_tiles_with_metrics(geojson.as_bytes(), 0, 0, "test", &options).unwrap();
+        
+        assert_eq!(metrics.oversized_tiles.len(), 1);
+        assert_eq!(metrics.oversized_tiles[0].largest_features[0].index, 1);
+        let expected = "Tile 0/0/0.pbf is 46 bytes (limit 10); largest features in layer 'test': #1 (id 2, 18 bytes)";
+        assert!(metadata.warnings.iter().any(|w| w.message == expected));
+    }
+
+    #[test]
+    fn test_hexbin_layer() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let options = TileOptions {
+            hexbin: Some(aggregate::Hexbin { layer_name: "hex".to_string(), radius: vec![(1, 128.0)], sum: Vec::new() }),
+            ..Default::default()
+        };
+        
+        let (tiles, _) = generate_tiles_with_options(geojson, 0, 2, "points", &options).unwrap();
+        
+        let layer_names = |path: &str| -> Vec<String> {
+            let tile = tiles.iter().find(|t| t.path == path).unwrap();
+            mvt_decoder::decode_tile(&tile.data).unwrap().into_iter().map(|layer| layer.name).collect()
+        };
+        assert_eq!(layer_names("0/0/0.pbf"), ["points"]);
+        let z1 = tiles.iter().find(|t| t.path.starts_with("1/")).unwrap();
+        assert_eq!(layer_names(&z1.path), ["points", "hex"]);
+    }
+
+    #[test]
+    fn test_foreign_members_in_metadata() {
+        let geojson = r#"{"type": "FeatureCollection", "name": "stations", "source": "survey 2024", "crs": {"type": "name"},
+            "features": [{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}]}"#;
+        let options = TileOptions {
+            foreign_members: Some(properties::PropertyFilter::Exclude(vec!["crs".to_string()])),
+            ..Default::default()
+        };
+        
+        let (_, metadata) = generate_tiles_with_options(geojson.as_bytes(), 0, 0, "test", &options).unwrap();
+        let kept: Vec<&String> = metadata.foreign_members.keys().collect();
+        assert_eq!(kept, ["name", "source"]);
+        assert_eq!(tilejson::to_tilejson(&metadata)["foreign_members"]["source"], "survey 2024");
+        
+        let (_, metadata) = generate_tiles_with_options(geojson.as_bytes(), 0, 0, "test", &TileOptions::default()).unwrap();
+        assert!(metadata.foreign_members.is_empty());
+    }
+
+    #[test]
+    fn test_sort_rank_orders_lines() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "bridge", "bridge": "yes"}, "geometry": {"type": "LineString", "coordinates": [[139.70, 35.70], [139.72, 35.70]]}},
+            {"type": "Feature", "properties": {"name": "tunnel", "tunnel": "yes"}, "geometry": {"type": "LineString", "coordinates": [[139.70, 35.71], [139.72, 35.71]]}},
+            {"type": "Feature", "properties": {"name": "road"}, "geometry": {"type": "LineString", "coordinates": [[139.70, 35.72], [139.72, 35.72]]}}
+        ]}"#;
+        let options = TileOptions { sort_rank: Some(properties::SortRank::OsmTags), ..Default::default() };
+        
+        let (tiles, _) = generate_tiles_with_options(geojson.as_bytes(), 0, 0, "roads", &options).unwrap();
+        
+        let decoded = mvt_decoder::decode_tile(&tiles[0].data).unwrap();
+        let names: Vec<&serde_json::Value> = decoded[0].features.iter().map(|f| &f.properties["name"]).collect();
+        assert_eq!(names, ["tunnel", "road", "bridge"]);
+        assert_eq!(decoded[0].features[2].properties["sort_rank"], 5);
+    }
+
+    #[test]
+    fn test_generate_from_parsed_features() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let (features, members) = geojson_parser::parse_geojson_with_members(geojson).unwrap();
+        
+        let (tiles, metadata, _) = generate_tiles_from_features(features, members, 0, 3, "points", &TileOptions::default()).unwrap();
+        let (expected, _) = generate_tiles_with_metadata(geojson, 0, 3, "points").unwrap();
+        
+        let contents = |tiles: &[TileFile]| tiles.iter().map(|t| (t.path.clone(), t.data.clone())).collect::<Vec<_>>();
+        assert_eq!(contents(&tiles), contents(&expected));
+        assert_eq!(metadata.layer_name, "points");
+    }
+
+    #[test]
+    fn test_fill_empty_tiles() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-10.0, -10.0]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [10.0, 10.0]}}
+        ]}"#;
+        let options = TileOptions { fill_empty_tiles: true, ..Default::default() };
+        
+        let (tiles, metadata) = generate_tiles_with_options(geojson, 4, 4, "test", &options).unwrap();
+        
+        // Bounds span tiles 7..=8 in both directions; the two points cover two of them
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(metadata.zooms[0].tile_count, 4);
+        let empty = tiles.iter().find(|t| t.path == "4/7/7.pbf").unwrap();
+        let layers = mvt_decoder::decode_tile(&empty.data).unwrap();
+        assert_eq!((layers[0].name.as_str(), layers[0].features.len()), ("test", 0));
+        let (tiles, _) = generate_tiles_with_options(geojson, 4, 4, "test", &TileOptions::default()).unwrap();
+        assert_eq!(tiles.len(), 2);
+    }
+
+    #[test]
+    fn test_coverage_in_tile_id_order() {
+        let bounds = (-30.0, -20.0, 45.0, 60.0);
+        let (min_x, min_y) = projection::lonlat_to_tile(bounds.0, bounds.3, 5);
+        let (max_x, max_y) = projection::lonlat_to_tile(bounds.2, bounds.1, 5);
+        let mut expected: Vec<TileCoord> = (min_x..=max_x)
+            .flat_map(|x| (min_y..=max_y).map(move |y| TileCoord::new(5, x, y)))
+            .collect();
+        expected.sort_unstable_by_key(|&coord| pmtiles::tile_id(coord));
+        
+        assert_eq!(coverage(bounds, 5).collect::<Vec<_>>(), expected);
+        assert_eq!(coverage(bounds, 0).collect::<Vec<_>>(), vec![TileCoord::new(0, 0, 0)]);
+        // The world at the max zoom starts with the zoom's first ids, without listing every tile
+        let world = (-180.0, -projection::MAX_LATITUDE, 180.0, projection::MAX_LATITUDE);
+        let first_id = ((1u64 << (2 * MAX_ZOOM as u32)) - 1) / 3;
+        let ids: Vec<u64> = coverage(world, MAX_ZOOM).take(4).map(pmtiles::tile_id).collect();
+        assert_eq!(ids, (first_id..first_id + 4).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_source_ids() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "id": 1001, "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "id": "2002", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.8, 35.7]}},
+            {"type": "Feature", "id": "way/7", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.9, 35.7]}}
+        ]}"#;
+        
+        let (tiles, _) = generate_tiles_with_metadata(geojson, 0, 0, "test").unwrap();
+        
+        let ids: Vec<Option<u64>> = mvt_decoder::decode_tile(&tiles[0].data).unwrap()[0].features.iter().map(|f| f.id).collect();
+        // The id that is not an integer falls back to its index in the tile
+        assert_eq!(ids, [Some(1001), Some(2002), Some(2)]);
+    }
+
+    #[test]
+    fn test_id_field_in_metadata() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"osm_id": 42}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}
+        ]}"#;
+        let options = TileOptions { id_strategy: properties::IdStrategy::Property("osm_id".to_string()), ..Default::default() };
+        
+        let (tiles, metadata) = generate_tiles_with_options(geojson, 0, 0, "test", &options).unwrap();
+        
+        assert_eq!(mvt_decoder::decode_tile(&tiles[0].data).unwrap()[0].features[0].id, Some(42));
+        assert_eq!(metadata.id_field.as_deref(), Some("osm_id"));
+        let document = tilejson::to_tilejson(&metadata);
+        let layers: serde_json::Value = serde_json::from_str(document["json"].as_str().unwrap()).unwrap();
+        assert_eq!(layers["vector_layers"][0]["id_field"], "osm_id");
+        assert!(metadata.warnings.is_empty());
+    }
 }
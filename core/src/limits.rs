@@ -0,0 +1,469 @@
+// Limits module
+// Resource limits that stop oversized inputs before they exhaust memory
+
+use crate::geojson_parser::{Feature, GeometryType};
+use crate::tiler::TileFeature;
+use crate::sink::TileSink;
+use crate::{projection, TileCoord, TileMetadata, TileOptions};
+use std::collections::HashSet;
+
+/// (min_lon, min_lat, max_lon, max_lat)
+type Bounds = (f64, f64, f64, f64);
+
+/// Estimated size of one property entry besides its key and value text
+const PROPERTY_OVERHEAD: usize = std::mem::size_of::<(String, serde_json::Value)>();
+/// Parsed GeoJSON value tree relative to the input text (freed before tiling)
+const PARSE_FACTOR: u64 = 2;
+/// Encoded size of a tile feature besides its coordinates (header, id, tag indices per property)
+const ENCODED_FEATURE_BYTES: u64 = 8;
+/// Encoded size of a delta-coded vertex
+const ENCODED_VERTEX_BYTES: u64 = 3;
+/// Encoded size of a tile besides its features (layer header, key and value tables)
+const ENCODED_TILE_BYTES: u64 = 64;
+
+/// Upper bounds on the work of one generation (`None` is unlimited)
+///
+/// Exceeding a limit fails generation with an `InputTooLarge` error saying what to reduce,
+/// instead of running out of memory (in the browser, a wasm abort).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Size of the input file, checked before parsing
+    pub max_input_bytes: Option<usize>,
+    /// Parsed features
+    pub max_features: Option<usize>,
+    /// Coordinates of all parsed geometries
+    pub max_vertices: Option<usize>,
+    /// Tiles over all zooms, estimated from the feature bounding boxes before tiling (and again
+    /// before each zoom `extend_zooms` adds)
+    pub max_tiles: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// Limits that keep a generation within a browser tab's wasm memory
+    pub fn browser() -> Self {
+        ResourceLimits {
+            max_input_bytes: Some(512 * 1024 * 1024),
+            max_features: Some(5_000_000),
+            max_vertices: Some(50_000_000),
+            max_tiles: Some(1_000_000),
+        }
+    }
+
+    pub(crate) fn check_input_size(&self, bytes: usize) -> Result<(), String> {
+        match self.max_input_bytes {
+            Some(max) if bytes > max => Err(format!(
+                "InputTooLarge: the input is {} MB (limit {} MB); filter it or split it into smaller files",
+                bytes / (1024 * 1024),
+                max / (1024 * 1024)
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Collect a reader's features, failing as soon as `max_features` or `max_vertices` is passed
+    pub(crate) fn collect_features(&self, features: impl Iterator<Item = Feature>) -> Result<Vec<Feature>, String> {
+        let mut collected = Vec::new();
+        let mut vertices = 0;
+        for feature in features {
+            if let Some(max) = self.max_features.filter(|&max| collected.len() >= max) {
+                return Err(format!(
+                    "InputTooLarge: more than {} features (limit {}); filter the data or split it into several tilesets",
+                    max, max
+                ));
+            }
+            if let Some(max) = self.max_vertices {
+                vertices += vertex_count(&feature.geometry);
+                if vertices > max {
+                    return Err(format!(
+                        "InputTooLarge: more than {} vertices (limit {}); simplify or filter the data",
+                        max, max
+                    ));
+                }
+            }
+            collected.push(feature);
+        }
+        Ok(collected)
+    }
+
+    pub(crate) fn check_features(&self, features: &[Feature]) -> Result<(), String> {
+        if let Some(max) = self.max_features.filter(|&max| features.len() > max) {
+            return Err(format!(
+                "InputTooLarge: {} features (limit {}); filter the data or split it into several tilesets",
+                features.len(),
+                max
+            ));
+        }
+        if let Some(max) = self.max_vertices {
+            let vertices: usize = features.iter().map(|feature| vertex_count(&feature.geometry)).sum();
+            if vertices > max {
+                return Err(format!(
+                    "InputTooLarge: {} vertices (limit {}); simplify or filter the data",
+                    vertices, max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Boxes whose tiles `check_tiles` counts: each feature's bounding box, or `bounds` with
+    /// `fill_empty_tiles` (none without `max_tiles`)
+    pub(crate) fn tile_boxes(&self, features: &[Feature], bounds: Bounds, fill_empty_tiles: bool) -> Vec<Bounds> {
+        match (self.max_tiles, fill_empty_tiles) {
+            (None, _) => Vec::new(),
+            (Some(_), true) => vec![bounds],
+            (Some(_), false) => features.iter().map(|feature| geometry_bounds(&feature.geometry)).collect(),
+        }
+    }
+
+    /// Estimate the tiles of each zoom from `tile_boxes` and fail at the first zoom that goes
+    /// over `max_tiles`
+    ///
+    /// A tile is counted when a box touches it, so long diagonal lines are overcounted.
+    pub(crate) fn check_tiles(&self, boxes: &[Bounds], min_zoom: u8, max_zoom: u8) -> Result<(), String> {
+        let Some(max) = self.max_tiles else {
+            return Ok(());
+        };
+        let mut total = 0;
+        for zoom in min_zoom..=max_zoom {
+            total += tiles_touched(boxes, zoom, max - total);
+            if total > max {
+                let advice = match zoom {
+                    zoom if zoom > min_zoom => format!("reduce max zoom to {} or filter the data", zoom - 1),
+                    _ => "reduce the zoom range or filter the data".to_string(),
+                };
+                return Err(format!("InputTooLarge: zooms {}-{} cover more than {} tiles; {}", min_zoom, zoom, max, advice));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sink that fails generation once more than `max_tiles` tiles were written to it
+pub(crate) struct LimitedSink<'a> {
+    sink: &'a mut dyn TileSink,
+    max_tiles: Option<usize>,
+    written: usize,
+}
+
+impl<'a> LimitedSink<'a> {
+    pub(crate) fn new(sink: &'a mut dyn TileSink, max_tiles: Option<usize>) -> Self {
+        Self { sink, max_tiles, written: 0 }
+    }
+}
+
+impl TileSink for LimitedSink<'_> {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        if let Some(max) = self.max_tiles.filter(|&max| self.written >= max) {
+            return Err(format!("InputTooLarge: more than {} tiles written (limit {}); reduce max zoom or filter the data", max, max));
+        }
+        self.written += 1;
+        self.sink.write_tile(coord, data)
+    }
+
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        self.sink.finish(metadata)
+    }
+}
+
+/// Projected resource use of a generation, for warning before it starts
+///
+/// The estimate is rough (within a small factor): tiles are counted from feature bounding
+/// boxes, and simplification and point thinning are not modeled, so it tends to overestimate
+/// for large polygons and long lines.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ResourceEstimate {
+    pub input_bytes: usize,
+    pub features: usize,
+    pub vertices: usize,
+    pub zooms: Vec<ZoomEstimate>,
+    /// Peak memory when the tiles are collected in memory (as the wasm functions do)
+    pub peak_memory_bytes: u64,
+    /// Encoded size of all tiles
+    pub output_bytes: u64,
+    /// `InputTooLarge` errors the generation would fail with under `options.limits`
+    pub exceeded: Vec<String>,
+}
+
+/// Projected tiles of one zoom
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ZoomEstimate {
+    pub zoom: u8,
+    pub tiles: u64,
+    pub bytes: u64,
+}
+
+/// Estimate peak memory and output size of generating the input with `options`
+///
+/// The input is parsed (so the estimate costs a parse), but nothing is tiled. Zooms that
+/// `extend_zooms` may add are estimated as if they were all generated.
+pub fn estimate_resources(bytes: &[u8], min_zoom: u8, max_zoom: u8, options: &TileOptions) -> Result<ResourceEstimate, String> {
+    crate::validate_zoom_range(min_zoom, max_zoom)?;
+    let max_zoom = max_zoom.saturating_add(options.extend_zooms.unwrap_or(0)).min(crate::MAX_ZOOM);
+    let mut exceeded: Vec<String> = options.limits.check_input_size(bytes.len()).err().into_iter().collect();
+    // Read without the limits so every exceeded one is reported with its total
+    let unlimited = TileOptions { limits: ResourceLimits::default(), ..options.clone() };
+    let (features, _, _) = crate::read_input(bytes, &unlimited)?;
+    exceeded.extend(options.limits.check_features(&features).err());
+    let bounds = crate::geojson_parser::calculate_bounds(&features)?;
+    let tile_boxes = options.limits.tile_boxes(&features, bounds, options.fill_empty_tiles);
+    exceeded.extend(options.limits.check_tiles(&tile_boxes, min_zoom, max_zoom).err());
+    
+    // Per feature: bounding box, vertices, property bytes and property count
+    let sizes: Vec<(Bounds, u64, u64, u64)> = features.iter()
+        .map(|feature| (
+            geometry_bounds(&feature.geometry),
+            vertex_count(&feature.geometry) as u64,
+            property_bytes(&feature.properties) as u64,
+            feature.properties.len() as u64,
+        ))
+        .collect();
+    let feature_bytes: u64 = sizes.iter()
+        .map(|&(_, vertices, properties, _)| {
+            std::mem::size_of::<Feature>() as u64 + vertices * std::mem::size_of::<geo_types::Coord<f64>>() as u64 + properties
+        })
+        .sum();
+    // Mercator coordinates cached for every zoom when several are generated without spilling
+    #[cfg(not(target_arch = "wasm32"))]
+    let spilling = options.spill.is_some();
+    #[cfg(target_arch = "wasm32")]
+    let spilling = false;
+    let vertices: u64 = sizes.iter().map(|&(_, vertices, _, _)| vertices).sum();
+    let feature_bytes = match min_zoom < max_zoom && !options.build_topology && !spilling {
+        true => feature_bytes + vertices * std::mem::size_of::<(f64, f64)>() as u64,
+        false => feature_bytes,
+    };
+    let extent = u64::from(options.max_zoom_extent.max(options.extent).unwrap_or(crate::tiler::DEFAULT_EXTENT).max(crate::tiler::DEFAULT_EXTENT));
+    
+    let mut zooms = Vec::new();
+    let mut tiled_bytes = Vec::new();
+    for zoom in min_zoom..=max_zoom {
+        let (mut tiled, mut encoded, mut entries) = (0u64, 0u64, 0u64);
+        for &(bbox, vertices, properties, keys) in &sizes {
+            let count = tile_range_count(bbox, zoom);
+            entries = entries.saturating_add(count);
+            // Each vertex lands in about one tile; clipping adds a few corner points per tile
+            let points = vertices.saturating_add(count.saturating_mul(4));
+            tiled = tiled
+                .saturating_add(count.saturating_mul(std::mem::size_of::<TileFeature>() as u64 + properties))
+                .saturating_add(points.saturating_mul(std::mem::size_of::<(i32, i32)>() as u64));
+            // A tile holds no more detail than its extent
+            encoded = encoded
+                .saturating_add(count.saturating_mul(ENCODED_FEATURE_BYTES + 2 * keys))
+                .saturating_add(vertices.min(count.saturating_mul(extent)) * ENCODED_VERTEX_BYTES);
+        }
+        let covered = tile_range_count(bounds, zoom);
+        let tiles = if options.fill_empty_tiles { covered } else { entries.min(covered) };
+        let bytes = encoded.saturating_add(tiles.saturating_mul(ENCODED_TILE_BYTES));
+        zooms.push(ZoomEstimate { zoom, tiles, bytes });
+        tiled_bytes.push(tiled);
+    }
+    
+    // Natively the next zoom is tiled while the current one is encoded
+    let mut tiling_peak = match cfg!(target_arch = "wasm32") {
+        true => tiled_bytes.iter().copied().max().unwrap_or(0),
+        false => tiled_bytes.windows(2).map(|pair| pair[0].saturating_add(pair[1])).chain(tiled_bytes.first().copied()).max().unwrap_or(0),
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(spill) = &options.spill {
+        tiling_peak = tiling_peak.min(2 * spill.memory_budget as u64);
+    }
+    let output_bytes = zooms.iter().fold(0u64, |sum, zoom| sum.saturating_add(zoom.bytes));
+    let parse_bytes = PARSE_FACTOR * bytes.len() as u64;
+    
+    Ok(ResourceEstimate {
+        input_bytes: bytes.len(),
+        features: features.len(),
+        vertices: vertices as usize,
+        zooms,
+        peak_memory_bytes: (bytes.len() as u64)
+            .saturating_add(feature_bytes)
+            .saturating_add(parse_bytes.max(tiling_peak))
+            .saturating_add(output_bytes),
+        output_bytes,
+        exceeded,
+    })
+}
+
+/// Rough in-memory size of a feature's properties (bytes)
+fn property_bytes(properties: &serde_json::Map<String, serde_json::Value>) -> usize {
+    properties.iter()
+        .map(|(key, value)| {
+            let text = match value {
+                serde_json::Value::String(s) => s.len(),
+                serde_json::Value::Array(_) | serde_json::Value::Object(_) => value.to_string().len(),
+                _ => 0,
+            };
+            PROPERTY_OVERHEAD + key.len() + text
+        })
+        .sum()
+}
+
+/// Tile x and y ranges of a zoom covering a box
+pub(crate) fn tile_range(bbox: Bounds, zoom: u8) -> Option<(std::ops::RangeInclusive<u32>, std::ops::RangeInclusive<u32>)> {
+    let (min_lon, min_lat, max_lon, max_lat) = bbox;
+    if !min_lon.is_finite() {
+        return None;
+    }
+    let (min_x, min_y) = projection::lonlat_to_tile(min_lon, max_lat.min(projection::MAX_LATITUDE), zoom);
+    let (max_x, max_y) = projection::lonlat_to_tile(max_lon, min_lat.max(-projection::MAX_LATITUDE), zoom);
+    Some((min_x..=max_x, min_y..=max_y))
+}
+
+fn tile_range_count(bbox: Bounds, zoom: u8) -> u64 {
+    tile_range(bbox, zoom).map_or(0, |(xs, ys)| {
+        u64::from(xs.end() - xs.start() + 1) * u64::from(ys.end() - ys.start() + 1)
+    })
+}
+
+fn vertex_count(geometry: &GeometryType) -> usize {
+    match geometry {
+        GeometryType::Point(_) => 1,
+        GeometryType::LineString(line) => line.0.len(),
+        GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => geometry
+            .polygons()
+            .iter()
+            .map(|polygon| polygon.exterior().0.len() + polygon.interiors().iter().map(|ring| ring.0.len()).sum::<usize>())
+            .sum(),
+    }
+}
+
+/// (min_lon, min_lat, max_lon, max_lat)
+pub(crate) fn geometry_bounds(geometry: &GeometryType) -> Bounds {
+    let coords: Box<dyn Iterator<Item = &geo_types::Coord<f64>>> = match geometry {
+        GeometryType::Point(point) => Box::new(std::iter::once(&point.0)),
+        GeometryType::LineString(line) => Box::new(line.0.iter()),
+        GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => {
+            Box::new(geometry.polygons().iter().flat_map(|polygon| polygon.exterior().0.iter()))
+        }
+    };
+    coords.fold((f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY), |b, c| {
+        (b.0.min(c.x), b.1.min(c.y), b.2.max(c.x), b.3.max(c.y))
+    })
+}
+
+/// Distinct tiles of a zoom touched by the boxes (counting stops past `limit`)
+fn tiles_touched(boxes: &[Bounds], zoom: u8, limit: usize) -> usize {
+    let mut tiles: HashSet<(u32, u32)> = HashSet::new();
+    for (xs, ys) in boxes.iter().filter_map(|&bbox| tile_range(bbox, zoom)) {
+        for x in xs {
+            for y in ys.clone() {
+                tiles.insert((x, y));
+                if tiles.len() > limit {
+                    return tiles.len();
+                }
+            }
+        }
+    }
+    tiles.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_tiles_with_options, TileOptions};
+
+    #[test]
+    fn test_resource_limits() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let generate = |limits: ResourceLimits| {
+            generate_tiles_with_options(geojson, 0, 14, "test", &TileOptions { limits, ..Default::default() })
+        };
+        
+        assert!(generate(ResourceLimits::browser()).is_ok());
+        let err = generate(ResourceLimits { max_input_bytes: Some(10), ..Default::default() }).unwrap_err();
+        assert!(err.starts_with("InputTooLarge: the input is 0 MB"));
+        let err = generate(ResourceLimits { max_features: Some(2), ..Default::default() }).unwrap_err();
+        assert!(err.starts_with("InputTooLarge: more than 2 features (limit 2)"));
+        let err = generate(ResourceLimits { max_vertices: Some(5), ..Default::default() }).unwrap_err();
+        assert!(err.starts_with("InputTooLarge: more than 5 vertices"));
+        
+        // Reading stops at the first feature over the limit
+        let limits = ResourceLimits { max_features: Some(2), ..Default::default() };
+        let mut read = 0;
+        let features = (0..1000).map(|_| {
+            read += 1;
+            crate::geojson_parser::Feature::new(GeometryType::Point(geo_types::Point::new(0.0, 0.0)), serde_json::Map::new())
+        });
+        assert!(limits.collect_features(features).is_err());
+        assert_eq!(read, 3);
+        // Features given in memory are still checked with their totals
+        let features = crate::read_input(geojson, &TileOptions::default()).unwrap().0;
+        let err = generate_tiles_with_options(geojson, 0, 0, "test", &TileOptions { limits, ..Default::default() }).unwrap_err();
+        assert!(err.contains("more than 2"));
+        let err = crate::generate_tiles_from_features(features, Default::default(), 0, 0, "test", &TileOptions { limits, ..Default::default() }).unwrap_err();
+        assert!(err.starts_with("InputTooLarge: 6 features (limit 2)"));
+        
+        // Each point touches one tile per zoom
+        let (tiles, _) = generate(ResourceLimits { max_tiles: Some(6 * 15), ..Default::default() }).unwrap();
+        assert!(tiles.len() <= 6 * 15);
+        let err = generate(ResourceLimits { max_tiles: Some(20), ..Default::default() }).unwrap_err();
+        assert!(err.contains("cover more than 20 tiles; reduce max zoom to"), "{}", err);
+    }
+
+    #[test]
+    fn test_limited_sink() {
+        let mut memory = crate::sink::MemorySink::default();
+        let mut sink = LimitedSink::new(&mut memory, Some(2));
+        sink.write_tile(TileCoord::new(0, 0, 0), b"a").unwrap();
+        sink.write_tile(TileCoord::new(1, 0, 0), b"b").unwrap();
+        let err = sink.write_tile(TileCoord::new(1, 1, 0), b"c").unwrap_err();
+        assert!(err.starts_with("InputTooLarge: more than 2 tiles written"), "{}", err);
+        assert_eq!(memory.tiles.len(), 2);
+    }
+
+    #[test]
+    fn test_estimate_resources() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let options = TileOptions { limits: ResourceLimits { max_features: Some(2), ..Default::default() }, ..Default::default() };
+        
+        let estimate = estimate_resources(geojson, 0, 4, &options).unwrap();
+        
+        assert_eq!((estimate.features, estimate.vertices), (6, 6));
+        let (tiles, _) = generate_tiles_with_options(geojson, 0, 4, "test", &TileOptions::default()).unwrap();
+        let estimated_tiles: u64 = estimate.zooms.iter().map(|zoom| zoom.tiles).sum();
+        assert!(estimated_tiles >= tiles.len() as u64);
+        let output: usize = tiles.iter().map(|tile| tile.data.len()).sum();
+        assert!(estimate.output_bytes as usize > output / 4 && (estimate.output_bytes as usize) < output * 4, "{} vs {}", estimate.output_bytes, output);
+        assert!(estimate.peak_memory_bytes > estimate.output_bytes + geojson.len() as u64);
+        assert_eq!(estimate.exceeded.len(), 1);
+        
+        let extended = TileOptions { extend_zooms: Some(2), ..TileOptions::default() };
+        let estimate = estimate_resources(geojson, 0, 4, &extended).unwrap();
+        assert_eq!(estimate.zooms.last().unwrap().zoom, 6);
+    }
+
+    #[test]
+    fn test_max_tiles_counts_extended_zooms() {
+        let points: Vec<String> = (0..16)
+            .map(|i| format!(r#"{{"type": "Feature", "properties": {{}}, "geometry": {{"type": "Point", "coordinates": [{}.5, {}.5]}}}}"#, i % 4, i / 4))
+            .collect();
+        let geojson = format!(r#"{{"type": "FeatureCollection", "features": [{}]}}"#, points.join(","));
+        let options = TileOptions {
+            tile_budget: crate::reduction::TileBudget { max_features: Some(6), max_bytes: None },
+            size_strategy: Some(crate::reduction::SizeStrategy::DropDensest),
+            extend_zooms: Some(10),
+            limits: ResourceLimits { max_tiles: Some(4), ..Default::default() },
+            ..Default::default()
+        };
+        
+        // The points share one tile up to zoom 4, so the second extra zoom goes over
+        let err = generate_tiles_with_options(geojson.as_bytes(), 0, 2, "test", &options).unwrap_err();
+        assert!(err.starts_with("InputTooLarge: zooms 0-4 cover more than 4 tiles"), "{}", err);
+        assert!(err.ends_with("(zoom 4 was added by extend_zooms)"), "{}", err);
+        
+        // Polygons count with the bounds they had before a topology took their rings over
+        let squares: Vec<String> = (0..16)
+            .map(|i| {
+                let (x, y) = ((i % 4) as f64 + 0.4, (i / 4) as f64 + 0.4);
+                format!(
+                    r#"{{"type": "Feature", "properties": {{}}, "geometry": {{"type": "Polygon", "coordinates": [[[{x}, {y}], [{}, {y}], [{}, {}], [{x}, {}], [{x}, {y}]]]}}}}"#,
+                    x + 0.2, x + 0.2, y + 0.2, y + 0.2
+                )
+            })
+            .collect();
+        let geojson = format!(r#"{{"type": "FeatureCollection", "features": [{}]}}"#, squares.join(","));
+        let options = TileOptions { build_topology: true, ..options };
+        let err = generate_tiles_with_options(geojson.as_bytes(), 0, 2, "test", &options).unwrap_err();
+        assert!(err.ends_with("was added by extend_zooms)"), "{}", err);
+    }
+}
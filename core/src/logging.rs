@@ -0,0 +1,182 @@
+// Logging module
+// Library diagnostics go through the `log` crate when the `log` feature is enabled, and
+// generation stages through an optional span hook
+
+use crate::Warning;
+use std::any::Any;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Warning-level diagnostic (stderr when the `log` feature is disabled)
+#[allow(unused_macros)] // unused when no input reader that logs (GeoJSON, CSV, Shapefile) is enabled
+macro_rules! log_warn {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::warn!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        eprintln!($($arg)*);
+    }};
+}
+
+/// Stage-level progress diagnostic (discarded when the `log` feature is disabled)
+macro_rules! log_debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+#[allow(unused_imports)]
+pub(crate) use {log_debug, log_warn};
+
+/// A stage of a generation run, as reported to the `SpanHook`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage<'a> {
+    /// `generate` (the whole run), `parse`, `tile`, `encode` (which also passes the tiles to the
+    /// sink) or `write` (the sink's `finish`, where archives are written)
+    pub name: &'static str,
+    pub layer: &'a str,
+    /// Zoom of the `tile` and `encode` stages
+    pub zoom: Option<u8>,
+}
+
+impl<'a> Stage<'a> {
+    pub(crate) fn new(name: &'static str, layer: &'a str) -> Self {
+        Stage { name, layer, zoom: None }
+    }
+    
+    pub(crate) fn at_zoom(self, zoom: u8) -> Self {
+        Stage { zoom: Some(zoom), ..self }
+    }
+}
+
+impl fmt::Display for Stage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.zoom {
+            Some(zoom) => write!(f, "Layer {}, zoom {}: {}", self.layer, zoom, self.name),
+            None => write!(f, "Layer {}: {}", self.layer, self.name),
+        }
+    }
+}
+
+/// Receives the stages of every generation run, e.g. to open `tracing` spans (which this crate
+/// does not depend on):
+///
+/// ```ignore
+/// struct TracingSpans;
+///
+/// impl SpanHook for TracingSpans {
+///     fn enter(&self, stage: &Stage) -> Box<dyn std::any::Any> {
+///         let span = tracing::info_span!("stage", name = stage.name, layer = stage.layer, zoom = stage.zoom);
+///         Box::new(span.entered())
+///     }
+/// }
+///
+/// vector_tile_core::logging::set_span_hook(TracingSpans).unwrap();
+/// ```
+///
+/// Stages of one zoom may run on worker threads, so `enter` can be called from any thread.
+pub trait SpanHook: Send + Sync {
+    /// Called when `stage` starts; the returned guard is dropped when it ends
+    fn enter(&self, stage: &Stage) -> Box<dyn Any>;
+}
+
+static SPAN_HOOK: OnceLock<Box<dyn SpanHook>> = OnceLock::new();
+
+/// Install the span hook of the process (once, like `log::set_logger`)
+pub fn set_span_hook(hook: impl SpanHook + 'static) -> Result<(), String> {
+    SPAN_HOOK.set(Box::new(hook)).map_err(|_| "A span hook is already set".to_string())
+}
+
+/// Enter a stage: the span hook's guard is held until the returned `Span` is dropped, and with
+/// the `log` feature the stage is logged as `<stage>: started` and `<stage>: finished in <ms> ms`
+pub(crate) fn span(stage: Stage) -> Span {
+    #[cfg(feature = "log")]
+    log::debug!("{}: started", stage);
+    Span {
+        #[cfg(feature = "log")]
+        label: stage.to_string(),
+        #[cfg(feature = "log")]
+        start: crate::metrics::now_ms(),
+        _guard: SPAN_HOOK.get().map(|hook| hook.enter(&stage)),
+    }
+}
+
+/// A stage entered with `span`, ended when dropped
+#[must_use = "the span ends as soon as it is dropped"]
+pub(crate) struct Span {
+    #[cfg(feature = "log")]
+    label: String,
+    #[cfg(feature = "log")]
+    start: f64,
+    _guard: Option<Box<dyn Any>>,
+}
+
+#[cfg(feature = "log")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        log::debug!("{}: finished in {:.1} ms", self.label, crate::metrics::now_ms() - self.start);
+    }
+}
+
+/// Log the warnings of one generation run (they are also returned in its metadata)
+pub(crate) fn log_warnings(warnings: &[Warning]) {
+    #[cfg(feature = "log")]
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+    #[cfg(not(feature = "log"))]
+    let _ = warnings;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Stages of the layer "spans", with `true` on entry and `false` when the guard is dropped
+    static EVENTS: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
+    struct Recorder;
+
+    struct Guard(String);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            EVENTS.lock().unwrap().push((self.0.clone(), false));
+        }
+    }
+
+    impl SpanHook for Recorder {
+        fn enter(&self, stage: &Stage) -> Box<dyn Any> {
+            if stage.layer != "spans" {
+                return Box::new(());
+            }
+            let label = stage.to_string();
+            EVENTS.lock().unwrap().push((label.clone(), true));
+            Box::new(Guard(label))
+        }
+    }
+
+    #[test]
+    fn test_span_hook() {
+        set_span_hook(Recorder).unwrap();
+        assert!(set_span_hook(Recorder).is_err());
+        let geojson = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}"#;
+        crate::generate_tiles_with_metrics(geojson, 0, 1, "spans", &crate::TileOptions::default()).unwrap();
+        
+        let events = EVENTS.lock().unwrap().clone();
+        let entered: Vec<&str> = events.iter().filter(|(_, entered)| *entered).map(|(label, _)| label.as_str()).collect();
+        assert_eq!(entered[..2], ["Layer spans: generate", "Layer spans: parse"]);
+        for zoom in 0..=1 {
+            assert!(entered.contains(&format!("Layer spans, zoom {}: tile", zoom).as_str()));
+            assert!(entered.contains(&format!("Layer spans, zoom {}: encode", zoom).as_str()));
+        }
+        assert_eq!(entered.last(), Some(&"Layer spans: write"));
+        // Every stage ends, the whole run last
+        assert_eq!(events.len(), 2 * entered.len());
+        assert_eq!(events.last(), Some(&("Layer spans: generate".to_string(), false)));
+    }
+}
@@ -0,0 +1,437 @@
+// MBTiles module
+// SQLite databases with a tiles table (TMS rows) and the TileJSON fields as metadata rows
+
+use crate::pmtiles::TileCompression;
+use crate::sink::TileSink;
+use crate::sqlite::{Database, Index, SqlValue, Table};
+use crate::{tilejson, TileCoord, TileMetadata};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// `application_id` of MBTiles 1.3 databases ("MPBX")
+const APPLICATION_ID: u32 = 0x4d504258;
+
+/// Tiles and metadata of an MBTiles database
+#[derive(Debug, Clone, Default)]
+pub struct MbTiles {
+    /// `metadata` table rows (TileJSON fields as text)
+    pub metadata: BTreeMap<String, String>,
+    /// Tiles in XYZ coordinates, decompressed (Brotli tiles, which this crate cannot
+    /// decompress, are kept as stored)
+    pub tiles: Vec<(TileCoord, Vec<u8>)>,
+    /// How the tiles were stored: Brotli when the `content_encoding` metadata row is `br`,
+    /// otherwise gzip or none by the tile bytes
+    pub tile_compression: TileCompression,
+}
+
+/// Read an MBTiles database (a plain `tiles` table, or the deduplicated `map`/`images` and
+/// `tiles_shallow`/`tiles_data` layouts behind a `tiles` view)
+///
+/// Gzipped tiles are decompressed (with the `zip` feature).
+pub fn read_mbtiles(bytes: &[u8]) -> Result<MbTiles, String> {
+    let database = Database::open(bytes)?;
+    let mut metadata = BTreeMap::new();
+    if let Some((columns, rows)) = database.table("metadata")? {
+        let (name, value) = (column(&columns, "name")?, column(&columns, "value")?);
+        for row in rows {
+            if let (SqlValue::Text(name), SqlValue::Text(value)) = (&row[name], &row[value]) {
+                metadata.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    
+    let mut tiles = Vec::new();
+    let brotli = metadata.get("content_encoding").map(String::as_str) == Some("br");
+    #[cfg_attr(not(feature = "zip"), allow(unused_mut))]
+    let mut tile_compression = if brotli { TileCompression::Brotli } else { TileCompression::None };
+    for (z, x, row, data) in tile_rows(&database)? {
+        let (Ok(z), Ok(x), Ok(row)) = (u8::try_from(z), u32::try_from(x), u32::try_from(row)) else {
+            return Err(format!("MBTiles tile {}/{}/{} is out of range", z, x, row));
+        };
+        if z > crate::MAX_ZOOM || x >> z != 0 || row >> z != 0 {
+            return Err(format!("MBTiles tile {}/{}/{} is out of range", z, x, row));
+        }
+        let data = match !brotli && data.starts_with(&[0x1f, 0x8b]) {
+            #[cfg(feature = "zip")]
+            true => {
+                tile_compression = TileCompression::Gzip;
+                crate::inflate::gunzip(&data)?
+            }
+            #[cfg(not(feature = "zip"))]
+            true => return Err("Gzipped MBTiles tiles need the 'zip' feature".to_string()),
+            false => data,
+        };
+        tiles.push((TileCoord::new(z, x, (1u32 << z) - 1 - row), data));
+    }
+    Ok(MbTiles { metadata, tiles, tile_compression })
+}
+
+/// Zoom, column, TMS row and data of a tile
+type TileRow = (i64, i64, i64, Vec<u8>);
+
+fn tile_rows(database: &Database) -> Result<Vec<TileRow>, String> {
+    let integer = |value: &SqlValue| match value {
+        SqlValue::Integer(n) => Ok(*n),
+        _ => Err("MBTiles tile coordinates must be integers".to_string()),
+    };
+    let blob = |value: SqlValue| match value {
+        SqlValue::Blob(data) => data,
+        SqlValue::Text(text) => text.into_bytes(),
+        _ => Vec::new(),
+    };
+    if let Some((columns, rows)) = database.table("tiles")? {
+        let [z, x, y, data] = ["zoom_level", "tile_column", "tile_row", "tile_data"].map(|name| column(&columns, name));
+        let (z, x, y, data) = (z?, x?, y?, data?);
+        return rows
+            .into_iter()
+            .map(|mut row| Ok((integer(&row[z])?, integer(&row[x])?, integer(&row[y])?, blob(std::mem::replace(&mut row[data], SqlValue::Null)))))
+            .collect();
+    }
+    
+    // Deduplicated layouts: coordinates with a tile id, and tile data by id
+    let layouts = [("map", "images", "tile_id"), ("tiles_shallow", "tiles_data", "tile_data_id")];
+    for (coordinates_table, data_table, id) in layouts {
+        let (Some((coordinate_columns, coordinates)), Some((data_columns, data))) = (database.table(coordinates_table)?, database.table(data_table)?) else {
+            continue;
+        };
+        let (data_id, tile_data) = (column(&data_columns, id)?, column(&data_columns, "tile_data")?);
+        let mut by_id: HashMap<String, Vec<u8>> = HashMap::new();
+        for mut row in data {
+            by_id.insert(id_key(&row[data_id]), blob(std::mem::replace(&mut row[tile_data], SqlValue::Null)));
+        }
+        let [z, x, y, tile_id] = ["zoom_level", "tile_column", "tile_row", id].map(|name| column(&coordinate_columns, name));
+        let (z, x, y, tile_id) = (z?, x?, y?, tile_id?);
+        return coordinates
+            .iter()
+            .filter_map(|row| {
+                let data = by_id.get(&id_key(&row[tile_id]))?;
+                Some(Ok((integer(&row[z]).ok()?, integer(&row[x]).ok()?, integer(&row[y]).ok()?, data.clone())))
+            })
+            .collect();
+    }
+    Err("MBTiles database has no tiles table".to_string())
+}
+
+/// Ids joining the deduplicated tables (integers and text compare as written)
+fn id_key(value: &SqlValue) -> String {
+    match value {
+        SqlValue::Integer(n) => n.to_string(),
+        SqlValue::Text(text) => text.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn column(columns: &[String], name: &str) -> Result<usize, String> {
+    columns.iter().position(|column| column.eq_ignore_ascii_case(name)).ok_or_else(|| format!("MBTiles table has no {} column", name))
+}
+
+/// Write tiles into an MBTiles 1.3 database
+///
+/// Tiles are buffered until `finish`, then written as one SQLite file with the `tiles` table,
+/// its `tile_index` and a `metadata` row per TileJSON field. No SQLite library is needed.
+pub struct MbTilesSink<W: Write> {
+    writer: W,
+    tiles: Vec<(TileCoord, Vec<u8>)>,
+    tile_compression: TileCompression,
+    existing: Option<MbTiles>,
+}
+
+impl<W: Write> MbTilesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            tiles: Vec::new(),
+            tile_compression: TileCompression::None,
+            existing: None,
+        }
+    }
+
+    /// Add the generated layer to `existing` tiles instead of writing a new tileset
+    ///
+    /// A layer of the same name is replaced in every tile; other layers are kept, and each
+    /// generated tile is merged into the existing tile at its coordinate (`merge::merge_tiles`).
+    /// `vector_layers`, tilestats, the zoom range and bounds of the metadata are updated, and
+    /// tiles are stored with the existing compression.
+    pub fn appending(mut self, existing: MbTiles) -> Self {
+        self.tile_compression = existing.tile_compression;
+        self.existing = Some(existing);
+        self
+    }
+
+    /// Compress the tiles with `compression`, recorded as the `content_encoding` metadata row
+    ///
+    /// MBTiles readers generally expect gzip; Brotli suits servers that pass the encoding on.
+    pub fn with_tile_compression(mut self, compression: TileCompression) -> Self {
+        self.tile_compression = compression;
+        self
+    }
+
+    /// Consume the sink and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> TileSink for MbTilesSink<W> {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        self.tiles.push((coord, data.to_vec()));
+        Ok(())
+    }
+
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        let mut fields = tilejson::to_tilejson(metadata);
+        if let Some(existing) = self.existing.take() {
+            if existing.tile_compression == TileCompression::Brotli {
+                return Err("MBTiles: cannot append to Brotli-compressed tiles (no decoder)".to_string());
+            }
+            self.tiles = append_tiles(existing.tiles, std::mem::take(&mut self.tiles), &metadata.layer_name)?;
+            fields = append_metadata(existing.metadata, fields, &metadata.layer_name);
+        }
+        let row = |coord: &TileCoord| (coord.z, coord.x, (1u32 << coord.z) - 1 - coord.y);
+        self.tiles.sort_unstable_by_key(|(coord, _)| row(coord));
+        if self.tiles.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err("MBTiles: tile written more than once".to_string());
+        }
+        
+        if let (Some(encoding), Some(fields)) = (self.tile_compression.content_encoding(), fields.as_object_mut()) {
+            fields.insert("content_encoding".to_string(), encoding.into());
+        }
+        
+        let tiles = std::mem::take(&mut self.tiles)
+            .into_iter()
+            .map(|(coord, data)| {
+                let (z, x, y) = row(&coord);
+                let data = self.tile_compression.compress(&data)?.into_owned();
+                Ok(vec![SqlValue::Integer(z as i64), SqlValue::Integer(x as i64), SqlValue::Integer(y as i64), SqlValue::Blob(data)])
+            })
+            .collect::<Result<_, String>>()?;
+        // Metadata values are text; TileJSON fields that are not strings are stored as JSON
+        let metadata_rows = fields
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| {
+                let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+                vec![SqlValue::Text(name.clone()), SqlValue::Text(value)]
+            })
+            .collect();
+        
+        let database = crate::sqlite::write_database(
+            &[
+                Table {
+                    name: "metadata".to_string(),
+                    sql: "CREATE TABLE metadata (name text, value text)".to_string(),
+                    rows: metadata_rows,
+                    index: None,
+                },
+                Table {
+                    name: "tiles".to_string(),
+                    sql: "CREATE TABLE tiles (zoom_level integer, tile_column integer, tile_row integer, tile_data blob)".to_string(),
+                    rows: tiles,
+                    index: Some(Index {
+                        name: "tile_index".to_string(),
+                        sql: "CREATE UNIQUE INDEX tile_index on tiles (zoom_level, tile_column, tile_row)".to_string(),
+                        columns: vec![0, 1, 2],
+                    }),
+                },
+            ],
+            APPLICATION_ID,
+        )?;
+        self.writer.write_all(&database).map_err(|e| format!("MBTiles write error: {}", e))?;
+        self.writer.flush().map_err(|e| format!("MBTiles write error: {}", e))
+    }
+}
+
+/// Existing tiles without `layer`, with the generated tiles merged in
+fn append_tiles(existing: Vec<(TileCoord, Vec<u8>)>, generated: Vec<(TileCoord, Vec<u8>)>, layer: &str) -> Result<Vec<(TileCoord, Vec<u8>)>, String> {
+    let mut tiles: BTreeMap<(u8, u32, u32), Vec<u8>> = BTreeMap::new();
+    for (coord, data) in existing {
+        tiles.insert((coord.z, coord.x, coord.y), crate::merge::remove_layer(&data, layer)?);
+    }
+    for (coord, data) in generated {
+        let tile = tiles.entry((coord.z, coord.x, coord.y)).or_default();
+        *tile = crate::merge::merge_tiles(tile, &data)?;
+    }
+    Ok(tiles
+        .into_iter()
+        .filter(|(_, data)| !data.is_empty())
+        .map(|((z, x, y), data)| (TileCoord::new(z, x, y), data))
+        .collect())
+}
+
+/// Existing metadata rows updated with the TileJSON fields of a generated layer
+fn append_metadata(existing: BTreeMap<String, String>, generated: Value, layer: &str) -> Value {
+    let field = |key: &str| generated[key].as_str().unwrap_or_default().to_string();
+    let mut fields: serde_json::Map<String, Value> = existing.into_iter().map(|(key, value)| (key, Value::String(value))).collect();
+    let existing = |key: &str| fields.get(key).and_then(Value::as_str).map(str::to_string);
+    
+    // The zoom range and bounds cover both tilesets
+    let zoom = |key: &str, pick: fn(u8, u8) -> u8| {
+        let generated: u8 = field(key).parse().unwrap_or(0);
+        existing(key).and_then(|value| value.parse().ok()).map_or(generated, |value| pick(value, generated))
+    };
+    let (min_zoom, max_zoom) = (zoom("minzoom", u8::min), zoom("maxzoom", u8::max));
+    let parse_bounds = |text: &str| -> Option<(f64, f64, f64, f64)> {
+        let values: Vec<f64> = text.split(',').map(|n| n.trim().parse().ok()).collect::<Option<_>>()?;
+        let [a, b, c, d] = values[..] else { return None };
+        Some((a, b, c, d))
+    };
+    let mut bounds = parse_bounds(&field("bounds")).unwrap_or((-180.0, -85.0511, 180.0, 85.0511));
+    if let Some((a, b, c, d)) = existing("bounds").as_deref().and_then(parse_bounds) {
+        bounds = (bounds.0.min(a), bounds.1.min(b), bounds.2.max(c), bounds.3.max(d));
+    }
+    let (lon, lat) = crate::geojson_parser::calculate_center(bounds);
+    
+    // The generated layer replaces its earlier entries in vector_layers and tilestats
+    let mut json: Value = existing("json").and_then(|text| serde_json::from_str(&text).ok()).unwrap_or_else(|| json!({}));
+    let generated_json: Value = serde_json::from_str(&field("json")).unwrap_or_else(|_| json!({}));
+    let mut replace = |path: &[&str], id: &str| {
+        let mut target = &mut json;
+        for key in path {
+            target = &mut target[*key];
+        }
+        let mut entries: Vec<Value> = target.as_array().cloned().unwrap_or_default();
+        entries.retain(|entry| entry[id] != layer);
+        let mut source = &generated_json;
+        for key in path {
+            source = &source[*key];
+        }
+        entries.extend(source.as_array().into_iter().flatten().cloned());
+        *target = Value::Array(entries);
+    };
+    replace(&["vector_layers"], "id");
+    replace(&["tilestats", "layers"], "layer");
+    json["tilestats"]["layerCount"] = json!(json["tilestats"]["layers"].as_array().map_or(0, Vec::len));
+    
+    for key in ["format", "name", "description", "version", "type"] {
+        if !fields.contains_key(key) {
+            fields.insert(key.to_string(), Value::String(field(key)));
+        }
+    }
+    fields.insert("minzoom".to_string(), json!(min_zoom.to_string()));
+    fields.insert("maxzoom".to_string(), json!(max_zoom.to_string()));
+    fields.insert("bounds".to_string(), json!(format!("{},{},{},{}", bounds.0, bounds.1, bounds.2, bounds.3)));
+    fields.insert("center".to_string(), json!(format!("{},{},{}", lon, lat, (min_zoom as u32 + max_zoom as u32) / 2)));
+    fields.insert("json".to_string(), json!(json.to_string()));
+    Value::Object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mbtiles_layout() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let mut sink = MbTilesSink::new(Vec::new());
+        crate::generate_tiles_to_sink(geojson, 0, 6, "cities", &crate::TileOptions::default(), &mut sink).unwrap();
+        let database = sink.into_inner();
+        
+        assert!(database.starts_with(b"SQLite format 3\0"));
+        assert_eq!(&database[68..72], b"MPBX");
+        let page_count = u32::from_be_bytes(database[28..32].try_into().unwrap()) as usize;
+        assert_eq!(database.len(), page_count * 4096);
+        // Page 1 is the schema: a table leaf with the metadata and tiles tables and the index
+        assert_eq!((database[100], u16::from_be_bytes([database[103], database[104]])), (0x0D, 3));
+        let text = String::from_utf8_lossy(&database);
+        assert!(text.contains("CREATE UNIQUE INDEX tile_index on tiles (zoom_level, tile_column, tile_row)"));
+        assert!(text.contains("vector_layers"));
+        
+        let mut sink = MbTilesSink::new(Vec::new());
+        sink.write_tile(TileCoord::new(1, 0, 0), b"a").unwrap();
+        sink.write_tile(TileCoord::new(1, 0, 0), b"b").unwrap();
+        assert_eq!(sink.finish(&TileMetadata::default()).unwrap_err(), "MBTiles: tile written more than once");
+    }
+    
+    #[test]
+    fn test_brotli_tiles() {
+        let tile = b"roads roads roads roads roads".to_vec();
+        let mut sink = MbTilesSink::new(Vec::new()).with_tile_compression(TileCompression::Brotli);
+        sink.write_tile(TileCoord::new(1, 0, 1), &tile).unwrap();
+        sink.finish(&TileMetadata::default()).unwrap();
+        let mbtiles = read_mbtiles(&sink.into_inner()).unwrap();
+        
+        assert_eq!(mbtiles.metadata["content_encoding"], "br");
+        assert_eq!(mbtiles.tile_compression, TileCompression::Brotli);
+        assert_eq!(mbtiles.tiles, vec![(TileCoord::new(1, 0, 1), crate::brotli::compress(&tile))]);
+        let mut sink = MbTilesSink::new(Vec::new()).appending(mbtiles);
+        assert!(sink.finish(&TileMetadata::default()).unwrap_err().contains("Brotli"));
+    }
+
+    fn generate(geojson: &[u8], layer: &str, sink: &mut dyn TileSink) {
+        crate::generate_tiles_to_sink(geojson, 0, 3, layer, &crate::TileOptions::default(), sink).unwrap();
+    }
+
+    fn layer_names(tile: &[u8]) -> Vec<String> {
+        crate::mvt_decoder::decode_tile(tile).unwrap().into_iter().map(|layer| layer.name).collect()
+    }
+
+    #[test]
+    fn test_read_round_trip() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let mut memory = crate::sink::MemorySink::default();
+        generate(geojson, "cities", &mut memory);
+        let mut sink = MbTilesSink::new(Vec::new());
+        generate(geojson, "cities", &mut sink);
+        
+        let mbtiles = read_mbtiles(&sink.into_inner()).unwrap();
+        let mut paths: Vec<(String, Vec<u8>)> = mbtiles.tiles.into_iter().map(|(coord, data)| (coord.to_path(), data)).collect();
+        let mut expected: Vec<(String, Vec<u8>)> = memory.tiles.into_iter().map(|tile| (tile.path, tile.data)).collect();
+        paths.sort();
+        expected.sort();
+        assert!(paths == expected);
+        assert_eq!((mbtiles.metadata["format"].as_str(), mbtiles.metadata["maxzoom"].as_str()), ("pbf", "3"));
+        assert_eq!(mbtiles.tile_compression, TileCompression::None);
+        assert!(read_mbtiles(b"not a database").is_err());
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_read_deduplicated_layout() {
+        // Written by SQLite itself: map/images tables behind a tiles view, 1 KiB pages, gzipped
+        // tiles and an unreferenced image on overflow pages
+        let mbtiles = read_mbtiles(include_bytes!("../../test_data/cities_dedup.mbtiles")).unwrap();
+        let mut memory = crate::sink::MemorySink::default();
+        generate(include_bytes!("../../test_data/points.geojson"), "cities", &mut memory);
+        
+        assert_eq!(mbtiles.tile_compression, TileCompression::Gzip);
+        assert_eq!(mbtiles.tiles.len(), memory.tiles.len());
+        for (coord, data) in &mbtiles.tiles {
+            let expected = memory.tiles.iter().find(|tile| tile.path == coord.to_path()).unwrap();
+            assert!(*data == expected.data);
+        }
+        assert_eq!(mbtiles.metadata["name"], "cities");
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_append_layer() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let tokyo = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [139.69, 35.68]}}"#;
+        let mut sink = MbTilesSink::new(Vec::new()).with_tile_compression(TileCompression::Gzip);
+        generate(geojson, "cities", &mut sink);
+        let first = read_mbtiles(&sink.into_inner()).unwrap();
+        assert_eq!(first.tile_compression, TileCompression::Gzip);
+        
+        // A new layer is merged into the tiles; generating it again replaces it
+        let mut sink = MbTilesSink::new(Vec::new()).appending(first.clone());
+        generate(tokyo, "tokyo", &mut sink);
+        let mut sink = MbTilesSink::new(Vec::new()).appending(read_mbtiles(&sink.into_inner()).unwrap());
+        generate(tokyo, "tokyo", &mut sink);
+        let appended = read_mbtiles(&sink.into_inner()).unwrap();
+        
+        assert_eq!(appended.tile_compression, TileCompression::Gzip);
+        assert_eq!(appended.tiles.len(), first.tiles.len());
+        let (_, tile) = appended.tiles.iter().find(|(coord, _)| *coord == TileCoord::new(3, 7, 3)).unwrap();
+        assert_eq!(layer_names(tile), ["cities", "tokyo"]);
+        let tokyo_features: usize = crate::mvt_decoder::decode_tile(tile).unwrap().iter().filter(|layer| layer.name == "tokyo").map(|layer| layer.features.len()).sum();
+        assert_eq!(tokyo_features, 1);
+        
+        let json: Value = serde_json::from_str(&appended.metadata["json"]).unwrap();
+        let ids: Vec<&str> = json["vector_layers"].as_array().unwrap().iter().map(|layer| layer["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["cities", "tokyo"]);
+        assert_eq!(json["tilestats"]["layerCount"], 2);
+        assert_eq!(appended.metadata["name"], "cities");
+    }
+}
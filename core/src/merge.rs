@@ -0,0 +1,187 @@
+// Tile merging module
+// Combine encoded tiles layer by layer
+
+use crate::mvt_decoder::{decode_tile, DecodedLayer};
+use crate::mvt_encoder::{encode_tile_at_extent, vector_tile};
+use crate::tiler::{TileFeature, TileGeometry};
+use crate::TileOptions;
+use prost::Message;
+use std::collections::HashSet;
+
+/// Which copies are kept when the same feature reaches a layer from several tiles
+/// (e.g. overlapping extracts of one dataset)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dedupe {
+    /// Keep every feature
+    #[default]
+    KeepAll,
+    /// Keep the first feature with each id (features without an id are all kept)
+    ById,
+    /// Keep the first feature with each geometry and set of properties, whatever its id
+    ByContent,
+}
+
+/// Merge two encoded tiles of the same coordinate into one
+///
+/// Layers with the same name are combined (features of `b` follow those of `a`, rescaled to
+/// the extent of `a`), other layers are kept as they are. Keys and values are re-dictionaried,
+/// and multi-part point and line features come out as one feature per part.
+pub fn merge_tiles(a: &[u8], b: &[u8]) -> Result<Vec<u8>, String> {
+    merge_tiles_with(a, b, Dedupe::KeepAll)
+}
+
+/// As `merge_tiles`, removing duplicates from each combined layer under `dedupe`
+pub fn merge_tiles_with(a: &[u8], b: &[u8], dedupe: Dedupe) -> Result<Vec<u8>, String> {
+    let mut layers = decode_tile(a)?;
+    
+    for layer in decode_tile(b)? {
+        match layers.iter_mut().find(|existing| existing.name == layer.name) {
+            Some(existing) => {
+                let extent = existing.extent;
+                existing.features.extend(rescale(layer, extent));
+                dedupe_features(&mut existing.features, dedupe);
+            }
+            None => layers.push(layer),
+        }
+    }
+    
+    if layers.iter().all(|layer| layer.features.is_empty()) {
+        return Ok(Vec::new());
+    }
+    
+    // Layers keep their own extent, so each is encoded on its own
+    // (encoded tiles concatenate into one tile with all layers)
+    let mut output = Vec::new();
+    for layer in &layers {
+        if !layer.features.is_empty() {
+            output.extend(encode_tile_at_extent(&layer.features, &layer.name, layer.extent, &TileOptions::default())?);
+        }
+    }
+    Ok(output)
+}
+
+/// An encoded tile without the layers named `name` (other layers are kept as they are)
+pub fn remove_layer(tile: &[u8], name: &str) -> Result<Vec<u8>, String> {
+    let mut tile = vector_tile::Tile::decode(tile).map_err(|e| format!("MVT decode error: {}", e))?;
+    tile.layers.retain(|layer| layer.name != name);
+    Ok(tile.encode_to_vec())
+}
+
+/// Keep the first of each feature that `dedupe` considers the same
+fn dedupe_features(features: &mut Vec<TileFeature>, dedupe: Dedupe) {
+    match dedupe {
+        Dedupe::KeepAll => {}
+        Dedupe::ById => {
+            let mut seen = HashSet::new();
+            features.retain(|feature| feature.id.is_none_or(|id| seen.insert(id)));
+        }
+        // Keys hold the full content, so distinct features never collide
+        Dedupe::ByContent => {
+            let mut seen = HashSet::new();
+            features.retain(|feature| seen.insert(content_key(feature)));
+        }
+    }
+}
+
+/// Geometry and properties of a feature as bytes
+fn content_key(feature: &TileFeature) -> Vec<u8> {
+    let mut key = Vec::new();
+    let mut coords = |tag: u8, coords: &[(i32, i32)]| {
+        key.push(tag);
+        key.extend_from_slice(&(coords.len() as u32).to_le_bytes());
+        for &(x, y) in coords {
+            key.extend_from_slice(&x.to_le_bytes());
+            key.extend_from_slice(&y.to_le_bytes());
+        }
+    };
+    match &feature.geometry {
+        TileGeometry::Point(x, y) => coords(b'P', &[(*x, *y)]),
+        TileGeometry::LineString(line) => coords(b'L', line),
+        TileGeometry::Polygon(rings) => rings.iter().for_each(|ring| coords(b'R', ring)),
+    }
+    key.extend_from_slice(serde_json::Value::Object(feature.properties.clone()).to_string().as_bytes());
+    key
+}
+
+/// Features of a layer in the coordinates of another extent
+fn rescale(layer: DecodedLayer, extent: u32) -> Vec<TileFeature> {
+    if layer.extent == extent {
+        return layer.features;
+    }
+    
+    let scale = extent as f64 / layer.extent as f64;
+    let point = |&(x, y): &(i32, i32)| ((x as f64 * scale).round() as i32, (y as f64 * scale).round() as i32);
+    layer
+        .features
+        .into_iter()
+        .map(|feature| TileFeature {
+            geometry: match &feature.geometry {
+                TileGeometry::Point(x, y) => {
+                    let (x, y) = point(&(*x, *y));
+                    TileGeometry::Point(x, y)
+                }
+                TileGeometry::LineString(coords) => TileGeometry::LineString(coords.iter().map(point).collect()),
+                TileGeometry::Polygon(rings) => TileGeometry::Polygon(
+                    rings.iter().map(|ring| ring.iter().map(point).collect()).collect(),
+                ),
+            },
+            properties: feature.properties,
+            id: feature.id,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i32, y: i32, properties: serde_json::Value) -> TileFeature {
+        TileFeature {
+            geometry: TileGeometry::Point(x, y),
+            properties: properties.as_object().unwrap().clone(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_combines_layers_by_name() {
+        let options = TileOptions::default();
+        let a = encode_tile_at_extent(&[point(10, 10, serde_json::json!({"kind": "a"}))], "pois", 4096, &options).unwrap();
+        let mut b = encode_tile_at_extent(&[point(5, 5, serde_json::json!({"kind": "b"}))], "pois", 512, &options).unwrap();
+        b.extend(encode_tile_at_extent(&[point(1, 1, serde_json::json!({}))], "roads", 4096, &options).unwrap());
+        
+        let layers = decode_tile(&merge_tiles(&a, &b).unwrap()).unwrap();
+        
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].name, "pois");
+        assert_eq!(layers[0].features.len(), 2);
+        assert!(matches!(layers[0].features[1].geometry, TileGeometry::Point(40, 40)));
+        assert_eq!(layers[0].features[1].properties["kind"], "b");
+        assert_eq!(layers[1].name, "roads");
+        assert!(merge_tiles(&[], &[]).unwrap().is_empty());
+        
+        let layers = decode_tile(&remove_layer(&b, "pois").unwrap()).unwrap();
+        assert_eq!(layers.iter().map(|layer| layer.name.as_str()).collect::<Vec<_>>(), ["roads"]);
+    }
+
+    #[test]
+    fn test_merge_dedupe() {
+        let options = TileOptions::default();
+        let with_id = |x, id, properties| TileFeature { id: Some(id), ..point(x, x, properties) };
+        let a = encode_tile_at_extent(&[with_id(10, 1, serde_json::json!({"name": "a"}))], "pois", 4096, &options).unwrap();
+        // The same place from an overlapping extract (another id), a repeated id and a new feature
+        let b = encode_tile_at_extent(&[
+            with_id(10, 7, serde_json::json!({"name": "a"})),
+            with_id(20, 1, serde_json::json!({"name": "b"})),
+            with_id(30, 2, serde_json::json!({"name": "c"})),
+        ], "pois", 4096, &options).unwrap();
+        let names = |dedupe| -> Vec<String> {
+            let layers = decode_tile(&merge_tiles_with(&a, &b, dedupe).unwrap()).unwrap();
+            layers[0].features.iter().map(|f| f.properties["name"].as_str().unwrap().to_string()).collect()
+        };
+        
+        assert_eq!(names(Dedupe::KeepAll), ["a", "a", "b", "c"]);
+        assert_eq!(names(Dedupe::ById), ["a", "a", "c"]);
+        assert_eq!(names(Dedupe::ByContent), ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,236 @@
+// Generation metrics module
+// Per-stage timings and output sizes collected during tile generation
+
+use crate::TileCoord;
+
+/// Timings and sizes for one generation run
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct GenerationMetrics {
+    /// Input parsing time (including property preprocessing and buffering)
+    pub parse_ms: f64,
+    /// Total encode time over all zoom levels
+    pub encode_ms: f64,
+    /// Total wall time of the run
+    pub total_ms: f64,
+    /// Tile features removed to fit the tile budget
+    pub features_dropped: usize,
+    pub zooms: Vec<ZoomMetrics>,
+    /// Keys flagged by the cardinality check (each with its worst tile)
+    pub high_cardinality: Vec<AttributeCardinality>,
+    /// Largest number of distinct keys in any tile
+    pub max_keys_per_tile: usize,
+    /// Tiles over the size warning threshold
+    pub oversized_tiles: Vec<OversizedTile>,
+}
+
+/// Tile over `TileSizeWarning::max_bytes`, with the features that contribute most to it
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct OversizedTile {
+    pub tile: String,
+    pub layer: String,
+    pub bytes: usize,
+    /// Largest features by encoded size, largest first
+    pub largest_features: Vec<FeatureSize>,
+}
+
+/// Encoded size of one tile feature (geometry and tags, without the shared dictionaries)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FeatureSize {
+    /// Position of the feature in the tile layer
+    pub index: usize,
+    pub id: Option<u64>,
+    pub bytes: usize,
+}
+
+/// Threshold of the tile size report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileSizeWarning {
+    /// Report tiles larger than this (bytes)
+    pub max_bytes: usize,
+    /// Features listed per reported tile
+    pub top_features: usize,
+}
+
+impl Default for TileSizeWarning {
+    fn default() -> Self {
+        Self { max_bytes: 500_000, top_features: 5 }
+    }
+}
+
+/// Attribute key with close to one distinct value per feature
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AttributeCardinality {
+    pub key: String,
+    pub distinct_values: usize,
+    /// Features in the tile that carry the key
+    pub features: usize,
+    pub tile: String,
+}
+
+/// Thresholds of the attribute cardinality check run on every encoded tile
+///
+/// Value dictionaries that grow with the feature count (unique timestamps, UUIDs, ...)
+/// are the most common cause of oversized tiles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardinalityLimits {
+    /// Keys carried by fewer features in a tile are not checked
+    pub min_features: usize,
+    /// Flag keys whose distinct values exceed this share of the features carrying them
+    pub max_distinct_ratio: f64,
+    /// Warn when a tile has more distinct keys than this
+    pub max_keys: usize,
+}
+
+impl Default for CardinalityLimits {
+    fn default() -> Self {
+        Self {
+            min_features: 100,
+            max_distinct_ratio: 0.9,
+            max_keys: 200,
+        }
+    }
+}
+
+impl GenerationMetrics {
+    /// Record a tile over the size threshold with its `top_features` largest features
+    pub(crate) fn record_oversized(&mut self, coord: TileCoord, layer: &str, bytes: usize, features: &[(Option<u64>, usize)], top_features: usize) {
+        let mut sizes: Vec<FeatureSize> = features.iter()
+            .enumerate()
+            .map(|(index, &(id, bytes))| FeatureSize { index, id, bytes })
+            .collect();
+        sizes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.index.cmp(&b.index)));
+        sizes.truncate(top_features);
+        self.oversized_tiles.push(OversizedTile {
+            tile: coord.to_path(),
+            layer: layer.to_string(),
+            bytes,
+            largest_features: sizes,
+        });
+    }
+
+    /// Record the key cardinalities of one tile (see `mvt_encoder::key_cardinality`)
+    pub(crate) fn record_cardinality(&mut self, coord: TileCoord, keys: &[(String, usize, usize)], limits: &CardinalityLimits) {
+        self.max_keys_per_tile = self.max_keys_per_tile.max(keys.len());
+        for (key, distinct_values, features) in keys {
+            if *features < limits.min_features || (*distinct_values as f64) <= *features as f64 * limits.max_distinct_ratio {
+                continue;
+            }
+            let report = AttributeCardinality {
+                key: key.clone(),
+                distinct_values: *distinct_values,
+                features: *features,
+                tile: coord.to_path(),
+            };
+            match self.high_cardinality.iter_mut().find(|r| r.key == *key) {
+                Some(existing) if existing.distinct_values < *distinct_values => *existing = report,
+                Some(_) => {}
+                None => self.high_cardinality.push(report),
+            }
+        }
+    }
+}
+
+/// Metrics for a single zoom level
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ZoomMetrics {
+    pub zoom: u8,
+    /// Time spent assigning features to tiles
+    pub tiling_ms: f64,
+    /// Time spent simplifying, reducing and encoding tiles
+    pub encode_ms: f64,
+    pub tile_count: usize,
+    /// Total encoded size of all tiles at this zoom
+    pub bytes: usize,
+    /// Encoded size of the largest tile at this zoom
+    pub max_tile_bytes: usize,
+}
+
+/// Tile counts and sizes of one zoom level, as reported in `TileMetadata`
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ZoomSummary {
+    pub zoom: u8,
+    pub tile_count: usize,
+    /// Total encoded size of all tiles at this zoom
+    pub bytes: usize,
+    /// Mean encoded tile size (0 when the zoom has no tiles)
+    pub average_tile_bytes: usize,
+    pub max_tile_bytes: usize,
+}
+
+impl From<&ZoomMetrics> for ZoomSummary {
+    fn from(zoom: &ZoomMetrics) -> Self {
+        Self {
+            zoom: zoom.zoom,
+            tile_count: zoom.tile_count,
+            bytes: zoom.bytes,
+            average_tile_bytes: zoom.bytes.checked_div(zoom.tile_count).unwrap_or(0),
+            max_tile_bytes: zoom.max_tile_bytes,
+        }
+    }
+}
+
+/// Millisecond clock (`performance.now()` in wasm, where `std::time::Instant` is unavailable)
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub(crate) fn now_ms() -> f64 {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance)]
+        fn now() -> f64;
+    }
+    now()
+}
+
+/// Millisecond clock (`performance.now()` in wasm, where `std::time::Instant` is unavailable)
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+/// No clock is reachable in wasm without the `wasm` feature, so timings read as zero
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+pub(crate) fn now_ms() -> f64 {
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_is_monotonic() {
+        let start = now_ms();
+        let end = now_ms();
+        assert!(end >= start);
+    }
+
+    #[test]
+    fn test_record_cardinality_keeps_worst_tile() {
+        let mut metrics = GenerationMetrics::default();
+        let limits = CardinalityLimits::default();
+        
+        metrics.record_cardinality(TileCoord::new(1, 0, 0), &[("time".to_string(), 95, 100), ("kind".to_string(), 3, 100)], &limits);
+        metrics.record_cardinality(TileCoord::new(1, 1, 0), &[("time".to_string(), 400, 400)], &limits);
+        metrics.record_cardinality(TileCoord::new(1, 1, 1), &[("time".to_string(), 50, 50)], &limits);
+        
+        assert_eq!(metrics.high_cardinality.len(), 1);
+        assert_eq!(metrics.high_cardinality[0].tile, "1/1/0.pbf");
+        assert_eq!(metrics.max_keys_per_tile, 2);
+    }
+
+    #[test]
+    fn test_record_oversized_lists_largest_features() {
+        let mut metrics = GenerationMetrics::default();
+        metrics.record_oversized(TileCoord::new(2, 1, 1), "roads", 900, &[(Some(7), 100), (None, 600), (Some(9), 200)], 2);
+        
+        let report = &metrics.oversized_tiles[0];
+        assert_eq!((report.tile.as_str(), report.layer.as_str(), report.bytes), ("2/1/1.pbf", "roads", 900));
+        let largest: Vec<(usize, Option<u64>)> = report.largest_features.iter().map(|f| (f.index, f.id)).collect();
+        assert_eq!(largest, [(1, None), (2, Some(9))]);
+    }
+}
@@ -0,0 +1,426 @@
+// Experimental MVT 3 encoder
+// Draft attribute model: typed inline values, nested lists/maps and delta-encoded number lists
+
+use crate::geometry_codec::encode_geometry;
+use crate::mvt_encoder::{feature_ids, NullHandling};
+use crate::tiler::TileFeature;
+use crate::TileOptions;
+use prost::Message;
+use std::collections::HashMap;
+
+/// Layer and feature messages with the draft MVT 3 fields
+///
+/// Field numbers follow the draft spec; v2 decoders skip the unknown fields.
+pub mod message {
+    use crate::mvt_encoder::vector_tile::tile::Value;
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Tile {
+        #[prost(message, repeated, tag = "3")]
+        pub layers: Vec<Layer>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Layer {
+        #[prost(uint32, required, tag = "15")]
+        pub version: u32,
+        #[prost(string, required, tag = "1")]
+        pub name: String,
+        #[prost(message, repeated, tag = "2")]
+        pub features: Vec<Feature>,
+        #[prost(string, repeated, tag = "3")]
+        pub keys: Vec<String>,
+        #[prost(message, repeated, tag = "4")]
+        pub values: Vec<Value>,
+        #[prost(uint32, optional, tag = "5")]
+        pub extent: Option<u32>,
+        #[prost(string, repeated, tag = "6")]
+        pub string_values: Vec<String>,
+        #[prost(float, repeated, tag = "7")]
+        pub float_values: Vec<f32>,
+        #[prost(double, repeated, tag = "8")]
+        pub double_values: Vec<f64>,
+        #[prost(fixed64, repeated, tag = "9")]
+        pub int_values: Vec<u64>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Feature {
+        #[prost(uint64, optional, tag = "1")]
+        pub id: Option<u64>,
+        #[prost(uint32, repeated, tag = "2")]
+        pub tags: Vec<u32>,
+        #[prost(int32, optional, tag = "3")]
+        pub r#type: Option<i32>,
+        #[prost(uint32, repeated, tag = "4")]
+        pub geometry: Vec<u32>,
+        #[prost(uint64, repeated, tag = "5")]
+        pub attributes: Vec<u64>,
+    }
+}
+
+use message::{Feature, Layer, Tile};
+
+// Complex value types (low 4 bits of each attribute word)
+const TYPE_STRING: u64 = 0;
+const TYPE_FLOAT: u64 = 1;
+const TYPE_DOUBLE: u64 = 2;
+const TYPE_UINT: u64 = 3;
+const TYPE_SINT: u64 = 4;
+const TYPE_INLINE_UINT: u64 = 5;
+const TYPE_INLINE_SINT: u64 = 6;
+const TYPE_BOOL_NULL: u64 = 7;
+const TYPE_LIST: u64 = 8;
+const TYPE_MAP: u64 = 9;
+const TYPE_NUMBER_LIST: u64 = 10;
+
+/// Largest parameter that fits next to the 4 type bits
+const MAX_INLINE: u64 = (1 << 60) - 1;
+
+fn complex_value(value_type: u64, parameter: u64) -> u64 {
+    (parameter << 4) | value_type
+}
+
+fn zigzag64(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag64(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+/// Encode one layer using the draft MVT 3 attribute model
+///
+/// Properties are written to `Feature.attributes` as key index / value pairs; arrays and
+/// objects are kept as nested lists and maps instead of being stringified, and arrays of
+/// integers are delta-encoded. Tags and the v2 value table are left empty.
+pub fn encode_tile_v3(
+    features: &[TileFeature],
+    layer_name: &str,
+    extent: u32,
+    options: &TileOptions,
+) -> Result<Vec<u8>, String> {
+    if features.is_empty() {
+        return Err("Features are empty".to_string());
+    }
+    encode_tile_multi_v3(&[(layer_name, features)], extent, options)
+}
+
+/// Encode several layers with the draft attribute model (see `mvt_encoder::encode_tile_multi`)
+pub(crate) fn encode_tile_multi_v3(
+    layers: &[(&str, &[TileFeature])],
+    extent: u32,
+    options: &TileOptions,
+) -> Result<Vec<u8>, String> {
+    let tile = Tile {
+        layers: layers
+            .iter()
+            .map(|(name, features)| encode_layer(features, name, extent, options))
+            .collect::<Result<_, _>>()?,
+    };
+    
+    let mut buf = Vec::with_capacity(tile.encoded_len());
+    tile.encode(&mut buf)
+        .map_err(|e| format!("Encode error: {}", e))?;
+    Ok(buf)
+}
+
+fn encode_layer(
+    features: &[TileFeature],
+    layer_name: &str,
+    extent: u32,
+    options: &TileOptions,
+) -> Result<Layer, String> {
+    let empty_string = serde_json::Value::String(String::new());
+    let mut builder = LayerBuilder::default();
+    let mut encoded_features = Vec::with_capacity(features.len());
+    let (ids, _) = feature_ids(features, options);
+    
+    for (tile_feature, id) in features.iter().zip(ids) {
+        let mut attributes = Vec::with_capacity(tile_feature.properties.len() * 2);
+        for (key, value) in &tile_feature.properties {
+            let value = match (value, options.null_handling) {
+                (serde_json::Value::Null, NullHandling::Skip) => continue,
+                (serde_json::Value::Null, NullHandling::EmptyString) => &empty_string,
+                (value, _) => value,
+            };
+            attributes.push(builder.key(key) as u64);
+            builder.value(value, &mut attributes);
+        }
+        
+        let (geom_type, geometry) = encode_geometry(&tile_feature.geometry, options.winding)?;
+        encoded_features.push(Feature {
+            id,
+            tags: Vec::new(),
+            r#type: Some(geom_type as i32),
+            geometry,
+            attributes,
+        });
+    }
+    
+    Ok(Layer {
+        version: 3,
+        name: layer_name.to_string(),
+        features: encoded_features,
+        keys: builder.keys,
+        values: Vec::new(),
+        extent: Some(extent),
+        string_values: builder.string_values,
+        float_values: builder.float_values,
+        double_values: builder.double_values,
+        int_values: builder.int_values,
+    })
+}
+
+/// Layer dictionaries (each entry is stored once)
+#[derive(Default)]
+struct LayerBuilder {
+    keys: Vec<String>,
+    key_index: HashMap<String, u32>,
+    string_values: Vec<String>,
+    string_index: HashMap<String, u64>,
+    float_values: Vec<f32>,
+    float_index: HashMap<u32, u64>,
+    double_values: Vec<f64>,
+    double_index: HashMap<u64, u64>,
+    int_values: Vec<u64>,
+    int_index: HashMap<u64, u64>,
+}
+
+impl LayerBuilder {
+    fn key(&mut self, key: &str) -> u32 {
+        if let Some(&idx) = self.key_index.get(key) {
+            return idx;
+        }
+        let idx = self.keys.len() as u32;
+        self.keys.push(key.to_string());
+        self.key_index.insert(key.to_string(), idx);
+        idx
+    }
+
+    /// Append the attribute words of a value
+    fn value(&mut self, value: &serde_json::Value, out: &mut Vec<u64>) {
+        match value {
+            serde_json::Value::Null => out.push(complex_value(TYPE_BOOL_NULL, 2)),
+            serde_json::Value::Bool(b) => out.push(complex_value(TYPE_BOOL_NULL, *b as u64)),
+            serde_json::Value::String(s) => {
+                let idx = match self.string_index.get(s.as_str()) {
+                    Some(&idx) => idx,
+                    None => {
+                        let idx = self.string_values.len() as u64;
+                        self.string_values.push(s.clone());
+                        self.string_index.insert(s.clone(), idx);
+                        idx
+                    }
+                };
+                out.push(complex_value(TYPE_STRING, idx));
+            }
+            serde_json::Value::Number(n) => out.push(self.number(n)),
+            serde_json::Value::Array(items) => {
+                if let Some(integers) = integer_list(items) {
+                    // Delta-encoded: each entry is the zig-zag difference to the previous one
+                    out.push(complex_value(TYPE_NUMBER_LIST, integers.len() as u64));
+                    let mut previous = 0i64;
+                    for n in integers {
+                        out.push(zigzag64(n.wrapping_sub(previous)));
+                        previous = n;
+                    }
+                } else {
+                    out.push(complex_value(TYPE_LIST, items.len() as u64));
+                    for item in items {
+                        self.value(item, out);
+                    }
+                }
+            }
+            serde_json::Value::Object(map) => {
+                out.push(complex_value(TYPE_MAP, map.len() as u64));
+                for (key, item) in map {
+                    out.push(self.key(key) as u64);
+                    self.value(item, out);
+                }
+            }
+        }
+    }
+
+    fn number(&mut self, n: &serde_json::Number) -> u64 {
+        if let Some(u) = n.as_u64() {
+            if u <= MAX_INLINE {
+                return complex_value(TYPE_INLINE_UINT, u);
+            }
+            return complex_value(TYPE_UINT, self.int(u));
+        }
+        if let Some(i) = n.as_i64() {
+            let zigzag = zigzag64(i);
+            if zigzag <= MAX_INLINE {
+                return complex_value(TYPE_INLINE_SINT, zigzag);
+            }
+            return complex_value(TYPE_SINT, self.int(zigzag));
+        }
+        
+        let f = n.as_f64().unwrap_or(0.0);
+        let single = f as f32;
+        if single as f64 == f {
+            let idx = *self.float_index.entry(single.to_bits()).or_insert_with(|| {
+                self.float_values.push(single);
+                self.float_values.len() as u64 - 1
+            });
+            complex_value(TYPE_FLOAT, idx)
+        } else {
+            let idx = *self.double_index.entry(f.to_bits()).or_insert_with(|| {
+                self.double_values.push(f);
+                self.double_values.len() as u64 - 1
+            });
+            complex_value(TYPE_DOUBLE, idx)
+        }
+    }
+
+    fn int(&mut self, bits: u64) -> u64 {
+        *self.int_index.entry(bits).or_insert_with(|| {
+            self.int_values.push(bits);
+            self.int_values.len() as u64 - 1
+        })
+    }
+}
+
+/// The values of a non-empty array made only of integers in the i64 range
+fn integer_list(items: &[serde_json::Value]) -> Option<Vec<i64>> {
+    if items.is_empty() {
+        return None;
+    }
+    items.iter().map(|item| item.as_i64()).collect()
+}
+
+/// Decode the attributes of a feature back to JSON properties
+pub fn decode_attributes(
+    layer: &Layer,
+    feature: &Feature,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let mut words = feature.attributes.iter().copied();
+    let mut properties = serde_json::Map::new();
+    while let Some(key) = words.next() {
+        let value = decode_value(layer, &mut words)?;
+        properties.insert(layer_key(layer, key)?, value);
+    }
+    Ok(properties)
+}
+
+fn layer_key(layer: &Layer, idx: u64) -> Result<String, String> {
+    layer.keys
+        .get(idx as usize)
+        .cloned()
+        .ok_or_else(|| format!("Key index {} out of range", idx))
+}
+
+fn decode_value(layer: &Layer, words: &mut impl Iterator<Item = u64>) -> Result<serde_json::Value, String> {
+    let word = words.next().ok_or("Attribute value is missing")?;
+    let (value_type, parameter) = (word & 0xf, word >> 4);
+    let index = |len: usize| -> Result<usize, String> {
+        if (parameter as usize) < len {
+            Ok(parameter as usize)
+        } else {
+            Err(format!("Value index {} out of range", parameter))
+        }
+    };
+    
+    let value = match value_type {
+        TYPE_STRING => layer.string_values[index(layer.string_values.len())?].clone().into(),
+        TYPE_FLOAT => (layer.float_values[index(layer.float_values.len())?] as f64).into(),
+        TYPE_DOUBLE => layer.double_values[index(layer.double_values.len())?].into(),
+        TYPE_UINT => layer.int_values[index(layer.int_values.len())?].into(),
+        TYPE_SINT => unzigzag64(layer.int_values[index(layer.int_values.len())?]).into(),
+        TYPE_INLINE_UINT => parameter.into(),
+        TYPE_INLINE_SINT => unzigzag64(parameter).into(),
+        TYPE_BOOL_NULL => match parameter {
+            0 => false.into(),
+            1 => true.into(),
+            _ => serde_json::Value::Null,
+        },
+        TYPE_LIST => (0..parameter)
+            .map(|_| decode_value(layer, words))
+            .collect::<Result<Vec<_>, _>>()?
+            .into(),
+        TYPE_MAP => {
+            let mut map = serde_json::Map::new();
+            for _ in 0..parameter {
+                let key = words.next().ok_or("Map key is missing")?;
+                let item = decode_value(layer, words)?;
+                map.insert(layer_key(layer, key)?, item);
+            }
+            map.into()
+        }
+        TYPE_NUMBER_LIST => {
+            let mut previous = 0i64;
+            // Each item takes one word, so the words left bound the list
+            let mut items = Vec::with_capacity((parameter as usize).min(words.size_hint().0));
+            for _ in 0..parameter {
+                let delta = words.next().ok_or("Number list is truncated")?;
+                previous = previous.wrapping_add(unzigzag64(delta));
+                items.push(serde_json::Value::from(previous));
+            }
+            items.into()
+        }
+        other => return Err(format!("Unknown attribute type {}", other)),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::TileGeometry;
+
+    #[test]
+    fn test_nested_attributes_round_trip() {
+        let properties = serde_json::json!({
+            "name": "Tokyo",
+            "population": 13960000,
+            "offset": -3,
+            "ratio": 0.1,
+            "huge": u64::MAX,
+            "open": true,
+            "note": null,
+            "ids": [100, 101, 99],
+            "tags": ["a", 1.5, null],
+            "address": {"city": "Tokyo", "zip": 1000001}
+        });
+        let feature = TileFeature {
+            geometry: TileGeometry::Point(10, 20),
+            properties: properties.as_object().unwrap().clone(),
+            id: None,
+        };
+        let options = TileOptions {
+            null_handling: NullHandling::EmptyString,
+            ..Default::default()
+        };
+        
+        let bytes = encode_tile_v3(&[feature], "test", 4096, &options).unwrap();
+        let tile = Tile::decode(bytes.as_slice()).unwrap();
+        let layer = &tile.layers[0];
+        let decoded = decode_attributes(layer, &layer.features[0]).unwrap();
+        
+        assert_eq!(layer.version, 3);
+        assert_eq!(decoded["note"], "");
+        let mut expected = properties.as_object().unwrap().clone();
+        expected.insert("note".to_string(), "".into());
+        assert_eq!(decoded, expected);
+        // "Tokyo" is stored once for both the top-level and the nested value
+        assert_eq!(layer.string_values.iter().filter(|s| *s == "Tokyo").count(), 1);
+    }
+
+    #[test]
+    fn test_integer_lists_are_delta_encoded() {
+        let mut builder = LayerBuilder::default();
+        let mut words = Vec::new();
+        builder.value(&serde_json::json!([100, 101, 99]), &mut words);
+        
+        assert_eq!(words, vec![complex_value(TYPE_NUMBER_LIST, 3), 200, 2, 3]);
+    }
+
+    #[test]
+    fn test_truncated_number_list() {
+        let layer = Layer { keys: vec!["list".to_string()], ..Default::default() };
+        let feature = Feature { attributes: vec![0, complex_value(TYPE_NUMBER_LIST, u64::MAX >> 4), 2], ..Default::default() };
+        
+        assert_eq!(decode_attributes(&layer, &feature).unwrap_err(), "Number list is truncated");
+    }
+}
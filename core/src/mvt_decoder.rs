@@ -0,0 +1,116 @@
+// MVT (Mapbox Vector Tile) decoder
+// Decode encoded tiles back into tile features
+
+use crate::geometry_codec::decode_geometry;
+use crate::mvt_encoder::vector_tile::{self, tile::Value};
+use crate::tiler::{TileFeature, DEFAULT_EXTENT};
+use prost::Message;
+
+/// Decoded layer of a tile
+#[derive(Debug, Clone)]
+pub struct DecodedLayer {
+    pub name: String,
+    pub extent: u32,
+    /// Multi-point and multi-line features are split into one feature per part
+    pub features: Vec<TileFeature>,
+}
+
+/// Decode every layer of an encoded tile
+pub fn decode_tile(bytes: &[u8]) -> Result<Vec<DecodedLayer>, String> {
+    let tile = vector_tile::Tile::decode(bytes)
+        .map_err(|e| format!("MVT decode error: {}", e))?;
+    
+    tile.layers
+        .into_iter()
+        .map(|layer| {
+            let mut features = Vec::new();
+            for feature in &layer.features {
+                let properties = decode_properties(&feature.tags, &layer.keys, &layer.values)?;
+                for geometry in decode_geometry(feature.r#type(), &feature.geometry)? {
+                    features.push(TileFeature {
+                        geometry,
+                        properties: properties.clone(),
+                        id: feature.id,
+                    });
+                }
+            }
+            Ok(DecodedLayer {
+                extent: layer.extent.unwrap_or(DEFAULT_EXTENT),
+                name: layer.name,
+                features,
+            })
+        })
+        .collect()
+}
+
+fn decode_properties(
+    tags: &[u32],
+    keys: &[String],
+    values: &[Value],
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    if !tags.len().is_multiple_of(2) {
+        return Err("Odd number of feature tags".to_string());
+    }
+    
+    tags.chunks(2)
+        .map(|pair| {
+            let key = keys.get(pair[0] as usize).ok_or("Tag key index out of range")?;
+            let value = values.get(pair[1] as usize).ok_or("Tag value index out of range")?;
+            Ok((key.clone(), mvt_value_to_json(value)))
+        })
+        .collect()
+}
+
+/// Convert MVT value to JSON value
+pub fn mvt_value_to_json(value: &Value) -> serde_json::Value {
+    if let Some(s) = &value.string_value {
+        s.clone().into()
+    } else if let Some(f) = value.float_value {
+        serde_json::Number::from_f64(f as f64).map_or(serde_json::Value::Null, serde_json::Value::Number)
+    } else if let Some(d) = value.double_value {
+        serde_json::Number::from_f64(d).map_or(serde_json::Value::Null, serde_json::Value::Number)
+    } else if let Some(i) = value.int_value.or(value.sint_value) {
+        i.into()
+    } else if let Some(u) = value.uint_value {
+        u.into()
+    } else if let Some(b) = value.bool_value {
+        b.into()
+    } else {
+        serde_json::Value::Null
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvt_encoder::encode_tile;
+    use crate::tiler::TileGeometry;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut properties = serde_json::Map::new();
+        properties.insert("name".to_string(), "park".into());
+        properties.insert("area".to_string(), 12.5.into());
+        let ring = vec![(0, 0), (100, 0), (100, 100), (0, 100), (0, 0)];
+        let hole = vec![(20, 20), (20, 80), (80, 80), (80, 20), (20, 20)];
+        let features = vec![
+            TileFeature { geometry: TileGeometry::Polygon(vec![ring.clone(), hole.clone()]), properties, id: None },
+            TileFeature { geometry: TileGeometry::LineString(vec![(5, 5), (-3, 40)]), properties: serde_json::Map::new(), id: None },
+        ];
+        
+        let layers = decode_tile(&encode_tile(&features, "parks").unwrap()).unwrap();
+        
+        assert_eq!(layers[0].name, "parks");
+        assert_eq!(layers[0].extent, 4096);
+        match &layers[0].features[0].geometry {
+            TileGeometry::Polygon(rings) => assert_eq!(rings, &vec![ring, hole]),
+            other => panic!("unexpected geometry: {:?}", other),
+        }
+        assert_eq!(layers[0].features[0].properties["name"], "park");
+        assert_eq!(layers[0].features[0].properties["area"], 12.5);
+        match &layers[0].features[1].geometry {
+            TileGeometry::LineString(coords) => assert_eq!(coords, &vec![(5, 5), (-3, 40)]),
+            other => panic!("unexpected geometry: {:?}", other),
+        }
+    }
+}
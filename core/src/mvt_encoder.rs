@@ -1,44 +1,258 @@
 // MVT (Mapbox Vector Tile) encoder
 // Encode tiles to binary format using Protocol Buffers
 
-use crate::tiler::{TileFeature, TileGeometry};
+use crate::geometry_codec::encode_geometry;
+use crate::hash::Fnv1a64;
+use crate::tiler::{TileFeature, DEFAULT_EXTENT};
+use crate::TileOptions;
 use prost::Message;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 // Protocol Buffer generated code
 pub mod vector_tile {
     include!(concat!(env!("OUT_DIR"), "/vector_tile.rs"));
 }
 
-use vector_tile::tile::{GeomType, Layer, Feature, Value};
+use vector_tile::tile::{Layer, Feature, Value};
+
+/// How `null` property values are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullHandling {
+    /// Omit the key from the feature entirely
+    #[default]
+    Skip,
+    /// Encode the value as an empty string
+    EmptyString,
+}
+
+/// MVT specification version written to each layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MvtVersion {
+    V1,
+    #[default]
+    V2,
+    /// Experimental draft attribute model (see `mvt3`)
+    #[cfg(feature = "mvt3")]
+    V3,
+}
+
+impl MvtVersion {
+    pub fn number(self) -> u32 {
+        match self {
+            MvtVersion::V1 => 1,
+            MvtVersion::V2 => 2,
+            #[cfg(feature = "mvt3")]
+            MvtVersion::V3 => 3,
+        }
+    }
+}
+
+/// Output compatibility with older MVT consumers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compatibility {
+    #[default]
+    Standard,
+    /// Avoid optional parts some legacy decoders reject (feature ids are omitted)
+    Legacy,
+}
+
+/// Ring orientation written to polygons
+///
+/// Rings with the winding of a feature's first ring are exteriors, the others holes
+/// (as the MVT spec classifies them), and each ring is turned to match the convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Winding {
+    /// MVT spec: exterior rings have positive area in tile coordinates (clockwise on screen)
+    #[default]
+    MvtScreen,
+    /// RFC 7946: exterior rings counter-clockwise as seen on the map, holes clockwise
+    Rfc7946,
+}
+
+/// What happens when several features of a layer would be written with the same id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdCollision {
+    /// Write the duplicates as they are (the spec allows it)
+    #[default]
+    Keep,
+    /// Give each duplicate a new id hashed from the original id and its occurrence
+    Rehash,
+    /// Write the duplicates without an id
+    DropId,
+}
+
+/// Ids written for the features of one layer, and the number of duplicates found
+///
+/// Features without a source id are numbered by their index in the layer, so those numbers
+/// can collide with source ids too.
+pub fn feature_ids(features: &[TileFeature], options: &TileOptions) -> (Vec<Option<u64>>, usize) {
+    if options.compatibility == Compatibility::Legacy || options.omit_ids {
+        return (vec![None; features.len()], 0);
+    }
+    
+    let mut used: HashSet<u64> = HashSet::with_capacity(features.len());
+    let mut duplicates = 0;
+    let ids = features
+        .iter()
+        .enumerate()
+        .map(|(index, feature)| {
+            let id = feature.id.unwrap_or(index as u64);
+            if used.insert(id) {
+                return Some(id);
+            }
+            duplicates += 1;
+            match options.id_collision {
+                IdCollision::Keep => Some(id),
+                IdCollision::DropId => None,
+                IdCollision::Rehash => {
+                    let mut occurrence = 0u64;
+                    loop {
+                        occurrence += 1;
+                        let mut hasher = Fnv1a64::default();
+                        hasher.write(&id.to_le_bytes());
+                        hasher.write(&occurrence.to_le_bytes());
+                        let rehashed = hasher.finish();
+                        if used.insert(rehashed) {
+                            return Some(rehashed);
+                        }
+                    }
+                }
+            }
+        })
+        .collect();
+    (ids, duplicates)
+}
+
+/// What happens to features whose geometry fails the encode-time checks, or whose input
+/// geometry type is unsupported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeometryChecks {
+    /// Fail tile generation with the geometry error
+    Strict,
+    /// Leave the feature out of its tile (the count is reported as a warning); unsupported
+    /// input features are skipped with a warning each
+    #[default]
+    Lenient,
+}
 
 /// Encode tile in MVT format
 pub fn encode_tile(features: &[TileFeature], layer_name: &str) -> Result<Vec<u8>, String> {
-    if features.is_empty() {
+    encode_tile_with_options(features, layer_name, &TileOptions::default())
+}
+
+/// Encode tile in MVT format (with options)
+pub fn encode_tile_with_options(
+    features: &[TileFeature],
+    layer_name: &str,
+    options: &TileOptions,
+) -> Result<Vec<u8>, String> {
+    encode_tile_at_extent(features, layer_name, DEFAULT_EXTENT, options)
+}
+
+/// Encode tile in MVT format with feature coordinates in `0..extent`
+pub fn encode_tile_at_extent(
+    features: &[TileFeature],
+    layer_name: &str,
+    extent: u32,
+    options: &TileOptions,
+) -> Result<Vec<u8>, String> {
+    encode_tile_multi(&[(layer_name, features)], extent, options)
+}
+
+/// Encode several layers into one tile
+///
+/// Each layer gets its own key/value dictionaries. Layers without features are left out,
+/// and layer names must be unique within the tile.
+pub fn encode_tile_multi(
+    layers: &[(&str, &[TileFeature])],
+    extent: u32,
+    options: &TileOptions,
+) -> Result<Vec<u8>, String> {
+    let layers: Vec<(&str, &[TileFeature])> = layers
+        .iter()
+        .copied()
+        .filter(|(_, features)| !features.is_empty())
+        .collect();
+    if layers.is_empty() {
         return Err("Features are empty".to_string());
     }
+    for (i, (name, _)) in layers.iter().enumerate() {
+        if layers[..i].iter().any(|(other, _)| other == name) {
+            return Err(format!("Duplicate layer name: {}", name));
+        }
+    }
+    #[cfg(feature = "mvt3")]
+    if options.mvt_version == MvtVersion::V3 {
+        return crate::mvt3::encode_tile_multi_v3(&layers, extent, options);
+    }
+    
+    // Build tile
+    let tile = vector_tile::Tile {
+        layers: layers
+            .iter()
+            .map(|(name, features)| encode_layer(features, name, extent, options))
+            .collect::<Result<_, _>>()?,
+    };
+    
+    // Encode to binary
+    let mut buf = Vec::with_capacity(tile.encoded_len());
+    tile.encode(&mut buf)
+        .map_err(|e| format!("Encode error: {}", e))?;
+    
+    Ok(buf)
+}
+
+/// Encode a tile holding one layer without features (a valid tile for coordinates with no data)
+pub fn encode_empty_tile(layer_name: &str, extent: u32, options: &TileOptions) -> Result<Vec<u8>, String> {
+    let tile = vector_tile::Tile { layers: vec![encode_layer(&[], layer_name, extent, options)?] };
+    let mut buf = Vec::with_capacity(tile.encoded_len());
+    tile.encode(&mut buf)
+        .map_err(|e| format!("Encode error: {}", e))?;
+    Ok(buf)
+}
+
+/// Build one layer with its own key and value dictionaries
+fn encode_layer(
+    features: &[TileFeature],
+    layer_name: &str,
+    extent: u32,
+    options: &TileOptions,
+) -> Result<Layer, String> {
+    let empty_string = serde_json::Value::String(String::new());
     
     // Build key and value dictionaries
+    // (lookups borrow keys and string values from the features, so only new entries are copied)
     let mut keys: Vec<String> = Vec::new();
     let mut values: Vec<Value> = Vec::new();
-    let mut key_index: HashMap<String, u32> = HashMap::new();
+    let mut key_index: HashMap<&str, u32> = HashMap::new();
     let mut value_index: HashMap<ValueKey, u32> = HashMap::new();
     
     // Encode features
-    let mut encoded_features = Vec::new();
+    let mut encoded_features = Vec::with_capacity(features.len());
+    let (ids, _) = feature_ids(features, options);
     
-    for (idx, tile_feature) in features.iter().enumerate() {
-        let mut tags = Vec::new();
+    for (tile_feature, id) in features.iter().zip(ids) {
+        let mut tags = Vec::with_capacity(tile_feature.properties.len() * 2);
         
         // Convert properties to tags
         for (key, value) in &tile_feature.properties {
+            // MVT has no null type, so either drop the key or substitute a value
+            let value = match value {
+                serde_json::Value::Null => match options.null_handling {
+                    NullHandling::Skip => continue,
+                    NullHandling::EmptyString => &empty_string,
+                },
+                value => value,
+            };
+            
             // Get or add key index
-            let key_idx = if let Some(&idx) = key_index.get(key) {
+            let key_idx = if let Some(&idx) = key_index.get(key.as_str()) {
                 idx
             } else {
                 let idx = keys.len() as u32;
                 keys.push(key.clone());
-                key_index.insert(key.clone(), idx);
+                key_index.insert(key, idx);
                 idx
             };
             
@@ -58,10 +272,10 @@ pub fn encode_tile(features: &[TileFeature], layer_name: &str) -> Result<Vec<u8>
         }
         
         // Encode geometry
-        let (geom_type, geometry) = encode_geometry(&tile_feature.geometry)?;
+        let (geom_type, geometry) = encode_geometry(&tile_feature.geometry, options.winding)?;
         
         encoded_features.push(Feature {
-            id: Some(idx as u64),
+            id,
             tags,
             r#type: Some(geom_type as i32),
             geometry,
@@ -69,175 +283,261 @@ pub fn encode_tile(features: &[TileFeature], layer_name: &str) -> Result<Vec<u8>
     }
     
     // Build layer
-    let layer = Layer {
-        version: 2,
+    Ok(Layer {
+        version: options.mvt_version.number(),
         name: layer_name.to_string(),
         features: encoded_features,
         keys,
         values,
-        extent: Some(4096),
-    };
-    
-    // Build tile
-    let tile = vector_tile::Tile {
-        layers: vec![layer],
-    };
-    
-    // Encode to binary
-    let mut buf = Vec::new();
-    tile.encode(&mut buf)
-        .map_err(|e| format!("Encode error: {}", e))?;
-    
-    Ok(buf)
+        extent: Some(extent),
+    })
 }
 
-/// Encode geometry in MVT format
-fn encode_geometry(geometry: &TileGeometry) -> Result<(GeomType, Vec<u32>), String> {
-    match geometry {
-        TileGeometry::Point(x, y) => {
-            let mut commands = Vec::new();
-            
-            // MoveTo command (command=1, count=1)
-            commands.push(command_integer(1, 1));
-            
-            // Coordinates (zig-zag encoding)
-            commands.push(zigzag_encode(*x));
-            commands.push(zigzag_encode(*y));
-            
-            Ok((GeomType::Point, commands))
-        }
-        TileGeometry::LineString(coords) => {
-            if coords.is_empty() {
-                return Err("LineString is empty".to_string());
-            }
-            
-            let mut commands = Vec::new();
-            
-            // MoveTo first point (command=1, count=1)
-            commands.push(command_integer(1, 1));
-            commands.push(zigzag_encode(coords[0].0));
-            commands.push(zigzag_encode(coords[0].1));
-            
-            if coords.len() > 1 {
-                // LineTo remaining points (command=2, count=n-1)
-                commands.push(command_integer(2, (coords.len() - 1) as u32));
-                
-                for i in 1..coords.len() {
-                    let dx = coords[i].0 - coords[i - 1].0;
-                    let dy = coords[i].1 - coords[i - 1].1;
-                    commands.push(zigzag_encode(dx));
-                    commands.push(zigzag_encode(dy));
-                }
-            }
-            
-            Ok((GeomType::Linestring, commands))
-        }
-        TileGeometry::Polygon(rings) => {
-            if rings.is_empty() {
-                return Err("Polygon is empty".to_string());
-            }
-            
-            let mut commands = Vec::new();
-            
-            for ring in rings {
-                if ring.len() < 4 {
-                    // Polygon requires at least 4 points (first and last are the same)
-                    continue;
-                }
-                
-                // In GeoJSON, last point = first point, so exclude the last point
-                let point_count = ring.len() - 1;
-                
-                // MoveTo first point
-                commands.push(command_integer(1, 1));
-                commands.push(zigzag_encode(ring[0].0));
-                commands.push(zigzag_encode(ring[0].1));
-                
-                // LineTo remaining points (excluding last point)
-                if point_count > 1 {
-                    commands.push(command_integer(2, (point_count - 1) as u32));
-                    
-                    for i in 1..point_count {
-                        let dx = ring[i].0 - ring[i - 1].0;
-                        let dy = ring[i].1 - ring[i - 1].1;
-                        commands.push(zigzag_encode(dx));
-                        commands.push(zigzag_encode(dy));
-                    }
-                }
-                
-                // ClosePath
-                commands.push(command_integer(7, 1));
+/// Id and encoded size of each feature as written to a layer (geometry and tags, without the
+/// shared key and value dictionaries)
+pub fn feature_sizes(features: &[TileFeature], extent: u32, options: &TileOptions) -> Result<Vec<(Option<u64>, usize)>, String> {
+    let layer = encode_layer(features, "", extent, options)?;
+    Ok(layer.features.iter().map(|feature| (feature.id, feature.encoded_len())).collect())
+}
+
+/// Distinct encoded values of each property key: `(key, distinct values, features carrying the key)`
+pub fn key_cardinality(features: &[TileFeature], options: &TileOptions) -> Vec<(String, usize, usize)> {
+    let mut keys: HashMap<&str, (HashSet<ValueKey>, usize)> = HashMap::new();
+    for feature in features {
+        for (key, value) in &feature.properties {
+            if value.is_null() && options.null_handling == NullHandling::Skip {
+                continue;
             }
-            
-            Ok((GeomType::Polygon, commands))
+            let (values, count) = keys.entry(key).or_default();
+            values.insert(ValueKey::from_json(value));
+            *count += 1;
         }
     }
-}
-
-/// Encode command and count
-fn command_integer(id: u32, count: u32) -> u32 {
-    (id & 0x7) | (count << 3)
-}
-
-/// Zig-zag encoding
-fn zigzag_encode(n: i32) -> u32 {
-    ((n << 1) ^ (n >> 31)) as u32
+    
+    let mut cardinality: Vec<(String, usize, usize)> = keys
+        .into_iter()
+        .map(|(key, (values, count))| (key.to_string(), values.len(), count))
+        .collect();
+    cardinality.sort();
+    cardinality
 }
 
 /// Convert JSON value to MVT value
 fn json_to_mvt_value(value: &serde_json::Value) -> Value {
-    match value {
-        serde_json::Value::String(s) => Value {
-            string_value: Some(s.clone()),
-            ..Default::default()
-        },
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Value {
-                    int_value: Some(i),
-                    ..Default::default()
-                }
-            } else if let Some(f) = n.as_f64() {
-                Value {
-                    double_value: Some(f),
-                    ..Default::default()
-                }
-            } else {
-                Value::default()
-            }
-        }
-        serde_json::Value::Bool(b) => Value {
-            bool_value: Some(*b),
-            ..Default::default()
-        },
-        _ => Value::default(),
-    }
+    ValueKey::from_json(value).to_mvt_value()
 }
 
 /// Value key (for HashMap)
+///
+/// Numbers are classified into the most compact MVT type that represents them
+/// losslessly: non-negative integers use `uint_value`, negative integers use
+/// `sint_value`, and floats use `float_value` when they survive an f32 round trip.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum ValueKey {
-    String(String),
-    Int(i64),
-    Double(String), // f64 cannot be hashed, so convert to string
+enum ValueKey<'a> {
+    String(Cow<'a, str>),
+    Uint(u64),
+    Sint(i64),
+    Float(u32),  // f32/f64 cannot be hashed, so keep the bit pattern
+    Double(u64),
     Bool(bool),
 }
 
-impl ValueKey {
-    fn from_json(value: &serde_json::Value) -> Self {
+impl<'a> ValueKey<'a> {
+    fn from_json(value: &'a serde_json::Value) -> Self {
         match value {
-            serde_json::Value::String(s) => ValueKey::String(s.clone()),
+            serde_json::Value::String(s) => ValueKey::String(Cow::Borrowed(s)),
             serde_json::Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    ValueKey::Int(i)
-                } else if let Some(f) = n.as_f64() {
-                    ValueKey::Double(f.to_string())
+                if let Some(u) = n.as_u64() {
+                    ValueKey::Uint(u)
+                } else if let Some(i) = n.as_i64() {
+                    ValueKey::Sint(i)
                 } else {
-                    ValueKey::String("0".to_string())
+                    let f = n.as_f64().unwrap_or(0.0);
+                    let single = f as f32;
+                    if single as f64 == f {
+                        ValueKey::Float(single.to_bits())
+                    } else {
+                        ValueKey::Double(f.to_bits())
+                    }
                 }
             }
             serde_json::Value::Bool(b) => ValueKey::Bool(*b),
-            _ => ValueKey::String(String::new()),
+            // Arrays and objects are stored as their JSON text (nulls are resolved by the caller)
+            _ => ValueKey::String(Cow::Owned(value.to_string())),
+        }
+    }
+
+    fn to_mvt_value(&self) -> Value {
+        match self {
+            ValueKey::String(s) => Value {
+                string_value: Some(s.to_string()),
+                ..Default::default()
+            },
+            ValueKey::Uint(u) => Value {
+                uint_value: Some(*u),
+                ..Default::default()
+            },
+            ValueKey::Sint(i) => Value {
+                sint_value: Some(*i),
+                ..Default::default()
+            },
+            ValueKey::Float(bits) => Value {
+                float_value: Some(f32::from_bits(*bits)),
+                ..Default::default()
+            },
+            ValueKey::Double(bits) => Value {
+                double_value: Some(f64::from_bits(*bits)),
+                ..Default::default()
+            },
+            ValueKey::Bool(b) => Value {
+                bool_value: Some(*b),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::TileGeometry;
+
+    fn decode(bytes: &[u8]) -> Layer {
+        vector_tile::Tile::decode(bytes).unwrap().layers.remove(0)
+    }
+
+    fn point_with_properties(properties: serde_json::Value) -> TileFeature {
+        TileFeature {
+            geometry: TileGeometry::Point(10, 20),
+            properties: properties.as_object().unwrap().clone(),
+            id: None,
         }
     }
+
+    #[test]
+    fn test_null_properties_are_skipped_by_default() {
+        let feature = point_with_properties(serde_json::json!({"name": "Tokyo", "note": null}));
+        let layer = decode(&encode_tile(&[feature], "test").unwrap());
+        
+        assert_eq!(layer.keys, vec!["name".to_string()]);
+        assert_eq!(layer.features[0].tags.len(), 2);
+        assert!(layer.values.iter().all(|v| *v != Value::default()));
+    }
+
+    #[test]
+    fn test_keys_keep_input_order() {
+        let geojson = br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"zeta": 1, "alpha": 2}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}},
+            {"type": "Feature", "properties": {"mid": 3, "alpha": 4, "zeta": 5}, "geometry": {"type": "Point", "coordinates": [139.7, 35.7]}}
+        ]}"#;
+        let (tiles, _) = crate::generate_tiles_with_options(geojson, 0, 0, "test", &TileOptions::default()).unwrap();
+        let layer = decode(&tiles[0].data);
+        
+        assert_eq!(layer.keys, ["zeta", "alpha", "mid"]);
+        assert_eq!(layer.features[1].tags, [2, 2, 1, 3, 0, 4]);
+    }
+
+    #[test]
+    fn test_null_properties_as_empty_string() {
+        let feature = point_with_properties(serde_json::json!({"note": null}));
+        let options = TileOptions {
+            null_handling: NullHandling::EmptyString,
+            ..Default::default()
+        };
+        let layer = decode(&encode_tile_with_options(&[feature], "test", &options).unwrap());
+        
+        assert_eq!(layer.keys, vec!["note".to_string()]);
+        assert_eq!(layer.values[0].string_value.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_nested_values_are_stringified() {
+        let feature = point_with_properties(serde_json::json!({"tags": [1, 2]}));
+        let layer = decode(&encode_tile(&[feature], "test").unwrap());
+        
+        assert_eq!(layer.values[0].string_value.as_deref(), Some("[1,2]"));
+    }
+
+    #[test]
+    fn test_compact_number_types() {
+        assert_eq!(json_to_mvt_value(&serde_json::json!(42)).uint_value, Some(42));
+        assert_eq!(json_to_mvt_value(&serde_json::json!(-7)).sint_value, Some(-7));
+        assert_eq!(json_to_mvt_value(&serde_json::json!(u64::MAX)).uint_value, Some(u64::MAX));
+        assert_eq!(json_to_mvt_value(&serde_json::json!(0.5)).float_value, Some(0.5));
+        assert_eq!(json_to_mvt_value(&serde_json::json!(0.1)).double_value, Some(0.1));
+    }
+
+    #[test]
+    fn test_legacy_version_and_compatibility() {
+        let options = TileOptions {
+            mvt_version: MvtVersion::V1,
+            compatibility: Compatibility::Legacy,
+            ..Default::default()
+        };
+        let features = vec![point_with_properties(serde_json::json!({}))];
+        
+        let layer = decode(&encode_tile_with_options(&features, "test", &options).unwrap());
+        
+        assert_eq!(layer.version, 1);
+        assert_eq!(layer.features[0].id, None);
+    }
+
+    #[test]
+    fn test_omit_ids() {
+        let mut feature = point_with_properties(serde_json::json!({}));
+        feature.id = Some(7);
+        let options = TileOptions { omit_ids: true, ..Default::default() };
+        
+        let layer = decode(&encode_tile_with_options(&[feature.clone(), feature], "test", &options).unwrap());
+        
+        assert_eq!(layer.features[0].id, None);
+        assert_eq!(layer.features[0], layer.features[1]);
+    }
+
+    #[test]
+    fn test_multi_layer_tile_has_separate_dictionaries() {
+        let roads = vec![point_with_properties(serde_json::json!({"kind": "road"}))];
+        let pois = vec![point_with_properties(serde_json::json!({"name": "Station", "kind": "rail"}))];
+        let layers: [(&str, &[TileFeature]); 3] = [("roads", &roads), ("empty", &[]), ("pois", &pois)];
+        
+        let bytes = encode_tile_multi(&layers, 4096, &TileOptions::default()).unwrap();
+        let tile = vector_tile::Tile::decode(bytes.as_slice()).unwrap();
+        
+        assert_eq!(tile.layers.len(), 2);
+        assert_eq!(tile.layers[0].keys, vec!["kind".to_string()]);
+        assert_eq!(tile.layers[1].name, "pois");
+        assert_eq!(tile.layers[1].keys.len(), 2);
+        
+        let duplicate: [(&str, &[TileFeature]); 2] = [("pois", &roads), ("pois", &pois)];
+        assert!(encode_tile_multi(&duplicate, 4096, &TileOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_key_cardinality() {
+        let features: Vec<TileFeature> = (0..4)
+            .map(|i| point_with_properties(serde_json::json!({"kind": "a", "time": i, "note": null})))
+            .collect();
+        
+        assert_eq!(
+            key_cardinality(&features, &TileOptions::default()),
+            vec![("kind".to_string(), 1, 4), ("time".to_string(), 4, 4)]
+        );
+    }
+
+    #[test]
+    fn test_id_collision_policies() {
+        let mut features: Vec<TileFeature> = (0..3).map(|_| point_with_properties(serde_json::json!({}))).collect();
+        features[0].id = Some(7);
+        features[1].id = Some(7);
+        // No source id, so numbered 2 by its index
+        features[2].id = None;
+        let ids_with = |id_collision| feature_ids(&features, &TileOptions { id_collision, ..Default::default() });
+        
+        assert_eq!(ids_with(IdCollision::Keep), (vec![Some(7), Some(7), Some(2)], 1));
+        assert_eq!(ids_with(IdCollision::DropId), (vec![Some(7), None, Some(2)], 1));
+        let (rehashed, duplicates) = ids_with(IdCollision::Rehash);
+        assert_eq!(duplicates, 1);
+        assert!(rehashed[1].is_some_and(|id| id != 7 && id != 2));
+        assert_eq!(rehashed, ids_with(IdCollision::Rehash).0);
+    }
 }
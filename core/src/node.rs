@@ -0,0 +1,380 @@
+// Node.js module
+// Node-API addon for server-side Node.js (loaded by node/index.js, build with `--features node`)
+
+use crate::pmtiles::PmTilesSink;
+use crate::sink::{DirectorySink, TileSink};
+use crate::{generate_tiles_to_sink, generate_tiles_with_metrics, input, tilejson};
+use crate::{metrics, TileMetadata, TileOptions};
+use std::ffi::{c_char, c_void, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::OnceLock;
+
+type Env = *mut c_void;
+type Value = *mut c_void;
+type CallbackInfo = *mut c_void;
+type Deferred = *mut c_void;
+type AsyncWork = *mut c_void;
+type Callback = unsafe extern "C" fn(Env, CallbackInfo) -> Value;
+type Execute = unsafe extern "C" fn(Env, *mut c_void);
+type Complete = unsafe extern "C" fn(Env, i32, *mut c_void);
+
+const STATUS_OK: i32 = 0;
+const TYPE_STRING: i32 = 4;
+const AUTO_LENGTH: usize = usize::MAX;
+
+/// Declare the Node-API functions used here; they are looked up in the running node
+/// process, so the library links without Node headers or import libraries
+///
+/// This stands in for napi-rs, whose bindings and build script would be the crate's only
+/// dependencies for this: Node-API is a stable C ABI exported by the node executable, and the
+/// two exported functions need just these calls. Addresses come from `dlsym` as data pointers,
+/// and `transmute` (after the null check) is the only conversion to a function pointer.
+macro_rules! node_api {
+    ($($name:ident($($arg:ty),*);)*) => {
+        #[allow(non_snake_case)]
+        struct NodeApi {
+            $($name: unsafe extern "C" fn($($arg),*) -> i32,)*
+        }
+        
+        /// The functions, or which one is missing outside Node.js
+        fn api() -> Result<&'static NodeApi, String> {
+            static API: OnceLock<Result<NodeApi, String>> = OnceLock::new();
+            API.get_or_init(|| {
+                Ok(NodeApi {
+                    $($name: {
+                        let address = symbol(concat!(stringify!($name), "\0"))?;
+                        // SAFETY: a non-null address of this Node-API function, declared with its C signature
+                        unsafe { std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($arg),*) -> i32>(address) }
+                    },)*
+                })
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+        }
+    };
+}
+
+node_api! {
+    napi_create_function(Env, *const c_char, usize, Callback, *mut c_void, *mut Value);
+    napi_set_named_property(Env, Value, *const c_char, Value);
+    napi_get_cb_info(Env, CallbackInfo, *mut usize, *mut Value, *mut Value, *mut *mut c_void);
+    napi_typeof(Env, Value, *mut i32);
+    napi_get_buffer_info(Env, Value, *mut *mut c_void, *mut usize);
+    napi_get_value_string_utf8(Env, Value, *mut c_char, usize, *mut usize);
+    napi_get_value_uint32(Env, Value, *mut u32);
+    napi_create_string_utf8(Env, *const c_char, usize, *mut Value);
+    napi_create_double(Env, f64, *mut Value);
+    napi_get_boolean(Env, bool, *mut Value);
+    napi_get_null(Env, *mut Value);
+    napi_create_object(Env, *mut Value);
+    napi_create_array_with_length(Env, usize, *mut Value);
+    napi_set_element(Env, Value, u32, Value);
+    napi_create_buffer_copy(Env, usize, *const c_void, *mut *mut c_void, *mut Value);
+    napi_throw_error(Env, *const c_char, *const c_char);
+    napi_create_error(Env, Value, Value, *mut Value);
+    napi_create_promise(Env, *mut Deferred, *mut Value);
+    napi_resolve_deferred(Env, Deferred, Value);
+    napi_reject_deferred(Env, Deferred, Value);
+    napi_create_async_work(Env, Value, Value, Execute, Complete, *mut c_void, *mut AsyncWork);
+    napi_queue_async_work(Env, AsyncWork);
+    napi_delete_async_work(Env, AsyncWork);
+}
+
+/// Address of a NUL-terminated symbol in the running process
+fn symbol(name: &str) -> Result<*mut c_void, String> {
+    let address = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr().cast()) };
+    match address.is_null() {
+        true => Err(format!("{} not found; the node feature is only for loading into Node.js", name.trim_end_matches('\0'))),
+        false => Ok(address),
+    }
+}
+
+/// Call a Node-API function, turning a missing function or a failed status into an error
+macro_rules! call {
+    ($name:ident($($arg:expr),*)) => {
+        match api() {
+            Ok(api) => match (api.$name)($($arg),*) {
+                STATUS_OK => Ok(()),
+                status => Err(format!("{} failed with status {}", stringify!($name), status)),
+            },
+            Err(e) => Err(e),
+        }
+    };
+}
+
+/// Register the exports (called by Node.js when the addon is loaded)
+///
+/// # Safety
+/// Only Node.js calls this, with a valid environment and exports object.
+#[no_mangle]
+pub unsafe extern "C" fn napi_register_module_v1(env: Env, exports: Value) -> Value {
+    // Without Node-API there is no way to throw, so the reason goes to stderr
+    if let Err(e) = api() {
+        eprintln!("vector-tile-core: {}", e);
+        return ptr::null_mut();
+    }
+    let functions: [(&str, Callback); 2] = [("generateTiles", generate_tiles), ("generateTilesToFile", generate_tiles_to_file)];
+    for (name, callback) in functions {
+        let mut function = ptr::null_mut();
+        let name = to_c_string(name);
+        let registered = call!(napi_create_function(env, name.as_ptr(), AUTO_LENGTH, callback, ptr::null_mut(), &mut function))
+            .and_then(|_| call!(napi_set_named_property(env, exports, name.as_ptr(), function)));
+        if let Err(e) = registered {
+            throw(env, &e);
+            return ptr::null_mut();
+        }
+    }
+    exports
+}
+
+/// `generateTiles(input, minZoom, maxZoom, layerName?)`: `{ tiles: [{ path, data }], metadata }`
+unsafe extern "C" fn generate_tiles(env: Env, info: CallbackInfo) -> Value {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let request = Request::from_args(env, info, false)?;
+        let options = TileOptions { reader: input::detect_reader(&request.input), ..Default::default() };
+        let (tiles, metadata, metrics) =
+            generate_tiles_with_metrics(&request.input, request.min_zoom, request.max_zoom, &request.layer_name, &options)?;
+        
+        let array = new_value(|value| call!(napi_create_array_with_length(env, tiles.len(), value)))?;
+        for (index, tile) in tiles.iter().enumerate() {
+            let object = new_value(|value| call!(napi_create_object(env, value)))?;
+            set(env, object, "path", string(env, &tile.path)?)?;
+            let data = new_value(|value| {
+                call!(napi_create_buffer_copy(env, tile.data.len(), tile.data.as_ptr().cast(), ptr::null_mut(), value))
+            })?;
+            set(env, object, "data", data)?;
+            call!(napi_set_element(env, array, index as u32, object))?;
+        }
+        let result = new_value(|value| call!(napi_create_object(env, value)))?;
+        set(env, result, "tiles", array)?;
+        set(env, result, "metadata", to_js(env, &metadata_json(&metadata, &metrics))?)?;
+        Ok(result)
+    }));
+    match result.unwrap_or_else(|_| Err("Tile generation panicked".to_string())) {
+        Ok(value) => value,
+        Err(e) => {
+            throw(env, &e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// `generateTilesToFile(input, output, minZoom, maxZoom, layerName?)`: a promise of the metadata
+///
+/// Tiles are generated on the libuv thread pool and written to a `.pmtiles` archive or, for any
+/// other output, a `{z}/{x}/{y}.pbf` directory, so the event loop is not blocked.
+unsafe extern "C" fn generate_tiles_to_file(env: Env, info: CallbackInfo) -> Value {
+    let queued = (|| {
+        let request = Request::from_args(env, info, true)?;
+        let mut promise = ptr::null_mut();
+        let mut deferred = ptr::null_mut();
+        call!(napi_create_promise(env, &mut deferred, &mut promise))?;
+        
+        let job = Box::into_raw(Box::new(FileJob { request, deferred, work: ptr::null_mut(), result: None }));
+        let name = string(env, "generateTilesToFile")?;
+        let created = call!(napi_create_async_work(env, ptr::null_mut(), name, execute_file_job, complete_file_job, job.cast(), &mut (*job).work));
+        if let Err(e) = created.and_then(|_| call!(napi_queue_async_work(env, (*job).work))) {
+            drop(Box::from_raw(job));
+            return Err(e);
+        }
+        Ok(promise)
+    })();
+    match queued {
+        Ok(promise) => promise,
+        Err(e) => {
+            throw(env, &e);
+            ptr::null_mut()
+        }
+    }
+}
+
+struct FileJob {
+    request: Request,
+    deferred: Deferred,
+    work: AsyncWork,
+    result: Option<Result<serde_json::Value, String>>,
+}
+
+/// Runs on a thread pool thread, so it must not call Node-API
+unsafe extern "C" fn execute_file_job(_env: Env, data: *mut c_void) {
+    let job = &mut *data.cast::<FileJob>();
+    let request = &job.request;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let output = request.output.as_deref().unwrap_or_default();
+        let mut sink: Box<dyn TileSink> = match output.ends_with(".pmtiles") {
+            true => {
+                let file = std::fs::File::create(output).map_err(|e| format!("Failed to create {}: {}", output, e))?;
+                Box::new(PmTilesSink::new(std::io::BufWriter::new(file)))
+            }
+            false => Box::new(DirectorySink::new(output)),
+        };
+        let options = TileOptions { reader: input::detect_reader(&request.input), ..Default::default() };
+        let (metadata, metrics) =
+            generate_tiles_to_sink(&request.input, request.min_zoom, request.max_zoom, &request.layer_name, &options, sink.as_mut())?;
+        Ok(metadata_json(&metadata, &metrics))
+    }));
+    job.result = Some(result.unwrap_or_else(|_| Err("Tile generation panicked".to_string())));
+}
+
+/// Runs on the main thread once `execute_file_job` is done
+unsafe extern "C" fn complete_file_job(env: Env, _status: i32, data: *mut c_void) {
+    let job = Box::from_raw(data.cast::<FileJob>());
+    let result = job.result.unwrap_or_else(|| Err("Tile generation was cancelled".to_string()));
+    let settled = match result.and_then(|metadata| to_js(env, &metadata)) {
+        Ok(metadata) => call!(napi_resolve_deferred(env, job.deferred, metadata)),
+        Err(e) => error(env, &e).and_then(|error| call!(napi_reject_deferred(env, job.deferred, error))),
+    };
+    if let Err(e) = settled {
+        throw(env, &e);
+    }
+    let _ = call!(napi_delete_async_work(env, job.work));
+}
+
+/// Arguments shared by both functions (the input is copied, so it outlives the call)
+struct Request {
+    input: Vec<u8>,
+    output: Option<String>,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: String,
+}
+
+impl Request {
+    unsafe fn from_args(env: Env, info: CallbackInfo, with_output: bool) -> Result<Request, String> {
+        let mut argv = [ptr::null_mut(); 5];
+        let mut argc = argv.len();
+        call!(napi_get_cb_info(env, info, &mut argc, argv.as_mut_ptr(), ptr::null_mut(), ptr::null_mut()))?;
+        let mut args = argv[..argc.min(argv.len())].iter().copied();
+        let mut next = |name: &str| args.next().ok_or_else(|| format!("Missing argument: {}", name));
+        
+        let input = read_bytes(env, next("input")?)?;
+        let output = match with_output {
+            true => Some(read_string(env, next("output")?)?),
+            false => None,
+        };
+        let min_zoom = read_zoom(env, next("minZoom")?, "minZoom")?;
+        let max_zoom = read_zoom(env, next("maxZoom")?, "maxZoom")?;
+        let layer_name = match next("layerName") {
+            Ok(value) if type_of(env, value)? == TYPE_STRING => read_string(env, value)?,
+            _ => "default".to_string(),
+        };
+        Ok(Request { input, output, min_zoom, max_zoom, layer_name })
+    }
+}
+
+/// Metadata in the shape of the C API's `vtc_result_metadata_json`
+fn metadata_json(metadata: &TileMetadata, metrics: &metrics::GenerationMetrics) -> serde_json::Value {
+    serde_json::json!({
+        "tilejson": tilejson::to_tilejson(metadata),
+        "warnings": metadata.warnings,
+        "metrics": metrics,
+    })
+}
+
+unsafe fn new_value(create: impl FnOnce(*mut Value) -> Result<(), String>) -> Result<Value, String> {
+    let mut value = ptr::null_mut();
+    create(&mut value)?;
+    Ok(value)
+}
+
+unsafe fn type_of(env: Env, value: Value) -> Result<i32, String> {
+    let mut kind = 0;
+    call!(napi_typeof(env, value, &mut kind))?;
+    Ok(kind)
+}
+
+/// A Buffer (or Uint8Array view of one), or a string as UTF-8
+unsafe fn read_bytes(env: Env, value: Value) -> Result<Vec<u8>, String> {
+    if type_of(env, value)? == TYPE_STRING {
+        return read_string(env, value).map(String::into_bytes);
+    }
+    let mut data = ptr::null_mut();
+    let mut len = 0;
+    call!(napi_get_buffer_info(env, value, &mut data, &mut len)).map_err(|_| "input must be a Buffer or a string".to_string())?;
+    Ok(match len {
+        0 => Vec::new(),
+        _ => std::slice::from_raw_parts(data.cast::<u8>(), len).to_vec(),
+    })
+}
+
+unsafe fn read_string(env: Env, value: Value) -> Result<String, String> {
+    let mut len = 0;
+    call!(napi_get_value_string_utf8(env, value, ptr::null_mut(), 0, &mut len)).map_err(|_| "Expected a string".to_string())?;
+    let mut buffer = vec![0u8; len + 1];
+    call!(napi_get_value_string_utf8(env, value, buffer.as_mut_ptr().cast(), buffer.len(), &mut len))?;
+    buffer.truncate(len);
+    String::from_utf8(buffer).map_err(|e| e.to_string())
+}
+
+unsafe fn read_zoom(env: Env, value: Value, name: &str) -> Result<u8, String> {
+    let mut zoom = 0;
+    call!(napi_get_value_uint32(env, value, &mut zoom)).map_err(|_| format!("{} must be a number", name))?;
+    u8::try_from(zoom).map_err(|_| format!("{} must be between 0 and {}", name, u8::MAX))
+}
+
+unsafe fn string(env: Env, s: &str) -> Result<Value, String> {
+    new_value(|value| call!(napi_create_string_utf8(env, s.as_ptr().cast(), s.len(), value)))
+}
+
+unsafe fn set(env: Env, object: Value, key: &str, value: Value) -> Result<(), String> {
+    let key = to_c_string(key);
+    call!(napi_set_named_property(env, object, key.as_ptr(), value))
+}
+
+/// Convert JSON to the equivalent JavaScript value
+unsafe fn to_js(env: Env, json: &serde_json::Value) -> Result<Value, String> {
+    use serde_json::Value as Json;
+    
+    match json {
+        Json::Null => new_value(|value| call!(napi_get_null(env, value))),
+        Json::Bool(b) => new_value(|value| call!(napi_get_boolean(env, *b, value))),
+        Json::Number(n) => new_value(|value| call!(napi_create_double(env, n.as_f64().unwrap_or(f64::NAN), value))),
+        Json::String(s) => string(env, s),
+        Json::Array(items) => {
+            let array = new_value(|value| call!(napi_create_array_with_length(env, items.len(), value)))?;
+            for (index, item) in items.iter().enumerate() {
+                call!(napi_set_element(env, array, index as u32, to_js(env, item)?))?;
+            }
+            Ok(array)
+        }
+        Json::Object(map) => {
+            let object = new_value(|value| call!(napi_create_object(env, value)))?;
+            for (key, item) in map {
+                set(env, object, key, to_js(env, item)?)?;
+            }
+            Ok(object)
+        }
+    }
+}
+
+unsafe fn error(env: Env, message: &str) -> Result<Value, String> {
+    let message = string(env, message)?;
+    new_value(|value| call!(napi_create_error(env, ptr::null_mut(), message, value)))
+}
+
+unsafe fn throw(env: Env, message: &str) {
+    let message = to_c_string(message);
+    let _ = call!(napi_throw_error(env, ptr::null(), message.as_ptr()));
+}
+
+/// Interior NUL bytes cannot cross the C boundary, so they are dropped
+fn to_c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_node() {
+        assert!(symbol("malloc\0").is_ok());
+        let err = api().err().unwrap();
+        assert!(err.starts_with("napi_create_function not found"), "{}", err);
+        unsafe {
+            assert_eq!(call!(napi_get_null(ptr::null_mut(), ptr::null_mut())), Err(err));
+            assert!(napi_register_module_v1(ptr::null_mut(), ptr::null_mut()).is_null());
+        }
+    }
+}
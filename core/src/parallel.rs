@@ -0,0 +1,218 @@
+// Parallel module
+// How generation work is split across threads (native builds; wasm runs on one thread)
+
+use crate::geojson_parser::{Feature, GeometryType};
+use crate::{EncodedTile, TileStages};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{tiler, transform::FeatureTransform};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{collections::hash_map::Entry, sync::Arc};
+use std::fmt;
+use std::str::FromStr;
+
+/// Average vertices per feature above which features count as huge
+const HUGE_FEATURE_VERTICES: usize = 1000;
+/// Features above which tiling is worth splitting into batches
+const MANY_FEATURES: usize = 100_000;
+/// Tiles handed to the encoding threads per round (per thread)
+#[cfg(not(target_arch = "wasm32"))]
+const TILES_PER_THREAD: usize = 16;
+
+/// How generation is spread over threads
+///
+/// Every strategy writes byte-identical output; only the split of the work differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParallelStrategy {
+    /// Pick one from the input (see `resolve`)
+    #[default]
+    Auto,
+    /// Tile the next zoom on a second thread while the current one is encoded
+    PerZoom,
+    /// As `PerZoom`, and encode the tiles of a zoom on all cores (few huge features, whose
+    /// tiling cannot be split but whose many clipped tiles can)
+    PerTile,
+    /// As `PerZoom`, and tile contiguous batches of features on all cores (millions of points)
+    PerFeatureBatch,
+}
+
+impl ParallelStrategy {
+    pub const ALL: [ParallelStrategy; 4] =
+        [ParallelStrategy::Auto, ParallelStrategy::PerZoom, ParallelStrategy::PerTile, ParallelStrategy::PerFeatureBatch];
+
+    /// Name used by `--parallel` (e.g. `per-tile`)
+    pub fn name(self) -> &'static str {
+        match self {
+            ParallelStrategy::Auto => "auto",
+            ParallelStrategy::PerZoom => "per-zoom",
+            ParallelStrategy::PerTile => "per-tile",
+            ParallelStrategy::PerFeatureBatch => "per-feature-batch",
+        }
+    }
+
+    /// The strategy to run with (never `Auto`)
+    ///
+    /// `Auto` uses `PerTile` when features average more than 1000 vertices, `PerFeatureBatch`
+    /// for more than 100k features and `PerZoom` otherwise (and on a single core).
+    pub fn resolve(self, features: &[Feature]) -> ParallelStrategy {
+        if self != ParallelStrategy::Auto {
+            return self;
+        }
+        if threads() == 1 || features.is_empty() {
+            return ParallelStrategy::PerZoom;
+        }
+        let vertices: usize = features.iter()
+            .map(|feature| match &feature.geometry {
+                GeometryType::Point(_) => 1,
+                GeometryType::LineString(line) => line.0.len(),
+                GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => feature
+                    .geometry
+                    .polygons()
+                    .iter()
+                    .map(|polygon| polygon.exterior().0.len() + polygon.interiors().iter().map(|ring| ring.0.len()).sum::<usize>())
+                    .sum(),
+            })
+            .sum();
+        if vertices / features.len() > HUGE_FEATURE_VERTICES {
+            ParallelStrategy::PerTile
+        } else if features.len() > MANY_FEATURES {
+            ParallelStrategy::PerFeatureBatch
+        } else {
+            ParallelStrategy::PerZoom
+        }
+    }
+}
+
+impl fmt::Display for ParallelStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ParallelStrategy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ParallelStrategy::ALL.into_iter().find(|strategy| strategy.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = ParallelStrategy::ALL.iter().map(|strategy| strategy.name()).collect();
+            format!("Unknown parallel strategy '{}' (expected one of: {})", name, names.join(", "))
+        })
+    }
+}
+
+/// Threads available to the generation
+pub(crate) fn threads() -> usize {
+    #[cfg(target_arch = "wasm32")]
+    return 1;
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Map the items on all cores, keeping their order
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn map_ordered<T: Send, R: Send>(items: Vec<T>, map: impl Fn(T) -> R + Sync) -> Vec<R> {
+    let chunk_size = items.len().div_ceil(threads()).max(1);
+    let mut chunks: Vec<Vec<T>> = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(chunk_size).collect());
+    }
+    let map = &map;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = chunks.into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(map).collect::<Vec<R>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("Generation thread panicked")).collect()
+    })
+}
+
+/// Tile contiguous batches of the features on all cores, merged back in feature order
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn tile_feature_batches(
+    features: &[Feature],
+    projected: Option<&[tiler::Projected]>,
+    zoom: u8,
+    extent: u32,
+    transforms: &[Arc<dyn FeatureTransform>],
+) -> Result<(tiler::TileMap, usize), String> {
+    let batch_size = features.len().div_ceil(threads()).max(1);
+    let batches: Vec<(usize, &[Feature])> = features.chunks(batch_size).enumerate().collect();
+    let tiled = map_ordered(batches, |(i, batch)| {
+        let projected = projected.map(|projected| &projected[i * batch_size..i * batch_size + batch.len()]);
+        tiler::tile_features_counting_clamped(batch, projected, zoom, extent, transforms)
+    });
+    
+    let mut tiles = tiler::tile_map_for(features.len(), zoom);
+    let mut clamped = 0;
+    for result in tiled {
+        let (batch_tiles, batch_clamped) = result?;
+        clamped += batch_clamped;
+        for (coord, mut features) in batch_tiles {
+            match tiles.entry(coord) {
+                Entry::Occupied(mut entry) => entry.get_mut().append(&mut features),
+                Entry::Vacant(entry) => {
+                    entry.insert(features);
+                }
+            }
+        }
+    }
+    Ok((tiles, clamped))
+}
+
+/// Tiles of a zoom encoded in order, on all cores when `parallel`
+///
+/// Tiles are taken in rounds, so at most a few per thread are held beyond what the
+/// sequential path holds.
+pub(crate) fn encode_tiles<'a>(
+    tiles: impl Iterator<Item = Result<crate::TiledTile, String>> + 'a,
+    stages: &'a TileStages<'a>,
+    parallel: bool,
+) -> Box<dyn Iterator<Item = Result<EncodedTile, String>> + 'a> {
+    let encode = move |tile: Result<crate::TiledTile, String>| {
+        let (coord, features, aggregate_layers) = tile?;
+        stages.encode(coord, features, aggregate_layers)
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    if parallel && threads() > 1 {
+        let mut tiles = tiles.peekable();
+        let round = threads() * TILES_PER_THREAD;
+        let mut encoded = std::collections::VecDeque::new();
+        return Box::new(std::iter::from_fn(move || {
+            if encoded.is_empty() && tiles.peek().is_some() {
+                encoded.extend(map_ordered(tiles.by_ref().take(round).collect(), encode));
+            }
+            encoded.pop_front()
+        }));
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = parallel;
+    Box::new(tiles.map(encode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_tiles_with_options, TileOptions};
+
+    #[test]
+    fn test_strategy_names() {
+        for strategy in ParallelStrategy::ALL {
+            assert_eq!(strategy.name().parse::<ParallelStrategy>(), Ok(strategy));
+        }
+        assert!("per-core".parse::<ParallelStrategy>().unwrap_err().contains("per-feature-batch"));
+    }
+
+    #[test]
+    fn test_strategies_write_identical_tiles() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        let generate = |parallel_strategy| {
+            let options = TileOptions { parallel_strategy, ..Default::default() };
+            let (tiles, _) = generate_tiles_with_options(geojson, 0, 6, "test", &options).unwrap();
+            tiles.into_iter().map(|tile| (tile.path, tile.data)).collect::<Vec<_>>()
+        };
+        
+        let expected = generate(ParallelStrategy::PerZoom);
+        assert_eq!(generate(ParallelStrategy::PerTile), expected);
+        assert_eq!(generate(ParallelStrategy::PerFeatureBatch), expected);
+        assert_eq!(map_ordered((0..100).collect(), |i: i32| i * 2), (0..100).map(|i| i * 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,905 @@
+// PMTiles output module
+// Single-file archives (PMTiles v3) with tiles clustered in Hilbert order
+
+pub use crate::sink::TileCompression;
+use crate::sink::TileSink;
+use crate::{tilejson, TileCoord, TileMetadata};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+
+const HEADER_LEN: usize = 127;
+/// The header and root directory must fit in the first 16 KiB of the archive
+const ROOT_DIRECTORY_MAX: usize = 16384 - HEADER_LEN;
+/// Root plus leaf levels read before an archive is rejected as malformed
+const MAX_DIRECTORY_DEPTH: usize = 4;
+const COMPRESSION_NONE: u8 = 1;
+const COMPRESSION_GZIP: u8 = 2;
+const COMPRESSION_BROTLI: u8 = 3;
+const TILE_TYPE_MVT: u8 = 1;
+/// `read_ranges` joins ranges this close together into one request
+const MAX_RANGE_GAP: u64 = 64 << 10;
+/// Size at which `read_ranges` stops joining ranges into a request
+const MAX_RANGE_REQUEST: u64 = 16 << 20;
+
+/// PMTiles tile id: tiles of lower zooms first, then the Hilbert curve position within the zoom
+///
+/// Consecutive ids are spatially adjacent, so clustering tile data by id keeps
+/// neighboring tiles close together in the archive.
+pub fn tile_id(coord: TileCoord) -> u64 {
+    let z = coord.z as u32;
+    let mut id = ((1u64 << (2 * z)) - 1) / 3;
+    let (mut x, mut y) = (coord.x as u64, coord.y as u64);
+    let mut s = if z == 0 { 0 } else { 1u64 << (z - 1) };
+    while s > 0 {
+        let rx = (x & s != 0) as u64;
+        let ry = (y & s != 0) as u64;
+        id += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant so the curve stays continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = (s - 1).wrapping_sub(x);
+                y = (s - 1).wrapping_sub(y);
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s >>= 1;
+    }
+    id
+}
+
+/// Tile at a PMTiles tile id (the inverse of `tile_id`)
+pub fn tile_coord(id: u64) -> TileCoord {
+    let z = zoom_of_tile_id(id);
+    let mut position = id - first_tile_id(z);
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut s = 1u64;
+    while s < 1u64 << z {
+        let rx = (position >> 1) & 1;
+        let ry = (position ^ rx) & 1;
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        position >>= 2;
+        s <<= 1;
+    }
+    TileCoord::new(z, x as u32, y as u32)
+}
+
+/// Directory entry (`run_length` 0 points to a leaf directory)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u64,
+    run_length: u64,
+}
+
+/// Header code of a tile compression
+fn compression_code(compression: TileCompression) -> u8 {
+    match compression {
+        TileCompression::None => COMPRESSION_NONE,
+        TileCompression::Gzip => COMPRESSION_GZIP,
+        TileCompression::Brotli => COMPRESSION_BROTLI,
+    }
+}
+
+/// Write tiles into a PMTiles v3 archive
+///
+/// Tiles are buffered until `finish`, then written ordered by tile id (clustered). Tiles with
+/// identical content are stored once, and directories are left uncompressed.
+pub struct PmTilesSink<W: Write> {
+    writer: W,
+    tiles: Vec<(u64, usize)>,
+    contents: HashMap<Vec<u8>, usize>,
+    tile_compression: TileCompression,
+}
+
+impl<W: Write> PmTilesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            tiles: Vec::new(),
+            contents: HashMap::new(),
+            tile_compression: TileCompression::None,
+        }
+    }
+
+    /// Compress the tiles with `compression` (recorded in the header)
+    pub fn with_tile_compression(mut self, compression: TileCompression) -> Self {
+        self.tile_compression = compression;
+        self
+    }
+
+    /// Consume the sink and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> TileSink for PmTilesSink<W> {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        let next = self.contents.len();
+        let content = match self.contents.get(data) {
+            Some(&content) => content,
+            None => *self.contents.entry(data.to_vec()).or_insert(next),
+        };
+        self.tiles.push((tile_id(coord), content));
+        Ok(())
+    }
+
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        self.tiles.sort_unstable_by_key(|&(id, _)| id);
+        if self.tiles.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err("PMTiles: tile written more than once".to_string());
+        }
+        
+        let mut contents: Vec<Cow<[u8]>> = vec![Cow::Borrowed(&[]); self.contents.len()];
+        for (data, &content) in &self.contents {
+            contents[content] = self.tile_compression.compress(data)?;
+        }
+        
+        // Tile data in tile id order; repeated content points back to its first copy
+        let mut tile_data = Vec::new();
+        let mut offsets: Vec<Option<u64>> = vec![None; contents.len()];
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut last_content = None;
+        for &(id, content) in &self.tiles {
+            if let Some(last) = entries.last_mut() {
+                // Runs of consecutive ids with the same content share one entry
+                if last.tile_id + last.run_length == id && last_content == Some(content) {
+                    last.run_length += 1;
+                    continue;
+                }
+            }
+            last_content = Some(content);
+            let offset = *offsets[content].get_or_insert_with(|| {
+                tile_data.extend_from_slice(&contents[content]);
+                (tile_data.len() - contents[content].len()) as u64
+            });
+            entries.push(Entry { tile_id: id, offset, length: contents[content].len() as u64, run_length: 1 });
+        }
+        
+        let (root, leaves) = build_directories(&entries);
+        let metadata_json = serde_json::to_vec(&tilejson::to_tilejson(metadata))
+            .map_err(|e| format!("Metadata serialization error: {}", e))?;
+        
+        let root_offset = HEADER_LEN as u64;
+        let metadata_offset = root_offset + root.len() as u64;
+        let leaves_offset = metadata_offset + metadata_json.len() as u64;
+        let data_offset = leaves_offset + leaves.len() as u64;
+        
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(b"PMTiles");
+        header.push(3);
+        for value in [
+            root_offset,
+            root.len() as u64,
+            metadata_offset,
+            metadata_json.len() as u64,
+            leaves_offset,
+            leaves.len() as u64,
+            data_offset,
+            tile_data.len() as u64,
+            entries.iter().map(|e| e.run_length).sum(), // addressed tiles
+            entries.len() as u64,                      // tile entries
+            contents.len() as u64,                     // tile contents
+        ] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        // Clustered, internal compression, tile compression, tile type
+        header.extend_from_slice(&[1, COMPRESSION_NONE, compression_code(self.tile_compression), TILE_TYPE_MVT]);
+        header.extend_from_slice(&[metadata.min_zoom, metadata.max_zoom]);
+        let e7 = |degrees: f64| ((degrees * 1e7).round() as i32).to_le_bytes();
+        let (min_lon, min_lat, max_lon, max_lat) = metadata.bounds;
+        for degrees in [min_lon, min_lat, max_lon, max_lat] {
+            header.extend_from_slice(&e7(degrees));
+        }
+        header.push(((metadata.min_zoom as u32 + metadata.max_zoom as u32) / 2) as u8);
+        header.extend_from_slice(&e7(metadata.center.0));
+        header.extend_from_slice(&e7(metadata.center.1));
+        
+        for part in [&header, &root, &metadata_json, &leaves, &tile_data] {
+            self.writer.write_all(part).map_err(|e| format!("PMTiles write error: {}", e))?;
+        }
+        self.writer.flush().map_err(|e| format!("PMTiles write error: {}", e))
+    }
+}
+
+/// Root directory and concatenated leaf directories
+///
+/// Leaves are only used when the entries do not fit in the root directory.
+fn build_directories(entries: &[Entry]) -> (Vec<u8>, Vec<u8>) {
+    let root = serialize_directory(entries);
+    if root.len() <= ROOT_DIRECTORY_MAX {
+        return (root, Vec::new());
+    }
+    
+    let mut leaf_size = 4096;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = serialize_directory(chunk);
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u64,
+                run_length: 0,
+            });
+            leaves.extend(leaf);
+        }
+        let root = serialize_directory(&root_entries);
+        if root.len() <= ROOT_DIRECTORY_MAX {
+            return (root, leaves);
+        }
+        leaf_size *= 2;
+    }
+}
+
+fn serialize_directory(entries: &[Entry]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_varint(&mut buf, entries.len() as u64);
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut buf, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut buf, entry.run_length);
+    }
+    for entry in entries {
+        write_varint(&mut buf, entry.length);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        // 0 means "directly after the previous entry"
+        let contiguous = i > 0 && entry.offset == entries[i - 1].offset + entries[i - 1].length;
+        write_varint(&mut buf, if contiguous { 0 } else { entry.offset + 1 });
+    }
+    buf
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Summary of an existing PMTiles v3 archive
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ArchiveInfo {
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub bounds: (f64, f64, f64, f64),
+    /// Longitude, latitude and zoom
+    pub center: (f64, f64, u8),
+    /// Compression of the tile data (`none`, `gzip`, `brotli`, `zstd` or `unknown`)
+    pub tile_compression: String,
+    /// Compression of the directories and metadata
+    pub internal_compression: String,
+    /// `mvt`, `png`, `jpeg`, `webp`, `avif` or `unknown`
+    pub tile_type: String,
+    /// Tiles per zoom level (deduplicated tiles are counted at every position)
+    pub tile_counts: BTreeMap<u8, u64>,
+    /// Distinct tile contents stored in the archive
+    pub tile_contents: u64,
+    pub tile_data_bytes: u64,
+    pub archive_bytes: u64,
+    /// JSON metadata (tippecanoe-style `metadata.json` for archives written by this crate)
+    pub metadata: serde_json::Value,
+}
+
+/// Read the header, metadata and directories of a PMTiles v3 archive held in memory
+///
+/// Gzip-compressed directories (as written by other tools) need the `zip` feature.
+pub fn read_info(archive: &[u8]) -> Result<ArchiveInfo, String> {
+    let header = Header::parse(archive)?;
+    let compression_name = |code: u8| match code {
+        1 => "none",
+        2 => "gzip",
+        3 => "brotli",
+        4 => "zstd",
+        _ => "unknown",
+    };
+    
+    let metadata_bytes = header.internal(header.slice(header.field(2), header.field(3))?)?;
+    let metadata = match metadata_bytes.is_empty() {
+        true => serde_json::Value::Null,
+        false => serde_json::from_slice(&metadata_bytes).map_err(|e| format!("Invalid PMTiles metadata: {}", e))?,
+    };
+    
+    // Walk the root and every leaf directory (each leaf once, at most a few levels deep)
+    let mut tile_counts = BTreeMap::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(header.internal(header.slice(header.field(0), header.field(1))?)?.into_owned(), 0)];
+    while let Some((directory, depth)) = pending.pop() {
+        for entry in deserialize_directory(&directory)? {
+            if entry.run_length == 0 {
+                if depth + 1 >= MAX_DIRECTORY_DEPTH {
+                    return Err("PMTiles directories are nested too deeply".to_string());
+                }
+                if visited.insert((entry.offset, entry.length)) {
+                    let leaf = header.slice(offset_in(header.field(4), entry.offset)?, entry.length)?;
+                    pending.push((header.internal(leaf)?.into_owned(), depth + 1));
+                }
+                continue;
+            }
+            count_run(&mut tile_counts, entry.tile_id, entry.run_length)?;
+        }
+    }
+    
+    let e7 = |at: usize| i32::from_le_bytes(archive[at..at + 4].try_into().unwrap()) as f64 / 1e7;
+    Ok(ArchiveInfo {
+        min_zoom: archive[100],
+        max_zoom: archive[101],
+        bounds: (e7(102), e7(106), e7(110), e7(114)),
+        center: (e7(119), e7(123), archive[118]),
+        tile_compression: compression_name(archive[98]).to_string(),
+        internal_compression: compression_name(archive[97]).to_string(),
+        tile_type: match archive[99] {
+            1 => "mvt",
+            2 => "png",
+            3 => "jpeg",
+            4 => "webp",
+            5 => "avif",
+            _ => "unknown",
+        }
+        .to_string(),
+        tile_counts,
+        tile_contents: header.field(10),
+        tile_data_bytes: header.field(7),
+        archive_bytes: archive.len() as u64,
+        metadata,
+    })
+}
+
+/// Add the tiles of a run to the per-zoom counts, splitting it at zoom boundaries
+fn count_run(tile_counts: &mut BTreeMap<u8, u64>, tile_id: u64, run_length: u64) -> Result<(), String> {
+    let end = tile_id.checked_add(run_length).ok_or("Invalid PMTiles directory: run overflows the tile ids")?;
+    let mut start = tile_id;
+    while start < end {
+        let zoom = zoom_of_tile_id(start);
+        let zoom_end = match zoom {
+            31.. => u64::MAX,
+            _ => first_tile_id(zoom + 1),
+        };
+        let run_end = end.min(zoom_end);
+        *tile_counts.entry(zoom).or_insert(0) += run_end - start;
+        start = run_end;
+    }
+    Ok(())
+}
+
+/// Zoom level of a tile id (zoom z starts at id (4^z - 1) / 3)
+fn zoom_of_tile_id(id: u64) -> u8 {
+    let mut zoom = 0;
+    while zoom < 31 && first_tile_id(zoom + 1) <= id {
+        zoom += 1;
+    }
+    zoom
+}
+
+fn first_tile_id(zoom: u8) -> u64 {
+    ((1u64 << (2 * zoom as u32)) - 1) / 3
+}
+
+/// Absolute position of a leaf directory or tile from its section start and entry offset
+fn offset_in(section: u64, offset: u64) -> Result<u64, String> {
+    section.checked_add(offset).ok_or_else(|| "PMTiles archive is truncated".to_string())
+}
+
+/// Validated archive header
+struct Header<'a> {
+    archive: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    fn parse(archive: &'a [u8]) -> Result<Self, String> {
+        if archive.len() < HEADER_LEN || &archive[0..7] != b"PMTiles" || archive[7] != 3 {
+            return Err("Not a PMTiles v3 archive".to_string());
+        }
+        Ok(Header { archive })
+    }
+
+    /// One of the eleven u64 offset/length/count fields
+    fn field(&self, index: usize) -> u64 {
+        let at = 8 + index * 8;
+        u64::from_le_bytes(self.archive[at..at + 8].try_into().unwrap())
+    }
+
+    fn slice(&self, offset: u64, length: u64) -> Result<&'a [u8], String> {
+        let truncated = || "PMTiles archive is truncated".to_string();
+        let start = usize::try_from(offset).map_err(|_| truncated())?;
+        let end = usize::try_from(offset.saturating_add(length)).map_err(|_| truncated())?;
+        self.archive.get(start..end).ok_or_else(truncated)
+    }
+
+    /// Decompress a directory or the metadata
+    fn internal(&self, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, String> {
+        match self.archive[97] {
+            COMPRESSION_NONE => Ok(Cow::Borrowed(bytes)),
+            #[cfg(feature = "zip")]
+            COMPRESSION_GZIP => crate::inflate::gunzip(bytes).map(Cow::Owned),
+            #[cfg(not(feature = "zip"))]
+            COMPRESSION_GZIP => Err("Gzip-compressed PMTiles directories need the 'zip' feature".to_string()),
+            _ => Err("Unsupported PMTiles directory compression".to_string()),
+        }
+    }
+}
+
+/// Look up one tile in a PMTiles v3 archive held in memory (tile data is returned as stored)
+pub fn read_tile(archive: &[u8], coord: TileCoord) -> Result<Option<&[u8]>, String> {
+    let header = Header::parse(archive)?;
+    let field = |index: usize| header.field(index);
+    let slice = |offset: u64, length: u64| header.slice(offset, length);
+    
+    let id = tile_id(coord);
+    let mut directory = header.internal(slice(field(0), field(1))?)?;
+    // Root plus at most a few leaf levels
+    for _ in 0..MAX_DIRECTORY_DEPTH {
+        let entries = deserialize_directory(&directory)?;
+        let index = entries.partition_point(|entry| entry.tile_id <= id);
+        let Some(entry) = index.checked_sub(1).map(|i| entries[i]) else {
+            return Ok(None);
+        };
+        if entry.run_length == 0 {
+            directory = header.internal(slice(offset_in(field(4), entry.offset)?, entry.length)?)?;
+        } else if id - entry.tile_id < entry.run_length {
+            return slice(offset_in(field(6), entry.offset)?, entry.length).map(Some);
+        } else {
+            return Ok(None);
+        }
+    }
+    Err("PMTiles directories are nested too deeply".to_string())
+}
+
+/// All tiles of one zoom in a PMTiles v3 archive held in memory (tile data is returned as stored)
+///
+/// Leaf directories covering other zooms are skipped, and runs are expanded into one entry per tile.
+pub fn read_zoom_tiles(archive: &[u8], zoom: u8) -> Result<Vec<(TileCoord, &[u8])>, String> {
+    let header = Header::parse(archive)?;
+    if zoom > crate::MAX_ZOOM {
+        return Err(format!("Zoom {} is above the maximum of {}", zoom, crate::MAX_ZOOM));
+    }
+    let (zoom_start, zoom_end) = (first_tile_id(zoom), first_tile_id(zoom + 1));
+    
+    let mut tiles = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(header.internal(header.slice(header.field(0), header.field(1))?)?.into_owned(), 0)];
+    while let Some((directory, depth)) = pending.pop() {
+        let entries = deserialize_directory(&directory)?;
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.run_length == 0 {
+                // A leaf holds the ids up to the next entry of its directory
+                let leaf_end = entries.get(i + 1).map_or(u64::MAX, |next| next.tile_id);
+                if leaf_end <= zoom_start || entry.tile_id >= zoom_end {
+                    continue;
+                }
+                if depth + 1 >= MAX_DIRECTORY_DEPTH {
+                    return Err("PMTiles directories are nested too deeply".to_string());
+                }
+                if visited.insert((entry.offset, entry.length)) {
+                    let leaf = header.slice(offset_in(header.field(4), entry.offset)?, entry.length)?;
+                    pending.push((header.internal(leaf)?.into_owned(), depth + 1));
+                }
+                continue;
+            }
+            let run_end = entry.tile_id.checked_add(entry.run_length).ok_or("Invalid PMTiles directory: run overflows the tile ids")?;
+            let ids = entry.tile_id.max(zoom_start)..run_end.min(zoom_end);
+            if !ids.is_empty() {
+                let data = header.slice(offset_in(header.field(6), entry.offset)?, entry.length)?;
+                tiles.extend(ids.map(|id| (tile_coord(id), data)));
+            }
+        }
+    }
+    tiles.sort_by_key(|(coord, _)| tile_id(*coord));
+    Ok(tiles)
+}
+
+/// Random access to the bytes of an archive (a buffer, a file, HTTP range requests, ...)
+pub trait RangeReader {
+    /// Read `length` bytes at `offset` (fewer when the archive ends first)
+    fn read_range(&self, offset: u64, length: u64) -> Result<Cow<'_, [u8]>, String>;
+}
+
+impl RangeReader for [u8] {
+    fn read_range(&self, offset: u64, length: u64) -> Result<Cow<'_, [u8]>, String> {
+        let start = (offset as usize).min(self.len());
+        let end = start.saturating_add(length as usize).min(self.len());
+        Ok(Cow::Borrowed(&self[start..end]))
+    }
+}
+
+/// Look up one tile through range reads, without loading the whole archive
+///
+/// Reads the first 16 KiB (header and root directory), each leaf directory on the way to the
+/// tile and the tile itself, so remote archives cost a few requests per tile.
+pub fn read_tile_ranges<R: RangeReader + ?Sized>(reader: &R, coord: TileCoord) -> Result<Option<Vec<u8>>, String> {
+    let start = reader.read_range(0, (HEADER_LEN + ROOT_DIRECTORY_MAX) as u64)?;
+    let header = Header::parse(&start)?;
+    let field = |index: usize| header.field(index);
+    let read = |offset: u64, length: u64| -> Result<Cow<'_, [u8]>, String> {
+        let bytes = reader.read_range(offset, length)?;
+        if (bytes.len() as u64) < length {
+            return Err("PMTiles archive is truncated".to_string());
+        }
+        Ok(bytes)
+    };
+    
+    let id = tile_id(coord);
+    let root = match header.slice(field(0), field(1)) {
+        Ok(root) => Cow::Borrowed(root),
+        Err(_) => read(field(0), field(1))?,
+    };
+    let mut directory = header.internal(&root)?.into_owned();
+    // Root plus at most a few leaf levels
+    for _ in 0..MAX_DIRECTORY_DEPTH {
+        let entries = deserialize_directory(&directory)?;
+        let index = entries.partition_point(|entry| entry.tile_id <= id);
+        let Some(entry) = index.checked_sub(1).map(|i| entries[i]) else {
+            return Ok(None);
+        };
+        if entry.run_length == 0 {
+            let leaf = read(offset_in(field(4), entry.offset)?, entry.length)?;
+            directory = header.internal(&leaf)?.into_owned();
+        } else if id - entry.tile_id < entry.run_length {
+            return read(offset_in(field(6), entry.offset)?, entry.length).map(|tile| Some(tile.into_owned()));
+        } else {
+            return Ok(None);
+        }
+    }
+    Err("PMTiles directories are nested too deeply".to_string())
+}
+
+/// `read_zoom_tiles` through range reads, fetching only the tiles `keep` accepts
+///
+/// Directories are read a level at a time, and tiles stored next to each other share a request
+/// (see `read_ranges`); leaf directories covering other zooms are skipped.
+pub fn read_zoom_tiles_ranges<R: RangeReader + ?Sized>(
+    reader: &R,
+    zoom: u8,
+    keep: impl Fn(TileCoord) -> bool,
+) -> Result<Vec<(TileCoord, Vec<u8>)>, String> {
+    if zoom > crate::MAX_ZOOM {
+        return Err(format!("Zoom {} is above the maximum of {}", zoom, crate::MAX_ZOOM));
+    }
+    let start = reader.read_range(0, (HEADER_LEN + ROOT_DIRECTORY_MAX) as u64)?;
+    let header = Header::parse(&start)?;
+    let field = |index: usize| header.field(index);
+    let read = |ranges: &[(u64, u64)]| -> Result<Vec<Cow<'_, [u8]>>, String> {
+        let pieces = read_ranges(reader, ranges)?;
+        match pieces.iter().zip(ranges).all(|(piece, &(_, length))| piece.len() as u64 == length) {
+            true => Ok(pieces),
+            false => Err("PMTiles archive is truncated".to_string()),
+        }
+    };
+    let (zoom_start, zoom_end) = (first_tile_id(zoom), first_tile_id(zoom + 1));
+    
+    let root = match header.slice(field(0), field(1)) {
+        Ok(root) => Cow::Borrowed(root),
+        Err(_) => read(&[(field(0), field(1))])?.remove(0),
+    };
+    let mut directories = vec![header.internal(&root)?.into_owned()];
+    let mut visited = HashSet::new();
+    let mut tiles = Vec::new();
+    for depth in 0.. {
+        let mut leaves = Vec::new();
+        for directory in &directories {
+            let entries = deserialize_directory(directory)?;
+            for (i, entry) in entries.iter().enumerate() {
+                if entry.run_length == 0 {
+                    // A leaf holds the ids up to the next entry of its directory
+                    let leaf_end = entries.get(i + 1).map_or(u64::MAX, |next| next.tile_id);
+                    if leaf_end <= zoom_start || entry.tile_id >= zoom_end {
+                        continue;
+                    }
+                    if depth + 1 >= MAX_DIRECTORY_DEPTH {
+                        return Err("PMTiles directories are nested too deeply".to_string());
+                    }
+                    if visited.insert((entry.offset, entry.length)) {
+                        leaves.push((offset_in(field(4), entry.offset)?, entry.length));
+                    }
+                    continue;
+                }
+                let run_end = entry.tile_id.checked_add(entry.run_length).ok_or("Invalid PMTiles directory: run overflows the tile ids")?;
+                let data = (offset_in(field(6), entry.offset)?, entry.length);
+                let ids = entry.tile_id.max(zoom_start)..run_end.min(zoom_end);
+                tiles.extend(ids.map(tile_coord).filter(|&coord| keep(coord)).map(|coord| (coord, data)));
+            }
+        }
+        if leaves.is_empty() {
+            break;
+        }
+        directories = read(&leaves)?.iter().map(|leaf| header.internal(leaf).map(Cow::into_owned)).collect::<Result<_, _>>()?;
+    }
+    
+    tiles.sort_by_key(|(coord, _)| tile_id(*coord));
+    let ranges: Vec<(u64, u64)> = tiles.iter().map(|&(_, range)| range).collect();
+    Ok(tiles.into_iter().zip(read(&ranges)?).map(|((coord, _), data)| (coord, data.into_owned())).collect())
+}
+
+/// Read each `(offset, length)` range, joining nearby ranges into one `read_range` call
+///
+/// Ranges at most 64 KiB apart share a call of up to 16 MiB, which saves round trips when the
+/// reader is remote. Results are in the order of `ranges` (shorter when the archive ends first).
+pub fn read_ranges<'r, R: RangeReader + ?Sized>(reader: &'r R, ranges: &[(u64, u64)]) -> Result<Vec<Cow<'r, [u8]>>, String> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|&i| ranges[i].0);
+    let mut pieces: Vec<Cow<'r, [u8]>> = vec![Cow::Borrowed(&[]); ranges.len()];
+    let mut first = 0;
+    while first < order.len() {
+        let start = ranges[order[first]].0;
+        let mut end = start.saturating_add(ranges[order[first]].1);
+        let mut last = first + 1;
+        while let Some(&next) = order.get(last) {
+            let (offset, length) = ranges[next];
+            let next_end = offset.saturating_add(length).max(end);
+            if offset > end.saturating_add(MAX_RANGE_GAP) || next_end - start > MAX_RANGE_REQUEST {
+                break;
+            }
+            end = next_end;
+            last += 1;
+        }
+        
+        let run = reader.read_range(start, end - start)?;
+        let span = |i: usize, len: usize| {
+            let (offset, length) = ranges[i];
+            let from = ((offset - start) as usize).min(len);
+            from..from.saturating_add(length as usize).min(len)
+        };
+        match run {
+            Cow::Borrowed(bytes) => order[first..last].iter().for_each(|&i| pieces[i] = Cow::Borrowed(&bytes[span(i, bytes.len())])),
+            Cow::Owned(bytes) => order[first..last].iter().for_each(|&i| pieces[i] = Cow::Owned(bytes[span(i, bytes.len())].to_vec())),
+        }
+        first = last;
+    }
+    Ok(pieces)
+}
+
+fn deserialize_directory(mut bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    let invalid = || "Invalid PMTiles directory".to_string();
+    let mut next = || read_varint(&mut bytes).ok_or_else(invalid);
+    let count = next()?;
+    
+    let mut entries = Vec::new();
+    let mut last_id = 0u64;
+    for _ in 0..count {
+        last_id = last_id.checked_add(next()?).ok_or_else(invalid)?;
+        entries.push(Entry { tile_id: last_id, offset: 0, length: 0, run_length: 0 });
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = next()?;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = next()?;
+    }
+    for i in 0..entries.len() {
+        entries[i].offset = match next()? {
+            0 if i > 0 => entries[i - 1].offset.checked_add(entries[i - 1].length).ok_or_else(invalid)?,
+            0 => return Err(invalid()),
+            offset => offset - 1,
+        };
+    }
+    Ok(entries)
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> TileMetadata {
+        TileMetadata {
+            max_zoom: 2,
+            layer_name: "test".to_string(),
+            bounds: (-180.0, -85.0, 180.0, 85.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hilbert_tile_ids() {
+        assert_eq!(tile_id(TileCoord::new(0, 0, 0)), 0);
+        let z1: Vec<u64> = [(0, 0), (0, 1), (1, 1), (1, 0)]
+            .iter()
+            .map(|&(x, y)| tile_id(TileCoord::new(1, x, y)))
+            .collect();
+        assert_eq!(z1, vec![1, 2, 3, 4]);
+        assert_eq!(tile_id(TileCoord::new(2, 0, 0)), 5);
+        assert_eq!(tile_id(TileCoord::new(2, 3, 0)), 20);
+        for id in 0..first_tile_id(6) {
+            assert_eq!(tile_id(tile_coord(id)), id);
+        }
+    }
+
+    #[test]
+    fn test_pmtiles_round_trip() {
+        let mut sink = PmTilesSink::new(Vec::new());
+        sink.write_tile(TileCoord::new(2, 3, 0), &[3]).unwrap();
+        sink.write_tile(TileCoord::new(0, 0, 0), &[0]).unwrap();
+        sink.write_tile(TileCoord::new(2, 0, 0), &[1, 1]).unwrap();
+        sink.write_tile(TileCoord::new(2, 0, 1), &[1, 1]).unwrap();
+        sink.finish(&metadata()).unwrap();
+        let archive = sink.into_inner();
+        
+        assert_eq!(&archive[0..7], b"PMTiles");
+        // Two identical tiles share one copy of the data
+        assert_eq!(u64::from_le_bytes(archive[88..96].try_into().unwrap()), 3);
+        assert_eq!(read_tile(&archive, TileCoord::new(0, 0, 0)).unwrap(), Some(&[0u8][..]));
+        assert_eq!(read_tile(&archive, TileCoord::new(2, 0, 1)).unwrap(), Some(&[1u8, 1][..]));
+        assert_eq!(read_tile(&archive, TileCoord::new(2, 3, 0)).unwrap(), Some(&[3u8][..]));
+        assert_eq!(read_tile(&archive, TileCoord::new(1, 0, 0)).unwrap(), None);
+        
+        let z2 = read_zoom_tiles(&archive, 2).unwrap();
+        let expected = [(TileCoord::new(2, 0, 0), &[1u8, 1][..]), (TileCoord::new(2, 0, 1), &[1, 1]), (TileCoord::new(2, 3, 0), &[3])];
+        assert_eq!(z2, expected);
+        assert!(read_zoom_tiles(&archive, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_info() {
+        let mut sink = PmTilesSink::new(Vec::new());
+        for (z, x, y) in [(0, 0, 0), (2, 0, 0), (2, 0, 1), (2, 3, 3)] {
+            sink.write_tile(TileCoord::new(z, x, y), &[z, 1]).unwrap();
+        }
+        sink.finish(&metadata()).unwrap();
+        let archive = sink.into_inner();
+        
+        let info = read_info(&archive).unwrap();
+        
+        assert_eq!((info.min_zoom, info.max_zoom), (0, 2));
+        assert_eq!(info.tile_counts, BTreeMap::from([(0, 1), (2, 3)]));
+        assert_eq!((info.tile_contents, info.tile_data_bytes), (2, 4));
+        assert_eq!((info.tile_compression.as_str(), info.tile_type.as_str()), ("none", "mvt"));
+        assert_eq!(info.bounds, (-180.0, -85.0, 180.0, 85.0));
+        assert_eq!(info.metadata["name"], "test");
+        
+        assert_eq!(zoom_of_tile_id(tile_id(TileCoord::new(12, 3000, 1000))), 12);
+    }
+
+    #[test]
+    fn test_tile_compression() {
+        let tile = b"roads roads roads roads roads".to_vec();
+        let archive = |compression| {
+            let mut sink = PmTilesSink::new(Vec::new()).with_tile_compression(compression);
+            for (x, y) in [(0, 0), (0, 1), (1, 1)] {
+                sink.write_tile(TileCoord::new(1, x, y), &tile).unwrap();
+            }
+            sink.finish(&metadata()).map(|_| sink.into_inner())
+        };
+        
+        let brotli = archive(TileCompression::Brotli).unwrap();
+        let info = read_info(&brotli).unwrap();
+        assert_eq!((info.tile_compression.as_str(), info.tile_contents), ("brotli", 1));
+        let stored = read_tile(&brotli, TileCoord::new(1, 1, 1)).unwrap().unwrap();
+        assert_eq!(stored, crate::brotli::compress(&tile));
+        
+        #[cfg(feature = "zip")]
+        {
+            let archive = archive(TileCompression::Gzip).unwrap();
+            let info = read_info(&archive).unwrap();
+            assert_eq!((info.tile_compression.as_str(), info.tile_contents), ("gzip", 1));
+            let stored = read_tile(&archive, TileCoord::new(1, 1, 1)).unwrap().unwrap();
+            assert_ne!(stored, &tile[..]);
+            assert_eq!(crate::inflate::gunzip(stored).unwrap(), tile);
+        }
+        #[cfg(not(feature = "zip"))]
+        assert!(archive(TileCompression::Gzip).unwrap_err().contains("'zip' feature"));
+    }
+
+    #[test]
+    fn test_read_info_malformed_directories() {
+        let mut sink = PmTilesSink::new(Vec::new());
+        sink.write_tile(TileCoord::new(0, 0, 0), &[0]).unwrap();
+        sink.finish(&metadata()).unwrap();
+        // The archive's header with `entries` as the root directory, leaves at the root's offset
+        let header = sink.into_inner()[..HEADER_LEN].to_vec();
+        let with_root = |entries: &[Entry]| {
+            let root = serialize_directory(entries);
+            let mut archive = header.clone();
+            for (field, value) in [(0, HEADER_LEN), (1, root.len()), (2, HEADER_LEN + root.len()), (3, 0), (4, HEADER_LEN)] {
+                archive[8 + field * 8..16 + field * 8].copy_from_slice(&(value as u64).to_le_bytes());
+            }
+            archive.extend_from_slice(&root);
+            archive
+        };
+        
+        // A leaf pointing back at itself
+        let length = serialize_directory(&[Entry { tile_id: 0, offset: 0, length: 0, run_length: 0 }]).len() as u64;
+        let archive = with_root(&[Entry { tile_id: 0, offset: 0, length, run_length: 0 }]);
+        assert!(read_info(&archive).unwrap().tile_counts.is_empty());
+        assert!(read_tile(&archive, TileCoord::new(0, 0, 0)).unwrap_err().contains("nested too deeply"));
+        
+        // Runs are counted per zoom without visiting every tile, and must not overflow
+        let archive = with_root(&[Entry { tile_id: 0, offset: 0, length: 1, run_length: 1 << 62 }]);
+        let counts = read_info(&archive).unwrap().tile_counts;
+        assert_eq!((counts[&0], counts[&1], counts[&12], counts.values().sum::<u64>()), (1, 4, 1 << 24, 1 << 62));
+        let archive = with_root(&[Entry { tile_id: 1, offset: 0, length: 1, run_length: u64::MAX }]);
+        assert!(read_info(&archive).unwrap_err().contains("overflows"));
+    }
+
+    #[test]
+    fn test_leaf_directories() {
+        let entries: Vec<Entry> = (0..20_000)
+            .map(|i| Entry { tile_id: i * 2, offset: i * 7, length: 3, run_length: 1 })
+            .collect();
+        let (root, leaves) = build_directories(&entries);
+        
+        assert!(root.len() <= ROOT_DIRECTORY_MAX);
+        assert!(!leaves.is_empty());
+        let root_entries = deserialize_directory(&root).unwrap();
+        assert!(root_entries.iter().all(|entry| entry.run_length == 0));
+        let first = root_entries[0];
+        let leaf = &leaves[first.offset as usize..(first.offset + first.length) as usize];
+        assert_eq!(deserialize_directory(leaf).unwrap()[..4096], entries[..4096]);
+    }
+
+    #[test]
+    fn test_read_tile_ranges() {
+        struct Counting<'a>(&'a [u8], std::cell::Cell<usize>);
+        impl RangeReader for Counting<'_> {
+            fn read_range(&self, offset: u64, length: u64) -> Result<Cow<'_, [u8]>, String> {
+                self.1.set(self.1.get() + 1);
+                self.0.read_range(offset, length)
+            }
+        }
+        
+        // Enough distinct tiles for leaf directories
+        let mut sink = PmTilesSink::new(Vec::new());
+        for i in 0..20_000u32 {
+            sink.write_tile(TileCoord::new(8, i % 256, i / 256), &i.to_le_bytes()).unwrap();
+        }
+        sink.finish(&metadata()).unwrap();
+        let archive = sink.into_inner();
+        
+        let reader = Counting(&archive, std::cell::Cell::new(0));
+        let tile = read_tile_ranges(&reader, TileCoord::new(8, 10, 70)).unwrap();
+        assert_eq!(tile.as_deref(), Some(&(70 * 256 + 10u32).to_le_bytes()[..]));
+        assert_eq!(reader.1.get(), 3);
+        assert_eq!(read_tile_ranges(&archive[..], TileCoord::new(8, 10, 200)).unwrap(), None);
+        
+        // Tiles of one zoom are read across the leaves
+        let z8 = read_zoom_tiles(&archive, 8).unwrap();
+        assert_eq!(z8.len(), 20_000);
+        assert!(z8.contains(&(TileCoord::new(8, 10, 70), &(70 * 256 + 10u32).to_le_bytes()[..])));
+        
+        // Through ranges: the header, the leaves in one request and the tiles kept in one more
+        let reader = Counting(&archive, std::cell::Cell::new(0));
+        let kept = read_zoom_tiles_ranges(&reader, 8, |coord| coord.x < 16 && coord.y < 16).unwrap();
+        assert_eq!(kept.len(), 256);
+        assert!(kept.contains(&(TileCoord::new(8, 10, 7), (7 * 256 + 10u32).to_le_bytes().to_vec())));
+        assert_eq!(reader.1.get(), 3);
+        let all = read_zoom_tiles_ranges(&archive[..], 8, |_| true).unwrap();
+        assert!(all.iter().map(|(coord, data)| (*coord, &data[..])).eq(z8.iter().copied()));
+        assert!(read_zoom_tiles_ranges(&archive[..archive.len() - 1], 8, |_| true).unwrap_err().contains("truncated"));
+    }
+
+    #[test]
+    fn test_read_ranges() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let pieces = read_ranges(&bytes[..], &[(200, 10), (0, 4), (250, 100), (2, 4)]).unwrap();
+        assert_eq!(pieces, [&bytes[200..210], &bytes[0..4], &bytes[250..], &bytes[2..6]]);
+        assert!(pieces.iter().all(|piece| matches!(piece, Cow::Borrowed(_))));
+        assert!(read_ranges(&bytes[..], &[]).unwrap().is_empty());
+    }
+}
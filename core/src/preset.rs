@@ -0,0 +1,112 @@
+// Preset module
+// Named generalization settings for common kinds of data
+
+use crate::reduction::{PointThinning, SizeStrategy, TileBudget};
+use crate::simplify::{Simplification, SimplifyMode};
+use crate::tiler::SortBy;
+use crate::TileOptions;
+use std::fmt;
+use std::str::FromStr;
+
+/// Tile size most renderers handle comfortably (bytes)
+const PRESET_MAX_TILE_BYTES: usize = 500_000;
+
+/// Built-in generalization preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Many points (POIs, sensor readings): thin crowded cells and drop from dense areas
+    PointsDense,
+    /// Road and river networks: light simplification, longest lines drawn first
+    Roads,
+    /// Administrative boundaries: strong simplification that keeps neighbors watertight
+    Boundaries,
+    /// Small adjacent polygons: light watertight simplification, smallest polygons merged in crowded tiles
+    Parcels,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::PointsDense, Preset::Roads, Preset::Boundaries, Preset::Parcels];
+
+    /// Name used by `--preset` (e.g. `points-dense`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::PointsDense => "points-dense",
+            Preset::Roads => "roads",
+            Preset::Boundaries => "boundaries",
+            Preset::Parcels => "parcels",
+        }
+    }
+
+    /// Options of this preset (tile units are for extent 4096)
+    pub fn options(self) -> TileOptions {
+        let budget = TileBudget { max_features: None, max_bytes: Some(PRESET_MAX_TILE_BYTES) };
+        let simplification = |tolerance: f64, mode: SimplifyMode| Some(Simplification { tolerance, mode });
+        match self {
+            Preset::PointsDense => TileOptions {
+                point_thinning: Some(PointThinning { cell_size: 32, rank_by: None }),
+                tile_budget: budget,
+                size_strategy: Some(SizeStrategy::DropDensest),
+                ..Default::default()
+            },
+            Preset::Roads => TileOptions {
+                simplification: simplification(8.0, SimplifyMode::Independent),
+                sort_by: Some(SortBy::LengthDescending),
+                tile_budget: budget,
+                size_strategy: Some(SizeStrategy::DropDensest),
+                ..Default::default()
+            },
+            Preset::Boundaries => TileOptions {
+                simplification: simplification(16.0, SimplifyMode::TopologyPreserving),
+                build_topology: true,
+                ..Default::default()
+            },
+            Preset::Parcels => TileOptions {
+                simplification: simplification(4.0, SimplifyMode::TopologyPreserving),
+                build_topology: true,
+                sort_by: Some(SortBy::AreaDescending),
+                tile_budget: budget,
+                size_strategy: Some(SizeStrategy::CoalesceSmallest),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Preset::ALL.into_iter().find(|preset| preset.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+            format!("Unknown preset '{}' (expected one of: {})", name, names.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_names() {
+        for preset in Preset::ALL {
+            assert_eq!(preset.name().parse::<Preset>(), Ok(preset));
+        }
+        assert!("highways".parse::<Preset>().unwrap_err().contains("points-dense"));
+    }
+
+    #[test]
+    fn test_preset_generates_tiles() {
+        let geojson = include_bytes!("../../test_data/points.geojson");
+        for preset in Preset::ALL {
+            let (tiles, _) = crate::generate_tiles_with_options(geojson, 0, 3, "default", &TileOptions::preset(preset)).unwrap();
+            assert!(!tiles.is_empty(), "{} produced no tiles", preset);
+        }
+    }
+}
@@ -1,12 +1,22 @@
 // Coordinate projection module
 // WGS84 (lon/lat) → WebMercator (x/y) conversion
 
+use geo_types::Coord;
 use std::f64::consts::PI;
 
 /// WebMercator projection constants
-const EARTH_RADIUS: f64 = 6378137.0; // Earth radius in meters
+pub(crate) const EARTH_RADIUS: f64 = 6378137.0; // Earth radius in meters
 const ORIGIN_SHIFT: f64 = 2.0 * PI * EARTH_RADIUS / 2.0;
 
+/// Latitude limit of WebMercator (the square world tile)
+pub const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Quantized coordinates are clamped to `±COORDINATE_LIMIT_FACTOR × extent`
+///
+/// Unclipped geometries far from their tile would otherwise saturate `i32` and produce
+/// deltas that overflow in the encoder or break decoders.
+pub const COORDINATE_LIMIT_FACTOR: i32 = 16;
+
 /// Convert lon/lat (WGS84) to WebMercator meters
 pub fn lonlat_to_meters(lon: f64, lat: f64) -> (f64, f64) {
     let mx = lon * ORIGIN_SHIFT / 180.0;
@@ -15,6 +25,14 @@ pub fn lonlat_to_meters(lon: f64, lat: f64) -> (f64, f64) {
     (mx, my)
 }
 
+/// Convert WebMercator meters to lon/lat (WGS84)
+pub fn meters_to_lonlat(mx: f64, my: f64) -> (f64, f64) {
+    let lon = mx / ORIGIN_SHIFT * 180.0;
+    let lat = my / ORIGIN_SHIFT * 180.0;
+    let lat = 180.0 / PI * (2.0 * (lat * PI / 180.0).exp().atan() - PI / 2.0);
+    (lon, lat)
+}
+
 /// Convert WebMercator meters to tile coordinates
 pub fn meters_to_tile(mx: f64, my: f64, zoom: u8) -> (u32, u32) {
     let resolution = get_resolution(zoom);
@@ -62,7 +80,7 @@ pub fn tile_bounds(tx: u32, ty: u32, zoom: u8) -> (f64, f64, f64, f64) {
 
 /// Convert WebMercator meters to pixel coordinates within tile
 pub fn meters_to_pixel_in_tile(mx: f64, my: f64, tx: u32, ty: u32, zoom: u8) -> (f64, f64) {
-    let (tile_min_x, tile_min_y, _, tile_max_y) = tile_bounds(tx, ty, zoom);
+    let (tile_min_x, _, _, tile_max_y) = tile_bounds(tx, ty, zoom);
     let resolution = get_resolution(zoom);
     
     let px = (mx - tile_min_x) / resolution;
@@ -71,8 +89,76 @@ pub fn meters_to_pixel_in_tile(mx: f64, my: f64, tx: u32, ty: u32, zoom: u8) ->
     (px, py)
 }
 
+/// Convert WebMercator meters to world coordinates in tile units (tile `(0, 0)` starts at the origin)
+pub fn meters_to_world(mx: f64, my: f64, zoom: u8, extent: u32) -> (f64, f64) {
+    let scale = extent as f64 / 256.0 / get_resolution(zoom);
+    ((mx + ORIGIN_SHIFT) * scale, (ORIGIN_SHIFT - my) * scale)
+}
+
+/// Convert a slice of lon/lat coordinates to WebMercator meters
+///
+/// Appends to `out` after one reservation. The conversion is `lonlat_to_meters` per coordinate,
+/// not a SIMD kernel: the `tan` and `ln` of each latitude are scalar libm calls, which keep the
+/// loop from vectorizing.
+pub fn lonlat_to_meters_batch(coords: &[Coord<f64>], out: &mut Vec<(f64, f64)>) {
+    out.reserve(coords.len());
+    out.extend(coords.iter().map(|c| lonlat_to_meters(c.x, c.y)));
+}
+
+/// Convert WebMercator meters to integer world coordinates (see `meters_to_world`), floored
+///
+/// Projected once per feature and zoom; `world_to_tile_batch` then places the coordinates in
+/// each tile by integer offset. Non-finite coordinates become `i64::MIN`.
+pub fn meters_to_world_batch(meters: &[(f64, f64)], zoom: u8, extent: u32, out: &mut Vec<(i64, i64)>) {
+    let scale = extent as f64 / 256.0 / get_resolution(zoom);
+    let floor = |v: f64| if v.is_nan() { i64::MIN } else { v.floor() as i64 };
+    out.reserve(meters.len());
+    out.extend(meters.iter().map(|&(mx, my)| (floor((mx + ORIGIN_SHIFT) * scale), floor((ORIGIN_SHIFT - my) * scale))));
+}
+
+/// Translate integer world coordinates into tile `(tx, ty)`, clamping them like `quantize_pixel`
+///
+/// Returns the number of coordinates clamped (a non-finite axis always is, to 0).
+pub fn world_to_tile_batch(world: &[(i64, i64)], tx: u32, ty: u32, extent: i32, out: &mut Vec<(i32, i32)>) -> usize {
+    let limit = (COORDINATE_LIMIT_FACTOR * extent) as i64;
+    let (origin_x, origin_y) = (tx as i64 * extent as i64, ty as i64 * extent as i64);
+    let local = |v: i64, origin: i64| match v {
+        i64::MIN => (0, true),
+        _ => {
+            let v = v.saturating_sub(origin);
+            (v.clamp(-limit, limit) as i32, !(-limit..=limit).contains(&v))
+        }
+    };
+    let mut clamped = 0;
+    
+    out.reserve(world.len());
+    out.extend(world.iter().map(|&(x, y)| {
+        let ((x, x_clamped), (y, y_clamped)) = (local(x, origin_x), local(y, origin_y));
+        clamped += (x_clamped || y_clamped) as usize;
+        (x, y)
+    }));
+    clamped
+}
+
+/// Quantize a pixel position (256 px tile) to tile coordinates, clamping it to `COORDINATE_LIMIT_FACTOR` extents
+///
+/// Positions are floored, as in `meters_to_world_batch`. Also returns whether the position was
+/// clamped (non-finite positions always are).
+pub fn quantize_pixel(px: f64, py: f64, extent: i32) -> ((i32, i32), bool) {
+    let limit = (COORDINATE_LIMIT_FACTOR * extent) as f64;
+    let x = (px / 256.0) * extent as f64;
+    let y = (py / 256.0) * extent as f64;
+    let in_range = |v: f64| (-limit..=limit).contains(&v);
+    if in_range(x) && in_range(y) {
+        return ((x.floor() as i32, y.floor() as i32), false);
+    }
+    
+    let clamp = |v: f64| if v.is_nan() { 0 } else { v.clamp(-limit, limit).floor() as i32 };
+    ((clamp(x), clamp(y)), true)
+}
+
 /// Get resolution (meters/pixel) at specified zoom level
-fn get_resolution(zoom: u8) -> f64 {
+pub fn get_resolution(zoom: u8) -> f64 {
     let initial_resolution = 2.0 * PI * EARTH_RADIUS / 256.0;
     initial_resolution / 2_f64.powi(zoom as i32)
 }
@@ -94,6 +180,44 @@ mod tests {
         assert!(my > 4_200_000.0 && my < 4_300_000.0);
     }
 
+    #[test]
+    fn test_meters_to_lonlat_roundtrip() {
+        let (mx, my) = lonlat_to_meters(139.7671, 35.6812);
+        let (lon, lat) = meters_to_lonlat(mx, my);
+        assert!((lon - 139.7671).abs() < 1e-9);
+        assert!((lat - 35.6812).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_world_to_tile_matches_pixel_in_tile() {
+        let coords = [Coord { x: 139.7671, y: 35.6812 }, Coord { x: -0.1, y: 51.5 }];
+        let mut meters = Vec::new();
+        lonlat_to_meters_batch(&coords, &mut meters);
+        meters.push((f64::NAN, 0.0));
+        let (tx, ty, zoom) = (14552, 6451, 14);
+        
+        let mut world = Vec::new();
+        meters_to_world_batch(&meters, zoom, 4096, &mut world);
+        let mut tile_coords = Vec::new();
+        let clamped = world_to_tile_batch(&world, tx, ty, 4096, &mut tile_coords);
+        
+        let (px, py) = meters_to_pixel_in_tile(meters[0].0, meters[0].1, tx, ty, zoom);
+        // Both paths floor, so they agree exactly
+        assert_eq!(quantize_pixel(px, py, 4096).0, tile_coords[0]);
+        // London lies thousands of tiles away
+        assert_eq!(tile_coords[1], (-65536, -65536));
+        assert_eq!(tile_coords[2].0, 0);
+        assert_eq!(clamped, 2);
+    }
+
+    #[test]
+    fn test_quantize_pixel_clamps() {
+        assert_eq!(quantize_pixel(128.0, 64.0, 4096), ((2048, 1024), false));
+        assert_eq!(quantize_pixel(-0.01, 0.01, 4096), ((-1, 0), false));
+        assert_eq!(quantize_pixel(-1e12, f64::INFINITY, 4096), ((-65536, 65536), true));
+        assert_eq!(quantize_pixel(f64::NAN, 0.0, 4096), ((0, 0), true));
+    }
+
     #[test]
     fn test_lonlat_to_tile() {
         // At zoom level 0, entire world is 1 tile
@@ -102,7 +226,7 @@ mod tests {
         assert_eq!(ty, 0);
         
         // Eastern hemisphere at zoom level 1
-        let (tx, ty) = lonlat_to_tile(90.0, 0.0, 1);
+        let (tx, _ty) = lonlat_to_tile(90.0, 0.0, 1);
         assert_eq!(tx, 1);
     }
 
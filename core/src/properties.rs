@@ -0,0 +1,744 @@
+// Property preprocessing module
+// Adjust features and their properties before tiling
+
+use crate::geojson_parser::{Feature, GeometryType};
+use crate::hash::Fnv1a64;
+use crate::projection::MAX_LATITUDE;
+use crate::Warning;
+use std::collections::HashMap;
+
+/// What to do with string values longer than the configured limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringOverflow {
+    /// Cut the string down to the limit
+    #[default]
+    Truncate,
+    /// Remove the property from the feature
+    Drop,
+}
+
+/// How feature ids are assigned
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum IdStrategy {
+    /// Keep source ids; features without one are numbered per tile
+    #[default]
+    Source,
+    /// Hash the values of these properties (missing properties count as null)
+    HashProperties(Vec<String>),
+    /// Hash the source geometry
+    HashGeometry,
+    /// Use the value of this property (a non-negative integer or numeric string); features
+    /// without one are numbered per tile
+    Property(String),
+}
+
+/// Assign ids according to the strategy
+///
+/// Hashed ids are FNV-1a over the input data, so the same feature gets the same id in
+/// every tile and on every run.
+pub fn assign_ids(features: &mut [Feature], strategy: &IdStrategy) {
+    for feature in features.iter_mut() {
+        let mut hasher = Fnv1a64::default();
+        match strategy {
+            IdStrategy::Source => return,
+            IdStrategy::HashProperties(keys) => {
+                for key in keys {
+                    let value = feature.properties.get(key).unwrap_or(&serde_json::Value::Null);
+                    hasher.write(key.as_bytes());
+                    hasher.write(&[0xff]);
+                    hasher.write(value.to_string().as_bytes());
+                    hasher.write(&[0xff]);
+                }
+            }
+            IdStrategy::HashGeometry => hash_geometry(&feature.geometry, &mut hasher),
+            IdStrategy::Property(key) => {
+                feature.id = feature.properties.get(key).and_then(property_id);
+                continue;
+            }
+        }
+        feature.id = Some(hasher.finish());
+    }
+}
+
+/// Property holding the draw order computed by [`assign_sort_rank`]
+pub const SORT_RANK: &str = "sort_rank";
+
+/// How the `sort_rank` attribute is filled (features are drawn in ascending rank)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortRank {
+    /// Copy a numeric property (numeric strings are parsed; missing values rank 0)
+    Property(String),
+    /// OpenStreetMap-style tags: `layer * 10`, plus 5 for `bridge` and minus 5 for `tunnel`
+    /// (any value but `no` counts)
+    OsmTags,
+}
+
+/// Set the `sort_rank` property of every feature
+///
+/// Road casings and bridges then render in the right order without client-side expressions.
+pub fn assign_sort_rank(features: &mut [Feature], rank: &SortRank) {
+    let number = |value: Option<&serde_json::Value>| match value {
+        Some(serde_json::Value::Number(n)) => n.as_f64(),
+        Some(serde_json::Value::String(s)) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    for feature in features.iter_mut() {
+        let properties = &feature.properties;
+        let value = match rank {
+            SortRank::Property(key) => number(properties.get(key)).unwrap_or(0.0),
+            SortRank::OsmTags => {
+                let tagged = |key: &str| match properties.get(key) {
+                    Some(serde_json::Value::String(s)) => s != "no",
+                    Some(serde_json::Value::Bool(b)) => *b,
+                    Some(serde_json::Value::Null) | None => false,
+                    Some(_) => true,
+                };
+                let mut value = number(properties.get("layer")).unwrap_or(0.0).round() * 10.0;
+                if tagged("bridge") {
+                    value += 5.0;
+                }
+                if tagged("tunnel") {
+                    value -= 5.0;
+                }
+                value
+            }
+        };
+        let value = if value.fract() == 0.0 { serde_json::json!(value as i64) } else { serde_json::json!(value) };
+        feature.properties.insert(SORT_RANK.to_string(), value);
+    }
+}
+
+/// Id value of a property (`42` or `"42"`)
+fn property_id(value: &serde_json::Value) -> Option<u64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Warn about features whose id property is missing or repeats an earlier feature's id
+///
+/// Joins back to the source data need every id to be present and unique in the layer.
+pub fn check_property_ids(features: &[Feature], key: &str, warnings: &mut Vec<Warning>) {
+    let mut seen = std::collections::HashSet::new();
+    let (mut missing, mut duplicates) = (0, 0);
+    for feature in features {
+        match feature.id {
+            Some(id) if !seen.insert(id) => duplicates += 1,
+            Some(_) => {}
+            None => missing += 1,
+        }
+    }
+    if missing > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("{} features have no integer id in property '{}'", missing, key),
+        });
+    }
+    if duplicates > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("{} features repeat an id of property '{}' used by an earlier feature", duplicates, key),
+        });
+    }
+}
+
+fn hash_geometry(geometry: &GeometryType, hasher: &mut Fnv1a64) {
+    let mut write_line = |tag: u8, line: &geo_types::LineString<f64>| {
+        hasher.write(&[tag]);
+        hasher.write(&(line.0.len() as u64).to_le_bytes());
+        for coord in &line.0 {
+            hasher.write(&coord.x.to_le_bytes());
+            hasher.write(&coord.y.to_le_bytes());
+        }
+    };
+    match geometry {
+        GeometryType::Point(point) => write_line(1, &geo_types::LineString::from(vec![point.0])),
+        GeometryType::LineString(line) => write_line(2, line),
+        GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => {
+            for polygon in geometry.polygons() {
+                write_line(3, polygon.exterior());
+                for hole in polygon.interiors() {
+                    write_line(4, hole);
+                }
+            }
+        }
+    }
+}
+
+/// Properties kept or dropped at the zooms `min_zoom..=max_zoom`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyZoomRule {
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub filter: PropertyFilter,
+}
+
+/// Property names to keep or remove
+///
+/// `Include` and `Exclude` compare names exactly, so a name ending in `*` only matches itself;
+/// the `Matching` variants read a trailing `*` as any suffix (e.g. `name:*`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyFilter {
+    /// Keep only these properties
+    Include(Vec<String>),
+    /// Remove these properties
+    Exclude(Vec<String>),
+    /// Keep only properties matching these patterns
+    IncludeMatching(Vec<String>),
+    /// Remove properties matching these patterns
+    ExcludeMatching(Vec<String>),
+}
+
+impl PropertyFilter {
+    /// Whether the filter keeps a property named `key`
+    pub fn keeps(&self, key: &str) -> bool {
+        match self {
+            PropertyFilter::Include(keys) => keys.iter().any(|k| k == key),
+            PropertyFilter::Exclude(keys) => !keys.iter().any(|k| k == key),
+            PropertyFilter::IncludeMatching(patterns) => Self::matches(patterns, key),
+            PropertyFilter::ExcludeMatching(patterns) => !Self::matches(patterns, key),
+        }
+    }
+
+    fn matches(patterns: &[String], key: &str) -> bool {
+        patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => pattern == key,
+        })
+    }
+}
+
+impl PropertyZoomRule {
+    /// Remove `properties` at zooms below `zoom` (e.g. `description` below z10)
+    pub fn exclude_below(zoom: u8, properties: &[&str]) -> Self {
+        PropertyZoomRule {
+            min_zoom: 0,
+            max_zoom: zoom.saturating_sub(1),
+            filter: PropertyFilter::Exclude(properties.iter().map(|p| p.to_string()).collect()),
+        }
+    }
+}
+
+/// Apply every rule covering `zoom`, in order
+pub fn apply_property_zoom_rules(properties: &mut serde_json::Map<String, serde_json::Value>, rules: &[PropertyZoomRule], zoom: u8) {
+    for rule in rules.iter().filter(|rule| (rule.min_zoom..=rule.max_zoom).contains(&zoom)) {
+        properties.retain(|key, _| rule.filter.keeps(key));
+    }
+}
+
+/// Table of attributes merged into features by a key (e.g. statistics per ISO country code)
+#[cfg(feature = "csv")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvJoin {
+    /// CSV text with a header row
+    pub csv: String,
+    pub delimiter: char,
+    /// Column holding the key
+    pub key_column: String,
+    /// Feature property matched against the key column
+    pub property: String,
+    /// Remove features without a matching row
+    pub drop_unmatched: bool,
+}
+
+/// Merge the other columns of the matching CSV row into each feature's properties
+///
+/// Keys are compared as text (numbers by their JSON form), joined values replace existing
+/// properties of the same name, and empty cells are skipped. When a key appears in several
+/// rows, the first row is used.
+#[cfg(feature = "csv")]
+pub fn join_csv(features: &mut Vec<Feature>, join: &CsvJoin, warnings: &mut Vec<Warning>) -> Result<(), String> {
+    let mut records = crate::input::csv_records(&join.csv, join.delimiter);
+    let header: Vec<String> = records.next().ok_or("Join CSV has no header row")?.iter().map(|h| h.trim().to_string()).collect();
+    let key_index = header
+        .iter()
+        .position(|h| h.eq_ignore_ascii_case(&join.key_column))
+        .ok_or_else(|| format!("Join CSV column not found: {}", join.key_column))?;
+    
+    let mut rows: HashMap<String, serde_json::Map<String, serde_json::Value>> = HashMap::new();
+    let mut duplicate_keys = 0;
+    for record in records {
+        let Some(key) = record.get(key_index).map(|k| k.trim().to_string()) else { continue };
+        if rows.contains_key(&key) {
+            duplicate_keys += 1;
+            continue;
+        }
+        let columns = record
+            .iter()
+            .zip(&header)
+            .enumerate()
+            .filter(|(index, (value, _))| *index != key_index && !value.is_empty())
+            .map(|(_, (value, name))| (name.clone(), crate::input::csv_value(value.trim())))
+            .collect();
+        rows.insert(key, columns);
+    }
+    
+    let mut unmatched = 0;
+    features.retain_mut(|feature| {
+        let key = match feature.properties.get(&join.property) {
+            Some(serde_json::Value::String(s)) => Some(s.trim().to_string()),
+            Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        };
+        match key.and_then(|key| rows.get(&key)) {
+            Some(columns) => {
+                feature.properties.extend(columns.clone());
+                true
+            }
+            None => {
+                unmatched += 1;
+                !join.drop_unmatched
+            }
+        }
+    });
+    
+    if duplicate_keys > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("Join CSV has {} rows repeating an earlier '{}' key (first row used)", duplicate_keys, join.key_column),
+        });
+    }
+    if unmatched > 0 {
+        let action = if join.drop_unmatched { "removed" } else { "kept without joined columns" };
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("{} features had no matching '{}' row in the join CSV and were {}", unmatched, join.key_column, action),
+        });
+    }
+    Ok(())
+}
+
+/// Time window on a timestamp property (`[start, end)`, either side open when `None`)
+///
+/// Bounds are ISO 8601 dates or date-times (`2023`, `2023-05`, `2023-05-01`, `2023-05-01T12:00:00+09:00`;
+/// without an offset they are UTC). Numeric property values are read as Unix epoch milliseconds,
+/// as in the USGS earthquake feeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub property: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+/// Keep only features whose timestamp falls in the window (features without a readable timestamp are removed)
+pub fn filter_time_window(features: &mut Vec<Feature>, window: &TimeWindow, warnings: &mut Vec<Warning>) -> Result<(), String> {
+    let bound = |bound: &Option<String>| match bound {
+        Some(text) => parse_timestamp(text).map(Some).ok_or_else(|| format!("Invalid ISO 8601 time: {}", text)),
+        None => Ok(None),
+    };
+    let (start, end) = (bound(&window.start)?, bound(&window.end)?);
+    
+    let (mut outside, mut unreadable) = (0, 0);
+    features.retain(|feature| {
+        let time = match feature.properties.get(&window.property) {
+            Some(serde_json::Value::Number(n)) => n.as_f64().map(|ms| ms / 1000.0),
+            Some(serde_json::Value::String(s)) => parse_timestamp(s),
+            _ => None,
+        };
+        let Some(time) = time else {
+            unreadable += 1;
+            return false;
+        };
+        let inside = start.is_none_or(|start| time >= start) && end.is_none_or(|end| time < end);
+        outside += !inside as usize;
+        inside
+    });
+    
+    if outside > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("Removed {} features outside the time window on '{}'", outside, window.property),
+        });
+    }
+    if unreadable > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("Removed {} features without a readable '{}' timestamp", unreadable, window.property),
+        });
+    }
+    Ok(())
+}
+
+/// Parse an ISO 8601 date or date-time into seconds since the Unix epoch
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    // Everything below slices by byte position
+    if !text.is_ascii() {
+        return None;
+    }
+    let number = |part: &str, digits: usize| -> Option<i64> {
+        (part.len() == digits && part.bytes().all(|b| b.is_ascii_digit())).then(|| part.parse().ok())?
+    };
+    
+    let (date, time) = match text.find(['T', 't', ' ']) {
+        Some(at) => (&text[..at], Some(&text[at + 1..])),
+        None => (text, None),
+    };
+    let mut parts = date.split('-');
+    let year = number(parts.next()?, 4)?;
+    let month = parts.next().map_or(Some(1), |m| number(m, 2))?;
+    let day = parts.next().map_or(Some(1), |d| number(d, 2))?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) as f64 * 86400.0;
+    
+    if let Some(time) = time {
+        // Split off the UTC offset (`Z`, `+09:00`, `-0500`)
+        let (clock, offset) = match time.find(['Z', 'z', '+', '-']) {
+            Some(at) => (&time[..at], &time[at..]),
+            None => (time, ""),
+        };
+        let mut fields = clock.split(':');
+        let hour = number(fields.next()?, 2)?;
+        let minute = number(fields.next()?, 2)?;
+        let second: f64 = match fields.next() {
+            Some(s) if s.len() >= 2 && s[..2].bytes().all(|b| b.is_ascii_digit()) => s.parse().ok()?,
+            Some(_) => return None,
+            None => 0.0,
+        };
+        if fields.next().is_some() || hour > 24 || minute > 59 || second >= 61.0 {
+            return None;
+        }
+        seconds += (hour * 3600 + minute * 60) as f64 + second;
+        
+        if !matches!(offset, "" | "Z" | "z") {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let digits = offset[1..].replace(':', "");
+            let offset_minutes = number(&digits[..2.min(digits.len())], 2)? * 60 + number(&digits[2..], 2)?;
+            seconds -= (sign * offset_minutes * 60) as f64;
+        }
+    }
+    Some(seconds)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Remove features that cannot be projected: any coordinate that is non-finite or outside
+/// ±180°/±90°, or every coordinate beyond the WebMercator latitude limit (polar features)
+pub fn remove_unprojectable(features: &mut Vec<Feature>, warnings: &mut Vec<Warning>) {
+    let mut index = 0;
+    features.retain(|feature| {
+        let feature_index = index;
+        index += 1;
+        
+        let coords: Vec<geo_types::Coord<f64>> = match &feature.geometry {
+            GeometryType::Point(point) => vec![point.0],
+            GeometryType::LineString(line) => line.0.clone(),
+            GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => feature
+                .geometry
+                .polygons()
+                .iter()
+                .flat_map(|polygon| std::iter::once(polygon.exterior()).chain(polygon.interiors()))
+                .flat_map(|ring| ring.0.iter().copied())
+                .collect(),
+        };
+        let invalid = coords.iter().any(|c| !c.x.is_finite() || !c.y.is_finite() || c.x.abs() > 180.0 || c.y.abs() > 90.0);
+        let polar = coords.iter().all(|c| c.y.abs() > MAX_LATITUDE);
+        let reason = match (invalid, polar) {
+            (true, _) => "it has invalid coordinates",
+            (false, true) => "it lies entirely beyond the WebMercator latitude limit",
+            (false, false) => return true,
+        };
+        warnings.push(Warning {
+            feature_index: Some(feature_index),
+            message: format!("Feature removed: {}", reason),
+        });
+        false
+    });
+}
+
+/// Remove exact duplicates (same geometry, properties and id), keeping the first occurrence
+pub fn remove_duplicates(features: &mut Vec<Feature>, warnings: &mut Vec<Warning>) {
+    // Features are bucketed by hash, then compared in full so collisions never drop anything
+    let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut keep = vec![true; features.len()];
+    for index in 0..features.len() {
+        let mut hasher = Fnv1a64::default();
+        hash_geometry(&features[index].geometry, &mut hasher);
+        hasher.write(serde_json::Value::Object(features[index].properties.clone()).to_string().as_bytes());
+        
+        let bucket = buckets.entry(hasher.finish()).or_default();
+        if bucket.iter().any(|&kept| features[kept] == features[index]) {
+            keep[index] = false;
+        } else {
+            bucket.push(index);
+        }
+    }
+    
+    let before = features.len();
+    let mut flags = keep.into_iter();
+    features.retain(|_| flags.next().unwrap_or(true));
+    let removed = before - features.len();
+    if removed > 0 {
+        warnings.push(Warning {
+            feature_index: None,
+            message: format!("Removed {} duplicate features", removed),
+        });
+    }
+}
+
+/// Enforce a maximum string length (in characters) on all string properties
+pub fn limit_string_lengths(
+    features: &mut [Feature],
+    max_length: usize,
+    overflow: StringOverflow,
+    warnings: &mut Vec<Warning>,
+) {
+    for (index, feature) in features.iter_mut().enumerate() {
+        let overlong: Vec<String> = feature.properties
+            .iter()
+            .filter(|(_, value)| matches!(value, serde_json::Value::String(s) if s.chars().count() > max_length))
+            .map(|(key, _)| key.clone())
+            .collect();
+        
+        if overlong.is_empty() {
+            continue;
+        }
+        
+        for key in &overlong {
+            match overflow {
+                StringOverflow::Truncate => {
+                    if let Some(serde_json::Value::String(s)) = feature.properties.get_mut(key) {
+                        // Cut on a char boundary so multi-byte text stays valid UTF-8
+                        let end = s.char_indices().nth(max_length).map(|(i, _)| i).unwrap_or(s.len());
+                        s.truncate(end);
+                    }
+                }
+                StringOverflow::Drop => {
+                    feature.properties.shift_remove(key);
+                }
+            }
+        }
+        
+        let action = match overflow {
+            StringOverflow::Truncate => "truncated",
+            StringOverflow::Drop => "dropped",
+        };
+        warnings.push(Warning {
+            feature_index: Some(index),
+            message: format!(
+                "String properties longer than {} characters {}: {}",
+                max_length,
+                action,
+                overlong.join(", ")
+            ),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point;
+
+    fn feature_with_description(description: &str) -> Feature {
+        let mut properties = serde_json::Map::new();
+        properties.insert("description".to_string(), serde_json::json!(description));
+        properties.insert("name".to_string(), serde_json::json!("ok"));
+        Feature::new(GeometryType::Point(Point::new(0.0, 0.0)), properties)
+    }
+
+    #[test]
+    fn test_truncate_long_strings() {
+        let mut features = vec![feature_with_description("東京都千代田区"), feature_with_description("ab")];
+        let mut warnings = Vec::new();
+        
+        limit_string_lengths(&mut features, 3, StringOverflow::Truncate, &mut warnings);
+        
+        assert_eq!(features[0].properties["description"], "東京都");
+        assert_eq!(features[1].properties["description"], "ab");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].feature_index, Some(0));
+    }
+
+    #[test]
+    fn test_drop_long_strings() {
+        let mut features = vec![feature_with_description("too long")];
+        let mut warnings = Vec::new();
+        
+        limit_string_lengths(&mut features, 3, StringOverflow::Drop, &mut warnings);
+        
+        assert!(!features[0].properties.contains_key("description"));
+        assert!(features[0].properties.contains_key("name"));
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_hashed_ids_are_stable() {
+        let mut features = vec![feature_with_description("a"), feature_with_description("b"), feature_with_description("a")];
+        
+        assign_ids(&mut features, &IdStrategy::HashProperties(vec!["description".to_string()]));
+        assert_eq!(features[0].id, features[2].id);
+        assert_ne!(features[0].id, features[1].id);
+        
+        assign_ids(&mut features, &IdStrategy::HashGeometry);
+        assert!(features[0].id.is_some());
+        assert_eq!(features[0].id, features[1].id);
+    }
+
+    #[test]
+    fn test_remove_duplicates() {
+        let mut features = vec![feature_with_description("a"), feature_with_description("b"), feature_with_description("a")];
+        let mut warnings = Vec::new();
+        
+        remove_duplicates(&mut features, &mut warnings);
+        
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[1].properties["description"], "b");
+        assert_eq!(warnings[0].message, "Removed 1 duplicate features");
+    }
+
+    #[test]
+    fn test_remove_unprojectable() {
+        let point = |lon: f64, lat: f64| Feature::new(GeometryType::Point(Point::new(lon, lat)), serde_json::Map::new());
+        let mut features = vec![point(139.7, 35.7), point(0.0, 89.0), point(f64::NAN, 0.0), point(200.0, 0.0)];
+        let mut warnings = Vec::new();
+        
+        remove_unprojectable(&mut features, &mut warnings);
+        
+        assert_eq!(features.len(), 1);
+        assert_eq!(warnings.iter().map(|w| w.feature_index).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01"), Some(0.0));
+        assert_eq!(parse_timestamp("2023"), Some(1672531200.0));
+        assert_eq!(parse_timestamp("2023-03-01T09:30:00+09:00"), Some(1677630600.0));
+        assert_eq!(parse_timestamp("2023-03-01T00:30:00.5Z"), Some(1677630600.5));
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59Z"), Some(-1.0));
+        assert_eq!(parse_timestamp("not a date"), None);
+        assert_eq!(parse_timestamp("2023-01-01T00:00:1é"), None);
+        assert_eq!(parse_timestamp("2023-01-01T00:00:00+é"), None);
+        assert_eq!(parse_timestamp("2023-13-01"), None);
+    }
+
+    #[test]
+    fn test_filter_time_window() {
+        let quake = |time: serde_json::Value| {
+            let mut feature = feature_with_description("quake");
+            feature.properties.insert("time".to_string(), time);
+            feature
+        };
+        let mut features = vec![
+            quake(serde_json::json!("2022-12-31T23:59:59Z")),
+            quake(serde_json::json!("2023-06-01")),
+            // Epoch milliseconds (2023-03-01T00:30:00Z)
+            quake(serde_json::json!(1677630600000u64)),
+            quake(serde_json::json!("2024-01-01")),
+            quake(serde_json::json!(null)),
+        ];
+        let window = TimeWindow {
+            property: "time".to_string(),
+            start: Some("2023".to_string()),
+            end: Some("2024".to_string()),
+        };
+        let mut warnings = Vec::new();
+        
+        filter_time_window(&mut features, &window, &mut warnings).unwrap();
+        
+        assert_eq!(features.len(), 2);
+        assert_eq!(warnings.len(), 2);
+        assert!(filter_time_window(&mut features, &TimeWindow { start: Some("soon".to_string()), ..window }, &mut warnings).is_err());
+    }
+
+    #[test]
+    fn test_property_zoom_rules() {
+        let rules = vec![
+            PropertyZoomRule::exclude_below(10, &["description", "wikidata"]),
+            PropertyZoomRule { min_zoom: 0, max_zoom: 4, filter: PropertyFilter::Include(vec!["name".to_string()]) },
+        ];
+        let properties = serde_json::json!({"name": "Tokyo", "population": 13960000, "description": "Capital", "wikidata": "Q1490"});
+        let properties_at = |zoom: u8| {
+            let mut properties = properties.as_object().unwrap().clone();
+            apply_property_zoom_rules(&mut properties, &rules, zoom);
+            properties.keys().cloned().collect::<Vec<_>>()
+        };
+        
+        assert_eq!(properties_at(2), ["name"]);
+        assert_eq!(properties_at(9), ["name", "population"]);
+        assert_eq!(properties_at(10).len(), 4);
+    }
+
+    #[test]
+    fn test_property_filter_wildcards() {
+        let names = PropertyFilter::Include(vec!["name:*".to_string()]);
+        let matching = PropertyFilter::IncludeMatching(vec!["name:*".to_string()]);
+        
+        // Exact filters treat `*` as part of the name
+        assert!(!names.keeps("name:en") && names.keeps("name:*"));
+        assert!(matching.keeps("name:en") && matching.keeps("name:") && !matching.keeps("name"));
+        assert!(PropertyFilter::Exclude(vec!["wiki*".to_string()]).keeps("wikidata"));
+        assert!(!PropertyFilter::ExcludeMatching(vec!["wiki*".to_string()]).keeps("wikidata"));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_join_csv() {
+        let country = |code: serde_json::Value| {
+            let mut feature = feature_with_description("country");
+            feature.properties.insert("iso_a3".to_string(), code);
+            feature
+        };
+        let mut features = vec![country(serde_json::json!("JPN")), country(serde_json::json!("FRA")), country(serde_json::json!(null))];
+        let join = CsvJoin {
+            csv: "ISO,population,capital\nJPN,124500000,Tokyo\nFRA,68200000,\nJPN,1,Duplicate\n".to_string(),
+            delimiter: ',',
+            key_column: "iso".to_string(),
+            property: "iso_a3".to_string(),
+            drop_unmatched: true,
+        };
+        let mut warnings = Vec::new();
+        
+        join_csv(&mut features, &join, &mut warnings).unwrap();
+        
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].properties["population"], 124500000);
+        assert_eq!(features[0].properties["capital"], "Tokyo");
+        assert!(!features[1].properties.contains_key("capital"));
+        assert_eq!(warnings.len(), 2);
+        assert!(join_csv(&mut features, &CsvJoin { key_column: "code".to_string(), ..join }, &mut warnings).is_err());
+    }
+
+    #[test]
+    fn test_assign_sort_rank() {
+        let road = |properties: serde_json::Value| Feature::new(GeometryType::Point(Point::new(0.0, 0.0)), properties.as_object().unwrap().clone());
+        let mut features = vec![
+            road(serde_json::json!({"highway": "primary"})),
+            road(serde_json::json!({"bridge": "yes", "layer": "1"})),
+            road(serde_json::json!({"tunnel": "yes", "layer": -1})),
+            road(serde_json::json!({"bridge": "no", "z": 2.5})),
+        ];
+        let ranks = |features: &[Feature]| features.iter().map(|f| f.properties[SORT_RANK].clone()).collect::<Vec<_>>();
+        
+        assign_sort_rank(&mut features, &SortRank::OsmTags);
+        assert_eq!(ranks(&features), [serde_json::json!(0), serde_json::json!(15), serde_json::json!(-15), serde_json::json!(0)]);
+        assign_sort_rank(&mut features, &SortRank::Property("z".to_string()));
+        assert_eq!(ranks(&features)[3], serde_json::json!(2.5));
+    }
+
+    #[test]
+    fn test_property_ids() {
+        let mut features: Vec<Feature> = ["7", "8", "x", "7"].iter().map(|id| feature_with_description(id)).collect();
+        
+        let mut warnings = Vec::new();
+        assign_ids(&mut features, &IdStrategy::Property("description".to_string()));
+        check_property_ids(&features, "description", &mut warnings);
+        
+        let ids: Vec<Option<u64>> = features.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [Some(7), Some(8), None, Some(7)]);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[1].message.starts_with("1 features repeat"));
+    }
+}
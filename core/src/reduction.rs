@@ -0,0 +1,435 @@
+// Feature reduction module
+// Thin out crowded tiles before encoding
+
+use crate::tiler::{compare_rank, geometry_area, TileFeature, TileGeometry};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Grid-based point thinning settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointThinning {
+    /// Cell size in tile units (at most one point is kept per cell)
+    pub cell_size: i32,
+    /// Property used to choose the surviving point in a cell (first point when `None`)
+    pub rank_by: Option<String>,
+}
+
+/// Keep at most one point per grid cell (non-point features are left untouched)
+pub fn thin_points(features: &mut Vec<TileFeature>, thinning: &PointThinning) {
+    let cell_size = thinning.cell_size.max(1);
+    let mut winners: HashMap<(i32, i32), usize> = HashMap::new();
+    
+    for (index, feature) in features.iter().enumerate() {
+        let TileGeometry::Point(x, y) = feature.geometry else {
+            continue;
+        };
+        let cell = (x.div_euclid(cell_size), y.div_euclid(cell_size));
+        
+        match winners.get(&cell) {
+            None => {
+                winners.insert(cell, index);
+            }
+            Some(&current) => {
+                if let Some(key) = &thinning.rank_by {
+                    let better = compare_rank(
+                        feature.properties.get(key),
+                        features[current].properties.get(key),
+                    ) == Ordering::Less;
+                    if better {
+                        winners.insert(cell, index);
+                    }
+                }
+            }
+        }
+    }
+    
+    let mut keep = vec![true; features.len()];
+    for (index, feature) in features.iter().enumerate() {
+        if matches!(feature.geometry, TileGeometry::Point(_, _)) {
+            keep[index] = false;
+        }
+    }
+    for &index in winners.values() {
+        keep[index] = true;
+    }
+    
+    let mut index = 0;
+    features.retain(|_| {
+        let kept = keep[index];
+        index += 1;
+        kept
+    });
+}
+
+/// Tiny-polygon accumulation ("pixel dust")
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TinyPolygons {
+    /// Polygons smaller than this (square tile units) are accumulated instead of drawn
+    pub min_area: f64,
+    /// Grid cell size in tile units; dust is collected separately per cell
+    pub cell_size: i32,
+}
+
+/// Replace polygons smaller than `min_area` with occasional squares of that area
+///
+/// Each tiny polygon adds its area to its grid cell. Whenever a cell has collected
+/// `min_area`, the polygon that filled it is drawn as a square of that area (keeping its
+/// properties), so aggregate coverage still reads correctly; leftover dust is dropped.
+pub fn accumulate_tiny_polygons(features: &mut Vec<TileFeature>, tiny: &TinyPolygons) {
+    let cell_size = tiny.cell_size.max(1);
+    let side = tiny.min_area.sqrt().round().max(1.0) as i32;
+    let mut dust: HashMap<(i32, i32), f64> = HashMap::new();
+    
+    features.retain_mut(|feature| {
+        if !matches!(feature.geometry, TileGeometry::Polygon(_)) {
+            return true;
+        }
+        let area = geometry_area(&feature.geometry);
+        if area >= tiny.min_area {
+            return true;
+        }
+        
+        let (x, y) = representative_point(&feature.geometry);
+        let collected = dust.entry((x.div_euclid(cell_size), y.div_euclid(cell_size))).or_insert(0.0);
+        *collected += area;
+        if *collected < tiny.min_area {
+            return false;
+        }
+        *collected -= tiny.min_area;
+        let (x0, y0) = (x - side / 2, y - side / 2);
+        let (x1, y1) = (x0 + side, y0 + side);
+        feature.geometry = TileGeometry::Polygon(vec![vec![(x0, y0), (x1, y0), (x1, y1), (x0, y1), (x0, y0)]]);
+        true
+    });
+}
+
+/// Per-tile size budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TileBudget {
+    /// Maximum number of features in a tile
+    pub max_features: Option<usize>,
+    /// Maximum encoded tile size in bytes
+    pub max_bytes: Option<usize>,
+}
+
+/// How over-budget tiles are reduced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeStrategy {
+    /// Drop features from the densest areas of the tile first
+    DropDensest,
+    /// Merge the smallest polygons into neighbors instead of dropping them
+    CoalesceSmallest,
+}
+
+/// Grid cells per tile side used to estimate local density
+const DENSITY_GRID: i32 = 16;
+
+/// Reduce features until the tile fits the budget
+///
+/// `extent` is the tile extent of the features (the density grid is laid over it). Returns the
+/// encoded tile and the number of features removed.
+pub fn fit_to_budget<F>(
+    features: &mut Vec<TileFeature>,
+    budget: &TileBudget,
+    strategy: SizeStrategy,
+    extent: u32,
+    encode: F,
+) -> Result<(Vec<u8>, usize), String>
+where
+    F: Fn(&[TileFeature]) -> Result<Vec<u8>, String>,
+{
+    let original_count = features.len();
+    let reduce = |features: &mut Vec<TileFeature>, target: usize| match strategy {
+        SizeStrategy::DropDensest => drop_densest(features, target, extent),
+        SizeStrategy::CoalesceSmallest => coalesce_smallest(features, target, extent),
+    };
+    
+    if let Some(max_features) = budget.max_features {
+        reduce(features, max_features.max(1));
+    }
+    
+    let mut data = encode(features)?;
+    if let Some(max_bytes) = budget.max_bytes {
+        while data.len() > max_bytes && features.len() > 1 {
+            // Shrink proportionally to the overshoot, always removing at least one feature
+            let ratio = max_bytes as f64 / data.len() as f64;
+            let target = ((features.len() as f64 * ratio * 0.9) as usize)
+                .min(features.len() - 1)
+                .max(1);
+            reduce(features, target);
+            data = encode(features)?;
+        }
+    }
+    
+    Ok((data, original_count - features.len()))
+}
+
+/// Drop features from the most crowded grid cells until `target` features remain
+fn drop_densest(features: &mut Vec<TileFeature>, target: usize, extent: u32) {
+    if features.len() <= target {
+        return;
+    }
+    
+    // Spatial index: feature indices bucketed by grid cell, in input order
+    let cell_size = (extent.min(i32::MAX as u32) as i32 / DENSITY_GRID).max(1);
+    let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (index, feature) in features.iter().enumerate() {
+        let (x, y) = representative_point(&feature.geometry);
+        cells.entry((x.div_euclid(cell_size), y.div_euclid(cell_size))).or_default().push(index);
+    }
+    
+    let mut keep = vec![true; features.len()];
+    for _ in 0..features.len() - target {
+        // Densest cell first; ties go to the cell listed first for a stable result
+        let densest = cells
+            .iter_mut()
+            .filter(|(_, members)| !members.is_empty())
+            .max_by(|(a_cell, a), (b_cell, b)| a.len().cmp(&b.len()).then(b_cell.cmp(a_cell)));
+        
+        match densest {
+            Some((_, members)) => {
+                let index = members.pop().expect("non-empty cell");
+                keep[index] = false;
+            }
+            None => break,
+        }
+    }
+    
+    let mut index = 0;
+    features.retain(|_| {
+        let kept = keep[index];
+        index += 1;
+        kept
+    });
+}
+
+/// Merge the smallest polygons into neighbors until `target` features remain
+///
+/// A polygon is merged into the nearest polygon with identical properties; polygons
+/// without such a neighbor are collected into a single property-less "other" feature.
+/// If there are not enough polygons to reach the target, the densest features are dropped.
+fn coalesce_smallest(features: &mut Vec<TileFeature>, target: usize, extent: u32) {
+    let mut other: Option<usize> = None;
+    
+    while features.len() > target {
+        let smallest = features
+            .iter()
+            .enumerate()
+            .filter(|(index, f)| Some(*index) != other && matches!(f.geometry, TileGeometry::Polygon(_)))
+            .min_by(|(_, a), (_, b)| geometry_area(&a.geometry).total_cmp(&geometry_area(&b.geometry)))
+            .map(|(index, _)| index);
+        let Some(smallest) = smallest else {
+            break;
+        };
+        
+        let origin = representative_point(&features[smallest].geometry);
+        let partner = features
+            .iter()
+            .enumerate()
+            .filter(|(index, f)| {
+                *index != smallest
+                    && matches!(f.geometry, TileGeometry::Polygon(_))
+                    && f.properties == features[smallest].properties
+            })
+            .min_by_key(|(_, f)| squared_distance(origin, representative_point(&f.geometry)))
+            .map(|(index, _)| index)
+            .or(other);
+        
+        match partner {
+            Some(partner) => {
+                let removed = features.remove(smallest);
+                let partner = if partner > smallest { partner - 1 } else { partner };
+                if let Some(index) = other {
+                    if index > smallest {
+                        other = Some(index - 1);
+                    }
+                }
+                if let (TileGeometry::Polygon(rings), TileGeometry::Polygon(extra)) =
+                    (&mut features[partner].geometry, removed.geometry)
+                {
+                    rings.extend(extra);
+                }
+            }
+            None => {
+                // First unmatched polygon becomes the "other" feature
+                features[smallest].properties.clear();
+                other = Some(smallest);
+            }
+        }
+    }
+    
+    drop_densest(features, target, extent);
+}
+
+fn squared_distance(a: (i32, i32), b: (i32, i32)) -> i64 {
+    let dx = a.0 as i64 - b.0 as i64;
+    let dy = a.1 as i64 - b.1 as i64;
+    dx * dx + dy * dy
+}
+
+/// Representative point of a geometry (bounding box center)
+fn representative_point(geometry: &TileGeometry) -> (i32, i32) {
+    let coords: &[(i32, i32)] = match geometry {
+        TileGeometry::Point(x, y) => return (*x, *y),
+        TileGeometry::LineString(coords) => coords,
+        TileGeometry::Polygon(rings) => rings.first().map(|r| r.as_slice()).unwrap_or(&[]),
+    };
+    if coords.is_empty() {
+        return (0, 0);
+    }
+    
+    let mut min = (i32::MAX, i32::MAX);
+    let mut max = (i32::MIN, i32::MIN);
+    for &(x, y) in coords {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    (
+        ((min.0 as i64 + max.0 as i64) / 2) as i32,
+        ((min.1 as i64 + max.1 as i64) / 2) as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i32, y: i32, rank: i64) -> TileFeature {
+        let mut properties = serde_json::Map::new();
+        properties.insert("rank".to_string(), serde_json::json!(rank));
+        TileFeature {
+            geometry: TileGeometry::Point(x, y),
+            properties,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_thin_points_one_per_cell() {
+        let mut features = vec![point(1, 1, 1), point(5, 5, 2), point(20, 20, 3)];
+        let thinning = PointThinning { cell_size: 16, rank_by: None };
+        
+        thin_points(&mut features, &thinning);
+        
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].properties["rank"], 1);
+    }
+
+    #[test]
+    fn test_thin_points_keeps_highest_ranked() {
+        let mut features = vec![point(1, 1, 1), point(5, 5, 2), point(20, 20, 3)];
+        let thinning = PointThinning { cell_size: 16, rank_by: Some("rank".to_string()) };
+        
+        thin_points(&mut features, &thinning);
+        
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].properties["rank"], 2);
+    }
+
+    #[test]
+    fn test_drop_densest_keeps_sparse_points() {
+        // Ten points crowded in one corner, one isolated point far away
+        let mut features: Vec<TileFeature> = (0..10).map(|i| point(10 + i, 10, i as i64)).collect();
+        features.push(point(4000, 4000, 99));
+        let budget = TileBudget { max_features: Some(5), max_bytes: None };
+        
+        let (_, dropped) = fit_to_budget(&mut features, &budget, SizeStrategy::DropDensest, 4096, |f| {
+            Ok(vec![0; f.len()])
+        }).unwrap();
+        
+        assert_eq!(dropped, 6);
+        assert_eq!(features.len(), 5);
+        assert!(features.iter().any(|f| f.properties["rank"] == 99));
+    }
+
+    #[test]
+    fn test_drop_densest_at_layer_extent() {
+        // In a 256-unit tile the corner points and the point at (250, 250) are in separate
+        // cells; over a 4096-unit grid they would share the first cell
+        let mut features: Vec<TileFeature> = (0..10).map(|i| point(10 + i, 10, i as i64)).collect();
+        features.push(point(250, 250, 99));
+        let budget = TileBudget { max_features: Some(5), max_bytes: None };
+        
+        fit_to_budget(&mut features, &budget, SizeStrategy::DropDensest, 256, |f| Ok(vec![0; f.len()])).unwrap();
+        
+        assert_eq!(features.len(), 5);
+        assert!(features.iter().any(|f| f.properties["rank"] == 99));
+    }
+
+    #[test]
+    fn test_fit_to_byte_budget() {
+        let mut features: Vec<TileFeature> = (0..100).map(|i| point(i * 40, i * 40, i as i64)).collect();
+        let budget = TileBudget { max_features: None, max_bytes: Some(500) };
+        
+        let (data, dropped) = fit_to_budget(&mut features, &budget, SizeStrategy::DropDensest, 4096, |f| {
+            Ok(vec![0; f.len() * 10])
+        }).unwrap();
+        
+        assert!(data.len() <= 500);
+        assert_eq!(features.len() + dropped, 100);
+    }
+
+    fn square(x: i32, size: i32, class: &str) -> TileFeature {
+        let mut properties = serde_json::Map::new();
+        properties.insert("class".to_string(), serde_json::json!(class));
+        TileFeature {
+            geometry: TileGeometry::Polygon(vec![vec![(x, 0), (x + size, 0), (x + size, size), (x, size), (x, 0)]]),
+            properties,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_coalesce_smallest_merges_matching_neighbors() {
+        let mut features = vec![
+            square(0, 100, "forest"),
+            square(200, 10, "forest"),
+            square(400, 50, "water"),
+        ];
+        let budget = TileBudget { max_features: Some(2), max_bytes: None };
+        
+        let (_, removed) = fit_to_budget(&mut features, &budget, SizeStrategy::CoalesceSmallest, 4096, |f| {
+            Ok(vec![0; f.len()])
+        }).unwrap();
+        
+        assert_eq!(removed, 1);
+        assert_eq!(features.len(), 2);
+        match &features[0].geometry {
+            TileGeometry::Polygon(rings) => assert_eq!(rings.len(), 2),
+            _ => panic!("Expected Polygon geometry"),
+        }
+    }
+
+    #[test]
+    fn test_coalesce_smallest_into_other_feature() {
+        let mut features = vec![
+            square(0, 100, "forest"),
+            square(200, 10, "sand"),
+            square(400, 20, "rock"),
+        ];
+        let budget = TileBudget { max_features: Some(2), max_bytes: None };
+        
+        fit_to_budget(&mut features, &budget, SizeStrategy::CoalesceSmallest, 4096, |f| {
+            Ok(vec![0; f.len()])
+        }).unwrap();
+        
+        assert_eq!(features.len(), 2);
+        assert!(features.iter().any(|f| f.properties.is_empty()));
+    }
+
+    #[test]
+    fn test_accumulate_tiny_polygons() {
+        let square = |x: i32, size: i32| TileFeature {
+            geometry: TileGeometry::Polygon(vec![vec![(x, 0), (x + size, 0), (x + size, size), (x, size), (x, 0)]]),
+            properties: serde_json::Map::new(),
+            id: None,
+        };
+        // Four 1x1 polygons in one cell add up to one 2x2 square; the large polygon is kept as is
+        let mut features = vec![square(0, 1), square(2, 1), square(4, 1), square(6, 1), square(100, 10)];
+        accumulate_tiny_polygons(&mut features, &TinyPolygons { min_area: 4.0, cell_size: 64 });
+        
+        assert_eq!(features.len(), 2);
+        assert_eq!(geometry_area(&features[0].geometry), 4.0);
+        assert_eq!(geometry_area(&features[1].geometry), 100.0);
+    }
+}
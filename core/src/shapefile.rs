@@ -0,0 +1,267 @@
+// Shapefile module
+// ESRI Shapefile (.shp geometry + .dbf attributes) parsing into features
+
+use crate::geojson_parser::{assign_holes, polygon_geometry, Feature, GeometryType};
+use crate::logging::log_warn;
+use geo_types::{Coord, LineString, Point};
+use serde_json::{Map, Value};
+
+/// Parse a Shapefile into features (coordinates must be WGS84 lon/lat)
+///
+/// Multi-part lines and points become one feature per part, and polygons one feature with holes
+/// assigned to the outer ring containing them. Null shapes and deleted records are skipped, and
+/// Z/M values are dropped.
+pub fn parse_shapefile(shp: &[u8], dbf: Option<&[u8]>) -> Result<Vec<Feature>, String> {
+    let records = match dbf {
+        Some(dbf) => parse_dbf(dbf)?,
+        None => Vec::new(),
+    };
+    
+    let i32_be = |at: usize| shp.get(at..at + 4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    if i32_be(0) != Some(9994) || shp.len() < 100 {
+        return Err("Not a Shapefile (.shp)".to_string());
+    }
+    
+    let mut features = Vec::new();
+    let mut offset = 100;
+    let mut index = 0;
+    while offset + 8 <= shp.len() {
+        let length = i32_be(offset + 4).unwrap_or(0).max(0) as usize * 2;
+        let content = shp
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| format!("Shapefile record {} is truncated", index + 1))?;
+        offset += 8 + length;
+        
+        let properties = match records.get(index) {
+            Some(Some(properties)) => properties.clone(),
+            // Deleted in the .dbf
+            Some(None) => {
+                index += 1;
+                continue;
+            }
+            None => Map::new(),
+        };
+        index += 1;
+        
+        for geometry in parse_shape(content)? {
+            features.push(Feature::new(geometry, properties.clone()));
+        }
+    }
+    
+    Ok(features)
+}
+
+/// Reject Shapefiles whose `.prj` declares a projected coordinate system
+pub fn check_projection(prj: &str) -> Result<(), String> {
+    if prj.trim_start().starts_with("PROJCS") {
+        return Err("Projected Shapefiles are not supported; reproject to WGS84 (EPSG:4326)".to_string());
+    }
+    Ok(())
+}
+
+fn parse_shape(content: &[u8]) -> Result<Vec<GeometryType>, String> {
+    let f64_at = |at: usize| content.get(at..at + 8).map(|b| f64::from_le_bytes(b.try_into().unwrap()));
+    let i32_at = |at: usize| content.get(at..at + 4).map(|b| i32::from_le_bytes(b.try_into().unwrap()));
+    let truncated = || "Shapefile record is truncated".to_string();
+    let coord_at = |at: usize| -> Result<Coord<f64>, String> {
+        Ok(Coord { x: f64_at(at).ok_or_else(truncated)?, y: f64_at(at + 8).ok_or_else(truncated)? })
+    };
+    
+    // Z and M variants (11-28) share the 2D layout of their base type, with the extra values at the end
+    let shape_type = i32_at(0).ok_or_else(truncated)?;
+    match shape_type % 10 {
+        0 => Ok(Vec::new()),
+        1 => Ok(vec![GeometryType::Point(Point(coord_at(4)?))]),
+        8 => {
+            let count = i32_at(36).ok_or_else(truncated)?.max(0) as usize;
+            if count > content.len().saturating_sub(40) / 16 {
+                return Err(truncated());
+            }
+            (0..count).map(|i| Ok(GeometryType::Point(Point(coord_at(40 + i * 16)?)))).collect()
+        }
+        3 | 5 => {
+            let part_count = i32_at(36).ok_or_else(truncated)?.max(0) as usize;
+            let point_count = i32_at(40).ok_or_else(truncated)?.max(0) as usize;
+            // The header counts must fit in the record before anything is allocated for them
+            if part_count > content.len().saturating_sub(44) / 4 {
+                return Err(truncated());
+            }
+            let points_start = 44 + part_count * 4;
+            if point_count > (content.len() - points_start) / 16 {
+                return Err(truncated());
+            }
+            let mut parts = Vec::with_capacity(part_count);
+            for part in 0..part_count {
+                let start = i32_at(44 + part * 4).ok_or_else(truncated)?.max(0) as usize;
+                let end = match part + 1 < part_count {
+                    true => i32_at(48 + part * 4).ok_or_else(truncated)?.max(0) as usize,
+                    false => point_count,
+                };
+                let coords = (start..end.max(start))
+                    .map(|i| coord_at(points_start + i * 16))
+                    .collect::<Result<Vec<_>, String>>()?;
+                parts.push(LineString(coords));
+            }
+            
+            if shape_type % 10 == 3 {
+                return Ok(parts.into_iter().filter(|p| p.0.len() >= 2).map(GeometryType::LineString).collect());
+            }
+            
+            // Outer rings are clockwise and holes counter-clockwise; both are reversed
+            // to the GeoJSON (RFC 7946) orientation. Parts need not list holes after their
+            // outer ring, so holes are matched by containment.
+            let rings = parts
+                .into_iter()
+                .filter(|r| r.0.len() >= 4)
+                .map(|mut ring| {
+                    ring.0.reverse();
+                    ring
+                })
+                .collect::<Vec<_>>();
+            match rings.is_empty() {
+                true => Ok(Vec::new()),
+                false => Ok(vec![polygon_geometry(assign_holes(rings))]),
+            }
+        }
+        _ => {
+            log_warn!("Skipping unsupported Shapefile shape type {}", shape_type);
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// dBASE records (`None` for deleted records)
+///
+/// Text is read as UTF-8, falling back to Latin-1 for values that are not valid UTF-8.
+fn parse_dbf(dbf: &[u8]) -> Result<Vec<Option<Map<String, Value>>>, String> {
+    let truncated = || "dBASE file (.dbf) is truncated".to_string();
+    let u16_at = |at: usize| dbf.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+    let record_count = dbf.get(4..8).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize).ok_or_else(truncated)?;
+    let header_length = u16_at(8).ok_or_else(truncated)?;
+    let record_length = u16_at(10).ok_or_else(truncated)?;
+    
+    // Field descriptors: 32 bytes each, terminated by 0x0D
+    let mut fields = Vec::new();
+    let mut at = 32;
+    while at + 32 <= header_length && dbf.get(at) != Some(&0x0D) {
+        let descriptor = &dbf[at..at + 32];
+        let name_end = descriptor[..11].iter().position(|&b| b == 0).unwrap_or(11);
+        let name = decode_text(&descriptor[..name_end]);
+        fields.push((name, descriptor[11], descriptor[16] as usize, descriptor[17]));
+        at += 32;
+    }
+    
+    let available = dbf.len().saturating_sub(header_length) / record_length.max(1);
+    if record_count > available {
+        return Err(format!("dBASE file (.dbf) is truncated: {} records declared, {} present", record_count, available));
+    }
+    
+    let mut records = Vec::with_capacity(record_count);
+    for index in 0..record_count {
+        let start = header_length + index * record_length;
+        let record = dbf.get(start..start + record_length).ok_or_else(truncated)?;
+        if record.first() == Some(&b'*') {
+            records.push(None);
+            continue;
+        }
+        
+        let mut properties = Map::new();
+        let mut at = 1;
+        for (name, field_type, length, decimals) in &fields {
+            let raw = record.get(at..at + length).ok_or_else(truncated)?;
+            at += length;
+            if let Some(value) = dbf_value(raw, *field_type, *decimals) {
+                properties.insert(name.clone(), value);
+            }
+        }
+        records.push(Some(properties));
+    }
+    Ok(records)
+}
+
+/// Convert one dBASE field (empty and unknown values are omitted)
+fn dbf_value(raw: &[u8], field_type: u8, decimals: u8) -> Option<Value> {
+    let text = decode_text(raw);
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return None;
+    }
+    match field_type {
+        b'N' | b'F' => {
+            if decimals == 0 {
+                if let Ok(n) = text.parse::<i64>() {
+                    return Some(Value::from(n));
+                }
+            }
+            text.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number)
+        }
+        b'L' => match text {
+            "T" | "t" | "Y" | "y" => Some(Value::Bool(true)),
+            "F" | "f" | "N" | "n" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        // Dates (YYYYMMDD) become ISO 8601 strings
+        b'D' if text.len() == 8 && text.bytes().all(|b| b.is_ascii_digit()) => {
+            Some(Value::String(format!("{}-{}-{}", &text[..4], &text[4..6], &text[6..])))
+        }
+        _ => Some(Value::String(text.to_string())),
+    }
+}
+
+fn decode_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_point_shapefile() {
+        let mut shp = vec![0u8; 100];
+        shp[..4].copy_from_slice(&9994i32.to_be_bytes());
+        for (i, (x, y)) in [(139.69, 35.68), (135.50, 34.69)].into_iter().enumerate() {
+            shp.extend_from_slice(&(i as i32 + 1).to_be_bytes());
+            shp.extend_from_slice(&10i32.to_be_bytes());
+            shp.extend_from_slice(&1i32.to_le_bytes());
+            shp.extend_from_slice(&f64::to_le_bytes(x));
+            shp.extend_from_slice(&f64::to_le_bytes(y));
+        }
+        
+        let features = parse_shapefile(&shp, None).unwrap();
+        
+        assert_eq!(features.len(), 2);
+        let GeometryType::Point(point) = &features[1].geometry else {
+            panic!("Expected Point geometry");
+        };
+        assert_eq!((point.x(), point.y()), (135.50, 34.69));
+    }
+
+    #[test]
+    fn test_header_counts_beyond_the_file() {
+        // A 33-byte .dbf declaring u32::MAX records
+        let mut dbf = vec![0u8; 33];
+        dbf[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        dbf[8..10].copy_from_slice(&33u16.to_le_bytes());
+        dbf[10..12].copy_from_slice(&1u16.to_le_bytes());
+        assert!(parse_dbf(&dbf).unwrap_err().contains("4294967295 records declared"));
+        
+        // A polyline declaring i32::MAX parts
+        let mut shape = vec![0u8; 44];
+        shape[..4].copy_from_slice(&3i32.to_le_bytes());
+        shape[36..40].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(parse_shape(&shape).is_err());
+        shape[36..40].copy_from_slice(&0i32.to_le_bytes());
+        shape[40..44].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(parse_shape(&shape).is_err());
+    }
+
+    #[test]
+    fn test_check_projection() {
+        assert!(check_projection(r#"GEOGCS["GCS_WGS_1984"]"#).is_ok());
+        assert!(check_projection(r#"PROJCS["JGD2011 / Japan Plane Rectangular CS IX"]"#).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+// Geometry simplification module
+// Douglas-Peucker simplification in tile coordinates
+
+use crate::projection::get_resolution;
+use crate::tiler::{TileFeature, TileGeometry};
+use crate::topology::extract_arcs;
+
+/// Simplification settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Simplification {
+    /// Maximum deviation in tile units (extent 4096, so 16 is roughly one screen pixel)
+    pub tolerance: f64,
+    pub mode: SimplifyMode,
+}
+
+/// How polygon boundaries are simplified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimplifyMode {
+    /// Simplify every ring on its own
+    #[default]
+    Independent,
+    /// Simplify boundaries shared between polygons once, keeping neighbors watertight
+    TopologyPreserving,
+}
+
+type Point = (i32, i32);
+
+/// Simplify all features of a tile
+pub fn simplify_features(features: &mut [TileFeature], simplification: &Simplification) {
+    let tolerance = simplification.tolerance;
+    if tolerance <= 0.0 {
+        return;
+    }
+    
+    simplify_lines(features, tolerance);
+    
+    match simplification.mode {
+        SimplifyMode::Independent => {
+            for feature in features.iter_mut() {
+                if let TileGeometry::Polygon(rings) = &mut feature.geometry {
+                    for ring in rings.iter_mut() {
+                        *ring = simplify_ring(ring, tolerance);
+                    }
+                }
+            }
+        }
+        SimplifyMode::TopologyPreserving => simplify_shared_rings(features, tolerance),
+    }
+}
+
+/// Simplify only the LineString features of a tile
+pub fn simplify_lines(features: &mut [TileFeature], tolerance: f64) {
+    for feature in features.iter_mut() {
+        if let TileGeometry::LineString(coords) = &mut feature.geometry {
+            *coords = douglas_peucker(coords, tolerance);
+        }
+    }
+}
+
+/// Convert a tolerance in tile units to WebMercator meters at the given zoom
+pub fn tolerance_in_meters(tolerance: f64, zoom: u8) -> f64 {
+    tolerance * (256.0 / 4096.0) * get_resolution(zoom)
+}
+
+/// Simplify a closed ring, keeping the original if it would collapse
+fn simplify_ring(ring: &[Point], tolerance: f64) -> Vec<Point> {
+    let simplified = douglas_peucker(ring, tolerance);
+    if simplified.len() < 4 {
+        ring.to_vec()
+    } else {
+        simplified
+    }
+}
+
+/// Topology-preserving simplification of all polygon rings in a tile
+///
+/// Rings are split into arcs at junctions (vertices where boundaries meet or diverge).
+/// Each distinct arc is simplified once, so both sides of a shared border end up
+/// with exactly the same vertices.
+fn simplify_shared_rings(features: &mut [TileFeature], tolerance: f64) {
+    let rings: Vec<Vec<Point>> = features
+        .iter()
+        .flat_map(|f| match &f.geometry {
+            TileGeometry::Polygon(rings) => rings.clone(),
+            _ => Vec::new(),
+        })
+        .collect();
+    let arc_set = extract_arcs(&rings);
+    let simplified: Vec<Vec<Point>> = arc_set.arcs
+        .iter()
+        .map(|arc| douglas_peucker(arc, tolerance))
+        .collect();
+    
+    let mut ring_index = 0;
+    for feature in features.iter_mut() {
+        let TileGeometry::Polygon(rings) = &mut feature.geometry else {
+            continue;
+        };
+        for ring in rings.iter_mut() {
+            let rebuilt = arc_set.ring_from(&simplified, ring_index);
+            if rebuilt.len() >= 4 {
+                *ring = rebuilt;
+            }
+            ring_index += 1;
+        }
+    }
+}
+
+/// Douglas-Peucker simplification (endpoints are always kept)
+pub fn douglas_peucker(coords: &[Point], tolerance: f64) -> Vec<Point> {
+    let points: Vec<(f64, f64)> = coords.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+    coords
+        .iter()
+        .zip(douglas_peucker_mask(&points, tolerance))
+        .filter(|(_, kept)| *kept)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// Douglas-Peucker simplification of floating-point coordinates
+pub fn douglas_peucker_f64(coords: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    coords
+        .iter()
+        .zip(douglas_peucker_mask(coords, tolerance))
+        .filter(|(_, kept)| *kept)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// Flags of the vertices kept by Douglas-Peucker
+fn douglas_peucker_mask(coords: &[(f64, f64)], tolerance: f64) -> Vec<bool> {
+    if coords.len() < 3 {
+        return vec![true; coords.len()];
+    }
+    
+    let mut keep = vec![false; coords.len()];
+    keep[0] = true;
+    keep[coords.len() - 1] = true;
+    
+    let mut stack = vec![(0, coords.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_distance = 0.0;
+        let mut max_index = first;
+        for i in first + 1..last {
+            let distance = segment_distance(coords[i], coords[first], coords[last]);
+            if distance > max_distance {
+                max_distance = distance;
+                max_index = i;
+            }
+        }
+        
+        if max_distance > tolerance {
+            keep[max_index] = true;
+            stack.push((first, max_index));
+            stack.push((max_index, last));
+        }
+    }
+    
+    keep
+}
+
+/// Distance from point `p` to segment `a`-`b`
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (px, py) = p;
+    let (ax, ay) = a;
+    let (dx, dy) = (b.0 - ax, b.1 - ay);
+    let length_sq = dx * dx + dy * dy;
+    
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((px - ax) * dx + (py - ay) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (cx, cy) = (ax + t * dx, ay + t * dy);
+    ((px - cx).powi(2) + (py - cy).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn polygon(ring: Vec<Point>) -> TileFeature {
+        TileFeature {
+            geometry: TileGeometry::Polygon(vec![ring]),
+            properties: serde_json::Map::new(),
+            id: None,
+        }
+    }
+
+    fn first_ring(feature: &TileFeature) -> &Vec<Point> {
+        match &feature.geometry {
+            TileGeometry::Polygon(rings) => &rings[0],
+            _ => panic!("Expected Polygon geometry"),
+        }
+    }
+
+    #[test]
+    fn test_douglas_peucker_removes_collinear_points() {
+        let line = vec![(0, 0), (5, 1), (10, 0), (20, 0)];
+        assert_eq!(douglas_peucker(&line, 2.0), vec![(0, 0), (20, 0)]);
+    }
+
+    #[test]
+    fn test_shared_border_stays_watertight() {
+        // Two squares sharing a wiggly border along x = 100
+        let border = vec![(100, 0), (102, 25), (99, 50), (101, 75), (100, 100)];
+        let mut left: Vec<Point> = vec![(0, 0)];
+        left.extend(border.iter().copied());
+        left.extend([(0, 100), (0, 0)]);
+        let mut right: Vec<Point> = border.iter().rev().copied().collect();
+        right.extend([(200, 0), (200, 100), (100, 100)]);
+        
+        let mut features = vec![polygon(left), polygon(right)];
+        let simplification = Simplification { tolerance: 5.0, mode: SimplifyMode::TopologyPreserving };
+        simplify_features(&mut features, &simplification);
+        
+        let left_vertices: HashSet<Point> = first_ring(&features[0]).iter().copied().collect();
+        let right_vertices: HashSet<Point> = first_ring(&features[1]).iter().copied().collect();
+        let shared: Vec<Point> = border.iter().copied().filter(|p| left_vertices.contains(p)).collect();
+        
+        // The border was simplified, and both polygons kept exactly the same border vertices
+        assert!(shared.len() < border.len());
+        for p in &border {
+            assert_eq!(left_vertices.contains(p), right_vertices.contains(p));
+        }
+    }
+}
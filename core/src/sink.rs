@@ -0,0 +1,423 @@
+// Tile output module
+// Destinations for generated tiles
+
+use crate::{hash, tilejson, TileCoord, TileFile, TileMetadata};
+use std::borrow::Cow;
+use std::fs;
+#[cfg(feature = "zip")]
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Destination for generated tiles
+///
+/// `write_tile` is called once per tile as tiles are produced, then `finish`
+/// once with the final metadata.
+pub trait TileSink {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String>;
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String>;
+}
+
+/// Compression of the stored tiles
+///
+/// Sinks record it for servers, which should send the tiles with the matching `Content-Encoding`
+/// header: in the PMTiles header, as the `content_encoding` MBTiles metadata row, or as
+/// `content_encoding` in `metadata.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileCompression {
+    #[default]
+    None,
+    /// Needs the `zip` feature
+    Gzip,
+    Brotli,
+}
+
+impl TileCompression {
+    /// `Content-Encoding` of the stored tiles (None when uncompressed)
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            TileCompression::None => None,
+            TileCompression::Gzip => Some("gzip"),
+            TileCompression::Brotli => Some("br"),
+        }
+    }
+
+    pub const ALL: [TileCompression; 3] = [TileCompression::None, TileCompression::Gzip, TileCompression::Brotli];
+
+    /// Name used by `FromStr` (`none`, `gzip` or `brotli`)
+    pub fn name(self) -> &'static str {
+        match self {
+            TileCompression::None => "none",
+            TileCompression::Gzip => "gzip",
+            TileCompression::Brotli => "brotli",
+        }
+    }
+
+    pub(crate) fn compress(self, data: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+        match self {
+            TileCompression::None => Ok(Cow::Borrowed(data)),
+            #[cfg(feature = "zip")]
+            TileCompression::Gzip => Ok(Cow::Owned(crate::deflate::gzip(data))),
+            #[cfg(not(feature = "zip"))]
+            TileCompression::Gzip => Err("Gzip tile compression needs the 'zip' feature".to_string()),
+            TileCompression::Brotli => Ok(Cow::Owned(crate::brotli::compress(data))),
+        }
+    }
+}
+
+impl FromStr for TileCompression {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        TileCompression::ALL.into_iter().find(|compression| compression.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = TileCompression::ALL.iter().map(|compression| compression.name()).collect();
+            format!("Unknown tile compression '{}' (expected one of: {})", name, names.join(", "))
+        })
+    }
+}
+
+/// `metadata.json`: the TileJSON, with `content_encoding` when the tiles are compressed
+fn metadata_json(metadata: &TileMetadata, compression: TileCompression) -> Result<String, String> {
+    let mut json = tilejson::to_tilejson(metadata);
+    if let (Some(encoding), Some(fields)) = (compression.content_encoding(), json.as_object_mut()) {
+        fields.insert("content_encoding".to_string(), encoding.into());
+    }
+    serde_json::to_string_pretty(&json).map_err(|e| format!("Metadata serialization error: {}", e))
+}
+
+/// Collect tiles in memory
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub tiles: Vec<TileFile>,
+}
+
+impl TileSink for MemorySink {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        self.tiles.push(TileFile {
+            path: coord.to_path(),
+            data: data.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self, _metadata: &TileMetadata) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Path, size and content hash of one written tile
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: usize,
+    /// Lowercase hex SHA-256 of the tile bytes
+    pub sha256: String,
+}
+
+/// Listing of every written tile (`manifest.json`), for integrity checks and cache invalidation
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct Manifest {
+    pub tiles: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn add(&mut self, path: impl Into<String>, data: &[u8]) {
+        self.tiles.push(ManifestEntry {
+            path: path.into(),
+            size: data.len(),
+            sha256: hash::sha256_hex(data),
+        });
+    }
+
+    /// Pretty-printed JSON with tiles sorted by path
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut sorted = self.clone();
+        sorted.tiles.sort_by(|a, b| a.path.cmp(&b.path));
+        serde_json::to_string_pretty(&sorted)
+            .map_err(|e| format!("Manifest serialization error: {}", e))
+    }
+}
+
+/// Write `{z}/{x}/{y}.pbf` files and `metadata.json` under a directory
+#[derive(Debug)]
+pub struct DirectorySink {
+    root: PathBuf,
+    manifest: Option<Manifest>,
+    tile_compression: TileCompression,
+}
+
+impl DirectorySink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), manifest: None, tile_compression: TileCompression::None }
+    }
+
+    /// Compress the tile files with `compression` (the names stay `.pbf`)
+    pub fn with_tile_compression(mut self, compression: TileCompression) -> Self {
+        self.tile_compression = compression;
+        self
+    }
+
+    /// Also write `manifest.json` with the size and hash of every tile
+    pub fn with_manifest(mut self) -> Self {
+        self.manifest = Some(Manifest::default());
+        self
+    }
+}
+
+impl TileSink for DirectorySink {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        let path = self.root.join(coord.to_path());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let data = self.tile_compression.compress(data)?;
+        fs::write(&path, &data)
+            .map_err(|e| format!("Failed to save tile {}: {}", path.display(), e))?;
+        if let Some(manifest) = &mut self.manifest {
+            manifest.add(coord.to_path(), &data);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create directory {}: {}", self.root.display(), e))?;
+        let json = metadata_json(metadata, self.tile_compression)?;
+        fs::write(self.root.join("metadata.json"), json)
+            .map_err(|e| format!("Failed to save metadata.json: {}", e))?;
+        if let Some(manifest) = &self.manifest {
+            fs::write(self.root.join("manifest.json"), manifest.to_json()?)
+                .map_err(|e| format!("Failed to save manifest.json: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Write tiles and `metadata.json` into an uncompressed ZIP archive
+///
+/// Entries are stored (no deflate), matching the directory layout of `DirectorySink`.
+#[cfg(feature = "zip")]
+pub struct ZipSink<W: Write> {
+    writer: W,
+    offset: u64,
+    entries: Vec<ZipEntry>,
+    manifest: Option<Manifest>,
+    tile_compression: TileCompression,
+}
+
+#[cfg(feature = "zip")]
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+#[cfg(feature = "zip")]
+impl<W: Write> ZipSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+            manifest: None,
+            tile_compression: TileCompression::None,
+        }
+    }
+
+    /// Compress the tile entries with `compression` (the names stay `.pbf`)
+    pub fn with_tile_compression(mut self, compression: TileCompression) -> Self {
+        self.tile_compression = compression;
+        self
+    }
+
+    /// Also add `manifest.json` with the size and hash of every tile
+    pub fn with_manifest(mut self) -> Self {
+        self.manifest = Some(Manifest::default());
+        self
+    }
+
+    /// Consume the sink and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub(crate) fn add_entry(&mut self, name: String, data: &[u8]) -> Result<(), String> {
+        let size = u32::try_from(data.len()).map_err(|_| format!("ZIP entry too large: {}", name))?;
+        let offset = u32::try_from(self.offset).map_err(|_| "ZIP archive exceeds 4 GB".to_string())?;
+        let crc = crc32(data);
+        
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x04034b50u32.to_le_bytes()); // local file header signature
+        header.extend_from_slice(&20u16.to_le_bytes());         // version needed to extract
+        header.extend_from_slice(&0u16.to_le_bytes());          // flags
+        header.extend_from_slice(&0u16.to_le_bytes());          // compression: stored
+        header.extend_from_slice(&0u16.to_le_bytes());          // modification time
+        header.extend_from_slice(&0x21u16.to_le_bytes());       // modification date (1980-01-01)
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());          // compressed size
+        header.extend_from_slice(&size.to_le_bytes());          // uncompressed size
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());          // extra field length
+        header.extend_from_slice(name.as_bytes());
+        
+        self.write(&header)?;
+        self.write(data)?;
+        self.entries.push(ZipEntry { name, crc, size, offset });
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer.write_all(bytes).map_err(|e| format!("ZIP write error: {}", e))?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<W: Write> TileSink for ZipSink<W> {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        let data = self.tile_compression.compress(data)?;
+        if let Some(manifest) = &mut self.manifest {
+            manifest.add(coord.to_path(), &data);
+        }
+        self.add_entry(coord.to_path(), &data)
+    }
+
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        let json = metadata_json(metadata, self.tile_compression)?;
+        self.add_entry("metadata.json".to_string(), json.as_bytes())?;
+        if let Some(manifest) = &self.manifest {
+            let manifest = manifest.to_json()?;
+            self.add_entry("manifest.json".to_string(), manifest.as_bytes())?;
+        }
+        
+        let directory_offset = u32::try_from(self.offset).map_err(|_| "ZIP archive exceeds 4 GB".to_string())?;
+        let entry_count = u16::try_from(self.entries.len()).map_err(|_| "Too many ZIP entries (max 65535)".to_string())?;
+        
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&0x02014b50u32.to_le_bytes()); // central directory signature
+            directory.extend_from_slice(&20u16.to_le_bytes());         // version made by
+            directory.extend_from_slice(&20u16.to_le_bytes());         // version needed to extract
+            directory.extend_from_slice(&0u16.to_le_bytes());          // flags
+            directory.extend_from_slice(&0u16.to_le_bytes());          // compression: stored
+            directory.extend_from_slice(&0u16.to_le_bytes());          // modification time
+            directory.extend_from_slice(&0x21u16.to_le_bytes());       // modification date
+            directory.extend_from_slice(&entry.crc.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());          // extra field length
+            directory.extend_from_slice(&0u16.to_le_bytes());          // comment length
+            directory.extend_from_slice(&0u16.to_le_bytes());          // disk number
+            directory.extend_from_slice(&0u16.to_le_bytes());          // internal attributes
+            directory.extend_from_slice(&0u32.to_le_bytes());          // external attributes
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x06054b50u32.to_le_bytes()); // end of central directory signature
+        end.extend_from_slice(&0u16.to_le_bytes());          // disk number
+        end.extend_from_slice(&0u16.to_le_bytes());          // disk with central directory
+        end.extend_from_slice(&entry_count.to_le_bytes());
+        end.extend_from_slice(&entry_count.to_le_bytes());
+        end.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        end.extend_from_slice(&directory_offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());          // comment length
+        
+        self.write(&directory)?;
+        self.write(&end)?;
+        self.writer.flush().map_err(|e| format!("ZIP write error: {}", e))
+    }
+}
+
+/// CRC-32 (IEEE 802.3) checksum
+#[cfg(feature = "zip")]
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> TileMetadata {
+        TileMetadata {
+            layer_name: "test".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_zip_sink_layout() {
+        let mut sink = ZipSink::new(Vec::new());
+        sink.write_tile(TileCoord::new(0, 0, 0), &[1, 2, 3]).unwrap();
+        sink.finish(&metadata()).unwrap();
+        let bytes = sink.into_inner();
+        
+        // Local header of the first entry, end of central directory with 2 entries
+        assert_eq!(&bytes[0..4], &[0x50, 0x4b, 0x03, 0x04]);
+        assert_eq!(&bytes[30..39], b"0/0/0.pbf");
+        let end = &bytes[bytes.len() - 22..];
+        assert_eq!(&end[0..4], &[0x50, 0x4b, 0x05, 0x06]);
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+    }
+
+    #[test]
+    fn test_memory_sink() {
+        let mut sink = MemorySink::default();
+        sink.write_tile(TileCoord::new(1, 0, 1), &[9]).unwrap();
+        
+        assert_eq!(sink.tiles[0].path, "1/0/1.pbf");
+        assert_eq!(sink.tiles[0].data, vec![9]);
+    }
+
+    #[test]
+    fn test_directory_sink_manifest() {
+        let root = std::env::temp_dir().join(format!("vtc-manifest-{}", std::process::id()));
+        let mut sink = DirectorySink::new(&root).with_manifest();
+        sink.write_tile(TileCoord::new(0, 0, 0), b"abc").unwrap();
+        sink.finish(&metadata()).unwrap();
+        
+        let manifest: serde_json::Value = serde_json::from_slice(&fs::read(root.join("manifest.json")).unwrap()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        
+        assert_eq!(manifest["tiles"][0]["path"], "0/0/0.pbf");
+        assert_eq!(manifest["tiles"][0]["size"], 3);
+        assert_eq!(manifest["tiles"][0]["sha256"], "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_directory_sink_compression() {
+        let root = std::env::temp_dir().join(format!("vtc-brotli-{}", std::process::id()));
+        let mut sink = DirectorySink::new(&root).with_tile_compression(TileCompression::Brotli);
+        sink.write_tile(TileCoord::new(0, 0, 0), b"abcabcabc").unwrap();
+        sink.finish(&metadata()).unwrap();
+        
+        let tile = fs::read(root.join("0/0/0.pbf")).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(root.join("metadata.json")).unwrap()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        
+        assert_eq!(tile, crate::brotli::compress(b"abcabcabc"));
+        assert_eq!(json["content_encoding"], "br");
+        assert!(metadata_json(&metadata(), TileCompression::None).unwrap().find("content_encoding").is_none());
+    }
+}
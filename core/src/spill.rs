@@ -0,0 +1,482 @@
+// Spill module
+// Disk-backed tiling for native runs whose tiled features do not fit in memory
+
+use crate::geojson_parser::Feature;
+use crate::tiler::{self, TileFeature, TileGeometry};
+use crate::transform::FeatureTransform;
+use crate::{logging, pmtiles, TileCoord, TileResult, ZoomTiles};
+use std::fs::{self, File};
+use std::collections::BinaryHeap;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Source features tiled per step before the memory estimate is checked
+const CHUNK_FEATURES: usize = 4096;
+
+/// Estimated size of one property entry besides its key and value text
+const PROPERTY_OVERHEAD: usize = std::mem::size_of::<(String, serde_json::Value)>();
+
+/// Distinguishes the spill files of concurrent generations in one process
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// External-memory settings
+///
+/// Only the tiled (projected and clipped) features are spilled; the parsed source
+/// features stay in memory. Each spill writes a run sorted by tile id, and the runs of a
+/// zoom are merged while encoding, so spilled zooms reach the sink one tile at a time in
+/// tile id (PMTiles) order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpillOptions {
+    /// Estimated size of tiled features held in memory (bytes) before they are written to disk
+    pub memory_budget: usize,
+    /// Directory of the temporary run files (the system temp directory when `None`)
+    pub directory: Option<PathBuf>,
+    /// Run files open at once while merging; more runs are first merged into fewer in passes
+    pub max_open_runs: usize,
+}
+
+impl Default for SpillOptions {
+    fn default() -> Self {
+        Self { memory_budget: 512 << 20, directory: None, max_open_runs: 64 }
+    }
+}
+
+/// Tile features, spilling them to sorted run files whenever the memory budget is exceeded
+///
+/// Produces the same tiles (with features in the same order) as tiling in memory; tiles stay
+/// in memory when the budget is never reached.
+pub(crate) fn tile_features(
+    features: &[Feature],
+    zoom: u8,
+    extent: u32,
+    transforms: &[Arc<dyn FeatureTransform>],
+    options: &SpillOptions,
+) -> Result<(ZoomTiles, usize), String> {
+    let mut tiles = tiler::tile_map_for(features.len(), zoom);
+    let mut estimate = 0;
+    let mut clamped = 0;
+    let mut writer: Option<RunWriter> = None;
+    
+    for chunk in features.chunks(CHUNK_FEATURES) {
+        let (chunk_tiles, chunk_clamped) = tiler::tile_features_counting_clamped(chunk, None, zoom, extent, transforms)?;
+        clamped += chunk_clamped;
+        for (coord, mut tile_features) in chunk_tiles {
+            estimate += tile_features.iter().map(estimated_size).sum::<usize>();
+            tiles.entry(coord).or_default().append(&mut tile_features);
+        }
+        if estimate > options.memory_budget {
+            writer.get_or_insert_with(|| RunWriter::create(zoom, options)).write(tiles.drain())?;
+            estimate = 0;
+        }
+    }
+    
+    match writer {
+        None => Ok((ZoomTiles::Memory(tiles), clamped)),
+        Some(mut writer) => {
+            writer.write(tiles.drain())?;
+            logging::log_debug!("Zoom {}: spilled tiles in {} sorted runs", zoom, writer.runs.paths.len());
+            Ok((ZoomTiles::Spilled(writer.runs), clamped))
+        }
+    }
+}
+
+/// Rough in-memory size of a tile feature (bytes)
+fn estimated_size(feature: &TileFeature) -> usize {
+    let points = match &feature.geometry {
+        TileGeometry::Point(..) => 1,
+        TileGeometry::LineString(line) => line.len(),
+        TileGeometry::Polygon(rings) => rings.iter().map(Vec::len).sum(),
+    };
+    let properties: usize = feature.properties.iter()
+        .map(|(key, value)| {
+            let text = match value {
+                serde_json::Value::String(s) => s.len(),
+                serde_json::Value::Array(_) | serde_json::Value::Object(_) => value.to_string().len(),
+                _ => 0,
+            };
+            PROPERTY_OVERHEAD + key.len() + text
+        })
+        .sum();
+    std::mem::size_of::<TileFeature>() + points * std::mem::size_of::<(i32, i32)>() + properties
+}
+
+/// Sorted run of spilled tiles being written
+struct RunWriter {
+    directory: PathBuf,
+    zoom: u8,
+    id: usize,
+    runs: SpillRuns,
+}
+
+impl RunWriter {
+    fn create(zoom: u8, options: &SpillOptions) -> Self {
+        Self {
+            directory: options.directory.clone().unwrap_or_else(std::env::temp_dir),
+            zoom,
+            id: SPILL_COUNTER.fetch_add(1, Ordering::Relaxed),
+            runs: SpillRuns { paths: Vec::new(), max_open: options.max_open_runs.max(2) },
+        }
+    }
+
+    /// Write the tiles as one more run file, ordered by tile id
+    fn write(&mut self, tiles: impl Iterator<Item = (TileCoord, Vec<TileFeature>)>) -> Result<(), String> {
+        let mut tiles: Vec<(u64, TileCoord, Vec<TileFeature>)> =
+            tiles.map(|(coord, features)| (pmtiles::tile_id(coord), coord, features)).collect();
+        tiles.sort_unstable_by_key(|&(id, ..)| id);
+        
+        let name = format!("vector-tile-spill-{}-{}-z{}-{}.bin", std::process::id(), self.id, self.zoom, self.runs.paths.len());
+        let path = self.directory.join(name);
+        self.runs.paths.push(path.clone());
+        write_run(&path, tiles.into_iter().map(|(_, coord, features)| Ok((coord, features))))
+    }
+}
+
+/// Write tiles (already in tile id order) to a run file
+fn write_run(path: &Path, tiles: impl Iterator<Item = TileResult>) -> Result<(), String> {
+    let write_error = |e: std::io::Error| format!("Failed to write spill file {}: {}", path.display(), e);
+    let mut file = BufWriter::new(File::create(path).map_err(write_error)?);
+    let mut record = Vec::new();
+    for tile in tiles {
+        let (coord, features) = tile?;
+        record.clear();
+        encode_record(coord, &features, &mut record)?;
+        file.write_all(&record).map_err(write_error)?;
+    }
+    file.flush().map_err(write_error)
+}
+
+/// Run files of one zoom, removed when dropped
+pub(crate) struct SpillRuns {
+    paths: Vec<PathBuf>,
+    /// Runs merged at once
+    max_open: usize,
+}
+
+impl SpillRuns {
+    /// Tiles of all runs in tile id order, merged so only the current tile of each run is in memory
+    pub(crate) fn into_tiles(self) -> Box<dyn Iterator<Item = TileResult>> {
+        match self.reduce().and_then(RunMerge::new) {
+            Ok(merge) => Box::new(merge),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
+    }
+
+    /// Merge consecutive groups of runs into one run each until `max_open` runs are left
+    ///
+    /// Groups keep their runs in order, so features of a tile stay in source order.
+    fn reduce(mut self) -> Result<Self, String> {
+        let mut pass = 0;
+        while self.paths.len() > self.max_open {
+            pass += 1;
+            let mut merged = SpillRuns { paths: Vec::new(), max_open: self.max_open };
+            while !self.paths.is_empty() {
+                let group: Vec<PathBuf> = self.paths.drain(..self.max_open.min(self.paths.len())).collect();
+                if let [path] = group.as_slice() {
+                    merged.paths.push(path.clone());
+                    continue;
+                }
+                let path = group[0].with_extension(format!("p{}.bin", pass));
+                merged.paths.push(path.clone());
+                // The group's files are removed once it is dropped
+                let group = SpillRuns { paths: group, max_open: self.max_open };
+                write_run(&path, RunMerge::new(group)?)?;
+            }
+            logging::log_debug!("Merged spill runs into {}", merged.paths.len());
+            self = merged;
+        }
+        Ok(self)
+    }
+}
+
+impl Drop for SpillRuns {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Next tile of a run; the heap yields the lowest tile id first, then the earliest run
+struct RunHead {
+    id: u64,
+    run: usize,
+    coord: TileCoord,
+    features: Vec<TileFeature>,
+}
+
+impl PartialEq for RunHead {
+    fn eq(&self, other: &Self) -> bool {
+        (self.id, self.run) == (other.id, other.run)
+    }
+}
+
+impl Eq for RunHead {}
+
+impl PartialOrd for RunHead {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RunHead {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (other.id, other.run).cmp(&(self.id, self.run))
+    }
+}
+
+/// K-way merge of sorted runs
+///
+/// A tile spilled in several runs is assembled in run order, which keeps its features in
+/// source order.
+struct RunMerge {
+    readers: Vec<Reader<BufReader<File>>>,
+    heap: BinaryHeap<RunHead>,
+    failed: bool,
+    runs: SpillRuns,
+}
+
+impl RunMerge {
+    fn new(runs: SpillRuns) -> Result<Self, String> {
+        let mut readers = Vec::with_capacity(runs.paths.len());
+        for path in &runs.paths {
+            let file = File::open(path).map_err(|e| format!("Failed to read spill file {}: {}", path.display(), e))?;
+            readers.push(Reader { input: BufReader::new(file) });
+        }
+        let mut merge = Self { readers, heap: BinaryHeap::new(), failed: false, runs };
+        for run in 0..merge.readers.len() {
+            merge.advance(run)?;
+        }
+        Ok(merge)
+    }
+
+    /// Queue the next tile of a run
+    fn advance(&mut self, run: usize) -> Result<(), String> {
+        let reader = &mut self.readers[run];
+        let at_end = reader.input.fill_buf().map(<[u8]>::is_empty).map_err(|e| e.to_string())?;
+        if !at_end {
+            let (coord, features) = decode_record(reader)
+                .map_err(|e| format!("Corrupt spill file {}: {}", self.runs.paths[run].display(), e))?;
+            self.heap.push(RunHead { id: pmtiles::tile_id(coord), run, coord, features });
+        }
+        Ok(())
+    }
+
+    fn next_tile(&mut self) -> Result<Option<(TileCoord, Vec<TileFeature>)>, String> {
+        let Some(RunHead { id, run, coord, mut features }) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.advance(run)?;
+        while self.heap.peek().is_some_and(|head| head.id == id) {
+            let mut head = self.heap.pop().expect("peeked");
+            features.append(&mut head.features);
+            self.advance(head.run)?;
+        }
+        Ok(Some((coord, features)))
+    }
+}
+
+impl Iterator for RunMerge {
+    type Item = TileResult;
+
+    fn next(&mut self) -> Option<TileResult> {
+        if self.failed {
+            return None;
+        }
+        let tile = self.next_tile().transpose();
+        self.failed = matches!(tile, Some(Err(_)));
+        tile
+    }
+}
+
+const GEOMETRY_POINT: u8 = 0;
+const GEOMETRY_LINESTRING: u8 = 1;
+const GEOMETRY_POLYGON: u8 = 2;
+
+/// Record layout (little-endian): z u8, x u32, y u32, feature count u32, then per feature an
+/// id flag u8 (+ u64), the geometry and the properties as length-prefixed JSON
+fn encode_record(coord: TileCoord, features: &[TileFeature], out: &mut Vec<u8>) -> Result<(), String> {
+    out.push(coord.z);
+    out.extend_from_slice(&coord.x.to_le_bytes());
+    out.extend_from_slice(&coord.y.to_le_bytes());
+    out.extend_from_slice(&(features.len() as u32).to_le_bytes());
+    for feature in features {
+        match feature.id {
+            Some(id) => {
+                out.push(1);
+                out.extend_from_slice(&id.to_le_bytes());
+            }
+            None => out.push(0),
+        }
+        match &feature.geometry {
+            TileGeometry::Point(x, y) => {
+                out.push(GEOMETRY_POINT);
+                encode_points(&[(*x, *y)], out);
+            }
+            TileGeometry::LineString(line) => {
+                out.push(GEOMETRY_LINESTRING);
+                out.extend_from_slice(&(line.len() as u32).to_le_bytes());
+                encode_points(line, out);
+            }
+            TileGeometry::Polygon(rings) => {
+                out.push(GEOMETRY_POLYGON);
+                out.extend_from_slice(&(rings.len() as u32).to_le_bytes());
+                for ring in rings {
+                    out.extend_from_slice(&(ring.len() as u32).to_le_bytes());
+                    encode_points(ring, out);
+                }
+            }
+        }
+        let properties = serde_json::to_vec(&feature.properties).map_err(|e| e.to_string())?;
+        out.extend_from_slice(&(properties.len() as u32).to_le_bytes());
+        out.extend_from_slice(&properties);
+    }
+    Ok(())
+}
+
+fn encode_points(points: &[(i32, i32)], out: &mut Vec<u8>) {
+    for (x, y) in points {
+        out.extend_from_slice(&x.to_le_bytes());
+        out.extend_from_slice(&y.to_le_bytes());
+    }
+}
+
+fn decode_record(reader: &mut Reader<impl Read>) -> Result<(TileCoord, Vec<TileFeature>), String> {
+    let coord = TileCoord { z: reader.u8()?, x: reader.u32()?, y: reader.u32()? };
+    let count = reader.u32()? as usize;
+    let mut features = Vec::with_capacity(count);
+    for _ in 0..count {
+        let id = match reader.u8()? {
+            0 => None,
+            _ => Some(u64::from_le_bytes(reader.array()?)),
+        };
+        let geometry = match reader.u8()? {
+            GEOMETRY_POINT => {
+                let (x, y) = reader.point()?;
+                TileGeometry::Point(x, y)
+            }
+            GEOMETRY_LINESTRING => TileGeometry::LineString(reader.points()?),
+            GEOMETRY_POLYGON => {
+                let rings = reader.u32()?;
+                TileGeometry::Polygon((0..rings).map(|_| reader.points()).collect::<Result<_, _>>()?)
+            }
+            tag => return Err(format!("unknown geometry tag {}", tag)),
+        };
+        let length = reader.u32()? as usize;
+        let mut properties = vec![0; length];
+        reader.input.read_exact(&mut properties).map_err(|_| "record is truncated".to_string())?;
+        let properties = serde_json::from_slice(&properties).map_err(|e| e.to_string())?;
+        features.push(TileFeature { geometry, properties, id });
+    }
+    Ok((coord, features))
+}
+
+struct Reader<R> {
+    input: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut bytes = [0; N];
+        self.input.read_exact(&mut bytes).map_err(|_| "record is truncated".to_string())?;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn point(&mut self) -> Result<(i32, i32), String> {
+        Ok((i32::from_le_bytes(self.array()?), i32::from_le_bytes(self.array()?)))
+    }
+
+    fn points(&mut self) -> Result<Vec<(i32, i32)>, String> {
+        let count = self.u32()? as usize;
+        (0..count).map(|_| self.point()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::TileSink;
+    use crate::{generate_tiles_to_sink, generate_tiles_with_options, TileMetadata, TileOptions};
+
+    /// More points than `CHUNK_FEATURES`, so a zero budget spills several runs
+    fn many_points() -> Vec<u8> {
+        let features: Vec<String> = (0..CHUNK_FEATURES * 2 + 100)
+            .map(|i| {
+                let (lon, lat) = ((i % 360) as f64 - 179.5, ((i * 7) % 160) as f64 - 79.5);
+                format!(r#"{{"type": "Feature", "properties": {{"n": {}}}, "geometry": {{"type": "Point", "coordinates": [{}, {}]}}}}"#, i, lon, lat)
+            })
+            .collect();
+        format!(r#"{{"type": "FeatureCollection", "features": [{}]}}"#, features.join(",")).into_bytes()
+    }
+
+    fn spill_options(directory: &std::path::Path) -> TileOptions {
+        TileOptions {
+            spill: Some(SpillOptions { memory_budget: 0, directory: Some(directory.to_path_buf()), ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    #[derive(Default)]
+    struct OrderSink(Vec<TileCoord>);
+
+    impl TileSink for OrderSink {
+        fn write_tile(&mut self, coord: TileCoord, _data: &[u8]) -> Result<(), String> {
+            self.0.push(coord);
+            Ok(())
+        }
+
+        fn finish(&mut self, _metadata: &TileMetadata) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_spilled_tiles_match_memory() {
+        let geojson = many_points();
+        let directory = std::env::temp_dir().join(format!("vector-tile-spill-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        
+        let (mut memory, _) = generate_tiles_with_options(&geojson, 0, 3, "default", &TileOptions::default()).unwrap();
+        let (mut spilled, _) = generate_tiles_with_options(&geojson, 0, 3, "default", &spill_options(&directory)).unwrap();
+        
+        memory.sort_by(|a, b| a.path.cmp(&b.path));
+        spilled.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(memory.len(), spilled.len());
+        for (a, b) in memory.iter().zip(&spilled) {
+            assert_eq!((&a.path, &a.data), (&b.path, &b.data));
+        }
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+        
+        // The three runs of a zoom merged two at a time (an extra pass) give the same tiles
+        let mut options = spill_options(&directory);
+        options.spill.as_mut().unwrap().max_open_runs = 2;
+        let (mut merged, _) = generate_tiles_with_options(&geojson, 0, 3, "default", &options).unwrap();
+        merged.sort_by(|a, b| a.path.cmp(&b.path));
+        let tiles = |tiles: &[crate::TileFile]| tiles.iter().map(|t| (t.path.clone(), t.data.clone())).collect::<Vec<_>>();
+        assert_eq!(tiles(&merged), tiles(&spilled));
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+        fs::remove_dir(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_spilled_tiles_in_tile_id_order() {
+        let directory = std::env::temp_dir().join(format!("vector-tile-spill-order-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mut sink = OrderSink::default();
+        generate_tiles_to_sink(&many_points(), 2, 3, "default", &spill_options(&directory), &mut sink).unwrap();
+        
+        let ids: Vec<u64> = sink.0.iter().map(|&coord| pmtiles::tile_id(coord)).collect();
+        assert!(ids.len() > 16);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,655 @@
+// SQLite module
+// Just enough of the SQLite 3 file format to write and read MBTiles databases without a SQLite library
+
+use std::borrow::Cow;
+
+const PAGE_SIZE: usize = 4096;
+const FILE_HEADER_LEN: usize = 100;
+const LEAF_TABLE: u8 = 0x0D;
+const INTERIOR_TABLE: u8 = 0x05;
+const LEAF_INDEX: u8 = 0x0A;
+const INTERIOR_INDEX: u8 = 0x02;
+/// Largest varint (9 bytes) plus a child page number and a cell pointer
+const INTERIOR_TABLE_CELL_MAX: usize = 4 + 9 + 2;
+
+/// A column value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// A table to write: its `CREATE TABLE` statement, rows (rowids 1, 2, ...) and an optional index
+pub(crate) struct Table {
+    pub name: String,
+    pub sql: String,
+    pub rows: Vec<Vec<SqlValue>>,
+    pub index: Option<Index>,
+}
+
+/// An index over integer columns of a table (rows must already be sorted by them)
+pub(crate) struct Index {
+    pub name: String,
+    pub sql: String,
+    pub columns: Vec<usize>,
+}
+
+/// A complete database file with `tables` (and their indexes)
+///
+/// Every page is in use (no free list), so the file passes `PRAGMA integrity_check`.
+pub(crate) fn write_database(tables: &[Table], application_id: u32) -> Result<Vec<u8>, String> {
+    let mut pages = Pages { data: vec![0; PAGE_SIZE] };
+    let mut schema = Vec::new();
+    for table in tables {
+        let rows = table.rows.iter().enumerate().map(|(i, row)| (i as i64 + 1, record(row)));
+        let root = write_table_tree(&mut pages, rows);
+        schema.push(schema_row("table", &table.name, &table.name, root, &table.sql));
+        if let Some(index) = &table.index {
+            let entries: Vec<Vec<u8>> = table
+                .rows
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let mut key: Vec<SqlValue> = index.columns.iter().map(|&column| row[column].clone()).collect();
+                    key.push(SqlValue::Integer(i as i64 + 1));
+                    record(&key)
+                })
+                .collect();
+            let root = write_index_tree(&mut pages, entries)?;
+            schema.push(schema_row("index", &index.name, &table.name, root, &index.sql));
+        }
+    }
+    
+    let cells: Vec<Vec<u8>> = schema.iter().enumerate().map(|(i, row)| table_leaf_cell(&mut pages, i as i64 + 1, row)).collect();
+    if cells.iter().map(|cell| cell.len() + 2).sum::<usize>() + FILE_HEADER_LEN + 8 > PAGE_SIZE {
+        return Err("SQLite schema does not fit in the first page".to_string());
+    }
+    pages.write_page(1, FILE_HEADER_LEN, LEAF_TABLE, &cells, None);
+    
+    let page_count = pages.count();
+    let header = &mut pages.data[..FILE_HEADER_LEN];
+    header[..16].copy_from_slice(b"SQLite format 3\0");
+    header[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+    // Legacy (rollback journal) read/write versions, no reserved bytes, the fixed payload fractions
+    header[18..24].copy_from_slice(&[1, 1, 0, 64, 32, 32]);
+    for (at, value) in [
+        (24, 1),               // file change counter
+        (28, page_count),      // database size in pages
+        (40, 1),               // schema cookie
+        (44, 4),               // schema format
+        (56, 1),               // UTF-8 text
+        (68, application_id),
+        (92, 1),               // version valid for (the change counter)
+        (96, 3_040_001),       // SQLite version of the format written
+    ] {
+        header[at..at + 4].copy_from_slice(&value.to_be_bytes());
+    }
+    Ok(pages.data)
+}
+
+fn schema_row(kind: &str, name: &str, table: &str, root: u32, sql: &str) -> Vec<u8> {
+    record(&[
+        SqlValue::Text(kind.to_string()),
+        SqlValue::Text(name.to_string()),
+        SqlValue::Text(table.to_string()),
+        SqlValue::Integer(root as i64),
+        SqlValue::Text(sql.to_string()),
+    ])
+}
+
+/// Pages of the file being written (page numbers start at 1)
+struct Pages {
+    data: Vec<u8>,
+}
+
+impl Pages {
+    fn count(&self) -> u32 {
+        (self.data.len() / PAGE_SIZE) as u32
+    }
+
+    fn allocate(&mut self) -> u32 {
+        self.data.resize(self.data.len() + PAGE_SIZE, 0);
+        self.count()
+    }
+
+    /// Write a b-tree page: the header at `offset`, the cell pointers after it and the cells
+    /// packed at the end of the page
+    fn write_page(&mut self, number: u32, offset: usize, kind: u8, cells: &[Vec<u8>], right_most: Option<u32>) {
+        let page = &mut self.data[(number as usize - 1) * PAGE_SIZE..number as usize * PAGE_SIZE];
+        let mut content = PAGE_SIZE;
+        let mut pointer = offset + if right_most.is_some() { 12 } else { 8 };
+        for cell in cells {
+            content -= cell.len();
+            page[content..content + cell.len()].copy_from_slice(cell);
+            page[pointer..pointer + 2].copy_from_slice(&(content as u16).to_be_bytes());
+            pointer += 2;
+        }
+        page[offset] = kind;
+        page[offset + 3..offset + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+        // A content area starting at 65536 is stored as 0
+        page[offset + 5..offset + 7].copy_from_slice(&(content as u16).to_be_bytes());
+        if let Some(right_most) = right_most {
+            page[offset + 8..offset + 12].copy_from_slice(&right_most.to_be_bytes());
+        }
+    }
+}
+
+/// Root page of a table b-tree with these (rowid, record) rows
+fn write_table_tree(pages: &mut Pages, rows: impl Iterator<Item = (i64, Vec<u8>)>) -> u32 {
+    // Leaves, each with its last rowid
+    let mut children: Vec<(u32, i64)> = Vec::new();
+    let mut cells: Vec<Vec<u8>> = Vec::new();
+    let mut used = 8;
+    let mut last_rowid = 0;
+    for (rowid, payload) in rows {
+        let cell = table_leaf_cell(pages, rowid, &payload);
+        if used + cell.len() + 2 > PAGE_SIZE {
+            let page = pages.allocate();
+            pages.write_page(page, 0, LEAF_TABLE, &cells, None);
+            children.push((page, last_rowid));
+            cells.clear();
+            used = 8;
+        }
+        used += cell.len() + 2;
+        cells.push(cell);
+        last_rowid = rowid;
+    }
+    let page = pages.allocate();
+    pages.write_page(page, 0, LEAF_TABLE, &cells, None);
+    children.push((page, last_rowid));
+    
+    // Interior levels: a cell per child but the last, which is the right-most pointer
+    let per_page = (PAGE_SIZE - 12) / INTERIOR_TABLE_CELL_MAX + 1;
+    while children.len() > 1 {
+        children = balanced_chunks(&children, per_page)
+            .map(|chunk| {
+                let (&(right_most, last_rowid), rest) = chunk.split_last().unwrap();
+                let cells: Vec<Vec<u8>> = rest
+                    .iter()
+                    .map(|&(child, rowid)| {
+                        let mut cell = child.to_be_bytes().to_vec();
+                        write_varint(&mut cell, rowid as u64);
+                        cell
+                    })
+                    .collect();
+                let page = pages.allocate();
+                pages.write_page(page, 0, INTERIOR_TABLE, &cells, Some(right_most));
+                (page, last_rowid)
+            })
+            .collect();
+    }
+    children[0].0
+}
+
+/// Root page of an index b-tree over these sorted keys
+///
+/// Unlike tables, interior pages hold keys themselves: the key between two pages moves up to
+/// their parent.
+fn write_index_tree(pages: &mut Pages, keys: Vec<Vec<u8>>) -> Result<u32, String> {
+    let cells: Vec<Vec<u8>> = keys
+        .into_iter()
+        .map(|key| {
+            let mut cell = Vec::new();
+            write_varint(&mut cell, key.len() as u64);
+            cell.extend(key);
+            cell
+        })
+        .collect();
+    // Keys long enough to overflow are never written (they are a few integers)
+    let cell_max = cells.iter().map(Vec::len).max().unwrap_or(0);
+    if cell_max > 256 {
+        return Err("SQLite index key is too long".to_string());
+    }
+    
+    // Leaves: `count` cells each, and the cell after each leaf but the last moves up
+    let per_leaf = (PAGE_SIZE - 8) / (cell_max + 2);
+    let leaves = (cells.len() + 1).div_ceil(per_leaf + 1).max(1);
+    let mut cells = cells.into_iter();
+    let mut children = Vec::new();
+    let mut separators = Vec::new();
+    for (i, count) in spread(cells.len() + 1 - leaves, leaves).enumerate() {
+        let page_cells: Vec<Vec<u8>> = cells.by_ref().take(count).collect();
+        let page = pages.allocate();
+        pages.write_page(page, 0, LEAF_INDEX, &page_cells, None);
+        children.push(page);
+        if i + 1 < leaves {
+            separators.extend(cells.next());
+        }
+    }
+    
+    // Interior levels: each separator gets the page before it as its left child
+    let per_page = (PAGE_SIZE - 12) / (cell_max + 4 + 2) + 1;
+    while children.len() > 1 {
+        let groups = children.len().div_ceil(per_page);
+        let mut next_children = Vec::new();
+        let mut next_separators = Vec::new();
+        let mut separators_left = separators.into_iter();
+        let mut children_left = children.into_iter();
+        for (i, count) in spread(children_left.len(), groups).enumerate() {
+            let group: Vec<u32> = children_left.by_ref().take(count).collect();
+            let cells: Vec<Vec<u8>> = group[..count - 1]
+                .iter()
+                .map(|child| {
+                    let mut cell = child.to_be_bytes().to_vec();
+                    cell.extend(separators_left.next().unwrap());
+                    cell
+                })
+                .collect();
+            let page = pages.allocate();
+            pages.write_page(page, 0, INTERIOR_INDEX, &cells, Some(group[count - 1]));
+            next_children.push(page);
+            if i + 1 < groups {
+                next_separators.extend(separators_left.next());
+            }
+        }
+        children = next_children;
+        separators = next_separators;
+    }
+    Ok(children[0])
+}
+
+/// `total` split into `parts` sizes that differ by at most one
+fn spread(total: usize, parts: usize) -> impl Iterator<Item = usize> {
+    (0..parts).map(move |i| total / parts + usize::from(i < total % parts))
+}
+
+/// Chunks of at most `size` items with sizes that differ by at most one (so none is nearly empty)
+fn balanced_chunks<T>(items: &[T], size: usize) -> impl Iterator<Item = &[T]> {
+    let mut rest = items;
+    spread(items.len(), items.len().div_ceil(size)).map(move |count| {
+        let (chunk, tail) = rest.split_at(count);
+        rest = tail;
+        chunk
+    })
+}
+
+/// A table leaf cell, moving the end of a long payload to overflow pages
+fn table_leaf_cell(pages: &mut Pages, rowid: i64, payload: &[u8]) -> Vec<u8> {
+    let mut cell = Vec::new();
+    write_varint(&mut cell, payload.len() as u64);
+    write_varint(&mut cell, rowid as u64);
+    let local = table_local_size(payload.len(), PAGE_SIZE);
+    cell.extend_from_slice(&payload[..local]);
+    if local < payload.len() {
+        let chunks: Vec<&[u8]> = payload[local..].chunks(PAGE_SIZE - 4).collect();
+        let first = pages.count() + 1;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let page = pages.allocate();
+            let next = if i + 1 < chunks.len() { page + 1 } else { 0 };
+            let start = (page as usize - 1) * PAGE_SIZE;
+            pages.data[start..start + 4].copy_from_slice(&next.to_be_bytes());
+            pages.data[start + 4..start + 4 + chunk.len()].copy_from_slice(chunk);
+        }
+        cell.extend_from_slice(&first.to_be_bytes());
+    }
+    cell
+}
+
+/// Bytes of a table b-tree payload stored in the cell itself (the rest overflows)
+fn table_local_size(payload: usize, usable: usize) -> usize {
+    let max_local = usable - 35;
+    if payload <= max_local {
+        return payload;
+    }
+    let min_local = (usable - 12) * 32 / 255 - 23;
+    let local = min_local + (payload - min_local) % (usable - 4);
+    if local <= max_local { local } else { min_local }
+}
+
+/// A record: the serial types of the values, then the values
+fn record(values: &[SqlValue]) -> Vec<u8> {
+    let mut types = Vec::new();
+    let mut body = Vec::new();
+    for value in values {
+        let serial_type = match value {
+            SqlValue::Null => 0,
+            SqlValue::Real(n) => {
+                body.extend_from_slice(&n.to_be_bytes());
+                7
+            }
+            SqlValue::Integer(0) => 8,
+            SqlValue::Integer(1) => 9,
+            &SqlValue::Integer(n) => {
+                let (serial_type, len) = match n {
+                    -0x80..=0x7F => (1, 1),
+                    -0x8000..=0x7FFF => (2, 2),
+                    -0x80_0000..=0x7F_FFFF => (3, 3),
+                    -0x8000_0000..=0x7FFF_FFFF => (4, 4),
+                    -0x8000_0000_0000..=0x7FFF_FFFF_FFFF => (5, 6),
+                    _ => (6, 8),
+                };
+                body.extend_from_slice(&n.to_be_bytes()[8 - len..]);
+                serial_type
+            }
+            SqlValue::Text(text) => {
+                body.extend_from_slice(text.as_bytes());
+                text.len() as u64 * 2 + 13
+            }
+            SqlValue::Blob(blob) => {
+                body.extend_from_slice(blob);
+                blob.len() as u64 * 2 + 12
+            }
+        };
+        write_varint(&mut types, serial_type);
+    }
+    // The header length counts its own varint
+    let mut header_len = types.len() + 1;
+    while varint_len(header_len as u64) + types.len() != header_len {
+        header_len = varint_len(header_len as u64) + types.len();
+    }
+    let mut record = Vec::with_capacity(header_len + body.len());
+    write_varint(&mut record, header_len as u64);
+    record.extend(types);
+    record.extend(body);
+    record
+}
+
+fn varint_len(value: u64) -> usize {
+    let mut buf = Vec::new();
+    write_varint(&mut buf, value);
+    buf.len()
+}
+
+/// SQLite varint: big-endian 7-bit groups, with all 8 bits of a ninth byte
+fn write_varint(buf: &mut Vec<u8>, value: u64) {
+    if value >> 56 != 0 {
+        for i in (1..9).rev() {
+            buf.push((value >> (i * 7 + 1)) as u8 | 0x80);
+        }
+        buf.push(value as u8);
+        return;
+    }
+    let groups = (1..9).find(|&n| value >> (7 * n) == 0).unwrap_or(8);
+    for i in (1..groups).rev() {
+        buf.push((value >> (7 * i)) as u8 | 0x80);
+    }
+    buf.push(value as u8 & 0x7F);
+}
+
+/// A database file opened for reading
+///
+/// Only rowid tables are read; write-ahead-log databases are rejected, since their latest
+/// changes live in the `-wal` file.
+pub(crate) struct Database<'a> {
+    bytes: &'a [u8],
+    page_size: usize,
+    usable: usize,
+}
+
+/// Column names and rows of a table
+pub(crate) type Rows = (Vec<String>, Vec<Vec<SqlValue>>);
+
+impl<'a> Database<'a> {
+    pub fn open(bytes: &'a [u8]) -> Result<Self, String> {
+        if !bytes.starts_with(b"SQLite format 3\0") || bytes.len() < FILE_HEADER_LEN {
+            return Err("Not an SQLite database".to_string());
+        }
+        let page_size = match u16::from_be_bytes([bytes[16], bytes[17]]) {
+            1 => 65536,
+            size => size as usize,
+        };
+        if bytes[18] == 2 || bytes[19] == 2 {
+            return Err("SQLite database is in WAL mode; checkpoint it first (PRAGMA journal_mode=DELETE)".to_string());
+        }
+        if u32::from_be_bytes(bytes[56..60].try_into().unwrap()) > 1 {
+            return Err("SQLite database text is not UTF-8".to_string());
+        }
+        if page_size < 512 || !page_size.is_power_of_two() {
+            return Err("SQLite database has an invalid page size".to_string());
+        }
+        Ok(Database { bytes, page_size, usable: page_size - bytes[20] as usize })
+    }
+
+    fn page(&self, number: u32) -> Result<&'a [u8], String> {
+        let start = (number as usize).checked_sub(1).ok_or_else(malformed)? * self.page_size;
+        self.bytes.get(start..start + self.page_size).ok_or_else(malformed)
+    }
+
+    /// Values of the table named `name` (`None` when it does not exist)
+    ///
+    /// Columns are read from the `CREATE TABLE` statement; an `INTEGER PRIMARY KEY` column
+    /// holds the rowid.
+    pub fn table(&self, name: &str) -> Result<Option<Rows>, String> {
+        let schema = self.rows(1)?;
+        let text = |row: &[SqlValue], i: usize| match row.get(i) {
+            Some(SqlValue::Text(text)) => text.clone(),
+            _ => String::new(),
+        };
+        let Some((_, entry)) = schema.iter().find(|(_, row)| text(row, 0) == "table" && text(row, 1).eq_ignore_ascii_case(name)) else {
+            return Ok(None);
+        };
+        let sql = text(entry, 4);
+        if sql.to_ascii_lowercase().contains("without rowid") {
+            return Err(format!("SQLite table {} has no rowid, which is not supported", name));
+        }
+        let Some(&SqlValue::Integer(root)) = entry.get(3) else {
+            return Err(malformed());
+        };
+        let columns = parse_columns(&sql);
+        let rows = self
+            .rows(root as u32)?
+            .into_iter()
+            .map(|(rowid, mut values)| {
+                // Columns added later are missing from older records
+                values.resize(columns.len().max(values.len()), SqlValue::Null);
+                for (value, (_, is_rowid)) in values.iter_mut().zip(&columns) {
+                    if *is_rowid && *value == SqlValue::Null {
+                        *value = SqlValue::Integer(rowid);
+                    }
+                }
+                values
+            })
+            .collect();
+        Ok(Some((columns.into_iter().map(|(name, _)| name).collect(), rows)))
+    }
+
+    /// Rows of the table b-tree rooted at `root`, in rowid order
+    fn rows(&self, root: u32) -> Result<Vec<(i64, Vec<SqlValue>)>, String> {
+        let page_count = self.bytes.len() / self.page_size;
+        let mut rows = Vec::new();
+        let mut stack = vec![root];
+        let mut visited = 0;
+        while let Some(number) = stack.pop() {
+            // A page reached twice means a cycle
+            visited += 1;
+            if visited > page_count {
+                return Err(malformed());
+            }
+            let page = self.page(number)?;
+            let offset = if number == 1 { FILE_HEADER_LEN } else { 0 };
+            let u16_at = |at: usize| page.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).ok_or_else(malformed);
+            let u32_at = |at: usize| page.get(at..at + 4).map(|b| u32::from_be_bytes(b.try_into().unwrap())).ok_or_else(malformed);
+            let kind = *page.get(offset).ok_or_else(malformed)?;
+            let cells = u16_at(offset + 3)?;
+            match kind {
+                LEAF_TABLE => {
+                    for i in 0..cells {
+                        let mut at = u16_at(offset + 8 + 2 * i)?;
+                        let (len, n) = read_varint(page, at)?;
+                        at += n;
+                        let (rowid, n) = read_varint(page, at)?;
+                        at += n;
+                        let payload = self.payload(page, at, len as usize)?;
+                        rows.push((rowid as i64, decode_record(&payload)?));
+                    }
+                }
+                INTERIOR_TABLE => {
+                    // Children are visited left to right
+                    stack.push(u32_at(offset + 8)?);
+                    for i in (0..cells).rev() {
+                        stack.push(u32_at(u16_at(offset + 12 + 2 * i)?)?);
+                    }
+                }
+                _ => return Err(malformed()),
+            }
+        }
+        Ok(rows)
+    }
+
+    /// A cell's payload of `len` bytes starting at `at`, following its overflow pages
+    fn payload(&self, page: &'a [u8], at: usize, len: usize) -> Result<Cow<'a, [u8]>, String> {
+        let local = table_local_size(len, self.usable);
+        let head = page.get(at..at + local).ok_or_else(malformed)?;
+        if local == len {
+            return Ok(Cow::Borrowed(head));
+        }
+        let mut payload = head.to_vec();
+        let mut next = page.get(at + local..at + local + 4).map(|b| u32::from_be_bytes(b.try_into().unwrap())).ok_or_else(malformed)?;
+        while payload.len() < len {
+            let overflow = self.page(next)?;
+            let take = (len - payload.len()).min(self.usable - 4);
+            payload.extend_from_slice(&overflow[4..4 + take]);
+            next = u32::from_be_bytes(overflow[..4].try_into().unwrap());
+        }
+        Ok(Cow::Owned(payload))
+    }
+}
+
+fn malformed() -> String {
+    "SQLite database is malformed".to_string()
+}
+
+/// Column names of a `CREATE TABLE` statement, and whether each is an alias of the rowid
+fn parse_columns(sql: &str) -> Vec<(String, bool)> {
+    let (Some(start), Some(end)) = (sql.find('('), sql.rfind(')')) else {
+        return Vec::new();
+    };
+    // Split the definitions at commas outside parentheses
+    let mut definitions = Vec::new();
+    let (mut depth, mut from) = (0, start + 1);
+    for (i, c) in sql[..end].char_indices().skip_while(|&(i, _)| i <= start) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                definitions.push(&sql[from..i]);
+                from = i + 1;
+            }
+            _ => {}
+        }
+    }
+    definitions.push(&sql[from..end]);
+    
+    definitions
+        .into_iter()
+        .map(str::trim)
+        .filter(|definition| {
+            let first = definition.split_whitespace().next().unwrap_or_default().to_ascii_uppercase();
+            !["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"].contains(&first.as_str())
+        })
+        .map(|definition| {
+            let (name, rest) = match definition.chars().next() {
+                Some(quote @ ('"' | '`' | '[')) => {
+                    let close = if quote == '[' { ']' } else { quote };
+                    definition[1..].split_once(close).unwrap_or((&definition[1..], ""))
+                }
+                _ => definition.split_once(char::is_whitespace).unwrap_or((definition, "")),
+            };
+            let words: Vec<String> = rest.split_whitespace().map(str::to_ascii_lowercase).collect();
+            let is_rowid = words.first().is_some_and(|ty| ty == "integer") && words.windows(2).any(|pair| pair == ["primary", "key"]);
+            (name.to_string(), is_rowid)
+        })
+        .collect()
+}
+
+fn decode_record(payload: &[u8]) -> Result<Vec<SqlValue>, String> {
+    let (header_len, mut at) = read_varint(payload, 0)?;
+    let mut body = header_len as usize;
+    let mut values = Vec::new();
+    while at < header_len as usize {
+        let (serial_type, n) = read_varint(payload, at)?;
+        at += n;
+        let len = match serial_type {
+            0 | 8 | 9 => 0,
+            1..=4 => serial_type as usize,
+            5 => 6,
+            6 | 7 => 8,
+            10 | 11 => return Err(malformed()),
+            _ => (serial_type as usize - 12) / 2,
+        };
+        let bytes = payload.get(body..body + len).ok_or_else(malformed)?;
+        body += len;
+        values.push(match serial_type {
+            0 => SqlValue::Null,
+            8 => SqlValue::Integer(0),
+            9 => SqlValue::Integer(1),
+            7 => SqlValue::Real(f64::from_be_bytes(bytes.try_into().unwrap())),
+            1..=6 => {
+                // Big-endian two's complement, sign-extended from the first byte
+                let fill = if bytes[0] & 0x80 != 0 { 0xFF } else { 0 };
+                let mut be = [fill; 8];
+                be[8 - len..].copy_from_slice(bytes);
+                SqlValue::Integer(i64::from_be_bytes(be))
+            }
+            _ if serial_type % 2 == 0 => SqlValue::Blob(bytes.to_vec()),
+            _ => SqlValue::Text(String::from_utf8_lossy(bytes).into_owned()),
+        });
+    }
+    Ok(values)
+}
+
+fn read_varint(bytes: &[u8], at: usize) -> Result<(u64, usize), String> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = *bytes.get(at + i).ok_or_else(malformed)?;
+        if i == 8 {
+            return Ok(((value << 8) | byte as u64, 9));
+        }
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte < 0x80 {
+            return Ok((value, i + 1));
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 127, 128, 16383, 16384, 1 << 56, u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            assert_eq!(read_varint(&buf, 0), Ok((value, buf.len())));
+        }
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let sql = "CREATE TABLE images (tile_data blob, \"tile id\" INTEGER PRIMARY KEY, note text DEFAULT (concat('a', 'b')), UNIQUE (tile_data))";
+        assert_eq!(
+            parse_columns(sql),
+            [("tile_data".to_string(), false), ("tile id".to_string(), true), ("note".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Enough rows, with payloads long enough to overflow, for interior pages in both trees
+        let rows: Vec<Vec<SqlValue>> = (0..5000_i64)
+            .map(|i| {
+                let blob = vec![i as u8; if i % 100 == 0 { 10_000 } else { (i % 300) as usize }];
+                vec![SqlValue::Integer(i - 2500), SqlValue::Integer(i64::MAX - i), SqlValue::Real(i as f64 / 4.0), SqlValue::Null, SqlValue::Blob(blob)]
+            })
+            .collect();
+        let table = Table {
+            name: "values".to_string(),
+            sql: "CREATE TABLE \"values\" (a integer, b integer, c real, d text, e blob)".to_string(),
+            rows: rows.clone(),
+            index: Some(Index { name: "values_index".to_string(), sql: "CREATE INDEX values_index ON \"values\" (a)".to_string(), columns: vec![0] }),
+        };
+        let bytes = write_database(&[table], 0).unwrap();
+        
+        let database = Database::open(&bytes).unwrap();
+        let (columns, read) = database.table("values").unwrap().unwrap();
+        assert_eq!(columns, ["a", "b", "c", "d", "e"]);
+        assert!(read == rows);
+        assert_eq!(database.table("other").unwrap(), None);
+        assert_eq!(Database::open(b"PK\x03\x04").err().unwrap(), "Not an SQLite database");
+    }
+}
@@ -0,0 +1,405 @@
+// Tile source module
+// Serve generated tiles by coordinate, deriving overzoomed tiles beyond max zoom
+
+use crate::clip::{self, ClipBox};
+use crate::geojson_parser::{calculate_bounds, Feature};
+use crate::mvt_decoder::decode_tile;
+use crate::mvt_encoder::encode_tile_at_extent;
+use crate::projection::{lonlat_to_meters, tile_bounds, EARTH_RADIUS};
+use crate::sink::TileSink;
+use crate::tiler::{self, TileFeature, TileGeometry};
+use crate::{TileCoord, TileMetadata, TileOptions};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Buffer around overzoomed tiles (in tile units) so clipped edges are not visible in rendering
+const OVERZOOM_BUFFER: f64 = 64.0;
+
+/// In-memory tileset that answers tile requests (e.g. from a preview or tile server)
+///
+/// Requests above the generated max zoom are answered by clipping and scaling the max-zoom
+/// ancestor tile, so clients can zoom past the generated range.
+#[derive(Debug, Default)]
+pub struct TileSource {
+    tiles: HashMap<TileCoord, Vec<u8>>,
+    metadata: Option<TileMetadata>,
+    source: Option<SourceFeatures>,
+}
+
+/// Re-tiled tile of an edit (`None` when the tile is now empty)
+pub type TileUpdate = (TileCoord, Option<Vec<u8>>);
+
+/// Input and settings kept by `TileSource::from_features` so edits can be re-tiled
+#[derive(Debug)]
+struct SourceFeatures {
+    features: Vec<Feature>,
+    min_zoom: u8,
+    /// Last generated zoom (beyond the requested one when `extend_zooms` added zooms)
+    max_zoom: u8,
+    /// Requested max zoom, from which `max_zoom_extent` applies
+    extent_zoom: u8,
+    layer_name: String,
+    options: TileOptions,
+}
+
+impl TileSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Generate a tileset from features, keeping them so `update_features` can apply edits
+    pub fn from_features(
+        features: Vec<Feature>,
+        min_zoom: u8,
+        max_zoom: u8,
+        layer_name: &str,
+        options: &TileOptions,
+    ) -> Result<Self, String> {
+        let mut source = Self::new();
+        crate::generate_to_sink(min_zoom, max_zoom, layer_name, options, &mut source, || Ok((features.clone(), Default::default(), Vec::new())))?;
+        // Edits re-tile the zooms that were generated, never adding more
+        let options = TileOptions { extend_zooms: None, ..options.clone() };
+        source.source = Some(SourceFeatures {
+            features,
+            min_zoom,
+            max_zoom: source.max_zoom(),
+            extent_zoom: max_zoom,
+            layer_name: layer_name.to_string(),
+            options,
+        });
+        Ok(source)
+    }
+    
+    /// Apply an edit and re-tile only the tiles the changed features touch
+    ///
+    /// `removed` features are matched by equality with the stored ones. Tiles are found and
+    /// re-tiled the way generation does it (filters, buffers and transforms included), and
+    /// hexbin or overview layers are recomputed from every stored feature. Returns the refreshed
+    /// tiles in tile id order; the metadata keeps describing the initial generation.
+    pub fn update_features(&mut self, added: Vec<Feature>, removed: &[Feature]) -> Result<Vec<TileUpdate>, String> {
+        let Some(source) = &mut self.source else {
+            return Err("TileSource was not created from features (see TileSource::from_features)".to_string());
+        };
+        let mut changed: Vec<Feature> = Vec::with_capacity(added.len() + removed.len());
+        for feature in removed {
+            if let Some(at) = source.features.iter().position(|f| f == feature) {
+                changed.push(source.features.remove(at));
+            }
+        }
+        changed.extend(added.iter().cloned());
+        source.features.extend(added);
+        
+        // Changed features as generation sees them, so filtered ones touch no tiles
+        let options = &source.options;
+        if crate::prepare_features(&mut changed, options, &mut Vec::new())?.is_some() {
+            changed.clear();
+        }
+        // Aggregates summarize every feature, and transforms may move features anywhere
+        let whole_set = options.hexbin.is_some() || options.overview.is_some() || !options.transforms.is_empty();
+        
+        let mut refreshed = Vec::new();
+        for zoom in source.min_zoom..=source.max_zoom {
+            // max_zoom_extent only applies from the tileset's max zoom
+            let mut options = options.clone();
+            if zoom < source.extent_zoom {
+                options.max_zoom_extent = None;
+            }
+            let extent = options.max_zoom_extent.or(options.extent).unwrap_or(tiler::DEFAULT_EXTENT);
+            let affected: HashSet<TileCoord> =
+                tiler::tile_features_at_extent(&changed, zoom, extent, &options.transforms)?.into_keys().collect();
+            if affected.is_empty() {
+                continue;
+            }
+            let mut nearby: Vec<Feature> = match whole_set {
+                true => source.features.clone(),
+                false => features_near(&source.features, &affected, zoom, options.buffer.as_ref().map_or(0.0, |b| b.distance)),
+            };
+            
+            let mut tiles = ZoomTiles { affected: &affected, tiles: HashMap::new() };
+            // Affected tiles left without features (e.g. outside the time window) become empty
+            if crate::prepare_features(&mut nearby, &options, &mut Vec::new())?.is_none() {
+                let input = (nearby, Default::default(), Vec::new());
+                crate::tile_prepared_features(zoom, zoom, &source.layer_name, &options, &mut tiles, input, 0.0)?;
+            }
+            let mut updates = tiles.tiles;
+            for &coord in &affected {
+                let tile = updates.remove(&coord);
+                match &tile {
+                    Some(data) => self.tiles.insert(coord, data.clone()),
+                    None => self.tiles.remove(&coord),
+                };
+                refreshed.push((coord, tile));
+            }
+        }
+        refreshed.sort_by_key(|(coord, _)| crate::pmtiles::tile_id(*coord));
+        Ok(refreshed)
+    }
+    
+    /// Metadata of the generated tileset (available once generation has finished)
+    pub fn metadata(&self) -> Option<&TileMetadata> {
+        self.metadata.as_ref()
+    }
+    
+    /// Get a tile (`None` if it has no data)
+    pub fn get_tile(&self, coord: TileCoord) -> Result<Option<Cow<'_, [u8]>>, String> {
+        if coord.z > crate::MAX_ZOOM {
+            return Err(format!("InvalidZoomRange: zoom {} exceeds the supported maximum of {}", coord.z, crate::MAX_ZOOM));
+        }
+        let max_zoom = self.max_zoom();
+        if coord.z <= max_zoom {
+            return Ok(self.tiles.get(&coord).map(|data| Cow::Borrowed(data.as_slice())));
+        }
+        
+        let dz = coord.z - max_zoom;
+        let parent = TileCoord::new(max_zoom, coord.x >> dz, coord.y >> dz);
+        match self.tiles.get(&parent) {
+            Some(data) => {
+                let offset = (coord.x - (parent.x << dz), coord.y - (parent.y << dz));
+                Ok(overzoom_tile(data, dz, offset)?.map(Cow::Owned))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    fn max_zoom(&self) -> u8 {
+        match &self.metadata {
+            Some(metadata) => metadata.max_zoom,
+            None => self.tiles.keys().map(|c| c.z).max().unwrap_or(0),
+        }
+    }
+}
+
+impl TileSink for TileSource {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        self.tiles.insert(coord, data.to_vec());
+        Ok(())
+    }
+    
+    fn finish(&mut self, metadata: &TileMetadata) -> Result<(), String> {
+        self.metadata = Some(metadata.clone());
+        Ok(())
+    }
+}
+
+/// Collects the re-tiled tiles of one zoom that an edit affects
+struct ZoomTiles<'a> {
+    affected: &'a HashSet<TileCoord>,
+    tiles: HashMap<TileCoord, Vec<u8>>,
+}
+
+impl TileSink for ZoomTiles<'_> {
+    fn write_tile(&mut self, coord: TileCoord, data: &[u8]) -> Result<(), String> {
+        if self.affected.contains(&coord) {
+            self.tiles.insert(coord, data.to_vec());
+        }
+        Ok(())
+    }
+    
+    fn finish(&mut self, _metadata: &TileMetadata) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Features whose bounding boxes come within half a tile (plus any buffer distance in meters) of
+/// an affected tile, in input order
+fn features_near(features: &[Feature], affected: &HashSet<TileCoord>, zoom: u8, buffer: f64) -> Vec<Feature> {
+    let boxes: Vec<(f64, f64, f64, f64)> = affected.iter().map(|c| tile_bounds(c.x, c.y, zoom)).collect();
+    // Ground meters grow by 1 / cos(latitude) = cosh(y / R) in mercator
+    let max_y = boxes.iter().map(|b| b.1.abs().max(b.3.abs())).fold(0.0, f64::max);
+    let margin = (boxes[0].2 - boxes[0].0) / 2.0 + buffer.abs() * (max_y / EARTH_RADIUS).cosh();
+    features
+        .iter()
+        .filter(|feature| {
+            let Ok((min_lon, min_lat, max_lon, max_lat)) = calculate_bounds(std::slice::from_ref(*feature)) else {
+                return false;
+            };
+            let (min_x, min_y) = lonlat_to_meters(min_lon, min_lat);
+            let (max_x, max_y) = lonlat_to_meters(max_lon, max_lat);
+            boxes.iter().any(|b| {
+                min_x <= b.2 + margin && max_x >= b.0 - margin && min_y <= b.3 + margin && max_y >= b.1 - margin
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Derive a descendant tile `dz` levels below an encoded tile
+///
+/// `offset` is the descendant's position among the `2^dz x 2^dz` children of the tile.
+pub fn overzoom_tile(data: &[u8], dz: u8, offset: (u32, u32)) -> Result<Option<Vec<u8>>, String> {
+    let scale = 2f64.powi(dz as i32);
+    let mut output = Vec::new();
+    
+    for layer in decode_tile(data)? {
+        let extent = layer.extent as f64;
+        let origin = (offset.0 as f64 * extent, offset.1 as f64 * extent);
+        let to_child = |&(x, y): &(i32, i32)| (x as f64 * scale - origin.0, y as f64 * scale - origin.1);
+        let clip_box = ClipBox { min: -OVERZOOM_BUFFER, max: extent + OVERZOOM_BUFFER };
+        
+        let features: Vec<TileFeature> = layer
+            .features
+            .into_iter()
+            .flat_map(|feature| {
+                let geometries: Vec<TileGeometry> = match &feature.geometry {
+                    TileGeometry::Point(x, y) => clip::clip_point(to_child(&(*x, *y)), &clip_box)
+                        .map(|p| TileGeometry::Point(round(p).0, round(p).1))
+                        .into_iter()
+                        .collect(),
+                    TileGeometry::LineString(coords) => {
+                        let line: Vec<(f64, f64)> = coords.iter().map(to_child).collect();
+                        clip::clip_line(&line, &clip_box)
+                            .into_iter()
+                            .map(|part| TileGeometry::LineString(part.into_iter().map(round).collect()))
+                            .collect()
+                    }
+                    TileGeometry::Polygon(rings) => clip_polygon(rings, &to_child, &clip_box).into_iter().collect(),
+                };
+                geometries.into_iter().map(move |geometry| TileFeature {
+                    geometry,
+                    properties: feature.properties.clone(),
+                    id: feature.id,
+                })
+            })
+            .collect::<Vec<_>>();
+        
+        if !features.is_empty() {
+            // Encoded tiles concatenate into one tile with all layers (repeated protobuf field)
+            output.extend(encode_tile_at_extent(&features, &layer.name, layer.extent, &TileOptions::default())?);
+        }
+    }
+    
+    Ok((!output.is_empty()).then_some(output))
+}
+
+/// Clip the rings of a (multi)polygon; holes are dropped along with their exterior
+fn clip_polygon(
+    rings: &[Vec<(i32, i32)>],
+    to_child: &impl Fn(&(i32, i32)) -> (f64, f64),
+    clip_box: &ClipBox,
+) -> Option<TileGeometry> {
+    let exterior_sign = crate::tiler::ring_signed_area(rings.first()?).signum();
+    let mut clipped = Vec::new();
+    let mut exterior_kept = false;
+    
+    for ring in rings {
+        let is_exterior = crate::tiler::ring_signed_area(ring).signum() == exterior_sign;
+        if !is_exterior && !exterior_kept {
+            continue;
+        }
+        
+        let points: Vec<(f64, f64)> = ring.iter().map(to_child).collect();
+        let ring: Vec<(i32, i32)> = clip::clip_ring(&points, clip_box).into_iter().map(round).collect();
+        let kept = ring.len() >= 4;
+        if kept {
+            clipped.push(ring);
+        }
+        if is_exterior {
+            exterior_kept = kept;
+        }
+    }
+    
+    (!clipped.is_empty()).then_some(TileGeometry::Polygon(clipped))
+}
+
+fn round((x, y): (f64, f64)) -> (i32, i32) {
+    (x.round() as i32, y.round() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvt_decoder::decode_tile;
+
+    fn source_with(features: &[TileFeature]) -> TileSource {
+        let mut source = TileSource::new();
+        source.write_tile(TileCoord::new(0, 0, 0), &crate::mvt_encoder::encode_tile(features, "test").unwrap()).unwrap();
+        source
+    }
+
+    #[test]
+    fn test_overzoom_scales_and_clips() {
+        let square = vec![(1024, 1024), (3072, 1024), (3072, 3072), (1024, 3072), (1024, 1024)];
+        let source = source_with(&[
+            TileFeature { geometry: TileGeometry::Point(100, 100), properties: serde_json::Map::new(), id: None },
+            TileFeature { geometry: TileGeometry::Polygon(vec![square]), properties: serde_json::Map::new(), id: None },
+        ]);
+        
+        // Top-left child at z1 holds the point and a quarter of the square
+        let tile = source.get_tile(TileCoord::new(1, 0, 0)).unwrap().unwrap();
+        let features = &decode_tile(&tile).unwrap()[0].features;
+        
+        assert!(matches!(features[0].geometry, TileGeometry::Point(200, 200)));
+        match &features[1].geometry {
+            TileGeometry::Polygon(rings) => {
+                let xs: Vec<i32> = rings[0].iter().map(|p| p.0).collect();
+                assert_eq!(*xs.iter().min().unwrap(), 2048);
+                assert_eq!(*xs.iter().max().unwrap(), 4096 + OVERZOOM_BUFFER as i32);
+            }
+            other => panic!("unexpected geometry: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_overzoom_empty_child() {
+        let source = source_with(&[TileFeature { geometry: TileGeometry::Point(100, 100), properties: serde_json::Map::new(), id: None }]);
+        
+        assert!(source.get_tile(TileCoord::new(1, 1, 1)).unwrap().is_none());
+        assert!(source.get_tile(TileCoord::new(0, 0, 0)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_update_features_refreshes_touched_tiles() {
+        let point = |lon: f64, lat: f64| Feature::new(crate::geojson_parser::GeometryType::Point(geo_types::Point::new(lon, lat)), serde_json::Map::new());
+        let features = vec![point(139.7, 35.7), point(-74.0, 40.7)];
+        let mut source = TileSource::from_features(features.clone(), 0, 4, "test", &TileOptions::default()).unwrap();
+        let tokyo = TileCoord::new(4, 14, 6);
+        assert!(source.get_tile(tokyo).unwrap().is_some());
+        
+        let moved = point(139.8, 35.7);
+        let refreshed = source.update_features(vec![moved.clone()], &features[..1]).unwrap();
+        
+        // Every zoom's tile over Tokyo is refreshed; New York tiles below z1 are untouched
+        let coords: Vec<TileCoord> = refreshed.iter().map(|(coord, _)| *coord).collect();
+        assert_eq!(coords.len(), 5);
+        assert!(coords.contains(&tokyo));
+        let expected = TileSource::from_features(vec![features[1].clone(), moved], 0, 4, "test", &TileOptions::default()).unwrap();
+        for (coord, tile) in &refreshed {
+            assert_eq!(tile.as_deref(), expected.get_tile(*coord).unwrap().as_deref(), "{:?}", coord);
+        }
+        assert!(TileSource::new().update_features(Vec::new(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_update_features_matches_generation() {
+        let point = |lon: f64, time: &str| {
+            let mut properties = serde_json::Map::new();
+            properties.insert("time".to_string(), serde_json::json!(time));
+            Feature::new(crate::geojson_parser::GeometryType::Point(geo_types::Point::new(lon, 35.7)), properties)
+        };
+        let options = TileOptions {
+            time_window: Some(crate::properties::TimeWindow {
+                property: "time".to_string(),
+                start: Some("2024-01-01".to_string()),
+                end: None,
+            }),
+            buffer: Some(crate::buffer::Buffer { distance: 5000.0, ..Default::default() }),
+            hexbin: Some(crate::aggregate::Hexbin { layer_name: "hex".to_string(), radius: vec![(0, 256.0)], sum: Vec::new() }),
+            ..Default::default()
+        };
+        let features = vec![point(139.7, "2024-06-01"), point(-74.0, "2024-06-01")];
+        let mut source = TileSource::from_features(features.clone(), 0, 3, "test", &options).unwrap();
+        
+        // Features outside the time window touch no tiles
+        assert!(source.update_features(vec![point(139.8, "2020-01-01")], &[]).unwrap().is_empty());
+        
+        // Removing the only Tokyo feature in the window empties its tiles instead of failing,
+        // and the hexagons are recounted from every feature
+        let refreshed = source.update_features(Vec::new(), &features[..1]).unwrap();
+        assert_eq!(refreshed.len(), 4);
+        let expected = TileSource::from_features(source.source.as_ref().unwrap().features.clone(), 0, 3, "test", &options).unwrap();
+        for (coord, tile) in &refreshed {
+            assert_eq!(tile.as_deref(), expected.get_tile(*coord).unwrap().as_deref(), "{:?}", coord);
+        }
+        assert!(refreshed.iter().any(|(coord, tile)| coord.z == 3 && tile.is_none()));
+    }
+}
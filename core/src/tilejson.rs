@@ -0,0 +1,350 @@
+// TileJSON module
+// Build tippecanoe-style metadata.json from tile metadata
+
+use crate::geojson_parser::{Feature, GeometryType};
+use crate::TileMetadata;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+
+/// Sample values listed per attribute
+const MAX_ATTRIBUTE_VALUES: usize = 100;
+/// Distinct values counted per attribute before counting stops
+const MAX_DISTINCT_VALUES: usize = 1000;
+
+/// Layer statistics in the tilestats format (used by map studios to build legends)
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct LayerStats {
+    pub count: usize,
+    /// Most common geometry type (empty when there are no features)
+    pub geometry: String,
+    pub attributes: Vec<AttributeStats>,
+}
+
+/// Statistics of one attribute
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AttributeStats {
+    pub attribute: String,
+    /// Distinct values (counting stops at 1000)
+    pub count: usize,
+    /// `string`, `number`, `boolean` or `mixed`
+    #[serde(rename = "type")]
+    pub value_type: String,
+    /// Up to 100 distinct non-numeric values
+    pub values: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// Human-readable documentation of one attribute
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub description: String,
+    pub unit: Option<String>,
+}
+
+/// Attribute descriptions written to `vector_layers[].fields` (instead of the value type)
+///
+/// A schema is a JSON object mapping property names to a description string or to an object
+/// with `description` and `unit` (`{"mag": {"description": "Magnitude", "unit": "Mw"}}`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub fields: BTreeMap<String, FieldInfo>,
+}
+
+impl FieldSchema {
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(text).map_err(|e| format!("Invalid schema JSON: {}", e))?;
+        let Value::Object(entries) = value else {
+            return Err("Schema must be a JSON object".to_string());
+        };
+        
+        let mut fields = BTreeMap::new();
+        for (name, entry) in entries {
+            let text = |key: &str| entry.get(key).and_then(Value::as_str).map(str::to_string);
+            let info = match &entry {
+                Value::String(description) => FieldInfo { description: description.clone(), unit: None },
+                Value::Object(_) => FieldInfo { description: text("description").unwrap_or_default(), unit: text("unit") },
+                _ => return Err(format!("Schema entry for '{}' must be a string or an object", name)),
+            };
+            fields.insert(name, info);
+        }
+        Ok(FieldSchema { fields })
+    }
+
+    /// Field text for `vector_layers` (`"Magnitude (Mw)"`)
+    fn describe(&self, attribute: &str) -> Option<String> {
+        let info = self.fields.get(attribute)?;
+        Some(match &info.unit {
+            Some(unit) => format!("{} ({})", info.description, unit),
+            None => info.description.clone(),
+        })
+    }
+}
+
+impl LayerStats {
+    /// Collect statistics over all features in one pass (null values are ignored)
+    pub fn from_features(features: &[Feature]) -> Self {
+        #[derive(Default)]
+        struct Accumulator {
+            distinct: HashSet<String>,
+            values: Vec<Value>,
+            types: [bool; 3], // string, number, boolean
+            min: Option<f64>,
+            max: Option<f64>,
+        }
+        
+        let mut geometry_counts = [0usize; 3];
+        let mut attributes: BTreeMap<&str, Accumulator> = BTreeMap::new();
+        for feature in features {
+            geometry_counts[match feature.geometry {
+                GeometryType::Point(_) => 0,
+                GeometryType::LineString(_) => 1,
+                GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => 2,
+            }] += 1;
+            
+            for (key, value) in &feature.properties {
+                if value.is_null() {
+                    continue;
+                }
+                let acc = attributes.entry(key).or_default();
+                match value {
+                    Value::Number(n) => {
+                        acc.types[1] = true;
+                        if let Some(n) = n.as_f64() {
+                            acc.min = Some(acc.min.map_or(n, |min| min.min(n)));
+                            acc.max = Some(acc.max.map_or(n, |max| max.max(n)));
+                        }
+                    }
+                    Value::Bool(_) => acc.types[2] = true,
+                    // Arrays and objects are encoded as their JSON text
+                    _ => acc.types[0] = true,
+                }
+                if acc.distinct.len() < MAX_DISTINCT_VALUES && acc.distinct.insert(value.to_string())
+                    && !value.is_number() && acc.values.len() < MAX_ATTRIBUTE_VALUES
+                {
+                    acc.values.push(value.clone());
+                }
+            }
+        }
+        
+        let geometry = match geometry_counts.iter().enumerate().max_by_key(|&(i, count)| (*count, std::cmp::Reverse(i))) {
+            Some((_, 0)) | None => String::new(),
+            Some((i, _)) => ["Point", "LineString", "Polygon"][i].to_string(),
+        };
+        let attributes = attributes
+            .into_iter()
+            .map(|(key, acc)| AttributeStats {
+                attribute: key.to_string(),
+                count: acc.distinct.len(),
+                value_type: match acc.types {
+                    [true, false, false] => "string",
+                    [false, true, false] => "number",
+                    [false, false, true] => "boolean",
+                    _ => "mixed",
+                }
+                .to_string(),
+                values: acc.values,
+                min: acc.min,
+                max: acc.max,
+            })
+            .collect();
+        
+        LayerStats { count: features.len(), geometry, attributes }
+    }
+}
+
+/// Build a tippecanoe-format `metadata.json` document
+pub fn to_tilejson(metadata: &TileMetadata) -> Value {
+    tilejson_document(std::slice::from_ref(metadata), &metadata.layer_name, metadata.bounds, metadata.center)
+}
+
+/// Build one `metadata.json` for several tilesets served together
+///
+/// Bounds are the union of all tilesets and the zoom range their envelope; each tileset
+/// contributes its own `vector_layers` and tilestats entries. Layer names must be unique.
+pub fn composite_tilejson(tilesets: &[TileMetadata]) -> Result<Value, String> {
+    let Some(first) = tilesets.first() else {
+        return Err("No tilesets to combine".to_string());
+    };
+    for (i, tileset) in tilesets.iter().enumerate() {
+        if tilesets[..i].iter().any(|other| other.layer_name == tileset.layer_name) {
+            return Err(format!("Duplicate layer name: {}", tileset.layer_name));
+        }
+    }
+    
+    let bounds = tilesets.iter().skip(1).fold(first.bounds, |(a, b, c, d), t| {
+        (a.min(t.bounds.0), b.min(t.bounds.1), c.max(t.bounds.2), d.max(t.bounds.3))
+    });
+    let center = crate::geojson_parser::calculate_center(bounds);
+    let name = tilesets.iter().map(|t| t.layer_name.as_str()).collect::<Vec<_>>().join(",");
+    Ok(tilejson_document(tilesets, &name, bounds, center))
+}
+
+fn tilejson_document(tilesets: &[TileMetadata], name: &str, bounds: (f64, f64, f64, f64), center: (f64, f64)) -> Value {
+    let (min_lon, min_lat, max_lon, max_lat) = bounds;
+    let (center_lon, center_lat) = center;
+    let min_zoom = tilesets.iter().map(|t| t.min_zoom).min().unwrap_or(0);
+    let max_zoom = tilesets.iter().map(|t| t.max_zoom).max().unwrap_or(0);
+    let center_zoom = (min_zoom as u32 + max_zoom as u32) / 2;
+    
+    let vector_layers = json!({
+        "vector_layers": tilesets.iter().map(vector_layer).collect::<Vec<_>>(),
+        "tilestats": {
+            "layerCount": tilesets.len(),
+            "layers": tilesets.iter().map(tilestats_layer).collect::<Vec<_>>(),
+        },
+    });
+    
+    let mut document = json!({
+        "name": name,
+        "description": name,
+        "version": "1",
+        "minzoom": min_zoom.to_string(),
+        "maxzoom": max_zoom.to_string(),
+        "center": format!("{},{},{}", center_lon, center_lat, center_zoom),
+        "bounds": format!("{},{},{},{}", min_lon, min_lat, max_lon, max_lat),
+        "type": "overlay",
+        "format": "pbf",
+        // The crate version only: a commit hash would make builds of the same data differ
+        "generator": format!("vector-tile-builder v{}", crate::version::version().version),
+        "generator_options": "Tile generation from GeoJSON",
+        "json": vector_layers.to_string(),
+    });
+    
+    // Dataset provenance, nested so it cannot clash with TileJSON keys (the first tileset wins)
+    let mut foreign_members = serde_json::Map::new();
+    for tileset in tilesets {
+        for (key, value) in &tileset.foreign_members {
+            foreign_members.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    if !foreign_members.is_empty() {
+        document["foreign_members"] = Value::Object(foreign_members);
+    }
+    document
+}
+
+fn vector_layer(metadata: &TileMetadata) -> Value {
+    // vector_layers field types are capitalized ("String", "Number", ...);
+    // attributes documented in the schema get their description instead
+    let fields: serde_json::Map<String, Value> = metadata.stats.attributes
+        .iter()
+        .map(|a| {
+            let mut value_type = a.value_type.clone();
+            value_type[..1].make_ascii_uppercase();
+            (a.attribute.clone(), Value::String(metadata.schema.describe(&a.attribute).unwrap_or(value_type)))
+        })
+        .collect();
+    
+    let mut layer = json!({
+        "id": metadata.layer_name,
+        "description": "",
+        "minzoom": metadata.min_zoom,
+        "maxzoom": metadata.max_zoom,
+        "fields": fields,
+    });
+    // Property the feature ids were taken from, for joining tiles back to the source data
+    if let Some(id_field) = &metadata.id_field {
+        layer["id_field"] = Value::String(id_field.clone());
+    }
+    layer
+}
+
+fn tilestats_layer(metadata: &TileMetadata) -> Value {
+    let stats = &metadata.stats;
+    json!({
+        "layer": metadata.layer_name,
+        "count": stats.count,
+        "geometry": if stats.geometry.is_empty() { "Unknown" } else { &stats.geometry },
+        "attributeCount": stats.attributes.len(),
+        "attributes": stats.attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_tilejson() {
+        let metadata = TileMetadata {
+            max_zoom: 5,
+            layer_name: "cities".to_string(),
+            bounds: (139.0, 35.0, 140.0, 36.0),
+            center: (139.5, 35.5),
+            ..Default::default()
+        };
+        
+        let tilejson = to_tilejson(&metadata);
+        
+        assert_eq!(tilejson["center"], "139.5,35.5,2");
+        assert_eq!(tilejson["maxzoom"], "5");
+        assert_eq!(tilejson["generator"], format!("vector-tile-builder v{}", env!("CARGO_PKG_VERSION")));
+        let layers: Value = serde_json::from_str(tilejson["json"].as_str().unwrap()).unwrap();
+        assert_eq!(layers["vector_layers"][0]["id"], "cities");
+    }
+
+    #[test]
+    fn test_layer_stats() {
+        let features: Vec<Feature> = [
+            serde_json::json!({"name": "Tokyo", "population": 13960000, "capital": true}),
+            serde_json::json!({"name": "Osaka", "population": 2750000, "capital": "no"}),
+            serde_json::json!({"name": "Tokyo", "population": null}),
+        ]
+        .into_iter()
+        .map(|properties| Feature::new(GeometryType::Point(geo_types::Point::new(0.0, 0.0)), properties.as_object().unwrap().clone()))
+        .collect();
+        
+        let stats = LayerStats::from_features(&features);
+        
+        assert_eq!(stats.geometry, "Point");
+        let [capital, name, population] = &stats.attributes[..] else {
+            panic!("Expected three attributes");
+        };
+        assert_eq!(capital.value_type, "mixed");
+        assert_eq!((name.count, name.values.clone()), (2, vec![Value::from("Tokyo"), Value::from("Osaka")]));
+        assert_eq!((population.min, population.max), (Some(2750000.0), Some(13960000.0)));
+        assert!(population.values.is_empty());
+    }
+
+    #[test]
+    fn test_composite_tilejson() {
+        let tileset = |layer_name: &str, min_zoom, max_zoom, bounds| TileMetadata {
+            min_zoom,
+            max_zoom,
+            layer_name: layer_name.to_string(),
+            bounds,
+            ..Default::default()
+        };
+        let tilesets = [
+            tileset("roads", 4, 14, (139.0, 35.0, 140.0, 36.0)),
+            tileset("buildings", 12, 16, (139.5, 34.5, 139.8, 35.5)),
+        ];
+        
+        let tilejson = composite_tilejson(&tilesets).unwrap();
+        
+        assert_eq!((tilejson["minzoom"].as_str(), tilejson["maxzoom"].as_str()), (Some("4"), Some("16")));
+        assert_eq!(tilejson["bounds"], "139,34.5,140,36");
+        let layers: Value = serde_json::from_str(tilejson["json"].as_str().unwrap()).unwrap();
+        assert_eq!(layers["vector_layers"][1]["id"], "buildings");
+        assert_eq!(layers["vector_layers"][1]["minzoom"], 12);
+        assert_eq!(layers["tilestats"]["layerCount"], 2);
+        assert!(composite_tilejson(&[tilesets[0].clone(), tilesets[0].clone()]).is_err());
+        assert!(composite_tilejson(&[]).is_err());
+    }
+
+    #[test]
+    fn test_field_schema() {
+        let json = r#"{"place": "Nearest named place", "mag": {"description": "Magnitude", "unit": "Mw"}}"#;
+        
+        let schema = FieldSchema::from_json(json).unwrap();
+        
+        assert_eq!(schema.describe("place").as_deref(), Some("Nearest named place"));
+        assert_eq!(schema.describe("mag").as_deref(), Some("Magnitude (Mw)"));
+        assert_eq!(schema.describe("depth"), None);
+        assert!(FieldSchema::from_json("mag = 5").unwrap_err().starts_with("Invalid schema JSON"));
+        assert!(FieldSchema::from_json(r#"{"mag": 5}"#).is_err());
+    }
+}
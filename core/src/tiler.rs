@@ -2,97 +2,275 @@
 // Assign features to tiles and convert to tile coordinates
 
 use crate::geojson_parser::{Feature, GeometryType};
-use crate::projection::{lonlat_to_tile, lonlat_to_meters, meters_to_pixel_in_tile, tile_bounds};
+use crate::projection::{
+    lonlat_to_tile, lonlat_to_meters, lonlat_to_meters_batch, meters_to_pixel_in_tile, meters_to_world_batch,
+    quantize_pixel, world_to_tile_batch,
+};
+use crate::transform::{apply_transforms, FeatureTransform};
 use crate::TileCoord;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use geo_types::{Point, LineString, Polygon, Coord};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::Arc;
+use geo_types::{Point, LineString, Polygon};
 
 /// Feature within tile
 #[derive(Debug, Clone)]
 pub struct TileFeature {
     pub geometry: TileGeometry,
     pub properties: serde_json::Map<String, serde_json::Value>,
+    /// Id of the source feature (see `Feature::id`)
+    pub id: Option<u64>,
 }
 
-/// Geometry within tile (tile coordinate system: 0-4096)
+/// Geometry within tile (tile coordinate system: 0-extent, 4096 by default)
 #[derive(Debug, Clone)]
 pub enum TileGeometry {
     Point(i32, i32),
     LineString(Vec<(i32, i32)>),
-    Polygon(Vec<Vec<(i32, i32)>>), // Exterior ring + interior rings (holes)
+    Polygon(Vec<Vec<(i32, i32)>>), // Exterior ring + interior rings (holes), possibly followed by more polygons
 }
 
-/// MVT extent (tile coordinate range)
-const EXTENT: i32 = 4096;
+/// Features by tile, hashed with `TileHasher`
+pub type TileMap = HashMap<TileCoord, Vec<TileFeature>, BuildHasherDefault<TileHasher>>;
+
+/// Tiles a map is pre-sized for at most (its features' Vecs grow from there)
+const MAX_PRESIZED_TILES: usize = 1 << 16;
+
+/// Multiply-rotate hasher (as FxHash) for tile coordinates
+///
+/// Coordinates are a few small integers, for which SipHash is needlessly slow.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TileHasher(u64);
+
+impl TileHasher {
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+impl Hasher for TileHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|&byte| self.add(byte as u64));
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.add(n as u64);
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.add(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.add(n);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+}
+
+/// Empty tile map sized for `features` at `zoom` (one tile per feature, at most the zoom's tiles)
+pub(crate) fn tile_map_for(features: usize, zoom: u8) -> TileMap {
+    let zoom_tiles = 1usize.checked_shl(2 * zoom as u32).unwrap_or(usize::MAX);
+    TileMap::with_capacity_and_hasher(features.min(zoom_tiles).min(MAX_PRESIZED_TILES), Default::default())
+}
+
+/// Default MVT extent (tile coordinate range)
+pub const DEFAULT_EXTENT: u32 = 4096;
+
+/// Feature order within an encoded layer (renderers draw in this order)
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortBy {
+    /// Ascending by property value (features without the property go last)
+    Property(String),
+    /// Largest polygons first, so smaller ones are drawn on top
+    AreaDescending,
+    /// Longest lines first
+    LengthDescending,
+}
 
 /// Assign features to tiles
 pub fn tile_features(
     features: &[Feature],
     zoom: u8,
-) -> Result<HashMap<TileCoord, Vec<TileFeature>>, String> {
-    let mut tiles: HashMap<TileCoord, Vec<TileFeature>> = HashMap::new();
+) -> Result<TileMap, String> {
+    tile_transformed_features(features, zoom, &[])
+}
+
+/// Assign features to tiles, running each through the transforms first
+pub fn tile_transformed_features(
+    features: &[Feature],
+    zoom: u8,
+    transforms: &[Arc<dyn FeatureTransform>],
+) -> Result<TileMap, String> {
+    tile_features_at_extent(features, zoom, DEFAULT_EXTENT, transforms)
+}
+
+/// Assign features to tiles with coordinates in `0..extent`, running each through the transforms first
+pub fn tile_features_at_extent(
+    features: &[Feature],
+    zoom: u8,
+    extent: u32,
+    transforms: &[Arc<dyn FeatureTransform>],
+) -> Result<TileMap, String> {
+    tile_features_counting_clamped(features, None, zoom, extent, transforms).map(|(tiles, _)| tiles)
+}
+
+/// A source geometry in WebMercator meters: its point, its line or its rings (exterior first)
+pub(crate) type Projected = Vec<Vec<(f64, f64)>>;
+
+/// Project each feature's geometry once, for reuse by every zoom
+pub(crate) fn project_features(features: &[Feature]) -> Vec<Projected> {
+    features.iter().map(|feature| project(&feature.geometry)).collect()
+}
+
+fn project(geometry: &GeometryType) -> Projected {
+    let project_coords = |coords: &[geo_types::Coord<f64>]| {
+        let mut meters = Vec::new();
+        lonlat_to_meters_batch(coords, &mut meters);
+        meters
+    };
+    match geometry {
+        GeometryType::Point(point) => vec![vec![lonlat_to_meters(point.x(), point.y())]],
+        GeometryType::LineString(line) => vec![project_coords(&line.0)],
+        GeometryType::Polygon(polygon) => std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .map(|ring| project_coords(&ring.0))
+            .collect(),
+        // The encoder tells exteriors from holes by the first ring's winding, so the later
+        // polygons' rings are wound to match it
+        GeometryType::MultiPolygon(polygons) => {
+            let signed_area = |ring: &[(f64, f64)]| -> f64 {
+                ring.windows(2).map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1).sum()
+            };
+            let mut rings: Projected = Vec::new();
+            let mut exterior_sign = None;
+            for polygon in &polygons.0 {
+                for (index, ring) in std::iter::once(polygon.exterior()).chain(polygon.interiors()).enumerate() {
+                    let mut meters = project_coords(&ring.0);
+                    let sign = signed_area(&meters).signum();
+                    let wanted = *exterior_sign.get_or_insert(sign);
+                    if (index == 0) != (sign == wanted) {
+                        meters.reverse();
+                    }
+                    rings.push(meters);
+                }
+            }
+            rings
+        }
+    }
+}
+
+/// `tile_features_at_extent`, also returning the number of clamped coordinates (see `quantize_pixel`)
+///
+/// `projected` holds the output of `project_features` for `features`; each geometry is
+/// projected here when it is `None` or a transform changed the geometry.
+pub(crate) fn tile_features_counting_clamped(
+    features: &[Feature],
+    projected: Option<&[Projected]>,
+    zoom: u8,
+    extent: u32,
+    transforms: &[Arc<dyn FeatureTransform>],
+) -> Result<(TileMap, usize), String> {
+    let extent = extent as i32;
+    let mut tiles = tile_map_for(features.len(), zoom);
+    let mut clamped = 0;
     
-    for feature in features {
+    for (index, source) in features.iter().enumerate() {
+        if !source.visible_at(zoom) {
+            continue;
+        }
+        let transformed;
+        let feature = if transforms.is_empty() {
+            source
+        } else {
+            match apply_transforms(source.clone(), zoom, transforms) {
+                Some(f) => {
+                    transformed = f;
+                    &transformed
+                }
+                None => continue,
+            }
+        };
+        let projected_here;
+        let meters = match projected {
+            Some(projected) if transforms.is_empty() || feature.geometry == source.geometry => &projected[index],
+            _ => {
+                projected_here = project(&feature.geometry);
+                &projected_here
+            }
+        };
+        
         match &feature.geometry {
             GeometryType::Point(point) => {
-                tile_point(point, &feature.properties, zoom, &mut tiles)?;
+                clamped += tile_point(point, meters[0][0], &feature.properties, feature.id, zoom, extent, &mut tiles)?;
             }
             GeometryType::LineString(line) => {
-                tile_linestring(line, &feature.properties, zoom, &mut tiles)?;
+                clamped += tile_linestring(line, &meters[0], &feature.properties, feature.id, zoom, extent, &mut tiles)?;
             }
-            GeometryType::Polygon(polygon) => {
-                tile_polygon(polygon, &feature.properties, zoom, &mut tiles)?;
+            GeometryType::Polygon(_) | GeometryType::MultiPolygon(_) => {
+                let polygons = feature.geometry.polygons();
+                clamped += tile_polygon(polygons, meters, &feature.properties, feature.id, zoom, extent, &mut tiles)?;
             }
         }
     }
     
-    Ok(tiles)
+    Ok((tiles, clamped))
 }
 
-/// Add Point to tile
+/// Add Point to tile (returns the number of clamped coordinates)
 fn tile_point(
     point: &Point<f64>,
+    meters: (f64, f64),
     properties: &serde_json::Map<String, serde_json::Value>,
+    id: Option<u64>,
     zoom: u8,
-    tiles: &mut HashMap<TileCoord, Vec<TileFeature>>,
-) -> Result<(), String> {
+    extent: i32,
+    tiles: &mut TileMap,
+) -> Result<usize, String> {
     let lon = point.x();
     let lat = point.y();
     
     // Get tile coordinates
     let (tx, ty) = lonlat_to_tile(lon, lat, zoom);
     
-    // Convert to WebMercator meters
-    let (mx, my) = lonlat_to_meters(lon, lat);
-    
     // Convert to pixel coordinates within tile
-    let (px, py) = meters_to_pixel_in_tile(mx, my, tx, ty, zoom);
+    let (px, py) = meters_to_pixel_in_tile(meters.0, meters.1, tx, ty, zoom);
     
-    // Convert to MVT extent coordinates (0-4096)
-    let tile_x = ((px / 256.0) * EXTENT as f64) as i32;
-    let tile_y = ((py / 256.0) * EXTENT as f64) as i32;
+    // Convert to MVT extent coordinates (0-extent)
+    let ((tile_x, tile_y), clamped) = quantize_pixel(px, py, extent);
     
     // Add to tile
     let coord = TileCoord::new(zoom, tx, ty);
     let tile_feature = TileFeature {
         geometry: TileGeometry::Point(tile_x, tile_y),
         properties: properties.clone(),
+        id,
     };
     
-    tiles.entry(coord).or_insert_with(Vec::new).push(tile_feature);
+    tiles.entry(coord).or_default().push(tile_feature);
     
-    Ok(())
+    Ok(clamped as usize)
 }
 
-/// Add LineString to tiles (supports multiple tiles)
+/// Add LineString to tiles (supports multiple tiles; returns the number of clamped coordinates)
 fn tile_linestring(
     line: &LineString<f64>,
+    meters: &[(f64, f64)],
     properties: &serde_json::Map<String, serde_json::Value>,
+    id: Option<u64>,
     zoom: u8,
-    tiles: &mut HashMap<TileCoord, Vec<TileFeature>>,
-) -> Result<(), String> {
+    extent: i32,
+    tiles: &mut TileMap,
+) -> Result<usize, String> {
     if line.0.is_empty() {
-        return Ok(());
+        return Ok(0);
     }
     
     // Calculate bounding box of LineString
@@ -102,99 +280,257 @@ fn tile_linestring(
     let (tx_min, ty_max) = lonlat_to_tile(min_lon, min_lat, zoom);
     let (tx_max, ty_min) = lonlat_to_tile(max_lon, max_lat, zoom);
     
-    // Place LineString in each tile
+    // Project once for the zoom, then place the LineString in each tile by offset
+    let mut world = Vec::new();
+    meters_to_world_batch(meters, zoom, extent as u32, &mut world);
+    let mut clamped = 0;
+    
     for tx in tx_min..=tx_max {
         for ty in ty_min..=ty_max {
-            // Convert all coordinates to this tile's coordinate system
+            // Translate all coordinates to this tile's coordinate system
             let mut tile_coords = Vec::new();
-            for coord in &line.0 {
-                let (mx, my) = lonlat_to_meters(coord.x, coord.y);
-                let (px, py) = meters_to_pixel_in_tile(mx, my, tx, ty, zoom);
-                
-                let tile_x = ((px / 256.0) * EXTENT as f64) as i32;
-                let tile_y = ((py / 256.0) * EXTENT as f64) as i32;
-                
-                tile_coords.push((tile_x, tile_y));
-            }
+            clamped += world_to_tile_batch(&world, tx, ty, extent, &mut tile_coords);
             
             // Add to tile
             let coord = TileCoord::new(zoom, tx, ty);
             let tile_feature = TileFeature {
                 geometry: TileGeometry::LineString(tile_coords),
                 properties: properties.clone(),
+                id,
             };
             
-            tiles.entry(coord).or_insert_with(Vec::new).push(tile_feature);
+            tiles.entry(coord).or_default().push(tile_feature);
         }
     }
     
-    Ok(())
+    Ok(clamped)
 }
 
-/// Add Polygon to tiles (supports multiple tiles)
+/// Add a Polygon, or the polygons of a MultiPolygon as one feature, to tiles (supports multiple
+/// tiles; returns the number of clamped coordinates)
 fn tile_polygon(
-    polygon: &Polygon<f64>,
+    polygons: &[Polygon<f64>],
+    rings_meters: &[Vec<(f64, f64)>],
     properties: &serde_json::Map<String, serde_json::Value>,
+    id: Option<u64>,
     zoom: u8,
-    tiles: &mut HashMap<TileCoord, Vec<TileFeature>>,
-) -> Result<(), String> {
-    let exterior = polygon.exterior();
-    if exterior.0.is_empty() {
-        return Ok(());
+    extent: i32,
+    tiles: &mut TileMap,
+) -> Result<usize, String> {
+    if polygons.iter().all(|polygon| polygon.exterior().0.is_empty()) {
+        return Ok(0);
     }
     
-    // Calculate bounding box of Polygon
-    let (min_lon, min_lat, max_lon, max_lat) = polygon_bounds(polygon);
+    // Calculate bounding box of the polygons
+    let (min_lon, min_lat, max_lon, max_lat) = polygons.iter().map(polygon_bounds).fold(
+        (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        |b, p| (b.0.min(p.0), b.1.min(p.1), b.2.max(p.2), b.3.max(p.3)),
+    );
     
     // Get range of intersecting tiles
     let (tx_min, ty_max) = lonlat_to_tile(min_lon, min_lat, zoom);
     let (tx_max, ty_min) = lonlat_to_tile(max_lon, max_lat, zoom);
     
-    // Place Polygon in each tile
+    // Project once for the zoom, then place the Polygon in each tile by offset
+    let rings_world: Vec<Vec<(i64, i64)>> = rings_meters
+        .iter()
+        .map(|meters| {
+            let mut world = Vec::new();
+            meters_to_world_batch(meters, zoom, extent as u32, &mut world);
+            world
+        })
+        .collect();
+    let mut clamped = 0;
     for tx in tx_min..=tx_max {
         for ty in ty_min..=ty_max {
-            // Convert exterior ring
-            let mut tile_rings = Vec::new();
-            let mut exterior_ring = Vec::new();
-            
-            for coord in &exterior.0 {
-                let (mx, my) = lonlat_to_meters(coord.x, coord.y);
-                let (px, py) = meters_to_pixel_in_tile(mx, my, tx, ty, zoom);
-                
-                let tile_x = ((px / 256.0) * EXTENT as f64) as i32;
-                let tile_y = ((py / 256.0) * EXTENT as f64) as i32;
-                
-                exterior_ring.push((tile_x, tile_y));
-            }
-            tile_rings.push(exterior_ring);
-            
-            // Convert interior rings (holes)
-            for interior in polygon.interiors() {
-                let mut interior_ring = Vec::new();
-                for coord in &interior.0 {
-                    let (mx, my) = lonlat_to_meters(coord.x, coord.y);
-                    let (px, py) = meters_to_pixel_in_tile(mx, my, tx, ty, zoom);
-                    
-                    let tile_x = ((px / 256.0) * EXTENT as f64) as i32;
-                    let tile_y = ((py / 256.0) * EXTENT as f64) as i32;
-                    
-                    interior_ring.push((tile_x, tile_y));
-                }
-                tile_rings.push(interior_ring);
-            }
+            let tile_rings: Vec<Vec<(i32, i32)>> = rings_world
+                .iter()
+                .map(|world| {
+                    let mut ring = Vec::new();
+                    clamped += world_to_tile_batch(world, tx, ty, extent, &mut ring);
+                    ring
+                })
+                .collect();
             
             // Add to tile
             let coord = TileCoord::new(zoom, tx, ty);
             let tile_feature = TileFeature {
                 geometry: TileGeometry::Polygon(tile_rings),
                 properties: properties.clone(),
+                id,
             };
             
-            tiles.entry(coord).or_insert_with(Vec::new).push(tile_feature);
+            tiles.entry(coord).or_default().push(tile_feature);
         }
     }
     
-    Ok(())
+    Ok(clamped)
+}
+
+/// Per-tile feature limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLimit {
+    /// Maximum number of features kept in each tile
+    pub n: usize,
+    /// Property ranking the features (highest values are kept)
+    pub rank_by: String,
+}
+
+/// Keep only the `n` highest-ranked features in a tile (preserving their relative order)
+pub fn limit_features(features: &mut Vec<TileFeature>, limit: &TileLimit) {
+    if features.len() <= limit.n {
+        return;
+    }
+    
+    let mut ranked: Vec<usize> = (0..features.len()).collect();
+    ranked.sort_by(|&a, &b| {
+        compare_rank(features[a].properties.get(&limit.rank_by), features[b].properties.get(&limit.rank_by))
+    });
+    
+    let mut keep = vec![false; features.len()];
+    for &index in ranked.iter().take(limit.n) {
+        keep[index] = true;
+    }
+    
+    let mut index = 0;
+    features.retain(|_| {
+        let kept = keep[index];
+        index += 1;
+        kept
+    });
+}
+
+/// Per-tile label rank of point features
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelRank {
+    /// Property ordering the points (highest values rank first)
+    pub rank_by: String,
+    /// Property receiving the rank (`1..=N` within each tile)
+    pub property: String,
+}
+
+impl LabelRank {
+    /// Rank points by `rank_by` into a `rank` property
+    pub fn new(rank_by: impl Into<String>) -> Self {
+        Self { rank_by: rank_by.into(), property: "rank".to_string() }
+    }
+}
+
+/// Number the points of a tile by rank, so styles can reveal labels progressively (`rank <= 5`)
+///
+/// Ties keep the input order; other geometries are left unranked.
+pub fn rank_labels(features: &mut [TileFeature], rank: &LabelRank) {
+    let mut points: Vec<usize> = (0..features.len())
+        .filter(|&i| matches!(features[i].geometry, TileGeometry::Point(..)))
+        .collect();
+    points.sort_by(|&a, &b| compare_rank(features[a].properties.get(&rank.rank_by), features[b].properties.get(&rank.rank_by)));
+    for (position, index) in points.into_iter().enumerate() {
+        features[index].properties.insert(rank.property.clone(), (position as u64 + 1).into());
+    }
+}
+
+/// Descending rank order (features without the ranking property rank lowest)
+pub(crate) fn compare_rank(a: Option<&serde_json::Value>, b: Option<&serde_json::Value>) -> Ordering {
+    match (a, b) {
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        _ => compare_property_values(b, a),
+    }
+}
+
+/// Sort features within a tile
+pub fn sort_features(features: &mut [TileFeature], sort_by: &SortBy) {
+    match sort_by {
+        SortBy::Property(key) => {
+            features.sort_by(|a, b| compare_property_values(a.properties.get(key), b.properties.get(key)));
+        }
+        SortBy::AreaDescending => {
+            features.sort_by(|a, b| geometry_area(&b.geometry).total_cmp(&geometry_area(&a.geometry)));
+        }
+        SortBy::LengthDescending => {
+            features.sort_by(|a, b| geometry_length(&b.geometry).total_cmp(&geometry_length(&a.geometry)));
+        }
+    }
+}
+
+/// Compare two property values (numbers < strings < booleans, missing values last)
+pub fn compare_property_values(a: Option<&serde_json::Value>, b: Option<&serde_json::Value>) -> Ordering {
+    use serde_json::Value;
+    
+    fn rank(value: Option<&Value>) -> u8 {
+        match value {
+            Some(Value::Number(_)) => 0,
+            Some(Value::String(_)) => 1,
+            Some(Value::Bool(_)) => 2,
+            Some(_) => 3,
+            None => 4,
+        }
+    }
+    
+    match (a, b) {
+        (Some(Value::Number(x)), Some(Value::Number(y))) => {
+            x.as_f64().unwrap_or(0.0).total_cmp(&y.as_f64().unwrap_or(0.0))
+        }
+        (Some(Value::String(x)), Some(Value::String(y))) => x.cmp(y),
+        (Some(Value::Bool(x)), Some(Value::Bool(y))) => x.cmp(y),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Area of a geometry in tile units (0 for non-polygons)
+///
+/// Rings wound like the first ring count as exteriors, the others as holes.
+pub(crate) fn geometry_area(geometry: &TileGeometry) -> f64 {
+    match geometry {
+        TileGeometry::Polygon(rings) => {
+            let Some(first) = rings.first() else {
+                return 0.0;
+            };
+            let exterior_sign = ring_signed_area(first).signum();
+            let mut area = 0.0;
+            for ring in rings {
+                let ring_area = ring_signed_area(ring);
+                if ring_area.signum() == exterior_sign {
+                    area += ring_area.abs();
+                } else {
+                    area -= ring_area.abs();
+                }
+            }
+            area.max(0.0)
+        }
+        _ => 0.0,
+    }
+}
+
+/// Length of a geometry in tile units (0 for points)
+fn geometry_length(geometry: &TileGeometry) -> f64 {
+    let path_length = |coords: &[(i32, i32)]| -> f64 {
+        coords
+            .windows(2)
+            .map(|w| {
+                let dx = (w[1].0 - w[0].0) as f64;
+                let dy = (w[1].1 - w[0].1) as f64;
+                (dx * dx + dy * dy).sqrt()
+            })
+            .sum()
+    };
+    
+    match geometry {
+        TileGeometry::Point(_, _) => 0.0,
+        TileGeometry::LineString(coords) => path_length(coords),
+        TileGeometry::Polygon(rings) => rings.first().map(|ring| path_length(ring)).unwrap_or(0.0),
+    }
+}
+
+/// Signed ring area (shoelace formula)
+pub(crate) fn ring_signed_area(ring: &[(i32, i32)]) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..ring.len() {
+        let (x1, y1) = ring[i];
+        let (x2, y2) = ring[(i + 1) % ring.len()];
+        sum += x1 as f64 * y2 as f64 - x2 as f64 * y1 as f64;
+    }
+    sum / 2.0
 }
 
 /// Calculate LineString bounding box
@@ -241,10 +577,121 @@ mod tests {
     fn test_tile_point() {
         let point = Point::new(139.7671, 35.6812);
         let properties = serde_json::Map::new();
-        let mut tiles = HashMap::new();
+        let mut tiles = TileMap::default();
         
-        tile_point(&point, &properties, 5, &mut tiles).unwrap();
+        let meters = lonlat_to_meters(point.x(), point.y());
+        tile_point(&point, meters, &properties, None, 5, DEFAULT_EXTENT as i32, &mut tiles).unwrap();
         
         assert_eq!(tiles.len(), 1);
     }
+
+    #[test]
+    fn test_projected_features_match() {
+        let features = crate::geojson_parser::parse_geojson(include_bytes!("../../test_data/points.geojson")).unwrap();
+        let projected = project_features(&features);
+        
+        for zoom in [0, 7, 14] {
+            let (cached, _) = tile_features_counting_clamped(&features, Some(&projected), zoom, DEFAULT_EXTENT, &[]).unwrap();
+            let (fresh, _) = tile_features_counting_clamped(&features, None, zoom, DEFAULT_EXTENT, &[]).unwrap();
+            let geometries = |tiles: &TileMap| {
+                let mut geometries: Vec<String> = tiles.iter()
+                    .map(|(coord, features)| format!("{:?} {:?}", coord, features.iter().map(|f| &f.geometry).collect::<Vec<_>>()))
+                    .collect();
+                geometries.sort();
+                geometries
+            };
+            assert_eq!(geometries(&cached), geometries(&fresh));
+        }
+    }
+
+    #[test]
+    fn test_multipolygon_is_one_feature() {
+        // Two squares wound in opposite directions, the first with a hole
+        let geojson = br#"{"type": "Feature", "properties": {}, "geometry": {"type": "MultiPolygon", "coordinates": [
+            [[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]], [[2, 2], [2, 4], [4, 4], [4, 2], [2, 2]]],
+            [[[20, 0], [20, 10], [30, 10], [30, 0], [20, 0]]]
+        ]}}"#;
+        let features = crate::geojson_parser::parse_geojson(geojson).unwrap();
+        let tiles = tile_features(&features, 0).unwrap();
+        
+        let tile = &tiles[&TileCoord::new(0, 0, 0)];
+        assert_eq!(tile.len(), 1);
+        let TileGeometry::Polygon(rings) = &tile[0].geometry else {
+            panic!("Expected Polygon geometry");
+        };
+        // Exteriors share the first ring's winding and the hole has the other one
+        let signs: Vec<f64> = rings.iter().map(|ring| ring_signed_area(ring).signum()).collect();
+        assert_eq!(signs, [signs[0], -signs[0], signs[0]]);
+    }
+
+    fn square(size: i32, properties: serde_json::Value) -> TileFeature {
+        TileFeature {
+            geometry: TileGeometry::Polygon(vec![vec![(0, 0), (size, 0), (size, size), (0, size), (0, 0)]]),
+            properties: properties.as_object().unwrap().clone(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_sort_by_area_descending() {
+        let mut features = vec![
+            square(10, serde_json::json!({"id": "small"})),
+            square(100, serde_json::json!({"id": "large"})),
+        ];
+        
+        sort_features(&mut features, &SortBy::AreaDescending);
+        
+        assert_eq!(features[0].properties["id"], "large");
+    }
+
+    #[test]
+    fn test_sort_by_property() {
+        let mut features = vec![
+            square(1, serde_json::json!({})),
+            square(1, serde_json::json!({"rank": 3})),
+            square(1, serde_json::json!({"rank": 1})),
+        ];
+        
+        sort_features(&mut features, &SortBy::Property("rank".to_string()));
+        
+        assert_eq!(features[0].properties["rank"], 1);
+        assert_eq!(features[1].properties["rank"], 3);
+        assert!(features[2].properties.is_empty());
+    }
+
+    #[test]
+    fn test_limit_features_keeps_highest_ranked() {
+        let mut features = vec![
+            square(1, serde_json::json!({"name": "a", "population": 10})),
+            square(1, serde_json::json!({"name": "b"})),
+            square(1, serde_json::json!({"name": "c", "population": 300})),
+            square(1, serde_json::json!({"name": "d", "population": 20})),
+        ];
+        let limit = TileLimit { n: 2, rank_by: "population".to_string() };
+        
+        limit_features(&mut features, &limit);
+        
+        let names: Vec<_> = features.iter().map(|f| f.properties["name"].clone()).collect();
+        assert_eq!(names, vec!["c", "d"]);
+    }
+
+    #[test]
+    fn test_rank_labels() {
+        let point = |properties: serde_json::Value| TileFeature {
+            geometry: TileGeometry::Point(0, 0),
+            properties: properties.as_object().unwrap().clone(),
+            id: None,
+        };
+        let mut features = vec![
+            point(serde_json::json!({"population": 10})),
+            square(1, serde_json::json!({"population": 999})),
+            point(serde_json::json!({})),
+            point(serde_json::json!({"population": 300})),
+        ];
+        
+        rank_labels(&mut features, &LabelRank::new("population"));
+        
+        let ranks: Vec<_> = features.iter().map(|f| f.properties.get("rank").cloned()).collect();
+        assert_eq!(ranks, [Some(2.into()), None, Some(3.into()), Some(1.into())]);
+    }
 }
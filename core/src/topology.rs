@@ -0,0 +1,314 @@
+// Topology module
+// TopoJSON-like shared-arc representation of polygon rings
+
+use crate::geojson_parser::{polygon_geometry, Feature, GeometryType};
+use crate::projection::{lonlat_to_meters, meters_to_lonlat};
+use crate::simplify::douglas_peucker_f64;
+use geo_types::{Coord, LineString, Polygon};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Reference to an arc, possibly traversed backwards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArcRef {
+    pub arc: usize,
+    pub reversed: bool,
+}
+
+/// Rings decomposed into arcs, each shared arc stored once
+#[derive(Debug, Clone)]
+pub struct ArcSet<P> {
+    pub arcs: Vec<Vec<P>>,
+    pub rings: Vec<Vec<ArcRef>>,
+}
+
+impl<P: Copy> ArcSet<P> {
+    /// Rebuild a closed ring from its arcs
+    pub fn ring(&self, index: usize) -> Vec<P> {
+        self.ring_from(&self.arcs, index)
+    }
+
+    /// Rebuild a closed ring using replacement arcs (e.g. simplified copies of `arcs`)
+    pub fn ring_from(&self, arcs: &[Vec<P>], index: usize) -> Vec<P> {
+        let mut ring: Vec<P> = Vec::new();
+        for arc_ref in &self.rings[index] {
+            let arc = &arcs[arc_ref.arc];
+            let points: Box<dyn Iterator<Item = &P>> = if arc_ref.reversed {
+                Box::new(arc.iter().rev())
+            } else {
+                Box::new(arc.iter())
+            };
+            // Consecutive arcs share their junction vertex
+            let skip = if ring.is_empty() { 0 } else { 1 };
+            ring.extend(points.skip(skip).copied());
+        }
+        ring
+    }
+}
+
+/// Split closed rings into arcs at junctions (vertices where boundaries meet or diverge)
+///
+/// Rings without a junction become a single arc starting at their smallest vertex,
+/// so identical rings map to the same arc regardless of their start point.
+pub fn extract_arcs<P: Copy + Eq + Hash + Ord>(rings: &[Vec<P>]) -> ArcSet<P> {
+    // Distinct neighbors of every vertex, across all rings
+    let mut neighbors: HashMap<P, HashSet<P>> = HashMap::new();
+    for ring in rings {
+        let open = open_ring(ring);
+        let n = open.len();
+        for i in 0..n {
+            let entry = neighbors.entry(open[i]).or_default();
+            entry.insert(open[(i + n - 1) % n]);
+            entry.insert(open[(i + 1) % n]);
+        }
+    }
+    let is_junction = |p: &P| neighbors.get(p).map(|n| n.len() > 2).unwrap_or(false);
+    
+    let mut arcs: Vec<Vec<P>> = Vec::new();
+    let mut arc_index: HashMap<Vec<P>, usize> = HashMap::new();
+    let mut arc_rings = Vec::with_capacity(rings.len());
+    
+    for ring in rings {
+        let open = open_ring(ring);
+        if open.is_empty() {
+            arc_rings.push(Vec::new());
+            continue;
+        }
+        
+        let start = (0..open.len())
+            .find(|&i| is_junction(&open[i]))
+            .unwrap_or_else(|| (0..open.len()).min_by_key(|&i| open[i]).unwrap_or(0));
+        let mut closed: Vec<P> = open[start..].iter().chain(open[..start].iter()).copied().collect();
+        closed.push(closed[0]);
+        
+        let mut refs = Vec::new();
+        let mut arc: Vec<P> = vec![closed[0]];
+        for (i, &p) in closed.iter().enumerate().skip(1) {
+            arc.push(p);
+            if is_junction(&p) || i == closed.len() - 1 {
+                refs.push(intern_arc(std::mem::replace(&mut arc, vec![p]), &mut arcs, &mut arc_index));
+            }
+        }
+        arc_rings.push(refs);
+    }
+    
+    ArcSet { arcs, rings: arc_rings }
+}
+
+/// Store an arc once per direction-independent key
+fn intern_arc<P: Copy + Eq + Hash + Ord>(
+    arc: Vec<P>,
+    arcs: &mut Vec<Vec<P>>,
+    arc_index: &mut HashMap<Vec<P>, usize>,
+) -> ArcRef {
+    let reversed_arc: Vec<P> = arc.iter().rev().copied().collect();
+    let reversed = reversed_arc < arc;
+    let key = if reversed { reversed_arc } else { arc };
+    
+    let index = *arc_index.entry(key).or_insert_with_key(|key| {
+        arcs.push(key.clone());
+        arcs.len() - 1
+    });
+    ArcRef { arc: index, reversed }
+}
+
+/// Ring without its closing point
+fn open_ring<P: PartialEq>(ring: &[P]) -> &[P] {
+    if ring.len() > 1 && ring.first() == ring.last() {
+        &ring[..ring.len() - 1]
+    } else {
+        ring
+    }
+}
+
+/// Hashable WebMercator vertex (bit pattern of the meter coordinates)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Vertex(u64, u64);
+
+impl Vertex {
+    fn new(mx: f64, my: f64) -> Self {
+        // +0.0 so that -0.0 and 0.0 are the same vertex
+        Vertex((mx + 0.0).to_bits(), (my + 0.0).to_bits())
+    }
+
+    fn meters(self) -> (f64, f64) {
+        (f64::from_bits(self.0), f64::from_bits(self.1))
+    }
+}
+
+/// Topology of the polygon features of a dataset
+///
+/// Polygon rings are projected to WebMercator once and stored as shared arcs; the
+/// features themselves keep only an empty placeholder geometry. Rings are rebuilt
+/// per zoom, so neighbors sharing a border always receive identical vertices.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    arcs: ArcSet<Vertex>,
+    /// Ring indices of each polygon of each feature (`None` for non-polygon features)
+    feature_rings: Vec<Option<Vec<Vec<usize>>>>,
+}
+
+impl Topology {
+    /// Move the polygon geometries of `features` into a shared-arc topology
+    pub fn from_features(features: &mut [Feature]) -> Self {
+        let mut rings: Vec<Vec<Vertex>> = Vec::new();
+        let mut feature_rings = Vec::with_capacity(features.len());
+        
+        for feature in features.iter_mut() {
+            let polygons = match &mut feature.geometry {
+                GeometryType::Polygon(polygon) => {
+                    vec![std::mem::replace(polygon, Polygon::new(LineString::new(Vec::new()), Vec::new()))]
+                }
+                GeometryType::MultiPolygon(polygons) => std::mem::take(&mut polygons.0),
+                GeometryType::Point(_) | GeometryType::LineString(_) => {
+                    feature_rings.push(None);
+                    continue;
+                }
+            };
+            
+            let mut polygon_rings = Vec::new();
+            for polygon in polygons {
+                let (exterior, interiors) = polygon.into_inner();
+                let mut indices = Vec::new();
+                for ring in std::iter::once(exterior).chain(interiors) {
+                    indices.push(rings.len());
+                    rings.push(
+                        ring.0
+                            .iter()
+                            .map(|c| {
+                                let (mx, my) = lonlat_to_meters(c.x, c.y);
+                                Vertex::new(mx, my)
+                            })
+                            .collect(),
+                    );
+                }
+                polygon_rings.push(indices);
+            }
+            feature_rings.push(Some(polygon_rings));
+        }
+        
+        Topology {
+            arcs: extract_arcs(&rings),
+            feature_rings,
+        }
+    }
+
+    /// Number of distinct arcs
+    pub fn arc_count(&self) -> usize {
+        self.arcs.arcs.len()
+    }
+
+    /// Features with their polygons rebuilt from the arcs
+    ///
+    /// With a `tolerance` (in WebMercator meters), every arc is simplified once before
+    /// the rings are assembled.
+    pub fn rebuild_features(&self, features: &[Feature], tolerance: Option<f64>) -> Vec<Feature> {
+        let arcs: Vec<Vec<Vertex>> = match tolerance {
+            Some(tolerance) => self.arcs.arcs
+                .iter()
+                .map(|arc| {
+                    let meters: Vec<(f64, f64)> = arc.iter().map(|v| v.meters()).collect();
+                    douglas_peucker_f64(&meters, tolerance)
+                        .into_iter()
+                        .map(|(mx, my)| Vertex::new(mx, my))
+                        .collect()
+                })
+                .collect(),
+            None => self.arcs.arcs.clone(),
+        };
+        
+        features
+            .iter()
+            .zip(&self.feature_rings)
+            .map(|(feature, polygon_rings)| match polygon_rings {
+                Some(polygon_rings) => {
+                    let polygons: Vec<Polygon<f64>> = polygon_rings
+                        .iter()
+                        .map(|rings| {
+                            let mut built: Vec<LineString<f64>> = rings
+                                .iter()
+                                .map(|&ring| {
+                                    let mut coords = self.arcs.ring_from(&arcs, ring);
+                                    // Fall back to the unsimplified ring if it collapsed
+                                    if coords.len() < 4 {
+                                        coords = self.arcs.ring(ring);
+                                    }
+                                    LineString::from(
+                                        coords
+                                            .into_iter()
+                                            .map(|v| {
+                                                let (mx, my) = v.meters();
+                                                let (x, y) = meters_to_lonlat(mx, my);
+                                                Coord { x, y }
+                                            })
+                                            .collect::<Vec<_>>(),
+                                    )
+                                })
+                                .collect();
+                            let exterior = if built.is_empty() { LineString::new(Vec::new()) } else { built.remove(0) };
+                            Polygon::new(exterior, built)
+                        })
+                        .collect();
+                    Feature {
+                        geometry: polygon_geometry(polygons),
+                        properties: feature.properties.clone(),
+                        id: feature.id,
+                        config: feature.config.clone(),
+                    }
+                }
+                None => feature.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_edge_is_stored_once() {
+        // Two unit squares sharing the edge x = 1
+        let left = vec![(0, 0), (1, 0), (1, 1), (0, 1), (0, 0)];
+        let right = vec![(1, 0), (2, 0), (2, 1), (1, 1), (1, 0)];
+        
+        let set = extract_arcs(&[left.clone(), right.clone()]);
+        
+        // Shared edge + the rest of each square
+        assert_eq!(set.arcs.len(), 3);
+        let shared = set.rings[0].iter().find(|r| set.rings[1].iter().any(|o| o.arc == r.arc)).unwrap();
+        assert_eq!(set.arcs[shared.arc].len(), 2);
+        
+        // Rings are rebuilt with the same vertices
+        let rebuilt: HashSet<(i32, i32)> = set.ring(0).into_iter().collect();
+        assert_eq!(rebuilt, left.into_iter().collect());
+        assert_eq!(set.ring(1).len(), right.len());
+    }
+
+    #[test]
+    fn test_topology_roundtrip() {
+        let geojson = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "properties": {"id": 1}, "geometry": {"type": "Polygon",
+                 "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]]}},
+                {"type": "Feature", "properties": {"id": 2}, "geometry": {"type": "Polygon",
+                 "coordinates": [[[1, 0], [2, 0], [2, 1], [1, 1], [1, 0]]]}}
+            ]
+        }"#;
+        let mut features = crate::geojson_parser::parse_geojson(geojson.as_bytes()).unwrap();
+        
+        let topology = Topology::from_features(&mut features);
+        let rebuilt = topology.rebuild_features(&features, None);
+        
+        assert_eq!(topology.arc_count(), 3);
+        match &rebuilt[1].geometry {
+            GeometryType::Polygon(polygon) => {
+                let first = polygon.exterior().0[0];
+                assert!((first.x - 1.0).abs() < 1e-9 || (first.x - 2.0).abs() < 1e-9);
+                assert_eq!(polygon.exterior().0.len(), 5);
+            }
+            _ => panic!("Expected Polygon geometry"),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Feature transform module
+// User-defined per-zoom feature processing executed by the tiler
+
+use crate::geojson_parser::Feature;
+use std::fmt;
+use std::sync::Arc;
+
+/// Per-zoom feature transform
+///
+/// Transforms run in order before a feature is assigned to tiles at each zoom level.
+/// Returning `None` removes the feature from that zoom.
+pub trait FeatureTransform: Send + Sync {
+    fn transform(&self, feature: Feature, zoom: u8) -> Option<Feature>;
+}
+
+/// Closures can be used directly as transforms
+impl<F> FeatureTransform for F
+where
+    F: Fn(Feature, u8) -> Option<Feature> + Send + Sync,
+{
+    fn transform(&self, feature: Feature, zoom: u8) -> Option<Feature> {
+        self(feature, zoom)
+    }
+}
+
+impl fmt::Debug for dyn FeatureTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FeatureTransform")
+    }
+}
+
+/// Run a feature through all transforms (stops at the first one that removes it)
+pub fn apply_transforms(
+    feature: Feature,
+    zoom: u8,
+    transforms: &[Arc<dyn FeatureTransform>],
+) -> Option<Feature> {
+    transforms
+        .iter()
+        .try_fold(feature, |feature, transform| transform.transform(feature, zoom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geojson_parser::GeometryType;
+    use geo_types::Point;
+
+    #[test]
+    fn test_transforms_run_in_order() {
+        let feature = Feature::new(GeometryType::Point(Point::new(0.0, 0.0)), serde_json::Map::new());
+        let transforms: Vec<Arc<dyn FeatureTransform>> = vec![
+            Arc::new(|mut f: Feature, zoom: u8| {
+                f.properties.insert("zoom".to_string(), serde_json::json!(zoom));
+                Some(f)
+            }),
+            Arc::new(|f: Feature, zoom: u8| if zoom < 5 { None } else { Some(f) }),
+        ];
+        
+        assert!(apply_transforms(feature.clone(), 3, &transforms).is_none());
+        let transformed = apply_transforms(feature, 7, &transforms).unwrap();
+        assert_eq!(transformed.properties["zoom"], 7);
+    }
+}
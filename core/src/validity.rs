@@ -0,0 +1,311 @@
+// Validity module
+// OGC simple-feature validity checks on input geometries
+
+use crate::geojson_parser::{Feature, GeometryType};
+use crate::mvt_encoder::GeometryChecks;
+use crate::Warning;
+use geo_types::{Coord, LineString, Polygon};
+
+/// Kind of validity problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidityKind {
+    /// Line with fewer than 2 distinct points, or ring with fewer than 3
+    TooFewPoints,
+    /// Ring whose last point differs from its first
+    UnclosedRing,
+    /// Ring crossing or touching itself, or two rings crossing
+    SelfIntersection,
+    /// Hole not inside the exterior ring, or inside another hole
+    HoleOutsideShell,
+    /// Ring turning back on itself at a vertex (a zero-width spike)
+    Spike,
+}
+
+/// One validity problem of an input feature
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ValidityIssue {
+    pub feature_index: usize,
+    pub kind: ValidityKind,
+    pub message: String,
+}
+
+/// Check the features' geometries, in feature order
+///
+/// Points are always valid. A polygon whose rings cross is not checked for hole containment,
+/// and a ring with a spike is not checked for self-intersection (the spike touches itself).
+pub fn validate_features(features: &[Feature]) -> Vec<ValidityIssue> {
+    let mut issues = Vec::new();
+    for (index, feature) in features.iter().enumerate() {
+        validate_geometry(index, &feature.geometry, &mut issues);
+    }
+    issues
+}
+
+/// Fail on the first invalid feature (strict), or warn about each problem (lenient)
+pub(crate) fn check_features(features: &[Feature], checks: GeometryChecks, warnings: &mut Vec<Warning>) -> Result<(), String> {
+    let issues = validate_features(features);
+    match (checks, issues.first()) {
+        (_, None) => Ok(()),
+        (GeometryChecks::Strict, Some(issue)) => Err(format!("Feature {}: {}", issue.feature_index, issue.message)),
+        (GeometryChecks::Lenient, _) => {
+            warnings.extend(issues.into_iter().map(|issue| Warning {
+                feature_index: Some(issue.feature_index),
+                message: format!("Invalid geometry: {}", issue.message),
+            }));
+            Ok(())
+        }
+    }
+}
+
+/// Check one geometry, reporting problems under the feature index `index`
+pub(crate) fn validate_geometry(index: usize, geometry: &GeometryType, issues: &mut Vec<ValidityIssue>) {
+    let mut push = |kind, message| issues.push(ValidityIssue { feature_index: index, kind, message });
+    match geometry {
+        GeometryType::Point(_) => {}
+        GeometryType::LineString(line) => {
+            let mut points: Vec<(u64, u64)> = line.0.iter().map(|c| (c.x.to_bits(), c.y.to_bits())).collect();
+            points.sort_unstable();
+            points.dedup();
+            if points.len() < 2 {
+                push(ValidityKind::TooFewPoints, "Line has fewer than 2 distinct points".to_string());
+            }
+        }
+        GeometryType::Polygon(polygon) => validate_polygon(polygon, &mut push),
+        GeometryType::MultiPolygon(polygons) => {
+            for polygon in &polygons.0 {
+                validate_polygon(polygon, &mut push);
+            }
+        }
+    }
+}
+
+fn ring_name(ring: usize) -> String {
+    match ring {
+        0 => "Exterior ring".to_string(),
+        hole => format!("Hole {}", hole),
+    }
+}
+
+fn validate_polygon(polygon: &Polygon<f64>, push: &mut dyn FnMut(ValidityKind, String)) {
+    // Rings that pass the per-ring checks: (ring number, vertices without the closing point)
+    let mut rings: Vec<(usize, Vec<Coord<f64>>)> = Vec::new();
+    let mut spiked = false;
+    for (number, ring) in std::iter::once(polygon.exterior()).chain(polygon.interiors()).enumerate() {
+        if ring.0.first() != ring.0.last() {
+            push(ValidityKind::UnclosedRing, format!("{} is not closed", ring_name(number)));
+            continue;
+        }
+        let vertices = ring_vertices(ring);
+        let mut distinct: Vec<(u64, u64)> = vertices.iter().map(|c| (c.x.to_bits(), c.y.to_bits())).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() < 3 {
+            push(ValidityKind::TooFewPoints, format!("{} has fewer than 3 distinct points", ring_name(number)));
+            continue;
+        }
+        if let Some(c) = find_spike(&vertices) {
+            push(ValidityKind::Spike, format!("{} has a spike at ({}, {})", ring_name(number), c.x, c.y));
+            spiked = true;
+            continue;
+        }
+        rings.push((number, vertices));
+    }
+    
+    if let Some((first, second, c)) = find_intersection(&rings) {
+        let message = if first == second {
+            format!("{} intersects itself at ({}, {})", ring_name(first), c.x, c.y)
+        } else {
+            format!("{} and {} cross at ({}, {})", ring_name(first), ring_name(second).to_lowercase(), c.x, c.y)
+        };
+        push(ValidityKind::SelfIntersection, message);
+        return;
+    }
+    // Containment is only meaningful for a complete polygon whose rings do not cross
+    if spiked || rings.first().map(|(number, _)| *number) != Some(0) {
+        return;
+    }
+    let (shell, holes) = rings.split_first().unwrap();
+    for (i, (number, hole)) in holes.iter().enumerate() {
+        if !inside(hole, &shell.1) {
+            push(ValidityKind::HoleOutsideShell, format!("{} lies outside the exterior ring", ring_name(*number)));
+            continue;
+        }
+        let nested = holes[..i].iter().find(|(_, other)| {
+            overlaps(&bbox(hole), &bbox(other)) && (inside(hole, other) || inside(other, hole))
+        });
+        if let Some((other, _)) = nested {
+            push(ValidityKind::HoleOutsideShell, format!("{} and hole {} are nested", ring_name(*number), other));
+        }
+    }
+}
+
+/// Ring vertices without the closing point and repeated consecutive points (valid in OGC)
+pub(crate) fn ring_vertices(ring: &LineString<f64>) -> Vec<Coord<f64>> {
+    let mut vertices: Vec<Coord<f64>> = Vec::with_capacity(ring.0.len());
+    for &c in &ring.0 {
+        if vertices.last() != Some(&c) {
+            vertices.push(c);
+        }
+    }
+    while vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    vertices
+}
+
+/// A vertex where the ring reverses direction along the same line
+fn find_spike(vertices: &[Coord<f64>]) -> Option<Coord<f64>> {
+    let n = vertices.len();
+    (0..n).map(|i| (vertices[(i + n - 1) % n], vertices[i], vertices[(i + 1) % n])).find_map(|(prev, c, next)| {
+        let turns_back = orient(prev, c, next) == 0.0 && (c.x - prev.x) * (next.x - c.x) + (c.y - prev.y) * (next.y - c.y) < 0.0;
+        turns_back.then_some(c)
+    })
+}
+
+/// First intersection found between ring edges: (ring number, ring number, point)
+///
+/// Edges of one ring may not meet except at shared vertices of neighboring edges; edges of
+/// different rings may touch at a point but not cross. Edges are swept by x so only edges with
+/// overlapping x ranges are compared.
+fn find_intersection(rings: &[(usize, Vec<Coord<f64>>)]) -> Option<(usize, usize, Coord<f64>)> {
+    // (ring position, edge index, start, end)
+    let mut edges: Vec<(usize, usize, Coord<f64>, Coord<f64>)> = Vec::new();
+    for (ring, (_, vertices)) in rings.iter().enumerate() {
+        let n = vertices.len();
+        edges.extend((0..n).map(|i| (ring, i, vertices[i], vertices[(i + 1) % n])));
+    }
+    let min_x = |edge: &(usize, usize, Coord<f64>, Coord<f64>)| edge.2.x.min(edge.3.x);
+    let max_x = |edge: &(usize, usize, Coord<f64>, Coord<f64>)| edge.2.x.max(edge.3.x);
+    edges.sort_by(|a, b| min_x(a).total_cmp(&min_x(b)));
+    
+    let mut active: Vec<usize> = Vec::new();
+    for (i, edge) in edges.iter().enumerate() {
+        active.retain(|&other| max_x(&edges[other]) >= min_x(edge));
+        for &other in &active {
+            let other = &edges[other];
+            if edge.2.y.max(edge.3.y) < other.2.y.min(other.3.y) || other.2.y.max(other.3.y) < edge.2.y.min(edge.3.y) {
+                continue;
+            }
+            let same_ring = edge.0 == other.0;
+            if same_ring {
+                let n = rings[edge.0].1.len();
+                let (a, b) = (edge.1.min(other.1), edge.1.max(other.1));
+                if b - a == 1 || (a == 0 && b == n - 1) {
+                    continue;
+                }
+            }
+            if let Some(c) = intersection(edge.2, edge.3, other.2, other.3, !same_ring) {
+                let (first, second) = (rings[edge.0.min(other.0)].0, rings[edge.0.max(other.0)].0);
+                return Some((first, second, c));
+            }
+        }
+        active.push(i);
+    }
+    None
+}
+
+/// Twice the signed area of the triangle (positive when `c` is left of `a -> b`)
+fn orient(a: Coord<f64>, b: Coord<f64>, c: Coord<f64>) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn on_segment(a: Coord<f64>, b: Coord<f64>, p: Coord<f64>) -> bool {
+    orient(a, b, p) == 0.0 && p.x >= a.x.min(b.x) && p.x <= a.x.max(b.x) && p.y >= a.y.min(b.y) && p.y <= a.y.max(b.y)
+}
+
+/// Where segments `a -> b` and `c -> d` meet (only proper crossings when `crossing_only`)
+fn intersection(a: Coord<f64>, b: Coord<f64>, c: Coord<f64>, d: Coord<f64>, crossing_only: bool) -> Option<Coord<f64>> {
+    let (d1, d2) = (orient(c, d, a), orient(c, d, b));
+    let (d3, d4) = (orient(a, b, c), orient(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        let t = d1 / (d1 - d2);
+        return Some(Coord { x: a.x + (b.x - a.x) * t, y: a.y + (b.y - a.y) * t });
+    }
+    if crossing_only {
+        return None;
+    }
+    [(c, d, a), (c, d, b), (a, b, c), (a, b, d)].into_iter().find(|&(s, e, p)| on_segment(s, e, p)).map(|(_, _, p)| p)
+}
+
+/// Whether ring `inner` lies inside ring `outer` (judged by a vertex not on `outer`'s boundary)
+pub(crate) fn inside(inner: &[Coord<f64>], outer: &[Coord<f64>]) -> bool {
+    let n = outer.len();
+    let on_boundary = |p: Coord<f64>| (0..n).any(|i| on_segment(outer[i], outer[(i + 1) % n], p));
+    let Some(p) = inner.iter().copied().find(|&p| !on_boundary(p)) else {
+        return true;
+    };
+    // Ray casting
+    let mut contained = false;
+    for i in 0..n {
+        let (a, b) = (outer[i], outer[(i + n - 1) % n]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            contained = !contained;
+        }
+    }
+    contained
+}
+
+/// [min_x, min_y, max_x, max_y]
+pub(crate) fn bbox(vertices: &[Coord<f64>]) -> [f64; 4] {
+    vertices.iter().fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY], |b, c| {
+        [b[0].min(c.x), b[1].min(c.y), b[2].max(c.x), b[3].max(c.y)]
+    })
+}
+
+pub(crate) fn overlaps(a: &[f64; 4], b: &[f64; 4]) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point;
+
+    fn polygon(rings: &[&[(f64, f64)]]) -> Feature {
+        let ring = |coords: &[(f64, f64)]| LineString::from(coords.to_vec());
+        Feature::new(GeometryType::Polygon(Polygon::new(ring(rings[0]), rings[1..].iter().map(|r| ring(r)).collect())), Default::default())
+    }
+
+    #[test]
+    fn test_validate_features() {
+        let square: &[(f64, f64)] = &[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)];
+        let features = [
+            Feature::new(GeometryType::Point(Point::new(1.0, 1.0)), Default::default()),
+            polygon(&[square, &[(1.0, 1.0), (1.0, 2.0), (2.0, 2.0), (1.0, 1.0)]]),
+            // Bowtie
+            polygon(&[&[(0.0, 0.0), (4.0, 4.0), (4.0, 0.0), (0.0, 4.0), (0.0, 0.0)]]),
+            polygon(&[square, &[(5.0, 5.0), (5.0, 6.0), (6.0, 6.0), (5.0, 5.0)]]),
+            polygon(&[&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (4.0, 6.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)]]),
+            polygon(&[square, &[(1.0, 1.0), (1.0, 3.0), (3.0, 3.0), (3.0, 1.0), (1.0, 1.0)], &[(2.0, 2.0), (2.0, 2.5), (2.5, 2.5), (2.0, 2.0)]]),
+            polygon(&[square, &[(3.0, 1.0), (3.0, 3.0), (5.0, 3.0), (5.0, 1.0), (3.0, 1.0)]]),
+            // A hole touching the exterior at one point is valid
+            polygon(&[square, &[(0.0, 2.0), (2.0, 3.0), (2.0, 1.0), (0.0, 2.0)]]),
+        ];
+        
+        let issues = validate_features(&features);
+        
+        let found: Vec<(usize, ValidityKind)> = issues.iter().map(|issue| (issue.feature_index, issue.kind)).collect();
+        assert_eq!(found, [
+            (2, ValidityKind::SelfIntersection),
+            (3, ValidityKind::HoleOutsideShell),
+            (4, ValidityKind::Spike),
+            (5, ValidityKind::HoleOutsideShell),
+            (6, ValidityKind::SelfIntersection),
+        ]);
+        assert_eq!(issues[0].message, "Exterior ring intersects itself at (2, 2)");
+        assert_eq!(issues[2].message, "Exterior ring has a spike at (4, 6)");
+        assert_eq!(issues[4].message, "Exterior ring and hole 1 cross at (4, 3)");
+    }
+
+    #[test]
+    fn test_strict_gate() {
+        let bowtie = polygon(&[&[(0.0, 0.0), (4.0, 4.0), (4.0, 0.0), (0.0, 4.0), (0.0, 0.0)]]);
+        let mut warnings = Vec::new();
+        
+        let err = check_features(std::slice::from_ref(&bowtie), GeometryChecks::Strict, &mut warnings).unwrap_err();
+        assert_eq!(err, "Feature 0: Exterior ring intersects itself at (2, 2)");
+        check_features(&[bowtie], GeometryChecks::Lenient, &mut warnings).unwrap();
+        assert_eq!(warnings[0].message, "Invalid geometry: Exterior ring intersects itself at (2, 2)");
+    }
+}
@@ -0,0 +1,61 @@
+// Version module
+// Crate version, commit and enabled features of this build
+
+use std::fmt;
+
+/// Which build of the library is running
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Short commit hash (`None` when built outside a git checkout)
+    pub git_hash: Option<&'static str>,
+    /// Cargo features compiled in
+    pub features: Vec<&'static str>,
+}
+
+/// Version of this build (the TileJSON `generator` records `version` without the commit)
+pub fn version() -> BuildInfo {
+    let features = [
+        ("geojson", cfg!(feature = "geojson")),
+        ("zip", cfg!(feature = "zip")),
+        ("shapefile", cfg!(feature = "shapefile")),
+        ("kml", cfg!(feature = "kml")),
+        ("flatgeobuf", cfg!(feature = "flatgeobuf")),
+        ("cli", cfg!(feature = "cli")),
+        ("wasm", cfg!(feature = "wasm")),
+        ("csv", cfg!(feature = "csv")),
+        ("log", cfg!(feature = "log")),
+        ("async", cfg!(feature = "async")),
+        ("http", cfg!(feature = "http")),
+        ("ffi", cfg!(feature = "ffi")),
+        ("mvt3", cfg!(feature = "mvt3")),
+    ];
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("VECTOR_TILE_CORE_GIT_HASH"),
+        features: features.into_iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name).collect(),
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    /// `0.1.0 (1a2b3c4d5e6f)`, or just the version without a commit
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.git_hash {
+            Some(hash) => write!(f, "{} ({})", self.version, hash),
+            None => f.write_str(self.version),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        let info = version();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.to_string().starts_with(info.version));
+        assert_eq!(info.features.contains(&"geojson"), cfg!(feature = "geojson"));
+    }
+}
@@ -2,7 +2,14 @@
 // Wasm functions called from browser
 
 use wasm_bindgen::prelude::*;
-use crate::generate_tiles_with_metadata;
+use crate::metrics::{GenerationMetrics, ZoomSummary};
+use crate::tilejson::{to_tilejson, LayerStats};
+#[cfg(feature = "geojson")]
+use crate::geojson_parser::{convert_documents, Feature};
+#[cfg(feature = "geojson")]
+use crate::generate_from_input;
+use crate::limits::ResourceLimits;
+use crate::{generate_tiles_with_metrics, TileFile, TileMetadata, TileOptions, Warning};
 
 /// Set panic hook for Wasm
 #[wasm_bindgen(start)]
@@ -10,11 +17,151 @@ pub fn init_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+// TypeScript shapes of the values returned as `JsValue` (serde-wasm-bindgen output:
+// `None` becomes `undefined`, maps become `Map`, tuples become arrays)
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+/** Thrown by the generation functions: the error message (`InputTooLarge: ...` when over the resource limits) */
+export type WasmError = string;
+
+export interface Warning {
+  /** Index of the input feature the warning refers to */
+  feature_index: number | undefined;
+  message: string;
+}
+
+export interface AttributeStats {
+  attribute: string;
+  /** Distinct values (counting stops at 1000) */
+  count: number;
+  type: "string" | "number" | "boolean" | "mixed";
+  /** Up to 100 distinct non-numeric values */
+  values: Array<string | number | boolean>;
+  min?: number;
+  max?: number;
+}
+
+export interface LayerStats {
+  count: number;
+  /** Most common geometry type (empty when there are no features) */
+  geometry: "" | "Point" | "LineString" | "Polygon";
+  attributes: AttributeStats[];
+}
+
+export interface ZoomSummary {
+  zoom: number;
+  tile_count: number;
+  bytes: number;
+  average_tile_bytes: number;
+  max_tile_bytes: number;
+}
+
+export interface TileMetadata {
+  min_zoom: number;
+  max_zoom: number;
+  layer_name: string;
+  /** [min_lon, min_lat, max_lon, max_lat] */
+  bounds: [number, number, number, number];
+  /** [lon, lat] */
+  center: [number, number];
+  warnings: Warning[];
+  stats: LayerStats;
+  zooms: ZoomSummary[];
+  /** Top-level GeoJSON foreign members kept by the options */
+  foreign_members: Map<string, unknown>;
+  /** Source property the feature ids come from */
+  id_field: string | undefined;
+}
+
+export interface ZoomMetrics {
+  zoom: number;
+  tiling_ms: number;
+  encode_ms: number;
+  tile_count: number;
+  bytes: number;
+  max_tile_bytes: number;
+}
+
+export interface AttributeCardinality {
+  key: string;
+  distinct_values: number;
+  features: number;
+  tile: string;
+}
+
+export interface FeatureSize {
+  index: number;
+  id: number | undefined;
+  bytes: number;
+}
+
+export interface OversizedTile {
+  tile: string;
+  layer: string;
+  bytes: number;
+  largest_features: FeatureSize[];
+}
+
+export interface GenerationMetrics {
+  parse_ms: number;
+  encode_ms: number;
+  total_ms: number;
+  features_dropped: number;
+  zooms: ZoomMetrics[];
+  high_cardinality: AttributeCardinality[];
+  max_keys_per_tile: number;
+  oversized_tiles: OversizedTile[];
+}
+
+/** Settings of `WasmTileSource.finalize` */
+export interface FinalizeOptions {
+  min_zoom: number;
+  max_zoom: number;
+  /** "default" when omitted */
+  layer_name?: string;
+}
+
+/** Returned by `validate_geometries` */
+export interface ValidityIssue {
+  /** Index of the input feature */
+  feature_index: number;
+  kind: "too_few_points" | "unclosed_ring" | "self_intersection" | "hole_outside_shell" | "spike";
+  message: string;
+}
+
+export interface ZoomEstimate {
+  zoom: number;
+  tiles: number;
+  bytes: number;
+}
+
+/** Returned by `estimate_resources` (rough, within a small factor) */
+export interface ResourceEstimate {
+  input_bytes: number;
+  features: number;
+  vertices: number;
+  zooms: ZoomEstimate[];
+  /** With the tiles held in memory, as `generate_pbf_tiles` does */
+  peak_memory_bytes: number;
+  output_bytes: number;
+  /** `InputTooLarge` errors generation would fail with */
+  exceeded: string[];
+}
+
+export interface BuildInfo {
+  version: string;
+  git_hash: string | undefined;
+  features: string[];
+}
+"#;
+
 /// Tile generation result (with metadata)
 #[wasm_bindgen]
 pub struct TileResult {
     tiles: Vec<TileData>,
     metadata: MetadataData,
+    metrics: GenerationMetrics,
+    tilejson: String,
 }
 
 #[wasm_bindgen]
@@ -23,21 +170,33 @@ impl TileResult {
     pub fn count(&self) -> usize {
         self.tiles.len()
     }
-    
+
     /// Get tile path at specified index
     pub fn get_path(&self, index: usize) -> Option<String> {
         self.tiles.get(index).map(|t| t.path.clone())
     }
-    
+
     /// Get tile data at specified index
     pub fn get_data(&self, index: usize) -> Option<Vec<u8>> {
         self.tiles.get(index).map(|t| t.data.clone())
     }
-    
+
     /// Get metadata
+    #[wasm_bindgen(unchecked_return_type = "TileMetadata")]
     pub fn get_metadata(&self) -> JsValue {
         serde_wasm_bindgen::to_value(&self.metadata).unwrap_or(JsValue::NULL)
     }
+
+    /// Get generation metrics (stage timings in ms, bytes per zoom)
+    #[wasm_bindgen(unchecked_return_type = "GenerationMetrics")]
+    pub fn get_metrics(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.metrics).unwrap_or(JsValue::NULL)
+    }
+
+    /// Get tippecanoe-format metadata.json (with field types and tilestats)
+    pub fn get_tilejson(&self) -> String {
+        self.tilejson.clone()
+    }
 }
 
 #[derive(Clone)]
@@ -46,25 +205,33 @@ struct TileData {
     data: Vec<u8>,
 }
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, Default, serde::Serialize)]
 struct MetadataData {
     min_zoom: u8,
     max_zoom: u8,
     layer_name: String,
     bounds: (f64, f64, f64, f64),
     center: (f64, f64),
+    warnings: Vec<Warning>,
+    stats: LayerStats,
+    zooms: Vec<ZoomSummary>,
+    foreign_members: serde_json::Map<String, serde_json::Value>,
+    id_field: Option<String>,
 }
 
 /// Generate vector tiles from GeoJSON (for Wasm, with metadata)
 /// 
+/// KML, KMZ and zipped Shapefiles (as dropped from open-data portals) are detected from the content.
+/// Inputs beyond `ResourceLimits::browser()` fail early with an `InputTooLarge` error.
+/// 
 /// # Arguments
-/// * `geojson_bytes` - GeoJSON byte array
+/// * `geojson_bytes` - GeoJSON (or KML, KMZ, zipped Shapefile) byte array
 /// * `min_zoom` - Minimum zoom level
 /// * `max_zoom` - Maximum zoom level
 /// * `layer_name` - Layer name
 /// 
 /// # Returns
-/// * `Result<TileResult, JsValue>` - TileResult on success, error message on failure
+/// * `Result<TileResult, JsValue>` - TileResult on success, error message (`WasmError`) on failure
 #[wasm_bindgen]
 pub fn generate_pbf_tiles(
     geojson_bytes: &[u8],
@@ -72,10 +239,181 @@ pub fn generate_pbf_tiles(
     max_zoom: u8,
     layer_name: &str,
 ) -> Result<TileResult, JsValue> {
+    let options = TileOptions { reader: crate::input::detect_reader(geojson_bytes), ..browser_options() };
+    
     // Generate tiles (with metadata)
-    let (tiles, metadata) = generate_tiles_with_metadata(geojson_bytes, min_zoom, max_zoom, layer_name)
-        .map_err(|e| JsValue::from_str(&e))?;
+    let (tiles, metadata, metrics) =
+        generate_tiles_with_metrics(geojson_bytes, min_zoom, max_zoom, layer_name, &options)
+            .map_err(|e| JsValue::from_str(&e))?;
     
+    Ok(tile_result(tiles, metadata, metrics))
+}
+
+/// Generate vector tiles from a GeoJSON FeatureCollection (or Feature) held as a JS object
+///
+/// The object is read directly, without `JSON.stringify` and a second copy of the data as bytes.
+#[cfg(feature = "geojson")]
+#[wasm_bindgen]
+pub fn generate_pbf_tiles_from_features(
+    #[wasm_bindgen(unchecked_param_type = "object")] geojson: JsValue,
+    min_zoom: u8,
+    max_zoom: u8,
+    layer_name: &str,
+) -> Result<TileResult, JsValue> {
+    let geojson: geojson::GeoJson = serde_wasm_bindgen::from_value(geojson)
+        .map_err(|e| JsValue::from_str(&format!("GeoJSON parse error: {}", e)))?;
+    let options = browser_options();
+    let input = convert_documents(vec![geojson], options.geometry_checks).map_err(|e| JsValue::from_str(&e))?;
+    
+    let (tiles, metadata, metrics) =
+        generate_from_input(input, min_zoom, max_zoom, layer_name, &options).map_err(|e| JsValue::from_str(&e))?;
+    
+    Ok(tile_result(tiles, metadata, metrics))
+}
+
+/// Check the input geometries for OGC validity before generating (`ValidityIssue[]`)
+///
+/// Lets the page list broken features while the user can still fix the data; the input is
+/// read like `generate_pbf_tiles` reads it.
+#[cfg(feature = "geojson")]
+#[wasm_bindgen(unchecked_return_type = "ValidityIssue[]")]
+pub fn validate_geometries(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    let options = TileOptions { reader: crate::input::detect_reader(bytes), ..browser_options() };
+    let (features, _, _) = crate::read_input(bytes, &options).map_err(|e| JsValue::from_str(&e))?;
+    let issues = crate::validity::validate_features(&features);
+    serde_wasm_bindgen::to_value(&issues).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Estimate the memory use and output size of `generate_pbf_tiles` (`ResourceEstimate`)
+///
+/// Parses the input but tiles nothing, so the page can warn (or suggest a lower max zoom)
+/// before starting a generation that would exhaust the tab's memory.
+#[cfg(feature = "geojson")]
+#[wasm_bindgen(unchecked_return_type = "ResourceEstimate")]
+pub fn estimate_resources(bytes: &[u8], min_zoom: u8, max_zoom: u8) -> Result<JsValue, JsValue> {
+    let options = TileOptions { reader: crate::input::detect_reader(bytes), ..browser_options() };
+    let estimate = crate::limits::estimate_resources(bytes, min_zoom, max_zoom, &options).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&estimate).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Features collected chunk by chunk, tiled by `finalize`
+///
+/// Chunks are converted as they arrive, so the caller can release each one (a file read,
+/// a drawing edit) instead of holding the whole input alongside the parsed features.
+#[cfg(feature = "geojson")]
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmTileSource {
+    features: Vec<Feature>,
+    foreign_members: serde_json::Map<String, serde_json::Value>,
+    warnings: Vec<Warning>,
+}
+
+/// Settings of `WasmTileSource.finalize`
+#[cfg(feature = "geojson")]
+#[derive(serde::Deserialize)]
+struct FinalizeOptions {
+    min_zoom: u8,
+    max_zoom: u8,
+    #[serde(default = "default_layer_name")]
+    layer_name: String,
+}
+
+#[cfg(feature = "geojson")]
+fn default_layer_name() -> String {
+    "default".to_string()
+}
+
+/// A FeatureCollection, a Feature, or an array of Features or of FeatureCollections
+#[cfg(feature = "geojson")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum FeatureChunk {
+    Features(Vec<geojson::Feature>),
+    Collections(Vec<geojson::FeatureCollection>),
+    GeoJson(Box<geojson::GeoJson>),
+}
+
+#[cfg(feature = "geojson")]
+#[wasm_bindgen]
+impl WasmTileSource {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add features held as JS objects; returns the number of features collected so far
+    #[wasm_bindgen(js_name = addFeatures)]
+    pub fn add_features(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "object | object[]")] chunk: JsValue,
+    ) -> Result<usize, JsValue> {
+        let chunk: FeatureChunk = serde_wasm_bindgen::from_value(chunk)
+            .map_err(|e| JsValue::from_str(&format!("GeoJSON parse error: {}", e)))?;
+        let documents = match chunk {
+            FeatureChunk::Features(features) => vec![geojson::GeoJson::FeatureCollection(geojson::FeatureCollection {
+                bbox: None,
+                features,
+                foreign_members: None,
+            })],
+            FeatureChunk::Collections(collections) => collections.into_iter().map(geojson::GeoJson::FeatureCollection).collect(),
+            FeatureChunk::GeoJson(geojson) => vec![*geojson],
+        };
+        let input = convert_documents(documents, browser_options().geometry_checks).map_err(|e| JsValue::from_str(&e))?;
+        Ok(self.add(input))
+    }
+
+    /// Add an encoded file (GeoJSON, KML, KMZ or zipped Shapefile, detected from the content)
+    #[wasm_bindgen(js_name = addBytes)]
+    pub fn add_bytes(&mut self, bytes: &[u8]) -> Result<usize, JsValue> {
+        let options = TileOptions { reader: crate::input::detect_reader(bytes), ..browser_options() };
+        let input = crate::read_input(bytes, &options).map_err(|e| JsValue::from_str(&e))?;
+        Ok(self.add(input))
+    }
+
+    /// Features collected so far
+    #[wasm_bindgen(js_name = featureCount)]
+    pub fn feature_count(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Tile the collected features (the source is empty afterwards and can be refilled)
+    pub fn finalize(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "FinalizeOptions")] options: JsValue,
+    ) -> Result<TileResult, JsValue> {
+        let options: FinalizeOptions = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?;
+        let input = (std::mem::take(&mut self.features), std::mem::take(&mut self.foreign_members), std::mem::take(&mut self.warnings));
+        
+        let (tiles, metadata, metrics) = generate_from_input(
+            input,
+            options.min_zoom,
+            options.max_zoom,
+            &options.layer_name,
+            &browser_options(),
+        )
+        .map_err(|e| JsValue::from_str(&e))?;
+        
+        Ok(tile_result(tiles, metadata, metrics))
+    }
+}
+
+#[cfg(feature = "geojson")]
+impl WasmTileSource {
+    /// Foreign members of the first chunk that has each one win; warnings keep the feature
+    /// indices of their chunk
+    fn add(&mut self, (features, foreign_members, warnings): crate::Input) -> usize {
+        self.features.extend(features);
+        self.warnings.extend(warnings);
+        for (key, value) in foreign_members {
+            self.foreign_members.entry(key).or_insert(value);
+        }
+        self.features.len()
+    }
+}
+
+fn tile_result(tiles: Vec<TileFile>, metadata: TileMetadata, metrics: GenerationMetrics) -> TileResult {
     // Convert to Wasm data structure
     let tile_data: Vec<TileData> = tiles
         .into_iter()
@@ -85,18 +423,43 @@ pub fn generate_pbf_tiles(
         })
         .collect();
     
+    let tilejson = serde_json::to_string_pretty(&to_tilejson(&metadata)).unwrap_or_default();
     let metadata_data = MetadataData {
         min_zoom: metadata.min_zoom,
         max_zoom: metadata.max_zoom,
         layer_name: metadata.layer_name,
         bounds: metadata.bounds,
         center: metadata.center,
+        warnings: metadata.warnings,
+        stats: metadata.stats,
+        zooms: metadata.zooms,
+        foreign_members: metadata.foreign_members,
+        id_field: metadata.id_field,
     };
     
-    Ok(TileResult { 
+    TileResult { 
         tiles: tile_data,
         metadata: metadata_data,
-    })
+        metrics,
+        tilejson,
+    }
+}
+
+/// Default options of the generation functions (browser resource limits)
+///
+/// Pages take files as users have them, so ones saved in another encoding are read (with a warning)
+fn browser_options() -> TileOptions {
+    TileOptions {
+        limits: ResourceLimits::browser(),
+        text_decoding: crate::encoding::TextDecoding::Detect,
+        ..Default::default()
+    }
+}
+
+/// Version, commit and enabled features of the wasm build (`{ version, git_hash, features }`)
+#[wasm_bindgen(js_name = version, unchecked_return_type = "BuildInfo")]
+pub fn build_info() -> JsValue {
+    serde_wasm_bindgen::to_value(&crate::version::version()).unwrap_or(JsValue::NULL)
 }
 
 /// Log output (for debugging)
@@ -112,6 +475,34 @@ pub fn debug_log(message: &str) {
     log(message);
 }
 
+/// Forward library diagnostics (`log` crate records) to the browser console
+#[cfg(feature = "log")]
+struct ConsoleLogger;
+
+#[cfg(feature = "log")]
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        log(&format!("[{}] {}", record.level(), record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the console logger (records up to `debug` when `verbose`, otherwise warnings only)
+#[cfg(feature = "log")]
+#[wasm_bindgen]
+pub fn init_console_logger(verbose: bool) {
+    static LOGGER: ConsoleLogger = ConsoleLogger;
+    
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(if verbose { log::LevelFilter::Debug } else { log::LevelFilter::Warn });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,7 +517,12 @@ mod tests {
             },
         ];
         
-        let result = TileResult { tiles: tile_data };
+        let metadata = MetadataData {
+            layer_name: "default".to_string(),
+            ..Default::default()
+        };
+        
+        let result = TileResult { tiles: tile_data, metadata, metrics: GenerationMetrics::default(), tilejson: String::new() };
         assert_eq!(result.count(), 1);
         assert_eq!(result.get_path(0), Some("0/0/0.pbf".to_string()));
     }
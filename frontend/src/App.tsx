@@ -3,6 +3,7 @@ import JSZip from "jszip";
 import "./App.css";
 import MapPreview from "./MapPreview.tsx";
 import { setTileStore, type TileStore } from "./tileStore";
+import type { GenerationMetrics } from "./worker";
 
 interface GenerationSettings {
   minZoom: number;
@@ -31,6 +32,7 @@ function App() {
   const [progress, setProgress] = useState(0);
   const [error, setError] = useState<string | null>(null);
   const [previewData, setPreviewData] = useState<PreviewData | null>(null);
+  const [metrics, setMetrics] = useState<GenerationMetrics | null>(null);
   const [generatedTiles, setGeneratedTiles] = useState<{
     tiles: Array<{ path: string; bytes: Uint8Array }>;
     tilejson: string;
@@ -132,6 +134,7 @@ function App() {
       if (message.type === "progress") {
         setProgress(message.value);
       } else if (message.type === "result-pbf") {
+        setMetrics(message.metrics);
         handleTilesGenerated(message.tiles, message.tilejson);
       } else if (message.type === "error") {
         setError(message.message);
@@ -160,7 +163,7 @@ function App() {
 
   const handleGenerate = async () => {
     if (!file) {
-      setError("Please select a GeoJSON file");
+      setError("Please select a GeoJSON, KML/KMZ or zipped Shapefile");
       return;
     }
 
@@ -206,7 +209,7 @@ function App() {
         <section className="info-section">
           <h2>📝 How to Use</h2>
           <ol>
-            <li>Select a GeoJSON file (supports Point, LineString, Polygon)</li>
+            <li>Select a GeoJSON, KML/KMZ or zipped Shapefile (supports Point, LineString, Polygon, MultiPolygon)</li>
             <li>Configure zoom levels (0-15) and layer name</li>
             <li>Click "Generate Tiles" button</li>
             <li>Download the generated tiles</li>
@@ -217,7 +220,7 @@ function App() {
           <h2>1. Select GeoJSON File</h2>
           <input
             type="file"
-            accept=".geojson,.json"
+            accept=".geojson,.json,.zip,.kml,.kmz"
             onChange={handleFileChange}
             disabled={isProcessing}
           />
@@ -343,6 +346,20 @@ function App() {
                 📦 Download ZIP
               </button>
             </div>
+            {metrics && (
+              <div className="metrics-summary">
+                Generated in {metrics.total_ms.toFixed(0)} ms (parse{" "}
+                {metrics.parse_ms.toFixed(0)} ms, encode{" "}
+                {metrics.encode_ms.toFixed(0)} ms) ·{" "}
+                {metrics.zooms
+                  .map(
+                    (z) => `z${z.zoom}: ${(z.bytes / 1024).toFixed(1)} KB`
+                  )
+                  .join(", ")}
+                {metrics.features_dropped > 0 &&
+                  ` · ${metrics.features_dropped} features dropped`}
+              </div>
+            )}
             <MapPreview {...previewData} />
           </section>
         )}
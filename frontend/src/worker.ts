@@ -1,5 +1,8 @@
 // WebWorker: Tile generation using Wasm
-import init, { generate_pbf_tiles, type TileResult } from './wasm/vector_tile_core'
+import init, { generate_pbf_tiles, type GenerationMetrics, type TileResult, type WasmError } from './wasm/vector_tile_core'
+
+// Per-stage timings reported by the tiler (typed by the wasm package)
+export type { GenerationMetrics }
 
 // Worker message type definitions
 interface GenerateMessage {
@@ -22,6 +25,7 @@ interface ResultMessage {
   type: 'result-pbf'
   tiles: Array<{ path: string; bytes: Uint8Array }>
   tilejson: string
+  metrics: GenerationMetrics
 }
 
 interface ErrorMessage {
@@ -32,54 +36,6 @@ interface ErrorMessage {
 // Wasm initialization flag
 let wasmInitialized = false
 
-// Generate metadata.json in tippecanoe format
-function generateTileJSON(metadata: any, minZoom: number, maxZoom: number, layerName: string): string {
-  const { bounds, center } = metadata
-  
-  // Calculate center_zoom (middle zoom level)
-  const centerZoom = Math.floor((minZoom + maxZoom) / 2)
-  
-  // metadata.json in tippecanoe format
-  const metadataJson = {
-    name: layerName,
-    description: `${layerName}`,
-    version: '1',
-    minzoom: minZoom.toString(),
-    maxzoom: maxZoom.toString(),
-    center: `${center[0]},${center[1]},${centerZoom}`,
-    bounds: `${bounds[0]},${bounds[1]},${bounds[2]},${bounds[3]}`,
-    type: 'overlay',
-    format: 'pbf',
-    generator: 'vector-tile-builder v0.1.0',
-    generator_options: `Browser-based tile generation from GeoJSON`,
-    json: JSON.stringify({
-      vector_layers: [
-        {
-          id: layerName,
-          description: '',
-          minzoom: minZoom,
-          maxzoom: maxZoom,
-          fields: {},
-        },
-      ],
-      tilestats: {
-        layerCount: 1,
-        layers: [
-          {
-            layer: layerName,
-            count: 0,
-            geometry: 'Unknown',
-            attributeCount: 0,
-            attributes: [],
-          },
-        ],
-      },
-    }),
-  }
-  
-  return JSON.stringify(metadataJson, null, 2)
-}
-
 // Initialize Wasm
 async function initializeWasm() {
   if (wasmInitialized) return
@@ -155,8 +111,12 @@ async function generateTiles(message: GenerateMessage) {
     const metadata = result.get_metadata()
     console.log('[Worker] Metadata:', metadata)
     
-    // Generate TileJSON
-    const tilejson = generateTileJSON(metadata, minZoom, maxZoom, layerName)
+    // Get generation metrics
+    const metrics: GenerationMetrics = result.get_metrics()
+    console.log(`[Worker] Generated in ${metrics.total_ms.toFixed(1)} ms`)
+    
+    // Get TileJSON (field types and tilestats are filled in by the tiler)
+    const tilejson = result.get_tilejson()
     console.log('[Worker] Generated TileJSON')
     
     // Complete
@@ -167,18 +127,23 @@ async function generateTiles(message: GenerateMessage) {
       type: 'result-pbf',
       tiles,
       tilejson,
+      metrics,
     } as ResultMessage)
     
   } catch (error) {
     console.error('[Worker] Error during tile generation:', error)
     
+    // Wasm functions throw the message itself (WasmError)
     const errorMessage = error instanceof Error 
       ? error.message 
-      : 'An error occurred during tile generation'
+      : typeof error === 'string'
+        ? (error as WasmError)
+        : 'An error occurred during tile generation'
     
+    // Resource limit errors already say what to reduce
     postMessage({
       type: 'error',
-      message: errorMessage,
+      message: errorMessage.replace(/^InputTooLarge: /, 'Input too large: '),
     } as ErrorMessage)
   }
 }
@@ -0,0 +1,32 @@
+/** Metadata of a generation run (the C API's metadata JSON) */
+export interface Metadata {
+  tilejson: Record<string, unknown>;
+  warnings: { feature_index?: number; message: string }[];
+  metrics: Record<string, unknown>;
+}
+
+export interface Tile {
+  /** `z/x/y.pbf` */
+  path: string;
+  data: Buffer;
+}
+
+/** Generate tiles in memory (GeoJSON, KML, KMZ or zipped Shapefile input) */
+export function generateTiles(
+  input: Buffer | string,
+  minZoom: number,
+  maxZoom: number,
+  layerName?: string,
+): { tiles: Tile[]; metadata: Metadata };
+
+/**
+ * Generate tiles on the libuv thread pool into a `.pmtiles` archive or a `{z}/{x}/{y}.pbf`
+ * directory, without blocking the event loop
+ */
+export function generateTilesToFile(
+  input: Buffer | string,
+  output: string,
+  minZoom: number,
+  maxZoom: number,
+  layerName?: string,
+): Promise<Metadata>;
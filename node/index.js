@@ -0,0 +1,16 @@
+// Node.js bindings for vector-tile-core (a Node-API addon built from core/ with `--features node`)
+//
+// Set VECTOR_TILE_CORE_NODE to the built shared library, or copy it here as vector_tile_core.node.
+'use strict';
+
+const path = require('path');
+
+const addonPath = process.env.VECTOR_TILE_CORE_NODE || path.join(__dirname, 'vector_tile_core.node');
+// Node loads addons by extension, so a library path (.so/.dylib) is loaded with process.dlopen
+const addon = { exports: {} };
+process.dlopen(addon, addonPath);
+
+module.exports = {
+  generateTiles: addon.exports.generateTiles,
+  generateTilesToFile: addon.exports.generateTilesToFile,
+};
@@ -0,0 +1,61 @@
+'use strict';
+
+const assert = require('node:assert');
+const fs = require('node:fs');
+const os = require('node:os');
+const path = require('node:path');
+const test = require('node:test');
+const { generateTiles, generateTilesToFile } = require('..');
+
+const point = JSON.stringify({
+  type: 'Feature',
+  properties: { name: 'Tokyo' },
+  geometry: { type: 'Point', coordinates: [139.7, 35.7] },
+});
+
+test('generateTiles returns tiles and metadata', () => {
+  const { tiles, metadata } = generateTiles(Buffer.from(point), 0, 2, 'points');
+  assert.deepStrictEqual(tiles.map((tile) => tile.path), ['0/0/0.pbf', '1/1/0.pbf', '2/3/1.pbf']);
+  assert.ok(Buffer.isBuffer(tiles[0].data) && tiles[0].data.length > 0);
+  assert.strictEqual(metadata.tilejson.name, 'points');
+  assert.deepStrictEqual(metadata.warnings, []);
+  // Strings are read as UTF-8 and the layer name defaults to "default"
+  assert.strictEqual(generateTiles(point, 0, 0).metadata.tilejson.name, 'default');
+});
+
+test('generateTiles throws on invalid input', () => {
+  assert.throws(() => generateTiles(Buffer.from('{'), 0, 2, 'points'), /GeoJSON parse error/);
+  assert.throws(() => generateTiles(Buffer.from(point), 0, 300, 'points'), /maxZoom must be between/);
+  assert.throws(() => generateTiles(Buffer.from(point)), /Missing argument: minZoom/);
+});
+
+test('generateTilesToFile writes a PMTiles archive or a directory', async () => {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'vtc-node-'));
+  try {
+    const archive = path.join(dir, 'points.pmtiles');
+    const metadata = await generateTilesToFile(Buffer.from(point), archive, 0, 2, 'points');
+    assert.strictEqual(metadata.tilejson.name, 'points');
+    assert.strictEqual(fs.readFileSync(archive).subarray(0, 7).toString(), 'PMTiles');
+    
+    const tiles = path.join(dir, 'tiles');
+    await generateTilesToFile(point, tiles, 0, 1);
+    assert.ok(fs.existsSync(path.join(tiles, '1', '1', '0.pbf')));
+    
+    await assert.rejects(generateTilesToFile(Buffer.from('{'), tiles, 0, 1), /GeoJSON parse error/);
+  } finally {
+    fs.rmSync(dir, { recursive: true, force: true });
+  }
+});
+
+test('the addon loads in worker threads', async () => {
+  const { Worker } = require('node:worker_threads');
+  const worker = new Worker(
+    `const { parentPort, workerData } = require('node:worker_threads');
+     const { generateTiles } = require(workerData.module);
+     parentPort.postMessage(generateTiles(workerData.point, 0, 2, 'points').tiles.length);`,
+    { eval: true, workerData: { module: path.join(__dirname, '..'), point } },
+  );
+  const [count] = await require('node:events').once(worker, 'message');
+  assert.strictEqual(count, 3);
+  await worker.terminate();
+});
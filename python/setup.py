@@ -0,0 +1,32 @@
+"""Build the shared library with cargo and bundle it in the package (`pip install ./python`)."""
+
+import os
+import shutil
+import subprocess
+
+from setuptools import setup
+from setuptools.command.build_py import build_py
+from setuptools.dist import Distribution
+
+CORE = os.path.join(os.path.dirname(os.path.abspath(__file__)), os.pardir, "core")
+
+
+class BuildWithLibrary(build_py):
+    def run(self):
+        super().run()
+        subprocess.check_call(["cargo", "build", "--release", "--lib", "--features", "ffi"], cwd=CORE)
+        # The package loads the library on first use, so it imports without one
+        from vector_tile_core import LIBRARY_NAME
+
+        target = os.environ.get("CARGO_TARGET_DIR", os.path.join(CORE, "target"))
+        shutil.copy(os.path.join(target, "release", LIBRARY_NAME), os.path.join(self.build_lib, "vector_tile_core"))
+
+
+class BinaryDistribution(Distribution):
+    """Wheels carry the shared library, so they are platform specific."""
+
+    def has_ext_modules(self):
+        return True
+
+
+setup(cmdclass={"build_py": BuildWithLibrary}, distclass=BinaryDistribution)
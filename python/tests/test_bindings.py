@@ -0,0 +1,145 @@
+"""Tests for the ctypes bindings (run from python/; the library in core/target is found):
+
+    cd ../core && cargo build --release --features ffi && cd ../python
+    python -m unittest discover tests
+"""
+
+import json
+import os
+import shutil
+import sqlite3
+import tempfile
+import unittest
+
+import vector_tile_core
+
+POINT = json.dumps({
+    "type": "Feature",
+    "properties": {"name": "Tokyo"},
+    "geometry": {"type": "Point", "coordinates": [139.7, 35.7]},
+})
+
+
+class GenerateTilesTest(unittest.TestCase):
+    def test_returns_tiles_and_metadata(self):
+        tiles, metadata = vector_tile_core.generate_tiles(POINT.encode(), 0, 2, "points")
+        self.assertEqual(sorted(tiles), ["0/0/0.pbf", "1/1/0.pbf", "2/3/1.pbf"])
+        self.assertTrue(all(isinstance(data, bytes) and data for data in tiles.values()))
+        self.assertEqual(metadata["tilejson"]["name"], "points")
+        self.assertEqual(metadata["warnings"], [])
+        # Strings are read as UTF-8 and the layer name defaults to "default"
+        self.assertEqual(vector_tile_core.generate_tiles(POINT, 0, 0)[1]["tilejson"]["name"], "default")
+
+    def test_options(self):
+        tokyo_only = {"bbox": [139.0, 35.0, 140.0, 36.0], "preset": "points-dense"}
+        collection = json.dumps({"type": "FeatureCollection", "features": [
+            json.loads(POINT),
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-74.0, 40.7]}},
+        ]})
+        self.assertEqual(len(vector_tile_core.generate_tiles(collection, 0, 2, "points")[0]), 5)
+        self.assertEqual(len(vector_tile_core.generate_tiles(collection, 0, 2, "points", tokyo_only)[0]), 3)
+        with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "unknown field"):
+            vector_tile_core.generate_tiles(POINT, 0, 2, "points", {"simplify": True})
+
+    def test_invalid_input_raises(self):
+        with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "GeoJSON parse error"):
+            vector_tile_core.generate_tiles(b"{", 0, 2, "points")
+
+    def test_unreadable_tile_raises(self):
+        lib = vector_tile_core._get_lib()
+
+        class FailingTiles:
+            """The real library, except that every tile lookup reports an out-of-range index."""
+
+            def __getattr__(self, name):
+                return getattr(lib, name)
+
+            def vtc_result_tile(self, *args):
+                return -1
+
+        vector_tile_core._lib = FailingTiles()
+        try:
+            with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "could not be read"):
+                vector_tile_core.generate_tiles(POINT, 0, 1, "points")
+        finally:
+            vector_tile_core._lib = lib
+
+
+class GenerateTilesToFileTest(unittest.TestCase):
+    def setUp(self):
+        self.directory = tempfile.mkdtemp()
+
+    def tearDown(self):
+        shutil.rmtree(self.directory)
+
+    def test_writes_archives(self):
+        pmtiles = os.path.join(self.directory, "points.pmtiles")
+        metadata = vector_tile_core.generate_tiles_to_file(POINT, pmtiles, 0, 2, "points", compression="brotli")
+        self.assertEqual(metadata["tilejson"]["name"], "points")
+        with open(pmtiles, "rb") as archive:
+            header = archive.read(127)
+        # Tile compression 3 (Brotli) at byte 98 of the header
+        self.assertEqual((header[:7], header[98]), (b"PMTiles", 3))
+
+        mbtiles = os.path.join(self.directory, "points.mbtiles")
+        vector_tile_core.generate_tiles_to_file(POINT, mbtiles, 0, 2, "points", {"preset": "points-dense"})
+        with sqlite3.connect(mbtiles) as database:
+            self.assertEqual(database.execute("SELECT count(*) FROM tiles").fetchone()[0], 3)
+
+        vector_tile_core.generate_tiles_to_file(POINT, self.directory, 0, 1, "points")
+        self.assertTrue(os.path.exists(os.path.join(self.directory, "1", "1", "0.pbf")))
+
+    def test_errors_raise(self):
+        with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "Unknown tile compression"):
+            vector_tile_core.generate_tiles_to_file(POINT, os.path.join(self.directory, "a.pmtiles"), 0, 2, compression="zstd")
+        with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "Failed to create"):
+            vector_tile_core.generate_tiles_to_file(POINT, os.path.join(self.directory, "missing", "a.mbtiles"), 0, 2)
+
+
+class TileSourceTest(unittest.TestCase):
+    def test_get_tile(self):
+        with vector_tile_core.TileSource(POINT, 0, 2, "points") as source:
+            layers = vector_tile_core.inspect_tile(source.get_tile(2, 3, 1))
+            self.assertEqual(layers[0]["name"], "points")
+            # Overzoomed from 2/3/1
+            self.assertEqual(vector_tile_core.inspect_tile(source.get_tile(4, 14, 6))[0]["name"], "points")
+            self.assertIsNone(source.get_tile(2, 0, 0))
+            with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "InvalidZoomRange"):
+                source.get_tile(40, 0, 0)
+        with self.assertRaisesRegex(ValueError, "closed"):
+            source.get_tile(0, 0, 0)
+
+    def test_invalid_input_raises(self):
+        with self.assertRaisesRegex(vector_tile_core.TileGenerationError, "GeoJSON parse error"):
+            vector_tile_core.TileSource(b"{", 0, 2)
+
+
+class LibraryTest(unittest.TestCase):
+    def test_exports(self):
+        self.assertEqual(
+            sorted(vector_tile_core.__all__),
+            ["TileGenerationError", "TileSource", "generate_tiles", "generate_tiles_to_file", "inspect_tile"],
+        )
+
+    def test_found_without_environment(self):
+        saved = os.environ.pop("VECTOR_TILE_CORE_LIB", None)
+        try:
+            self.assertTrue(os.path.exists(vector_tile_core._library_path()))
+        finally:
+            if saved is not None:
+                os.environ["VECTOR_TILE_CORE_LIB"] = saved
+
+
+class InspectTileTest(unittest.TestCase):
+    def test_summarizes_layers(self):
+        tiles, _ = vector_tile_core.generate_tiles(POINT, 0, 0, "points")
+        layers = vector_tile_core.inspect_tile(tiles["0/0/0.pbf"])
+        self.assertEqual(layers[0]["name"], "points")
+
+    def test_invalid_tile_raises(self):
+        with self.assertRaises(ValueError):
+            vector_tile_core.inspect_tile(b"\xff\xff\xff")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,243 @@
+"""Python bindings for vector-tile-core (ctypes over the C API in core/include/vector_tile_core.h).
+
+`pip install ./python` builds the shared library with cargo and bundles it in the package; in a
+source checkout the library built by `cargo build --features ffi` in `core/` is found without
+installing. `VECTOR_TILE_CORE_LIB` overrides both. The bindings load the library with ctypes
+instead of being a compiled (pyo3) extension, so one build serves every Python 3.8+.
+
+    import vector_tile_core
+    tiles, metadata = vector_tile_core.generate_tiles(gdf.to_json().encode(), 0, 10, "parcels")
+    vector_tile_core.generate_tiles_to_file(gdf.to_json(), "parcels.pmtiles", 0, 14, "parcels")
+    with vector_tile_core.TileSource(gdf.to_json(), 0, 14, "parcels") as source:
+        tile = source.get_tile(16, 58211, 25806)
+"""
+
+import ctypes
+import ctypes.util
+import json
+import os
+import sys
+
+__all__ = ["generate_tiles", "generate_tiles_to_file", "inspect_tile", "TileSource", "TileGenerationError"]
+
+
+class TileGenerationError(Exception):
+    """Raised when the tiler rejects the input."""
+
+
+class _Buffer(ctypes.Structure):
+    _fields_ = [("data", ctypes.POINTER(ctypes.c_uint8)), ("len", ctypes.c_size_t)]
+
+
+LIBRARY_NAME = {"darwin": "libvector_tile_core.dylib", "win32": "vector_tile_core.dll"}.get(
+    sys.platform, "libvector_tile_core.so"
+)
+
+
+def _library_path():
+    """`VECTOR_TILE_CORE_LIB`, the copy bundled by setup.py, a build in a source checkout
+    (core/target next to python/), then a library on the system search path."""
+    if "VECTOR_TILE_CORE_LIB" in os.environ:
+        return os.environ["VECTOR_TILE_CORE_LIB"]
+    package = os.path.dirname(os.path.abspath(__file__))
+    target = os.environ.get("CARGO_TARGET_DIR", os.path.join(package, os.pardir, os.pardir, "core", "target"))
+    paths = [os.path.join(package, LIBRARY_NAME)]
+    paths += [os.path.join(target, profile, LIBRARY_NAME) for profile in ("release", "debug")]
+    for path in paths:
+        if os.path.exists(path):
+            return path
+    installed = ctypes.util.find_library("vector_tile_core")
+    if installed is None:
+        raise OSError(
+            "vector-tile-core library not found in %s; install the package with pip, run "
+            "`cargo build --release --features ffi` in core/ or set VECTOR_TILE_CORE_LIB" % ", ".join(paths)
+        )
+    return installed
+
+
+def _load():
+    lib = ctypes.CDLL(_library_path())
+    lib.vtc_generate.restype = ctypes.c_void_p
+    lib.vtc_generate.argtypes = [
+        ctypes.c_char_p, ctypes.c_size_t, ctypes.c_uint8, ctypes.c_uint8, ctypes.c_char_p,
+    ]
+    lib.vtc_generate_with_options.restype = ctypes.c_void_p
+    lib.vtc_generate_with_options.argtypes = [
+        ctypes.c_char_p, ctypes.c_size_t, ctypes.c_uint8, ctypes.c_uint8, ctypes.c_char_p, ctypes.c_char_p,
+    ]
+    lib.vtc_generate_to_file.restype = ctypes.c_void_p
+    lib.vtc_generate_to_file.argtypes = [
+        ctypes.c_char_p, ctypes.c_size_t, ctypes.c_uint8, ctypes.c_uint8, ctypes.c_char_p, ctypes.c_char_p,
+        ctypes.c_char_p, ctypes.c_char_p,
+    ]
+    lib.vtc_result_error.restype = ctypes.c_char_p
+    lib.vtc_result_error.argtypes = [ctypes.c_void_p]
+    lib.vtc_result_tile_count.restype = ctypes.c_size_t
+    lib.vtc_result_tile_count.argtypes = [ctypes.c_void_p]
+    lib.vtc_result_tile.restype = ctypes.c_int32
+    lib.vtc_result_tile.argtypes = [
+        ctypes.c_void_p, ctypes.c_size_t, ctypes.POINTER(ctypes.c_char_p), ctypes.POINTER(_Buffer),
+    ]
+    lib.vtc_result_metadata_json.restype = ctypes.c_char_p
+    lib.vtc_result_metadata_json.argtypes = [ctypes.c_void_p]
+    lib.vtc_result_free.restype = None
+    lib.vtc_result_free.argtypes = [ctypes.c_void_p]
+    lib.vtc_source_new.restype = ctypes.c_void_p
+    lib.vtc_source_new.argtypes = [
+        ctypes.c_char_p, ctypes.c_size_t, ctypes.c_uint8, ctypes.c_uint8, ctypes.c_char_p, ctypes.c_char_p,
+    ]
+    lib.vtc_source_error.restype = ctypes.c_char_p
+    lib.vtc_source_error.argtypes = [ctypes.c_void_p]
+    lib.vtc_source_tile.restype = ctypes.c_int32
+    lib.vtc_source_tile.argtypes = [
+        ctypes.c_void_p, ctypes.c_uint8, ctypes.c_uint32, ctypes.c_uint32, ctypes.POINTER(_Buffer),
+    ]
+    lib.vtc_source_free.restype = None
+    lib.vtc_source_free.argtypes = [ctypes.c_void_p]
+    lib.vtc_inspect_tile.restype = ctypes.c_void_p
+    lib.vtc_inspect_tile.argtypes = [ctypes.c_char_p, ctypes.c_size_t]
+    lib.vtc_string_free.restype = None
+    lib.vtc_string_free.argtypes = [ctypes.c_void_p]
+    return lib
+
+
+_lib = None
+
+
+def _get_lib():
+    global _lib
+    if _lib is None:
+        _lib = _load()
+    return _lib
+
+
+def generate_tiles(geojson, min_zoom, max_zoom, layer_name="default", options=None):
+    """Generate tiles from GeoJSON bytes (or str).
+
+    `options` is a dict of the keys accepted by `vtc_generate_with_options` (e.g.
+    `{"preset": "roads", "bbox": [139.5, 35.5, 140.0, 35.9]}`); with options, KML, KMZ and
+    zipped Shapefile inputs are detected from their content.
+
+    Returns `(tiles, metadata)`: `tiles` maps "z/x/y.pbf" paths to tile bytes and `metadata`
+    is a dict with "tilejson", "warnings" and "metrics".
+    """
+    lib = _get_lib()
+    geojson = _bytes(geojson)
+    layer = layer_name.encode("utf-8")
+    if options is None:
+        result = lib.vtc_generate(geojson, len(geojson), min_zoom, max_zoom, layer)
+    else:
+        result = lib.vtc_generate_with_options(geojson, len(geojson), min_zoom, max_zoom, layer, _options_json(options))
+    try:
+        _check(lib, result)
+        tiles = {}
+        path = ctypes.c_char_p()
+        buffer = _Buffer()
+        for index in range(lib.vtc_result_tile_count(result)):
+            if lib.vtc_result_tile(result, index, ctypes.byref(path), ctypes.byref(buffer)) != 0:
+                raise TileGenerationError("Tile %d of the result could not be read" % index)
+            tiles[path.value.decode("utf-8")] = ctypes.string_at(buffer.data, buffer.len)
+        return tiles, _metadata(lib, result)
+    finally:
+        lib.vtc_result_free(result)
+
+
+def generate_tiles_to_file(geojson, output_path, min_zoom, max_zoom, layer_name="default", options=None, compression=None):
+    """Generate tiles into `output_path` and return the metadata dict (as `generate_tiles`).
+
+    A path ending in ".pmtiles" gets a PMTiles archive, one ending in ".mbtiles" an MBTiles
+    database, and any other path a z/x/y.pbf directory. `compression` is "gzip" or "brotli"
+    (None for uncompressed tiles); servers should send the tiles with that Content-Encoding.
+    """
+    lib = _get_lib()
+    geojson = _bytes(geojson)
+    result = lib.vtc_generate_to_file(
+        geojson, len(geojson), min_zoom, max_zoom, layer_name.encode("utf-8"), _options_json(options or {}),
+        os.fsencode(output_path), None if compression is None else compression.encode("utf-8"),
+    )
+    try:
+        _check(lib, result)
+        return _metadata(lib, result)
+    finally:
+        lib.vtc_result_free(result)
+
+
+class TileSource:
+    """Generated tiles answering tile requests, e.g. for a preview or tile server.
+
+    Zooms past `max_zoom` are clipped and scaled from the max-zoom tiles. Close the source (or
+    use it as a context manager) to free the tiles.
+    """
+
+    def __init__(self, geojson, min_zoom, max_zoom, layer_name="default", options=None):
+        self._lib = _get_lib()
+        geojson = _bytes(geojson)
+        self._source = self._lib.vtc_source_new(
+            geojson, len(geojson), min_zoom, max_zoom, layer_name.encode("utf-8"), _options_json(options or {}),
+        )
+        error = self._lib.vtc_source_error(self._source)
+        if error is not None:
+            self.close()
+            raise TileGenerationError(error.decode("utf-8"))
+
+    def get_tile(self, z, x, y):
+        """Bytes of tile z/x/y, or None if it has no data."""
+        if self._source is None:
+            raise ValueError("The tile source is closed")
+        buffer = _Buffer()
+        status = self._lib.vtc_source_tile(self._source, z, x, y, ctypes.byref(buffer))
+        if status < 0:
+            raise TileGenerationError(self._lib.vtc_source_error(self._source).decode("utf-8"))
+        return ctypes.string_at(buffer.data, buffer.len) if status == 0 else None
+
+    def close(self):
+        if self._source is not None:
+            self._lib.vtc_source_free(self._source)
+            self._source = None
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.close()
+
+    def __del__(self):
+        if getattr(self, "_source", None) is not None:
+            self.close()
+
+
+def _bytes(geojson):
+    """Input bytes; strings are encoded as UTF-8."""
+    return geojson.encode("utf-8") if isinstance(geojson, str) else geojson
+
+
+def _options_json(options):
+    return json.dumps(options).encode("utf-8")
+
+
+def _check(lib, result):
+    error = lib.vtc_result_error(result)
+    if error is not None:
+        raise TileGenerationError(error.decode("utf-8"))
+
+
+def _metadata(lib, result):
+    metadata_json = lib.vtc_result_metadata_json(result)
+    if metadata_json is None:
+        raise TileGenerationError("The result has no metadata")
+    return json.loads(metadata_json.decode("utf-8"))
+
+
+def inspect_tile(data):
+    """Summarize the layers of an encoded tile as a list of dicts."""
+    lib = _get_lib()
+    pointer = lib.vtc_inspect_tile(data, len(data))
+    if not pointer:
+        raise ValueError("Tile inspection failed")
+    try:
+        summary = json.loads(ctypes.string_at(pointer).decode("utf-8"))
+    finally:
+        lib.vtc_string_free(pointer)
+    if "error" in summary:
+        raise ValueError(summary["error"])
+    return summary["layers"]